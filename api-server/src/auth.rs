@@ -0,0 +1,32 @@
+//! API-key authentication
+//!
+//! Every route but `/health` needs one of the keys the server was started
+//! with, sent as `x-api-key: <key>` or `Authorization: Bearer <key>`.
+
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+
+use crate::error::ApiError;
+use crate::AppState;
+
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let headers = request.headers();
+    let key = headers.get("x-api-key").and_then(|value| value.to_str().ok()).or_else(|| {
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+    });
+    match key {
+        Some(key) if state.api_keys.contains(key) => Ok(next.run(request).await),
+        Some(_) => Err(ApiError::unauthorized("Invalid API key")),
+        None => Err(ApiError::unauthorized("Missing API key; send it in x-api-key")),
+    }
+}
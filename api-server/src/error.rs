@@ -0,0 +1,53 @@
+//! Errors as JSON responses
+//!
+//! Handlers return `anyhow` chains like the SDK; a `ClientError` anywhere in
+//! the chain picks the status code, the way it picks the client's exit code.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use calculator_sdk::ClientError;
+use serde_json::json;
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError { status, message: message.into() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::UNAUTHORIZED, message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let status = match error.chain().find_map(|cause| cause.downcast_ref::<ClientError>()) {
+            Some(ClientError::InvalidArgs(_)) => StatusCode::BAD_REQUEST,
+            Some(ClientError::Simulation(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+            Some(ClientError::Rpc(_)) | Some(ClientError::Transaction(_)) => StatusCode::BAD_GATEWAY,
+            Some(ClientError::CallbackTimeout(_)) => StatusCode::GATEWAY_TIMEOUT,
+            None => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError::new(status, format!("{:#}", error))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use axum::routing::{get, post};
+use axum::{middleware, Router};
+use calculator_indexer::store::Store;
+use calculator_sdk::CalculatorClient;
+use clap::Parser;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+mod auth;
+mod error;
+mod routes;
+
+#[derive(Parser)]
+#[command(name = "api-server")]
+#[command(about = "Serves calculator submissions, results, history and statistics over HTTP")]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// RPC URL for the Solana cluster
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// Indexer database history and statistics are read from
+    #[arg(long, env = "DATABASE_URL", default_value = "sqlite://calculator-index.db?mode=rwc")]
+    database_url: String,
+
+    /// Calculator program to submit to [default: the deployed calculator program]
+    #[arg(long)]
+    program_id: Option<Pubkey>,
+
+    /// Keypair paying for sponsored submissions [default: ~/.config/solana/id.json]
+    #[arg(long)]
+    keypair: Option<PathBuf>,
+
+    /// Calculator state account sponsored submissions are recorded in
+    #[arg(long)]
+    state: Pubkey,
+
+    /// API key clients must send in `x-api-key` or as a bearer token; repeat it or give a comma-separated list
+    #[arg(long = "api-key", env = "API_KEYS", value_delimiter = ',', required = true)]
+    api_keys: Vec<String>,
+}
+
+/// What every handler shares
+pub struct AppState {
+    pub calculator: CalculatorClient,
+    pub store: Store,
+    /// Pays for and requests every submission
+    pub payer: Keypair,
+    pub state: Pubkey,
+    pub api_keys: HashSet<String>,
+    /// Held while submitting, so concurrent submissions don't race for the
+    /// state's next record index
+    pub submitting: Mutex<()>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let keypair_path = match cli.keypair {
+        Some(path) => path,
+        None => {
+            let home = std::env::var_os("HOME").context("HOME is not set; pass --keypair explicitly")?;
+            PathBuf::from(home).join(".config").join("solana").join("id.json")
+        }
+    };
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", keypair_path.display(), e))?;
+
+    let rpc = Arc::new(RpcClient::new_with_commitment(cli.rpc_url, CommitmentConfig::confirmed()));
+    let calculator =
+        CalculatorClient::new(rpc).with_program_id(cli.program_id.unwrap_or_else(bonsol_calculator_backend::id));
+    let state = Arc::new(AppState {
+        calculator,
+        store: Store::connect(&cli.database_url).await?,
+        payer,
+        state: cli.state,
+        api_keys: cli.api_keys.into_iter().filter(|key| !key.is_empty()).collect(),
+        submitting: Mutex::new(()),
+    });
+
+    let api = Router::new()
+        .route("/calculations", post(routes::submit))
+        .route("/calculations/{execution_id}", get(routes::calculation))
+        .route("/history", get(routes::history))
+        .route("/stats", get(routes::stats))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_api_key));
+    let app = Router::new().route("/health", get(routes::health)).merge(api).with_state(state.clone());
+
+    let listener =
+        tokio::net::TcpListener::bind(cli.listen).await.with_context(|| format!("Failed to listen on {}", cli.listen))?;
+    println!("🚀 Serving on http://{}", cli.listen);
+    println!("   Sponsoring submissions from {} into {}", state.payer.pubkey(), state.state);
+    axum::serve(listener, app).await.context("Server failed")?;
+    Ok(())
+}
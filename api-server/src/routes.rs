@@ -0,0 +1,176 @@
+//! Request handlers
+//!
+//! Submissions go through the SDK, paid for and requested by the server's
+//! keypair. Results, history and statistics come from the indexer's
+//! database; a calculation the indexer hasn't seen yet is looked up in its
+//! calculation record instead, so a result can be read as soon as the
+//! callback lands.
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use calculator_indexer::store::{CalculationRow, HistoryFilter, Stats};
+use calculator_sdk::{
+    expression, pad_execution_id, parse_operation, records, validate_execution_id, Calculation, CalculationRecord,
+    CalculationStatus, HistoryEntry, OPERATION_NAMES,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::ApiError;
+use crate::AppState;
+
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+const MAX_HISTORY_LIMIT: i64 = 500;
+
+/// A calculation to submit: an infix or RPN `expression`, or an `operation`
+/// name with its operands
+#[derive(Deserialize)]
+pub struct SubmitRequest {
+    expression: Option<String>,
+    /// Read `expression` as space-separated RPN tokens
+    #[serde(default)]
+    rpn: bool,
+    operation: Option<String>,
+    operand_a: Option<i64>,
+    operand_b: Option<i64>,
+    /// Generated when not given
+    execution_id: Option<String>,
+}
+
+impl SubmitRequest {
+    fn calculation(&self) -> Result<Calculation, ApiError> {
+        if let Some(expression) = &self.expression {
+            let tokens = if self.rpn { expression::parse_rpn(expression) } else { expression::parse(expression) };
+            let tokens = tokens.map_err(|e| ApiError::bad_request(format!("Invalid expression: {:#}", e)))?;
+            return Ok(Calculation::from_tokens(tokens));
+        }
+        let Some(name) = &self.operation else {
+            return Err(ApiError::bad_request("Give an expression, or an operation with its operands"));
+        };
+        let op_code = parse_operation(name)
+            .ok_or_else(|| ApiError::bad_request(format!("Unknown operation {:?}; use {}", name, OPERATION_NAMES)))?;
+        let operand_a = self.operand_a.ok_or_else(|| ApiError::bad_request("operand_a is required"))?;
+        Ok(Calculation::Single { op_code, operand_a, operand_b: self.operand_b.unwrap_or(0) })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SubmitResponse {
+    execution_id: String,
+    description: String,
+    signature: String,
+    state: String,
+    index: u64,
+    record: String,
+}
+
+#[derive(Serialize)]
+pub struct CalculationResponse {
+    /// `index` when read from the indexer's database, `chain` when read from
+    /// a calculation record the indexer hasn't seen yet
+    source: &'static str,
+    #[serde(flatten)]
+    calculation: CalculationRow,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    payer: Option<String>,
+    state: Option<String>,
+    before_slot: Option<i64>,
+    limit: Option<i64>,
+}
+
+pub async fn health() -> &'static str {
+    "ok"
+}
+
+pub async fn submit(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SubmitRequest>,
+) -> Result<(StatusCode, Json<SubmitResponse>), ApiError> {
+    let calculation = request.calculation()?;
+    let submission = {
+        let _submitting = state.submitting.lock().await;
+        state.calculator.submit(&state.payer, &state.state, &calculation, request.execution_id.as_deref()).await?
+    };
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(SubmitResponse {
+            execution_id: submission.execution_id,
+            description: calculation.describe(),
+            signature: submission.signature.to_string(),
+            state: submission.state.to_string(),
+            index: submission.index,
+            record: submission.record.to_string(),
+        }),
+    ))
+}
+
+pub async fn calculation(
+    State(state): State<Arc<AppState>>,
+    Path(execution_id): Path<String>,
+) -> Result<Json<CalculationResponse>, ApiError> {
+    validate_execution_id(&execution_id)?;
+    let execution_id = pad_execution_id(&execution_id);
+    if let Some(calculation) = state.store.calculation(&execution_id).await? {
+        return Ok(Json(CalculationResponse { source: "index", calculation }));
+    }
+    let calculator = &state.calculator;
+    match records::find_record(calculator.rpc(), calculator.program_id(), &execution_id).await {
+        Ok((_, record)) => Ok(Json(CalculationResponse { source: "chain", calculation: row_from_record(&record) })),
+        Err(_) => Err(ApiError::not_found(format!("No calculation with execution ID {}", execution_id))),
+    }
+}
+
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<CalculationRow>>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    if !(1..=MAX_HISTORY_LIMIT).contains(&limit) {
+        return Err(ApiError::bad_request(format!("limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+    }
+    let filter = HistoryFilter { payer: query.payer, state: query.state, before_slot: query.before_slot, limit };
+    Ok(Json(state.store.history(&filter).await?))
+}
+
+pub async fn stats(State(state): State<Arc<AppState>>) -> Result<Json<Stats>, ApiError> {
+    Ok(Json(state.store.stats().await?))
+}
+
+/// A calculation record in the shape of an indexed row, with what only the
+/// transactions tell (signatures, cycles) left out
+fn row_from_record(record: &CalculationRecord) -> CalculationRow {
+    let (outcome, error) = match HistoryEntry::from(record).status {
+        CalculationStatus::Pending => (None, None),
+        CalculationStatus::AwaitingFinalize => (Some("awaiting_finalize"), None),
+        CalculationStatus::Complete | CalculationStatus::Local => (Some("completed"), None),
+        CalculationStatus::Failed(reason) => (Some("failed"), Some(format!("{:?}", reason))),
+    };
+    let single = record.expression.is_empty();
+    CalculationRow {
+        execution_id: record.execution_id.clone(),
+        state: Some(record.state.to_string()),
+        record_index: Some(record.index as i64),
+        payer: Some(record.payer.to_string()),
+        requester: Some(record.requester.to_string()),
+        operation: Some(record.operation),
+        operand_a: single.then_some(record.operand_a),
+        operand_b: single.then_some(record.operand_b),
+        description: Some(record.describe()),
+        computed_locally: Some(record.is_local),
+        submit_signature: None,
+        submitted_slot: Some(record.submitted_slot as i64),
+        submitted_at: Some(record.timestamp),
+        retried_as: None,
+        outcome: outcome.map(str::to_string),
+        result: record.result,
+        error,
+        cycles: None,
+        callback_signature: None,
+        completed_slot: record.completed_slot.map(|slot| slot as i64),
+        latency_slots: record.completed_slot.map(|slot| slot.saturating_sub(record.submitted_slot) as i64),
+    }
+}
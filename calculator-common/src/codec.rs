@@ -0,0 +1,157 @@
+//! Length-prefixed Borsh encoding for program-owned account data
+//!
+//! Account data is laid out as a little-endian `u32` payload length, the Borsh
+//! payload, then zeroes up to the end of the account. Writing zero-fills
+//! whatever a previous, longer payload left behind, and reading only decodes
+//! the prefixed payload and requires every byte of it to be consumed, so a
+//! shrinking `Option` or `Vec` field can never resurrect stale bytes.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{msg, program_error::ProgramError};
+
+/// Size of the payload length prefix
+pub const LEN_PREFIX: usize = 4;
+
+/// Account size needed to hold a payload of at most `max_payload_len` bytes
+pub const fn account_size(max_payload_len: usize) -> usize {
+    LEN_PREFIX + max_payload_len
+}
+
+/// Serializes `value` into `data` behind a length prefix and zeroes the rest
+pub fn encode<T: BorshSerialize>(value: &T, data: &mut [u8]) -> Result<(), ProgramError> {
+    let payload = value.try_to_vec()?;
+    let end = LEN_PREFIX + payload.len();
+    if end > data.len() {
+        msg!("Encoded payload of {} bytes does not fit in {} bytes of account data", payload.len(), data.len());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    data[..LEN_PREFIX].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    data[LEN_PREFIX..end].copy_from_slice(&payload);
+    data[end..].fill(0);
+    Ok(())
+}
+
+/// Deserializes the length-prefixed payload at the start of `data`
+///
+/// Fails on uninitialized data, a prefix running past the end of the account,
+/// or a payload with trailing bytes.
+pub fn decode<T: BorshDeserialize>(data: &[u8]) -> Result<T, ProgramError> {
+    T::try_from_slice(payload(data)?).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Deserializes only the leading fields of the payload
+///
+/// `T` must mirror a prefix of the stored type, so handlers that only touch
+/// those fields skip decoding the rest.
+pub fn decode_head<T: BorshDeserialize>(data: &[u8]) -> Result<T, ProgramError> {
+    T::deserialize(&mut payload(data)?).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Overwrites the leading fields of the payload in place
+///
+/// `T` must mirror a fixed-size prefix of the stored type; the length prefix
+/// and the rest of the payload are left untouched.
+pub fn encode_head<T: BorshSerialize>(value: &T, data: &mut [u8]) -> Result<(), ProgramError> {
+    let head = value.try_to_vec()?;
+    if head.len() > payload(data)?.len() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    data[LEN_PREFIX..LEN_PREFIX + head.len()].copy_from_slice(&head);
+    Ok(())
+}
+
+fn payload(data: &[u8]) -> Result<&[u8], ProgramError> {
+    if data.len() < LEN_PREFIX {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let mut prefix = [0u8; LEN_PREFIX];
+    prefix.copy_from_slice(&data[..LEN_PREFIX]);
+    let payload_len = u32::from_le_bytes(prefix) as usize;
+    if payload_len == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    data.get(LEN_PREFIX..LEN_PREFIX + payload_len).ok_or_else(|| {
+        msg!("Payload length {} exceeds {} bytes of account data", payload_len, data.len());
+        ProgramError::InvalidAccountData
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct Account {
+        count: u64,
+        flag: bool,
+        label: Vec<u8>,
+    }
+
+    /// The leading fixed-size fields of `Account`
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct Head {
+        count: u64,
+        flag: bool,
+    }
+
+    fn account(label: &[u8]) -> Account {
+        Account { count: 7, flag: true, label: label.to_vec() }
+    }
+
+    #[test]
+    fn round_trips_and_zero_fills_a_shrinking_payload() {
+        let mut data = [0xffu8; 64];
+        encode(&account(b"a long label"), &mut data).unwrap();
+        assert_eq!(decode::<Account>(&data), Ok(account(b"a long label")));
+
+        encode(&account(b"ab"), &mut data).unwrap();
+        let end = LEN_PREFIX + account(b"ab").try_to_vec().unwrap().len();
+        assert!(data[end..].iter().all(|&byte| byte == 0));
+        assert_eq!(decode::<Account>(&data), Ok(account(b"ab")));
+    }
+
+    #[test]
+    fn rejects_payloads_that_do_not_fit() {
+        let mut data = [0u8; 8];
+        assert_eq!(encode(&account(b"label"), &mut data), Err(ProgramError::AccountDataTooSmall));
+    }
+
+    #[test]
+    fn rejects_uninitialized_and_truncated_data() {
+        assert_eq!(decode::<Account>(&[0u8; 2]), Err(ProgramError::UninitializedAccount));
+        assert_eq!(decode::<Account>(&[0u8; 32]), Err(ProgramError::UninitializedAccount));
+
+        let mut data = [0u8; 64];
+        encode(&account(b"label"), &mut data).unwrap();
+        // The prefix runs past the end of the account
+        assert_eq!(decode::<Account>(&data[..20]), Err(ProgramError::InvalidAccountData));
+        // A payload longer than the stored type leaves trailing bytes
+        data[..LEN_PREFIX].copy_from_slice(&30u32.to_le_bytes());
+        assert_eq!(decode::<Account>(&data), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn heads_read_and_overwrite_only_the_leading_fields() {
+        let mut data = [0u8; 64];
+        encode(&account(b"label"), &mut data).unwrap();
+        assert_eq!(decode_head::<Head>(&data), Ok(Head { count: 7, flag: true }));
+
+        encode_head(&Head { count: 8, flag: false }, &mut data).unwrap();
+        assert_eq!(decode::<Account>(&data), Ok(Account { count: 8, flag: false, label: b"label".to_vec() }));
+    }
+
+    #[test]
+    fn heads_never_write_past_the_payload() {
+        let mut data = [0u8; 64];
+        encode(&Head { count: 1, flag: true }, &mut data).unwrap();
+        let before = data;
+        assert_eq!(encode_head(&account(b"label"), &mut data), Err(ProgramError::InvalidAccountData));
+        assert_eq!(data, before);
+        let uninitialized = encode_head(&Head { count: 2, flag: false }, &mut [0u8; 16]);
+        assert_eq!(uninitialized, Err(ProgramError::UninitializedAccount));
+    }
+}
@@ -0,0 +1,214 @@
+//! Constants of the calculator's input frames, journals and accounts
+//!
+//! These are the numbers the guest, both programs and the client have to
+//! agree on. The module has no Solana dependency, so the guest uses it with
+//! the crate's default features off.
+
+/// Borsh discriminant of `CalculatorInstruction::Callback`, i.e. its position
+/// in the enum
+pub const CALLBACK_DISCRIMINANT: u8 = 3;
+
+/// `CallbackConfig::instruction_prefix` for Bonsol callbacks into this program
+///
+/// Bonsol prepends the prefix to the callback data, so the prefixed data
+/// decodes as `CalculatorInstruction::Callback`.
+pub const CALLBACK_INSTRUCTION_PREFIX: [u8; 1] = [CALLBACK_DISCRIMINANT];
+
+/// Calculator operation codes, defined once in `calculator-ops` so the guest
+/// and this program number them the same way
+pub use calculator_ops::{
+    OPERATIONS, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE, OP_GCD, OP_ISQRT, OP_LOG2, OP_MAX,
+    OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT,
+};
+
+/// Schema version byte the guest reads ahead of the words of a frame one byte
+/// longer than a whole number of words. Version 1 frames hold a single
+/// operation or an expression applying `FRAME_VERSION_1_OPERATIONS`; frames
+/// without the byte are read as version 1
+pub const FRAME_VERSION_1: u8 = 1;
+
+/// Schema version of frames that may apply any operation and may also be
+/// batch, wide, decimal, private range or statistics frames, which
+/// `encode_batch` and its siblings prefix
+pub const FRAME_VERSION_2: u8 = 2;
+
+/// The operations a version 1 frame may apply, the ones the first images
+/// knew; the guest proves any other as an unknown operation unless the frame
+/// is version 2
+pub const FRAME_VERSION_1_OPERATIONS: [i64; 4] = [OP_ADD, OP_SUBTRACT, OP_MULTIPLY, OP_DIVIDE];
+
+/// Operation code of records submitted through `SubmitExpression`; the guest
+/// reads it as the first word of an expression input frame
+pub use calculator_ops::OP_EXPRESSION;
+
+/// First word of a batch input frame, which the guest evaluates into a
+/// batch journal of one outcome per operation
+pub use calculator_ops::OP_BATCH;
+
+/// First word of a wide-arithmetic input frame, which the guest evaluates on
+/// `i128` or 256-bit operands into a wide journal
+pub use calculator_ops::OP_WIDE;
+
+/// First word of a fixed-point decimal input frame, which the guest
+/// evaluates into a decimal journal
+pub use calculator_ops::OP_DECIMAL;
+
+/// First word of a private-operand range frame, which the guest evaluates
+/// into a range journal that withholds the result
+pub use calculator_ops::OP_PRIVATE_RANGE;
+
+/// First word of a statistics input frame, which the guest evaluates into a
+/// version 1 journal of the aggregate
+pub use calculator_ops::OP_STATISTICS;
+
+/// First word of a chained input frame, which the guest evaluates on the
+/// result of a previous journal into a chained journal
+pub use calculator_ops::OP_CHAINED;
+
+/// Statistics a statistics frame can compute
+pub use calculator_ops::{STATISTICS, STAT_MAX, STAT_MEAN, STAT_MEDIAN, STAT_MIN, STAT_SUM, STAT_VARIANCE};
+
+/// Maximum number of operations in a batch input frame
+pub const MAX_BATCH_OPERATIONS: usize = 16;
+
+/// Maximum number of values in a statistics input frame
+pub const MAX_STATISTICS_VALUES: usize = 64;
+
+/// Maximum number of tokens in a `SubmitExpression` program
+pub const MAX_EXPRESSION_TOKENS: usize = 16;
+
+/// Maximum number of values a `SubmitExpression` program holds on its stack at once
+pub const MAX_STACK_DEPTH: usize = 8;
+
+/// Kinds of the `(kind, value)` tokens of an expression frame: push the
+/// literal value, or apply the operation value
+pub const EXPR_TOKEN_PUSH: i64 = 0;
+pub const EXPR_TOKEN_OP: i64 = 1;
+
+/// Kinds of the stack tokens of an expression frame, whose value is ignored:
+/// duplicate the top value, or swap the top two
+pub const EXPR_TOKEN_DUP: i64 = 2;
+pub const EXPR_TOKEN_SWAP: i64 = 3;
+
+/// Version byte opening the journal the guest commits
+pub const JOURNAL_VERSION_1: u8 = 1;
+
+/// Journal status of a calculation the guest computed
+pub const JOURNAL_STATUS_OK: u8 = 0;
+
+/// Journal statuses of calculations the guest could not compute; their
+/// journals carry a zero result
+pub const JOURNAL_STATUS_DIV_BY_ZERO: u8 = 1;
+pub const JOURNAL_STATUS_OVERFLOW: u8 = 2;
+pub const JOURNAL_STATUS_UNKNOWN_OP: u8 = 3;
+pub const JOURNAL_STATUS_INVALID_OPERAND: u8 = 4;
+/// Journal status of an input frame the guest couldn't evaluate; always
+/// committed in a metered journal
+pub const JOURNAL_STATUS_MALFORMED_INPUT: u8 = 5;
+
+/// Length of a version 1 journal: version, status, the result as a
+/// little-endian `i64`, and the SHA-256 digest of the input the guest read
+pub const JOURNAL_V1_LEN: usize = 1 + 1 + 8 + 32;
+
+/// Version byte opening the journal the guest commits for a batch frame:
+/// the operation count, the SHA-256 digest of the input, then a status byte
+/// and little-endian `i64` result per operation
+pub const JOURNAL_BATCH_VERSION_1: u8 = 2;
+
+/// Version byte opening the batch journal that also commits to its outcomes
+/// with a Merkle root, between the input digest and the outcomes; see
+/// `merkle` for the tree
+pub const JOURNAL_BATCH_VERSION_2: u8 = 9;
+
+/// Length of each outcome of a batch journal: the status and the result as a
+/// little-endian `i64`
+pub const BATCH_OUTCOME_LEN: usize = 1 + 8;
+
+/// Domain prefixes of the batch Merkle tree's leaves and inner nodes
+pub const MERKLE_LEAF_PREFIX: u8 = 0x00;
+pub const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+/// Version byte opening the journal the guest commits for a wide frame: the
+/// status, the result's length in bytes, the result as little-endian two's
+/// complement of that length, then the SHA-256 digest of the input
+pub const JOURNAL_WIDE_VERSION_1: u8 = 3;
+
+/// Version byte opening the journal the guest commits for a decimal frame:
+/// the status, the result as a little-endian `i64` count of `10^-scale`
+/// units, the scale byte, then the SHA-256 digest of the input
+pub const JOURNAL_DECIMAL_VERSION_1: u8 = 4;
+
+/// Length of a version 1 decimal journal
+pub const JOURNAL_DECIMAL_V1_LEN: usize = 1 + 1 + 8 + 1 + 32;
+
+/// Version byte opening the journal the guest commits for a range frame: the
+/// status, whether the result is within the bounds, then the SHA-256 digest of
+/// the public part of the input
+pub const JOURNAL_RANGE_VERSION_1: u8 = 5;
+
+/// Length of a version 1 range journal
+pub const JOURNAL_RANGE_V1_LEN: usize = 1 + 1 + 1 + 32;
+
+/// Version byte opening the journal the guest commits when it meters itself:
+/// a version 1 journal with the zkVM cycles the calculation consumed, as a
+/// little-endian `u64`, between the result and the input digest
+pub const JOURNAL_METERED_VERSION_1: u8 = 6;
+
+/// Length of a version 1 metered journal
+pub const JOURNAL_METERED_V1_LEN: usize = 1 + 1 + 8 + 8 + 32;
+
+/// Version byte opening the metered journal that also records the overflow
+/// semantics applied: a `JOURNAL_SEMANTICS_*` bitmask between the cycle count
+/// and the input digest
+pub const JOURNAL_METERED_VERSION_2: u8 = 8;
+
+/// Length of a version 2 metered journal
+pub const JOURNAL_METERED_V2_LEN: usize = 1 + 1 + 8 + 8 + 1 + 32;
+
+/// Semantics bit set when some operation saturated where the checked one would overflow
+pub const JOURNAL_SEMANTICS_SATURATED: u8 = 1;
+
+/// Semantics bit set when some operation wrapped where the checked one would overflow
+pub const JOURNAL_SEMANTICS_WRAPPED: u8 = 2;
+
+/// Version byte opening the journal the guest commits for a chained frame:
+/// the status, the result as a little-endian `i64`, the previous result it
+/// started from, the SHA-256 digest of the previous journal, then the SHA-256
+/// digest of the input
+pub const JOURNAL_CHAINED_VERSION_1: u8 = 7;
+
+/// Length of a version 1 chained journal
+pub const JOURNAL_CHAINED_V1_LEN: usize = 1 + 1 + 8 + 8 + 32 + 32;
+
+/// Longest journal a calculation record has room for, the version 2 metered one
+pub const MAX_JOURNAL_LEN: usize = JOURNAL_METERED_V2_LEN;
+
+/// Seed prefix for per-calculation record PDAs
+pub const CALCULATION_SEED: &[u8] = b"calculation";
+
+/// Seed prefix for the fee treasury PDA
+pub const TREASURY_SEED: &[u8] = b"treasury";
+
+/// Seed prefix for per-requester counter PDAs
+pub const REQUESTER_SEED: &[u8] = b"requester";
+
+/// Seed prefix for named variable PDAs
+pub const VARIABLE_SEED: &[u8] = b"variable";
+
+/// Maximum variable name length; the name is used as a PDA seed
+pub const MAX_VARIABLE_NAME_LEN: usize = 32;
+
+/// Longest execution ID Bonsol accepts, which calculation records have room for
+pub const MAX_EXECUTION_ID_LEN: usize = 16;
+
+/// Maximum number of submitter-provided accounts forwarded to the callback
+pub const MAX_CALLBACK_EXTRA_ACCOUNTS: usize = 4;
+
+/// Maximum length in bytes of a submitter-provided calculation label
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// Maximum number of keys in the owner multisig
+pub const MAX_OWNERS: usize = 10;
+
+/// Length of a hex-encoded RISC0 image ID
+pub const IMAGE_ID_LEN: usize = 64;
@@ -0,0 +1,307 @@
+//! Input frames the guest reads
+//!
+//! A frame is a run of little-endian `i64` words, behind a schema version
+//! byte for the version 2 frames; see `constants` for the versions and the
+//! first words that tell the frames apart. `CalculationRecord::input` is the
+//! frame of a single operation or an expression, which is headerless unless
+//! it applies an operation outside `FRAME_VERSION_1_OPERATIONS`; the other
+//! frames are built by the `encode_*` functions here.
+
+use calculator_ops::{arity, is_infix, Decimal, Operation, WideInt};
+
+use crate::constants::{
+    EXPR_TOKEN_DUP, EXPR_TOKEN_OP, EXPR_TOKEN_PUSH, EXPR_TOKEN_SWAP, FRAME_VERSION_1_OPERATIONS, FRAME_VERSION_2,
+    OP_BATCH, OP_CHAINED, OP_DECIMAL, OP_EXPRESSION, OP_PRIVATE_RANGE, OP_STATISTICS, OP_WIDE,
+};
+use crate::state::{ExprToken, ResultBounds};
+
+/// The schema version byte a frame applying `operations` starts with:
+/// `FRAME_VERSION_2` when one of them is outside `FRAME_VERSION_1_OPERATIONS`,
+/// otherwise `None`, for the headerless frame every image reads
+pub fn version_header(mut operations: impl Iterator<Item = i64>) -> Option<u8> {
+    operations
+        .any(|operation| !FRAME_VERSION_1_OPERATIONS.contains(&operation))
+        .then_some(FRAME_VERSION_2)
+}
+
+/// Encodes `a op b` as the guest's single-operation input frame
+///
+/// The frame is the operation code and both operands as little-endian `i64`
+/// words, behind a `FRAME_VERSION_2` byte when `version_header` asks for one.
+pub fn encode_single(operation: i64, operand_a: i64, operand_b: i64) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 24);
+    frame.extend(version_header(core::iter::once(operation)));
+    for word in [operation, operand_a, operand_b] {
+        frame.extend_from_slice(&word.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes an RPN program as the guest's expression input frame
+///
+/// The frame is little-endian `i64` words: `OP_EXPRESSION`, the token count,
+/// then a `(kind, value)` pair per token, where kind 0 pushes the literal
+/// `value`, kind 1 applies the operation `value`, kind 2 duplicates the top
+/// value and kind 3 swaps the top two; the value of the last two is zero.
+/// Like a single operation's, the frame starts with a `FRAME_VERSION_2` byte
+/// when `version_header` asks for one.
+pub fn encode_expression(tokens: &[ExprToken]) -> Vec<u8> {
+    let operations = tokens.iter().filter_map(|token| match *token {
+        ExprToken::Op(operation) => Some(operation),
+        _ => None,
+    });
+    let mut frame = Vec::with_capacity(1 + 16 + tokens.len() * 16);
+    frame.extend(version_header(operations));
+    frame.extend_from_slice(&OP_EXPRESSION.to_le_bytes());
+    frame.extend_from_slice(&(tokens.len() as i64).to_le_bytes());
+    for token in tokens {
+        let (kind, value) = match *token {
+            ExprToken::Push(literal) => (EXPR_TOKEN_PUSH, literal),
+            ExprToken::Op(operation) => (EXPR_TOKEN_OP, operation),
+            ExprToken::Dup => (EXPR_TOKEN_DUP, 0),
+            ExprToken::Swap => (EXPR_TOKEN_SWAP, 0),
+        };
+        frame.extend_from_slice(&kind.to_le_bytes());
+        frame.extend_from_slice(&value.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes `(operation, a, b)` triples as the guest's batch input frame
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_BATCH`, the operation count, then the operation code and both operands
+/// of each operation. The guest proves them together and commits a
+/// `BatchJournal`.
+pub fn encode_batch(operations: &[(i64, i64, i64)]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 16 + operations.len() * 24);
+    frame.push(FRAME_VERSION_2);
+    frame.extend_from_slice(&OP_BATCH.to_le_bytes());
+    frame.extend_from_slice(&(operations.len() as i64).to_le_bytes());
+    for &(operation, operand_a, operand_b) in operations {
+        frame.extend_from_slice(&operation.to_le_bytes());
+        frame.extend_from_slice(&operand_a.to_le_bytes());
+        frame.extend_from_slice(&operand_b.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes `a op b` on `i128` or 256-bit operands as the guest's wide input frame
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_WIDE`, the operand width in bytes, the operation code, then both
+/// operands as little-endian two's complement. The guest commits a
+/// `WideJournal`.
+pub fn encode_wide<T: WideInt>(operation: i64, operand_a: T, operand_b: T) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 24 + 2 * T::BYTES);
+    frame.push(FRAME_VERSION_2);
+    frame.extend_from_slice(&OP_WIDE.to_le_bytes());
+    frame.extend_from_slice(&(T::BYTES as i64).to_le_bytes());
+    frame.extend_from_slice(&operation.to_le_bytes());
+    let mut operand = vec![0u8; T::BYTES];
+    for value in [operand_a, operand_b] {
+        value.write_le(&mut operand);
+        frame.extend_from_slice(&operand);
+    }
+    frame
+}
+
+/// Encodes `a op b`, rounded half to even to `scale` digits, as the guest's decimal input frame
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_DECIMAL`, the operation code, the result scale, then the value and
+/// scale of each operand. The guest commits a `DecimalJournal`.
+pub fn encode_decimal(operation: i64, operand_a: Decimal, operand_b: Decimal, scale: u8) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 7 * 8);
+    frame.push(FRAME_VERSION_2);
+    for word in [
+        OP_DECIMAL,
+        operation,
+        scale as i64,
+        operand_a.value,
+        operand_a.scale as i64,
+        operand_b.value,
+        operand_b.scale as i64,
+    ] {
+        frame.extend_from_slice(&word.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes `values` as the guest's statistics input frame for `statistic`, one of the `STAT_*` codes
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_STATISTICS`, the statistic, the value count, then the values. The
+/// guest commits the aggregate in a metered journal, so `decode_outcome`
+/// reads it.
+pub fn encode_statistics(statistic: i64, values: &[i64]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 24 + values.len() * 8);
+    frame.push(FRAME_VERSION_2);
+    frame.extend_from_slice(&OP_STATISTICS.to_le_bytes());
+    frame.extend_from_slice(&statistic.to_le_bytes());
+    frame.extend_from_slice(&(values.len() as i64).to_le_bytes());
+    for value in values {
+        frame.extend_from_slice(&value.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes the public part of the guest's private-operand range frame
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_PRIVATE_RANGE`, the operation code, operand B and the inclusive
+/// bounds. Operand A is sent as a private input of its 8 little-endian bytes,
+/// which the guest reads after this frame. The guest commits a `RangeJournal` of whether `a op b` is within
+/// `bounds`, binding only this public part.
+pub fn encode_private_range(operation: i64, operand_b: i64, bounds: ResultBounds) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(1 + 5 * 8);
+    frame.push(FRAME_VERSION_2);
+    for word in [OP_PRIVATE_RANGE, operation, operand_b, bounds.min, bounds.max] {
+        frame.extend_from_slice(&word.to_le_bytes());
+    }
+    frame
+}
+
+/// Encodes `previous result op b` as the guest's chained input frame, where
+/// the previous result is the one `previous_journal` proved
+///
+/// The frame is a `FRAME_VERSION_2` byte, then little-endian `i64` words:
+/// `OP_CHAINED`, the operation code, operand B, then the previous journal,
+/// zero-padded to a whole number of words. The previous journal must be a
+/// single-result or chained journal. The guest commits a `ChainedJournal`
+/// carrying the previous result and the digest of the previous journal, so
+/// a consumer holding that journal can check the lineage with
+/// `ChainedJournal::extends`.
+pub fn encode_chained(operation: i64, operand_b: i64, previous_journal: &[u8]) -> Vec<u8> {
+    let padded_len = previous_journal.len().div_ceil(8) * 8;
+    let mut frame = Vec::with_capacity(1 + 3 * 8 + padded_len);
+    frame.push(FRAME_VERSION_2);
+    for word in [OP_CHAINED, operation, operand_b] {
+        frame.extend_from_slice(&word.to_le_bytes());
+    }
+    frame.extend_from_slice(previous_journal);
+    frame.resize(1 + 3 * 8 + padded_len, 0);
+    frame
+}
+
+/// Renders a validated RPN program in infix form, e.g. `(3 + 4) * 2` or `max(3, 4) * 2`
+pub fn format_expression(tokens: &[ExprToken]) -> String {
+    let mut stack: Vec<String> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        match *token {
+            ExprToken::Push(literal) => stack.push(literal.to_string()),
+            ExprToken::Op(operation) => {
+                let b = if arity(operation) == 2 { stack.pop().unwrap_or_default() } else { String::new() };
+                let a = stack.pop().unwrap_or_default();
+                let rendered = Operation { op: operation, a, b };
+                stack.push(if is_infix(operation) { format!("({})", rendered) } else { rendered.to_string() });
+            }
+            // Stack tokens only move values, so the infix form repeats or reorders them
+            ExprToken::Dup => {
+                if let Some(top) = stack.last().cloned() {
+                    stack.push(top);
+                }
+            }
+            ExprToken::Swap => {
+                if let [.., a, b] = stack.as_mut_slice() {
+                    std::mem::swap(a, b);
+                }
+            }
+        }
+    }
+
+    let rendered = stack.join(" ");
+    match rendered.strip_prefix('(').and_then(|inner| inner.strip_suffix(')')) {
+        Some(inner) if stack.len() == 1 && tokens.len() > 1 => inner.to_string(),
+        _ => rendered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calculator_ops::I256;
+
+    use super::*;
+    use crate::constants::{OPERATIONS, OP_ADD, OP_MAX, OP_MODULO, OP_MULTIPLY, STAT_SUM};
+
+    fn words(frame: &[u8]) -> Vec<i64> {
+        frame.chunks_exact(8).map(|word| i64::from_le_bytes(word.try_into().unwrap())).collect()
+    }
+
+    #[test]
+    fn only_operations_outside_version_1_get_a_header() {
+        for operation in OPERATIONS {
+            let frame = encode_single(operation, 6, 7);
+            if FRAME_VERSION_1_OPERATIONS.contains(&operation) {
+                assert_eq!(words(&frame), [operation, 6, 7]);
+            } else {
+                assert_eq!(frame[0], FRAME_VERSION_2);
+                assert_eq!(words(&frame[1..]), [operation, 6, 7]);
+            }
+        }
+        assert_eq!(version_header([OP_ADD, OP_MULTIPLY].into_iter()), None);
+        assert_eq!(version_header([OP_ADD, OP_MODULO].into_iter()), Some(FRAME_VERSION_2));
+        assert_eq!(version_header(core::iter::empty()), None);
+    }
+
+    #[test]
+    fn expressions_are_headed_by_their_operations() {
+        let sum = [ExprToken::Push(3), ExprToken::Push(4), ExprToken::Op(OP_ADD), ExprToken::Dup, ExprToken::Swap];
+        assert_eq!(words(&encode_expression(&sum)), [OP_EXPRESSION, 5, 0, 3, 0, 4, 1, OP_ADD, 2, 0, 3, 0]);
+
+        let max = [ExprToken::Push(3), ExprToken::Push(4), ExprToken::Op(OP_MAX)];
+        let frame = encode_expression(&max);
+        assert_eq!(frame[0], FRAME_VERSION_2);
+        assert_eq!(words(&frame[1..]), [OP_EXPRESSION, 3, 0, 3, 0, 4, 1, OP_MAX]);
+    }
+
+    #[test]
+    fn headed_frames_are_one_byte_past_whole_words() {
+        // The guest tells the two layouts apart by length alone
+        let headed = [
+            encode_single(OP_MODULO, 1, 2),
+            encode_batch(&[(OP_ADD, 1, 2), (OP_MODULO, 3, 4)]),
+            encode_wide(OP_ADD, 1i128, 2i128),
+            encode_wide(OP_ADD, I256::from(1i128), I256::from(2i128)),
+            encode_decimal(OP_ADD, Decimal { value: 1, scale: 0 }, Decimal { value: 2, scale: 1 }, 1),
+            encode_statistics(STAT_SUM, &[1, 2, 3]),
+            encode_private_range(OP_ADD, 1, ResultBounds { min: 0, max: 10 }),
+            encode_chained(OP_ADD, 1, &[1; 19]),
+        ];
+        for frame in headed {
+            assert_eq!((frame.len() % 8, frame[0]), (1, FRAME_VERSION_2), "{:02x?}", frame);
+        }
+        for frame in [encode_single(OP_ADD, 1, 2), encode_expression(&[ExprToken::Push(1)])] {
+            assert_eq!(frame.len() % 8, 0);
+        }
+    }
+
+    #[test]
+    fn frames_lay_out_their_words() {
+        let batch = encode_batch(&[(OP_ADD, 1, 2), (OP_MODULO, 3, 4)]);
+        assert_eq!(words(&batch[1..]), [OP_BATCH, 2, OP_ADD, 1, 2, OP_MODULO, 3, 4]);
+
+        let wide = encode_wide(OP_ADD, -1i128, 2i128);
+        assert_eq!(words(&wide[1..]), [OP_WIDE, 16, OP_ADD, -1, -1, 2, 0]);
+
+        let statistics = encode_statistics(STAT_SUM, &[5, -5]);
+        assert_eq!(words(&statistics[1..]), [OP_STATISTICS, STAT_SUM, 2, 5, -5]);
+
+        // The previous journal is zero-padded to whole words
+        let chained = encode_chained(OP_ADD, 9, &[0xab; 10]);
+        assert_eq!(chained.len(), 1 + 3 * 8 + 16);
+        assert_eq!(words(&chained[1..25]), [OP_CHAINED, OP_ADD, 9]);
+        assert_eq!(chained[25..35], [0xab; 10]);
+        assert!(chained[35..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn expressions_render_in_infix_form() {
+        let tokens = [ExprToken::Push(3), ExprToken::Push(4), ExprToken::Op(OP_ADD), ExprToken::Push(2)];
+        let product = [&tokens[..], &[ExprToken::Op(OP_MULTIPLY)]].concat();
+        assert_eq!(format_expression(&product), "(3 + 4) * 2");
+        let max = [ExprToken::Push(3), ExprToken::Push(4), ExprToken::Op(OP_MAX)];
+        assert_eq!(format_expression(&max), "max(3, 4)");
+        assert_eq!(format_expression(&[ExprToken::Push(5), ExprToken::Dup, ExprToken::Op(OP_MULTIPLY)]), "5 * 5");
+    }
+}
@@ -0,0 +1,236 @@
+//! Instructions of the native calculator program
+//!
+//! The Borsh discriminant of each variant is its position in the enum, so
+//! variants are only ever appended; `Callback` in particular must stay where
+//! Bonsol's configured instruction prefix expects it.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::state::{CalculatorConfig, CallbackAccount, ExprToken, ProverVersion, ResultBounds};
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum CalculatorInstruction {
+    /// Initialize calculator state
+    ///
+    /// The payer becomes the sole owner; `SetOwners` turns it into a multisig.
+    /// Accounts: payer (signer), state, system program, treasury PDA.
+    Initialize,
+    
+    /// Submit a calculation request to Bonsol ZK network
+    ///
+    /// Accounts: payer (signer), requester, state, record PDA for index
+    /// `calculation_count`, system program, treasury PDA, requester stats PDA
+    /// of the requester, Bonsol deployment account of the configured image,
+    /// Bonsol execution request PDA of the requester and execution ID (see
+    /// `bonsol_interface::util::execution_address`), the calculator program
+    /// and the Bonsol program.
+    /// When a token fee is configured, followed by the payer's token account, the treasury's
+    /// associated token account and the token program.
+    /// Fails with `CalculatorError::ImageNotDeployed` when the image has not
+    /// been deployed to Bonsol on this cluster.
+    /// The payer covers fees, tip and rent while the calculation is attributed
+    /// to the requester, which only has to sign when
+    /// `config.requester_must_sign` is set; pass the payer twice to submit
+    /// for yourself.
+    /// When `execution_id` is `None` the program derives one from the state
+    /// account, calculation count and current slot, and returns it as
+    /// transaction return data; the execution request PDA passed must then be
+    /// the one for the slot the transaction lands in.
+    /// `callback_accounts` are forwarded to the callback after the state account.
+    /// When `result_hook` is set, that program is invoked with the result once
+    /// the callback completes.
+    /// With `manual_finalize`, the callback only stores the journal and the
+    /// an owner must accept it through `FinalizeCalculation`.
+    /// A `referrer` receives `referral_share_bps` of the lamport fee once the
+    /// calculation completes.
+    /// Results outside `result_bounds` are rejected and the calculation is
+    /// marked failed.
+    /// With `config.emit_memo` set, the callback also writes a one-line result
+    /// summary through the SPL Memo program.
+    /// `prover_version` pins the Bonsol prover, falling back to
+    /// `config.prover_version` when `None`.
+    /// `label` is a UTF-8 description of at most `MAX_LABEL_LEN` bytes kept
+    /// with the record and shown in history.
+    /// `operand_a_variable` / `operand_b_variable` name variables (see
+    /// `SetVariable`) whose current values replace the literal operands; each
+    /// named variable's PDA follows the deployment account, `a` before `b`,
+    /// ahead of the token fee accounts.
+    SubmitCalculation {
+        execution_id: Option<String>,
+        operation: i64,
+        operand_a: i64,
+        operand_b: i64,
+        callback_accounts: Vec<CallbackAccount>,
+        result_hook: Option<Pubkey>,
+        manual_finalize: bool,
+        referrer: Option<Pubkey>,
+        result_bounds: Option<ResultBounds>,
+        prover_version: Option<ProverVersion>,
+        label: Option<String>,
+        operand_a_variable: Option<String>,
+        operand_b_variable: Option<String>,
+    },
+    
+    /// Get a window of calculation history (read-only)
+    ///
+    /// Covers indices `offset..offset + limit`, clamped to the calculation
+    /// count and to `MAX_HISTORY_LIMIT`. Logs each record and returns a Borsh
+    /// `HistorySnapshot` as transaction return data, so `simulateTransaction`
+    /// callers get structured results.
+    /// Accounts: state, then the record PDAs of the window in index order.
+    /// Pruned records are passed at their (closed) PDA addresses and skipped.
+    GetHistory {
+        offset: u32,
+        limit: u8,
+    },
+    
+    /// Callback instruction from Bonsol when ZK computation completes
+    ///
+    /// Must stay at position `constants::CALLBACK_DISCRIMINANT` in this enum,
+    /// since Bonsol is configured with that byte as the instruction prefix.
+    /// Accounts: Bonsol execution request PDA of the record's requester and
+    /// execution ID (signer), state, record, then the callback accounts,
+    /// result hook program, treasury + referrer and memo program registered
+    /// at submission. Bonsol signs with the execution request PDA when it
+    /// invokes the callback, so no one else can complete a calculation.
+    /// Fails with `CalculatorError::UnauthorizedCallback` otherwise.
+    Callback {
+        execution_id: String,
+        journal: Vec<u8>,
+    },
+
+    /// An owner accepts a journal stored by a manual-finalize callback
+    ///
+    /// Any single owner may finalize.
+    /// Accounts: owner (signer), state, record, then the callback accounts,
+    /// result hook program, treasury + referrer and memo program registered
+    /// at submission.
+    FinalizeCalculation {
+        execution_id: String,
+    },
+
+    /// Replace the calculator configuration
+    ///
+    /// The treasury's associated token account for a token fee mint must be
+    /// created before submissions can pay the fee.
+    /// Accounts: state, owner signers.
+    Configure {
+        config: CalculatorConfig,
+    },
+
+    /// Resubmit a calculation whose execution expired without a callback
+    ///
+    /// The retry is recorded at the next index with an execution ID derived
+    /// from the original and its retry count, returned as transaction return
+    /// data. Fees already paid carry over to the retry, and the retry stays
+    /// attributed to the original requester.
+    /// Accounts: payer (signer), state, original record, new record PDA for
+    /// index `calculation_count`, system program, the original requester,
+    /// Bonsol deployment account of the configured image, Bonsol execution
+    /// request PDA of the requester and retry execution ID, the calculator
+    /// program and the Bonsol program.
+    RetryCalculation {
+        execution_id: String,
+    },
+
+    /// Sweep collected fees above the treasury's rent-exempt minimum
+    ///
+    /// Accounts: state, owner signers, treasury PDA, destination. Token fees
+    /// are swept too when followed by the treasury's token account, the
+    /// destination token account and the token program.
+    WithdrawFees,
+
+    /// Compute a small calculation directly on-chain, without Bonsol
+    ///
+    /// Both operands must be within `config.local_operand_limit` in magnitude.
+    /// The arithmetic uses checked math and the record is created already
+    /// complete, so no fees are charged and no callback follows.
+    /// Accounts: payer (signer), state, record PDA for index
+    /// `calculation_count`, system program, requester stats PDA of the payer.
+    SubmitLocalCalculation {
+        operation: i64,
+        operand_a: i64,
+        operand_b: i64,
+    },
+
+    /// Replace the guest image requested for new executions
+    ///
+    /// Accounts: state, owner signers.
+    SetImageId {
+        image_id: String,
+    },
+
+    /// Stop or resume accepting submissions and retries
+    ///
+    /// Callbacks for executions already in flight are still processed.
+    /// Accounts: state, owner signers.
+    Pause {
+        paused: bool,
+    },
+
+    /// Replace the owner multisig
+    ///
+    /// Accounts: state, owner signers meeting the current threshold.
+    SetOwners {
+        owners: Vec<Pubkey>,
+        threshold: u8,
+    },
+
+    /// Close completed records older than `before_timestamp`
+    ///
+    /// Rent from each closed record is refunded to the first owner signer.
+    /// Records that are still pending or too recent are skipped, and the
+    /// state's counters are left untouched.
+    /// Accounts: state, owner signers, then the record PDAs to prune.
+    PruneHistory {
+        before_timestamp: i64,
+    },
+
+    /// Submit an arithmetic expression as an RPN program to Bonsol
+    ///
+    /// The guest evaluates `tokens` with checked math and proves the single
+    /// value left on the stack. At most `MAX_EXPRESSION_TOKENS` tokens and
+    /// `MAX_STACK_DEPTH` values on the stack, and every `Op` must find its
+    /// operands on the stack: one for `OP_ABS`, two for the others. `Dup`
+    /// needs one value and `Swap` two.
+    /// Accounts: same as `SubmitCalculation`; the submission uses its
+    /// defaults for every other option.
+    SubmitExpression {
+        execution_id: Option<String>,
+        tokens: Vec<ExprToken>,
+    },
+
+    /// Create or update a named variable usable as an operand
+    ///
+    /// `name` is at most 32 bytes. Rent for a new variable is paid by the
+    /// first owner signer.
+    /// Accounts: state, owner signers, variable PDA, system program.
+    SetVariable {
+        name: String,
+        value: i64,
+    },
+}
+
+impl CalculatorInstruction {
+    /// Variant name, used in compute-unit audit logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            CalculatorInstruction::Initialize => "Initialize",
+            CalculatorInstruction::SubmitCalculation { .. } => "SubmitCalculation",
+            CalculatorInstruction::GetHistory { .. } => "GetHistory",
+            CalculatorInstruction::Callback { .. } => "Callback",
+            CalculatorInstruction::FinalizeCalculation { .. } => "FinalizeCalculation",
+            CalculatorInstruction::Configure { .. } => "Configure",
+            CalculatorInstruction::RetryCalculation { .. } => "RetryCalculation",
+            CalculatorInstruction::WithdrawFees => "WithdrawFees",
+            CalculatorInstruction::SubmitLocalCalculation { .. } => "SubmitLocalCalculation",
+            CalculatorInstruction::SetImageId { .. } => "SetImageId",
+            CalculatorInstruction::Pause { .. } => "Pause",
+            CalculatorInstruction::SetOwners { .. } => "SetOwners",
+            CalculatorInstruction::PruneHistory { .. } => "PruneHistory",
+            CalculatorInstruction::SubmitExpression { .. } => "SubmitExpression",
+            CalculatorInstruction::SetVariable { .. } => "SetVariable",
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! Decoding of the journal committed by the calculator guest
+//!
+//! Version 1 journals are binary: a version byte, a status byte, the result
+//! as a little-endian `i64` and the SHA-256 digest of the exact input the
+//! guest read. Guests built before that committed the result as a 32-byte
+//! space-padded decimal string; those journals still decode, so executions
+//! proven by an older image can complete. A non-zero status means the guest
+//! proved the calculation fails, e.g. by dividing by zero, and the callback
+//! marks it failed with that reason. Batch frames get a `BatchJournal` with
+//! an outcome per operation and a Merkle root over them instead, and wide frames a `WideJournal` with a
+//! length-prefixed `i128` or 256-bit result. Chained frames get a
+//! `ChainedJournal` binding the result to the journal it started from.
+//! Metered journals are version 1 journals that also carry the zkVM cycles
+//! the guest's arithmetic consumed, and from version 2 a bitmask of the
+//! saturating or wrapping semantics its operations fell back on.
+
+use calculator_ops::{Decimal, WideInt, I256};
+use solana_program::{hash::hash, msg, program_error::ProgramError};
+
+use crate::constants::{
+    BATCH_OUTCOME_LEN, JOURNAL_BATCH_VERSION_1, JOURNAL_BATCH_VERSION_2, JOURNAL_CHAINED_V1_LEN,
+    JOURNAL_CHAINED_VERSION_1, JOURNAL_DECIMAL_V1_LEN, JOURNAL_DECIMAL_VERSION_1, JOURNAL_METERED_V1_LEN,
+    JOURNAL_METERED_V2_LEN, JOURNAL_METERED_VERSION_1, JOURNAL_METERED_VERSION_2, JOURNAL_RANGE_V1_LEN,
+    JOURNAL_RANGE_VERSION_1, JOURNAL_STATUS_DIV_BY_ZERO, JOURNAL_STATUS_INVALID_OPERAND, JOURNAL_STATUS_MALFORMED_INPUT,
+    JOURNAL_STATUS_OK, JOURNAL_STATUS_OVERFLOW, JOURNAL_STATUS_UNKNOWN_OP, JOURNAL_V1_LEN, JOURNAL_VERSION_1,
+    JOURNAL_WIDE_VERSION_1, MAX_BATCH_OPERATIONS,
+};
+use crate::merkle::{merkle_root, outcome_leaf, InclusionProof};
+use crate::state::FailureReason;
+
+/// A decoded guest journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestJournal {
+    pub status: u8,
+    pub result: i64,
+    /// SHA-256 digest of the input frame; legacy journals carry none
+    pub input_digest: Option<[u8; 32]>,
+    /// zkVM cycles the guest's arithmetic consumed; only metered journals carry them
+    pub cycles: Option<u64>,
+    /// `JOURNAL_SEMANTICS_*` bits of the overflow semantics applied; only version 2 metered journals carry them
+    pub semantics: Option<u8>,
+}
+
+impl GuestJournal {
+    /// Parses a version 1 or metered journal, or a legacy padded-string one
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        match journal.first() {
+            Some(&JOURNAL_VERSION_1) if journal.len() == JOURNAL_V1_LEN => {
+                let mut result = [0u8; 8];
+                result.copy_from_slice(&journal[2..10]);
+                let mut input_digest = [0u8; 32];
+                input_digest.copy_from_slice(&journal[10..JOURNAL_V1_LEN]);
+                Ok(GuestJournal {
+                    status: journal[1],
+                    result: i64::from_le_bytes(result),
+                    input_digest: Some(input_digest),
+                    cycles: None,
+                    semantics: None,
+                })
+            }
+            Some(&JOURNAL_METERED_VERSION_1) if journal.len() == JOURNAL_METERED_V1_LEN => {
+                Ok(parse_metered(journal, None))
+            }
+            Some(&JOURNAL_METERED_VERSION_2) if journal.len() == JOURNAL_METERED_V2_LEN => {
+                Ok(parse_metered(journal, Some(journal[18])))
+            }
+            // A decimal string starts with a digit or a minus sign, never a version byte
+            Some(b'-' | b'0'..=b'9') => Ok(GuestJournal {
+                status: JOURNAL_STATUS_OK,
+                result: parse_legacy(journal)?,
+                input_digest: None,
+                cycles: None,
+                semantics: None,
+            }),
+            _ => {
+                msg!("Journal of {} bytes is neither a version 1 journal nor a result string", journal.len());
+                Err(ProgramError::InvalidInstructionData)
+            }
+        }
+    }
+
+    /// Encodes the journal as the guest commits it: metered when it carries a cycle count, version 2 of
+    /// it when it also carries semantics, version 1 otherwise
+    pub fn encode(&self) -> Vec<u8> {
+        let mut journal = Vec::with_capacity(JOURNAL_METERED_V2_LEN);
+        journal.push(match (self.cycles, self.semantics) {
+            (Some(_), Some(_)) => JOURNAL_METERED_VERSION_2,
+            (Some(_), None) => JOURNAL_METERED_VERSION_1,
+            (None, _) => JOURNAL_VERSION_1,
+        });
+        journal.push(self.status);
+        journal.extend_from_slice(&self.result.to_le_bytes());
+        if let Some(cycles) = self.cycles {
+            journal.extend_from_slice(&cycles.to_le_bytes());
+            if let Some(semantics) = self.semantics {
+                journal.push(semantics);
+            }
+        }
+        journal.extend_from_slice(&self.input_digest.unwrap_or_default());
+        journal
+    }
+}
+
+// A metered journal of either version, whose input digest fills its last 32 bytes
+fn parse_metered(journal: &[u8], semantics: Option<u8>) -> GuestJournal {
+    let mut result = [0u8; 8];
+    result.copy_from_slice(&journal[2..10]);
+    let mut cycles = [0u8; 8];
+    cycles.copy_from_slice(&journal[10..18]);
+    let mut input_digest = [0u8; 32];
+    input_digest.copy_from_slice(&journal[journal.len() - 32..]);
+    GuestJournal {
+        status: journal[1],
+        result: i64::from_le_bytes(result),
+        input_digest: Some(input_digest),
+        cycles: Some(u64::from_le_bytes(cycles)),
+        semantics,
+    }
+}
+
+/// Decodes the result of a guest journal, failing unless the guest reports success
+pub fn decode_journal(journal: &[u8]) -> Result<i64, ProgramError> {
+    decode_outcome(journal)?.map_err(|reason| {
+        msg!("Journal reports {:?} instead of a result", reason);
+        ProgramError::InvalidInstructionData
+    })
+}
+
+/// Decodes what a guest journal proves: the result, or why the guest could not compute one
+pub fn decode_outcome(journal: &[u8]) -> Result<Result<i64, FailureReason>, ProgramError> {
+    let journal = GuestJournal::parse(journal)?;
+    outcome(journal.status, journal.result)
+}
+
+/// The decoded journal of a batch frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchJournal {
+    /// SHA-256 digest of the input frame
+    pub input_digest: [u8; 32],
+    /// Merkle root over the outcomes; version 1 batch journals carry none
+    pub merkle_root: Option<[u8; 32]>,
+    /// Outcome of each operation, in frame order
+    pub outcomes: Vec<Result<i64, FailureReason>>,
+    // Status and result of each operation, as the Merkle leaves commit them
+    entries: Vec<(u8, i64)>,
+}
+
+impl BatchJournal {
+    const HEADER_LEN: usize = 1 + 1 + 32;
+
+    /// Parses a batch journal of either version, checking a version 2
+    /// journal's Merkle root against its outcomes
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        let header_len = match journal.first() {
+            Some(&JOURNAL_BATCH_VERSION_1) => Self::HEADER_LEN,
+            Some(&JOURNAL_BATCH_VERSION_2) => Self::HEADER_LEN + 32,
+            _ => 0,
+        };
+        if header_len == 0 || journal.len() < header_len {
+            msg!("Journal is not a batch journal");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let count = journal[1] as usize;
+        let expected_len = header_len + count * BATCH_OUTCOME_LEN;
+        if count == 0 || count > MAX_BATCH_OPERATIONS || journal.len() != expected_len {
+            msg!("Batch journal of {} bytes does not hold {} outcomes", journal.len(), count);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut input_digest = [0u8; 32];
+        input_digest.copy_from_slice(&journal[2..Self::HEADER_LEN]);
+        let entries: Vec<(u8, i64)> = journal[header_len..]
+            .chunks_exact(BATCH_OUTCOME_LEN)
+            .map(|entry| {
+                let mut result = [0u8; 8];
+                result.copy_from_slice(&entry[1..]);
+                (entry[0], i64::from_le_bytes(result))
+            })
+            .collect();
+        let outcomes = entries.iter().map(|&(status, result)| outcome(status, result)).collect::<Result<_, _>>()?;
+
+        let merkle_root = if header_len > Self::HEADER_LEN {
+            let mut root = [0u8; 32];
+            root.copy_from_slice(&journal[Self::HEADER_LEN..header_len]);
+            let leaves: Vec<[u8; 32]> = entries.iter().map(|&(status, result)| outcome_leaf(status, result)).collect();
+            if merkle_root(&leaves) != root {
+                msg!("Batch journal's Merkle root does not match its outcomes");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            Some(root)
+        } else {
+            None
+        };
+        Ok(BatchJournal { input_digest, merkle_root, outcomes, entries })
+    }
+
+    /// The proof that the outcome at `index` is committed to by the Merkle
+    /// root, `None` when it is out of range
+    pub fn inclusion_proof(&self, index: usize) -> Option<InclusionProof> {
+        let &(status, result) = self.entries.get(index)?;
+        let leaves: Vec<[u8; 32]> = self.entries.iter().map(|&(status, result)| outcome_leaf(status, result)).collect();
+        InclusionProof::new(&leaves, index, status, result)
+    }
+}
+
+/// The decoded journal of a wide frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WideJournal {
+    /// Width of the operands and result in bytes: 16 for `i128`, 32 for 256-bit
+    pub width: u8,
+    /// The result, sign-extended to 256 bits, or why the guest couldn't compute one
+    pub outcome: Result<I256, FailureReason>,
+    /// SHA-256 digest of the input frame
+    pub input_digest: [u8; 32],
+}
+
+impl WideJournal {
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        if journal.len() < 3 || journal[0] != JOURNAL_WIDE_VERSION_1 {
+            msg!("Journal is not a wide journal");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let width = journal[2];
+        let result_bytes = width as usize;
+        if !matches!(result_bytes, 16 | 32) || journal.len() != 3 + result_bytes + 32 {
+            msg!("Wide journal of {} bytes does not hold a {}-byte result", journal.len(), width);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let bytes = &journal[3..3 + result_bytes];
+        let result = match result_bytes {
+            16 => I256::from(i128::read_le(bytes)),
+            _ => I256::read_le(bytes),
+        };
+        let mut input_digest = [0u8; 32];
+        input_digest.copy_from_slice(&journal[3 + result_bytes..]);
+        Ok(WideJournal { width, outcome: outcome(journal[1], result)?, input_digest })
+    }
+}
+
+/// The decoded journal of a decimal frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecimalJournal {
+    /// The result at the requested scale, or why the guest couldn't compute one
+    pub outcome: Result<Decimal, FailureReason>,
+    /// SHA-256 digest of the input frame
+    pub input_digest: [u8; 32],
+}
+
+impl DecimalJournal {
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        if journal.len() != JOURNAL_DECIMAL_V1_LEN || journal[0] != JOURNAL_DECIMAL_VERSION_1 {
+            msg!("Journal of {} bytes is not a decimal journal", journal.len());
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&journal[2..10]);
+        let result = Decimal { value: i64::from_le_bytes(value), scale: journal[10] };
+        let mut input_digest = [0u8; 32];
+        input_digest.copy_from_slice(&journal[11..]);
+        Ok(DecimalJournal { outcome: outcome(journal[1], result)?, input_digest })
+    }
+}
+
+/// The decoded journal of a private-operand range frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeJournal {
+    /// Whether the result is within the frame's bounds, or why the guest
+    /// couldn't compute it
+    pub outcome: Result<bool, FailureReason>,
+    /// SHA-256 digest of the public part of the input frame, without the
+    /// private operand
+    pub input_digest: [u8; 32],
+}
+
+impl RangeJournal {
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        if journal.len() != JOURNAL_RANGE_V1_LEN || journal[0] != JOURNAL_RANGE_VERSION_1 {
+            msg!("Journal of {} bytes is not a range journal", journal.len());
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let within = match journal[2] {
+            0 => false,
+            1 => true,
+            flag => {
+                msg!("Range journal has an invalid bounds flag {}", flag);
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        };
+        let mut input_digest = [0u8; 32];
+        input_digest.copy_from_slice(&journal[3..]);
+        Ok(RangeJournal { outcome: outcome(journal[1], within)?, input_digest })
+    }
+}
+
+/// The decoded journal of a chained frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChainedJournal {
+    /// The result of applying the frame's operation to `previous_result`, or
+    /// why the guest couldn't compute one
+    pub outcome: Result<i64, FailureReason>,
+    /// The result of the previous journal the calculation started from
+    pub previous_result: i64,
+    /// SHA-256 digest of the previous journal
+    pub previous_journal_digest: [u8; 32],
+    /// SHA-256 digest of the input frame
+    pub input_digest: [u8; 32],
+}
+
+impl ChainedJournal {
+    pub fn parse(journal: &[u8]) -> Result<Self, ProgramError> {
+        if journal.len() != JOURNAL_CHAINED_V1_LEN || journal[0] != JOURNAL_CHAINED_VERSION_1 {
+            msg!("Journal of {} bytes is not a chained journal", journal.len());
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut result = [0u8; 8];
+        result.copy_from_slice(&journal[2..10]);
+        let mut previous_result = [0u8; 8];
+        previous_result.copy_from_slice(&journal[10..18]);
+        let mut previous_journal_digest = [0u8; 32];
+        previous_journal_digest.copy_from_slice(&journal[18..50]);
+        let mut input_digest = [0u8; 32];
+        input_digest.copy_from_slice(&journal[50..]);
+        Ok(ChainedJournal {
+            outcome: outcome(journal[1], i64::from_le_bytes(result))?,
+            previous_result: i64::from_le_bytes(previous_result),
+            previous_journal_digest,
+            input_digest,
+        })
+    }
+
+    /// Whether this calculation started from `previous_journal`, which the
+    /// caller has verified, e.g. as the journal stored by an earlier callback
+    pub fn extends(&self, previous_journal: &[u8]) -> bool {
+        hash(previous_journal).to_bytes() == self.previous_journal_digest
+    }
+}
+
+/// The result a journal status reports, or why the guest couldn't compute one
+fn outcome<T>(status: u8, result: T) -> Result<Result<T, FailureReason>, ProgramError> {
+    match status {
+        JOURNAL_STATUS_OK => Ok(Ok(result)),
+        JOURNAL_STATUS_DIV_BY_ZERO => Ok(Err(FailureReason::DivisionByZero)),
+        JOURNAL_STATUS_OVERFLOW => Ok(Err(FailureReason::Overflow)),
+        JOURNAL_STATUS_UNKNOWN_OP => Ok(Err(FailureReason::UnknownOperation)),
+        JOURNAL_STATUS_INVALID_OPERAND => Ok(Err(FailureReason::InvalidOperand)),
+        JOURNAL_STATUS_MALFORMED_INPUT => Ok(Err(FailureReason::MalformedInput)),
+        status => {
+            msg!("Journal reports unknown status {}", status);
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// The result of a legacy journal: a space-padded decimal string
+fn parse_legacy(journal: &[u8]) -> Result<i64, ProgramError> {
+    let text = core::str::from_utf8(journal).map_err(|_| {
+        msg!("Journal is not valid UTF-8");
+        ProgramError::InvalidInstructionData
+    })?;
+    text.trim_end().parse::<i64>().map_err(|_| {
+        msg!("Journal does not contain an i64 result: {:?}", text);
+        ProgramError::InvalidInstructionData
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::JOURNAL_SEMANTICS_WRAPPED;
+
+    fn journal(status: u8, result: i64, cycles: Option<u64>, semantics: Option<u8>) -> GuestJournal {
+        GuestJournal { status, result, input_digest: Some([7; 32]), cycles, semantics }
+    }
+
+    #[test]
+    fn every_version_decodes_its_outcome() {
+        let versions = [
+            (journal(JOURNAL_STATUS_OK, -42, None, None), JOURNAL_VERSION_1, JOURNAL_V1_LEN),
+            (journal(JOURNAL_STATUS_OK, -42, Some(1_234), None), JOURNAL_METERED_VERSION_1, JOURNAL_METERED_V1_LEN),
+            (
+                journal(JOURNAL_STATUS_OK, -42, Some(1_234), Some(JOURNAL_SEMANTICS_WRAPPED)),
+                JOURNAL_METERED_VERSION_2,
+                JOURNAL_METERED_V2_LEN,
+            ),
+        ];
+        for (guest, version, len) in versions {
+            let bytes = guest.encode();
+            assert_eq!((bytes[0], bytes.len()), (version, len));
+            assert_eq!(GuestJournal::parse(&bytes), Ok(guest));
+            assert_eq!(decode_outcome(&bytes), Ok(Ok(-42)));
+        }
+    }
+
+    #[test]
+    fn legacy_decimal_strings_decode() {
+        let padded = format!("{:<32}", -9_000_000_000i64);
+        let guest = GuestJournal::parse(padded.as_bytes()).unwrap();
+        assert_eq!((guest.status, guest.result, guest.input_digest), (JOURNAL_STATUS_OK, -9_000_000_000, None));
+        assert_eq!(decode_journal(b"42"), Ok(42));
+        assert_eq!(decode_journal(b"4 2"), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(decode_journal(b"99999999999999999999"), Err(ProgramError::InvalidInstructionData));
+    }
+
+    #[test]
+    fn statuses_decode_to_failure_reasons() {
+        let reasons = [
+            (JOURNAL_STATUS_DIV_BY_ZERO, FailureReason::DivisionByZero),
+            (JOURNAL_STATUS_OVERFLOW, FailureReason::Overflow),
+            (JOURNAL_STATUS_UNKNOWN_OP, FailureReason::UnknownOperation),
+            (JOURNAL_STATUS_INVALID_OPERAND, FailureReason::InvalidOperand),
+            (JOURNAL_STATUS_MALFORMED_INPUT, FailureReason::MalformedInput),
+        ];
+        for (status, reason) in reasons {
+            let bytes = journal(status, 0, Some(1), Some(0)).encode();
+            assert_eq!(decode_outcome(&bytes), Ok(Err(reason)));
+            assert_eq!(decode_journal(&bytes), Err(ProgramError::InvalidInstructionData));
+        }
+        let unknown = journal(0xff, 0, None, None).encode();
+        assert_eq!(decode_outcome(&unknown), Err(ProgramError::InvalidInstructionData));
+    }
+
+    #[test]
+    fn rejects_journals_of_the_wrong_length_or_version() {
+        let mut bytes = journal(JOURNAL_STATUS_OK, 1, None, None).encode();
+        bytes.push(0);
+        assert_eq!(GuestJournal::parse(&bytes), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(GuestJournal::parse(&[]), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(GuestJournal::parse(&[0xee; JOURNAL_V1_LEN]), Err(ProgramError::InvalidInstructionData));
+    }
+
+    fn batch_journal(version: u8, outcomes: &[(u8, i64)]) -> Vec<u8> {
+        let mut journal = vec![version, outcomes.len() as u8];
+        journal.extend_from_slice(&[3; 32]);
+        if version == JOURNAL_BATCH_VERSION_2 {
+            let leaves: Vec<[u8; 32]> = outcomes.iter().map(|&(status, result)| outcome_leaf(status, result)).collect();
+            journal.extend_from_slice(&merkle_root(&leaves));
+        }
+        for &(status, result) in outcomes {
+            journal.push(status);
+            journal.extend_from_slice(&result.to_le_bytes());
+        }
+        journal
+    }
+
+    #[test]
+    fn batch_journals_check_their_root_and_prove_each_outcome() {
+        let outcomes = [(JOURNAL_STATUS_OK, 5), (JOURNAL_STATUS_DIV_BY_ZERO, 0), (JOURNAL_STATUS_OK, -1)];
+        let batch = BatchJournal::parse(&batch_journal(JOURNAL_BATCH_VERSION_2, &outcomes)).unwrap();
+        assert_eq!(batch.outcomes, vec![Ok(5), Err(FailureReason::DivisionByZero), Ok(-1)]);
+        let root = batch.merkle_root.unwrap();
+        for index in 0..outcomes.len() {
+            assert!(batch.inclusion_proof(index).unwrap().verify(&root));
+        }
+        assert_eq!(batch.inclusion_proof(outcomes.len()), None);
+
+        // Version 1 carries no root
+        let legacy = BatchJournal::parse(&batch_journal(JOURNAL_BATCH_VERSION_1, &outcomes)).unwrap();
+        assert_eq!((legacy.merkle_root, legacy.outcomes), (None, batch.outcomes));
+
+        let mut tampered = batch_journal(JOURNAL_BATCH_VERSION_2, &outcomes);
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(BatchJournal::parse(&tampered), Err(ProgramError::InvalidInstructionData));
+        let mut truncated = batch_journal(JOURNAL_BATCH_VERSION_2, &outcomes);
+        truncated.pop();
+        assert_eq!(BatchJournal::parse(&truncated), Err(ProgramError::InvalidInstructionData));
+    }
+}
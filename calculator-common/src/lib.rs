@@ -0,0 +1,44 @@
+//! Types shared by the native calculator program, its Anchor port, the ZK
+//! guest and the client
+//!
+//! `constants` holds the frame and journal layouts and the account limits
+//! every component agrees on, and builds without any Solana dependency. With
+//! the default `solana` feature the crate also has the program's instructions
+//! and accounts, its execution ID derivation, the encoding of the guest's
+//! input frames and the decoding of its journals, so the programs and the
+//! client can't drift apart on any of them.
+
+pub mod constants;
+
+#[cfg(feature = "solana")]
+pub mod codec;
+#[cfg(feature = "solana")]
+pub mod frame;
+#[cfg(feature = "solana")]
+pub mod instruction;
+#[cfg(feature = "solana")]
+pub mod journal;
+#[cfg(feature = "solana")]
+pub mod merkle;
+#[cfg(feature = "solana")]
+pub mod state;
+
+#[cfg(feature = "solana")]
+pub use frame::{
+    encode_batch, encode_chained, encode_decimal, encode_expression, encode_private_range, encode_single,
+    encode_statistics, encode_wide, format_expression, version_header,
+};
+#[cfg(feature = "solana")]
+pub use instruction::CalculatorInstruction;
+#[cfg(feature = "solana")]
+pub use journal::{
+    decode_journal, decode_outcome, BatchJournal, ChainedJournal, DecimalJournal, GuestJournal, RangeJournal,
+    WideJournal,
+};
+#[cfg(feature = "solana")]
+pub use state::{
+    calculation_address, derive_execution_id, derive_retry_execution_id, requester_stats_address, treasury_address,
+    variable_address, CalculationRecord, CalculationStatus, CalculatorConfig, CalculatorState, CallbackAccount,
+    ExprToken, FailureReason, HistoryEntry, HistorySnapshot, ProverVersion, RequesterStats, ResultBounds,
+    ResultHookData, StateCounters, TokenFee, Variable, MAX_HISTORY_LIMIT, MEMO_PROGRAM_ID, RESULT_HOOK_DISCRIMINATOR,
+};
@@ -0,0 +1,169 @@
+//! Merkle commitments over the outcomes of a batch journal
+//!
+//! Version 2 batch journals carry the root of a binary SHA-256 Merkle tree
+//! whose leaves are the operation outcomes in frame order, so the callback
+//! program of a direct Bonsol execution can store the 32-byte root and anyone
+//! holding the journal can later prove a single outcome against it. The
+//! calculator program's own callback rejects batch journals. The guest builds
+//! the same tree:
+//!
+//! - a leaf is `sha256(0x00 || status || result)`, with the result as a
+//!   little-endian `i64`, the 9 bytes the journal holds for the outcome
+//! - an inner node is `sha256(0x01 || left || right)`; the prefixes keep a
+//!   leaf from passing as a node
+//! - a level with an odd number of nodes carries its last node up unhashed
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::hashv;
+
+use crate::constants::{MERKLE_LEAF_PREFIX, MERKLE_NODE_PREFIX};
+
+/// The leaf committing to an operation's status and result
+pub fn outcome_leaf(status: u8, result: i64) -> [u8; 32] {
+    hashv(&[&[MERKLE_LEAF_PREFIX], &[status], &result.to_le_bytes()]).to_bytes()
+}
+
+fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[MERKLE_NODE_PREFIX], left, right]).to_bytes()
+}
+
+// The level above `level`, pairing its nodes from the left
+fn parents(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node(left, right),
+            _ => pair[0],
+        })
+        .collect()
+}
+
+/// The root of the tree over `leaves`, which must not be empty
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parents(&level);
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+/// A proof that one outcome of a batch is committed to by its Merkle root
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InclusionProof {
+    /// Position of the operation in the batch frame
+    pub index: u8,
+    /// Number of operations in the batch
+    pub count: u8,
+    pub status: u8,
+    pub result: i64,
+    /// Sibling of each node on the path from the leaf to the root, skipping
+    /// the levels where the node is carried up without one
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+    /// Builds the proof for the leaf at `index`, `None` when it is out of range
+    pub fn new(leaves: &[[u8; 32]], index: usize, status: u8, result: i64) -> Option<Self> {
+        if index >= leaves.len() || leaves.len() > u8::MAX as usize {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut position = index;
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            level = parents(&level);
+            position /= 2;
+        }
+        Some(InclusionProof { index: index as u8, count: leaves.len() as u8, status, result, siblings })
+    }
+
+    /// Whether the proof leads from its outcome to `root`
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        if self.index >= self.count {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = outcome_leaf(self.status, self.result);
+        let mut position = self.index as usize;
+        let mut width = self.count as usize;
+        while width > 1 {
+            if position % 2 == 1 {
+                let Some(left) = siblings.next() else { return false };
+                hash = node(left, &hash);
+            } else if position + 1 < width {
+                let Some(right) = siblings.next() else { return false };
+                hash = node(&hash, right);
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none() && hash == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: usize) -> Vec<[u8; 32]> {
+        (0..count).map(|index| outcome_leaf(0, index as i64)).collect()
+    }
+
+    #[test]
+    fn roots_pair_from_the_left_and_carry_odd_nodes_up() {
+        let leaves = leaves(3);
+        assert_eq!(merkle_root(&leaves[..1]), leaves[0]);
+        assert_eq!(merkle_root(&leaves[..2]), node(&leaves[0], &leaves[1]));
+        assert_eq!(merkle_root(&leaves), node(&node(&leaves[0], &leaves[1]), &leaves[2]));
+    }
+
+    #[test]
+    fn leaves_and_nodes_hash_apart() {
+        assert_ne!(outcome_leaf(0, 1), outcome_leaf(1, 1));
+        assert_ne!(outcome_leaf(0, 1), outcome_leaf(0, 2));
+        // A node over two leaves never equals a leaf over the same bytes
+        let (left, right) = (outcome_leaf(0, 1), outcome_leaf(0, 2));
+        assert_ne!(node(&left, &right), hashv(&[&[MERKLE_LEAF_PREFIX], &left, &right]).to_bytes());
+    }
+
+    #[test]
+    fn every_outcome_proves_against_the_root() {
+        for count in 1..=16 {
+            let leaves = leaves(count);
+            let root = merkle_root(&leaves);
+            for index in 0..count {
+                let proof = InclusionProof::new(&leaves, index, 0, index as i64).unwrap();
+                assert!(proof.verify(&root), "leaf {} of {}", index, count);
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_fail() {
+        let leaves = leaves(5);
+        let root = merkle_root(&leaves);
+        let proof = InclusionProof::new(&leaves, 2, 0, 2).unwrap();
+
+        assert!(!InclusionProof { result: 3, ..proof.clone() }.verify(&root));
+        assert!(!InclusionProof { status: 1, ..proof.clone() }.verify(&root));
+        assert!(!InclusionProof { index: 3, ..proof.clone() }.verify(&root));
+        assert!(!InclusionProof { index: 5, ..proof.clone() }.verify(&root));
+        let mut siblings = proof.siblings.clone();
+        siblings.pop();
+        assert!(!InclusionProof { siblings, ..proof.clone() }.verify(&root));
+        let mut siblings = proof.siblings.clone();
+        siblings.push(root);
+        assert!(!InclusionProof { siblings, ..proof.clone() }.verify(&root));
+        assert!(!proof.verify(&leaves[0]));
+    }
+
+    #[test]
+    fn out_of_range_leaves_have_no_proof() {
+        assert_eq!(InclusionProof::new(&leaves(3), 3, 0, 0), None);
+        assert_eq!(InclusionProof::new(&[], 0, 0, 0), None);
+    }
+}
@@ -0,0 +1,440 @@
+//! Accounts the calculator program owns, the return data it produces, the
+//! PDAs it derives and the Bonsol execution IDs it assigns
+//!
+//! Accounts are stored with the length-prefixed Borsh encoding of `codec`, so
+//! a client decodes a fetched `CalculatorState` or `CalculationRecord` with
+//! `codec::decode` exactly as the program loads it.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use calculator_ops::Operation;
+use solana_program::{
+    hash::{hashv, Hash},
+    program::MAX_RETURN_DATA,
+    pubkey::Pubkey,
+};
+
+use crate::constants::{
+    CALCULATION_SEED, IMAGE_ID_LEN, MAX_CALLBACK_EXTRA_ACCOUNTS, MAX_EXECUTION_ID_LEN, MAX_EXPRESSION_TOKENS,
+    MAX_JOURNAL_LEN, MAX_LABEL_LEN, MAX_OWNERS, MAX_VARIABLE_NAME_LEN, REQUESTER_SEED, TREASURY_SEED, VARIABLE_SEED,
+};
+use crate::frame::{encode_expression, encode_single, format_expression};
+
+/// Instruction data prefix for result hook invocations
+pub const RESULT_HOOK_DISCRIMINATOR: [u8; 8] = *b"calchook";
+
+/// SPL Memo program (v2), used for result summaries
+pub const MEMO_PROGRAM_ID: Pubkey = solana_program::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct CalculatorState {
+    // The leading fixed-size fields are mirrored by `StateCounters`
+    pub is_initialized: bool,
+    pub calculation_count: u64,
+    pub pending_count: u64,
+    pub treasury_bump: u8,
+    /// Owner multisig; `threshold` of these keys must sign admin instructions
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub config: CalculatorConfig,
+    /// Guest image requested for new executions, replaced by `SetImageId`
+    pub image_id: String,
+    /// While set, new submissions and retries are rejected
+    pub paused: bool,
+}
+
+/// Owner-controlled settings, replaced as a whole by `Configure`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct CalculatorConfig {
+    /// Protocol fee transferred from the payer to the treasury on every submission
+    pub fee_lamports: u64,
+    /// When false only owners may submit calculations
+    pub open_submissions: bool,
+    /// SPL token fee transferred to the treasury's associated token account,
+    /// charged in addition to `fee_lamports`
+    pub token_fee: Option<TokenFee>,
+    /// Share of `fee_lamports`, in basis points, paid to a submission's referrer on completion
+    pub referral_share_bps: u16,
+    /// Largest operand magnitude `SubmitLocalCalculation` computes on-chain; 0 disables it
+    pub local_operand_limit: u64,
+    /// Issue an SPL Memo summarizing each completed calculation
+    pub emit_memo: bool,
+    /// Require the requester to sign `SubmitCalculation`; otherwise a relayer
+    /// may pay for and submit calculations attributed to any requester
+    pub requester_must_sign: bool,
+    /// Prover version requested for submissions that don't pin one; `None`
+    /// leaves the choice to Bonsol
+    pub prover_version: Option<ProverVersion>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct TokenFee {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// Per-calculation account, a PDA derived from the state account and the
+/// calculation's index (see `calculation_address`)
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct CalculationRecord {
+    pub state: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    pub execution_id: String,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: Option<i64>,
+    pub timestamp: i64,
+    pub is_complete: bool,
+    pub callback_accounts: Vec<CallbackAccount>,
+    pub result_hook: Option<Pubkey>,
+    pub manual_finalize: bool,
+    pub pending_journal: Option<Vec<u8>>,
+    pub submitted_slot: u64,
+    pub completed_slot: Option<u64>,
+    pub fee_lamports: u64,
+    pub token_fee_amount: u64,
+    pub referrer: Option<Pubkey>,
+    pub referral_share_bps: u16,
+    pub referral_lamports: u64,
+    pub expiration_slot: u64,
+    /// Execution this calculation retries, if any
+    pub parent_execution_id: Option<String>,
+    pub retry_count: u8,
+    /// Set once a `RetryCalculation` has superseded this execution
+    pub retried: bool,
+    /// Inclusive range the result must fall in to be accepted
+    pub result_bounds: Option<ResultBounds>,
+    /// Why the calculation was completed without a result
+    pub failure: Option<FailureReason>,
+    /// Computed on-chain by `SubmitLocalCalculation` instead of proven through Bonsol
+    pub is_local: bool,
+    /// Memo program is forwarded to the callback and receives a result summary
+    pub emit_memo: bool,
+    /// Account the calculation is attributed to
+    pub requester: Pubkey,
+    /// Account that paid for the submission (or the retry)
+    pub payer: Pubkey,
+    /// Prover version the execution was requested with; retries keep it
+    pub prover_version: Option<ProverVersion>,
+    /// Submitter-provided description, at most `MAX_LABEL_LEN` bytes
+    pub label: Option<String>,
+    /// Raw guest journal `result` was decoded from (see `decode_journal`),
+    /// kept so the decoding can be re-verified from on-chain data
+    pub journal: Option<Vec<u8>>,
+    /// RPN program of a `SubmitExpression` calculation, whose `operation` is
+    /// `OP_EXPRESSION`; empty for single-operation calculations
+    pub expression: Vec<ExprToken>,
+    /// Variable `operand_a` was resolved from at submission, if any
+    pub operand_a_variable: Option<String>,
+    /// Variable `operand_b` was resolved from at submission, if any
+    pub operand_b_variable: Option<String>,
+    /// SHA-256 digest of the input frame sent to Bonsol, which the callback
+    /// compares with the digest the guest commits; `None` for local calculations
+    pub input_hash: Option<[u8; 32]>,
+}
+
+/// Per-requester submission counters, a PDA derived from the state account and
+/// the requester (see `requester_stats_address`)
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct RequesterStats {
+    pub state: Pubkey,
+    pub requester: Pubkey,
+    pub bump: u8,
+    /// Calculations submitted through Bonsol, not counting retries
+    pub calculation_count: u64,
+    /// Calculations computed on-chain by `SubmitLocalCalculation`
+    pub local_count: u64,
+    pub last_submitted_slot: u64,
+}
+
+/// Named value curated by the owners, a PDA derived from the state account and
+/// the name (see `variable_address`)
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Variable {
+    pub state: Pubkey,
+    pub name: String,
+    pub bump: u8,
+    pub value: i64,
+    pub updated_slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ResultBounds {
+    pub min: i64,
+    pub max: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FailureReason {
+    /// The proven result fell outside the submission's `result_bounds`
+    ResultOutOfBounds,
+    /// The guest proved the calculation divides by zero
+    DivisionByZero,
+    /// The guest proved the calculation overflows an `i64`
+    Overflow,
+    /// The guest proved the calculation uses an unknown operation code
+    UnknownOperation,
+    /// The guest proved an operand is outside the operation's domain, e.g. a negative exponent
+    InvalidOperand,
+    /// The guest proved it read an input frame it couldn't evaluate
+    MalformedInput,
+}
+
+/// Token of an RPN program submitted through `SubmitExpression`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ExprToken {
+    /// Push a literal onto the stack
+    Push(i64),
+    /// Pop `b`, then `a`, and push `a op b` for one of the `OP_*` codes
+    Op(i64),
+    /// Push a copy of the top value
+    Dup,
+    /// Swap the top two values
+    Swap,
+}
+
+/// Bonsol prover version an execution can be pinned to
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ProverVersion {
+    V1_0_1,
+}
+
+/// Additional account forwarded to the callback alongside the state account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct CallbackAccount {
+    pub pubkey: Pubkey,
+    pub is_writable: bool,
+}
+
+impl CalculatorState {
+    // Maximum Borsh payload; accounts also hold the codec length prefix
+    // bool + u64 + u64 + treasury bump + owner list + threshold + config + image id + paused flag
+    pub const LEN: usize = 1 + 8 + 8 + 1 + 4 + MAX_OWNERS * 32 + 1 + CalculatorConfig::LEN
+        + 4 + IMAGE_ID_LEN + 1;
+
+    pub fn is_owner(&self, key: &Pubkey) -> bool {
+        self.owners.contains(key)
+    }
+
+    /// The fixed-size fields `StateCounters` mirrors
+    pub fn counters(&self) -> StateCounters {
+        StateCounters {
+            is_initialized: self.is_initialized,
+            calculation_count: self.calculation_count,
+            pending_count: self.pending_count,
+            treasury_bump: self.treasury_bump,
+        }
+    }
+}
+
+/// Leading fields of `CalculatorState`, read and written in place by handlers
+/// that only move the counters
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct StateCounters {
+    pub is_initialized: bool,
+    pub calculation_count: u64,
+    pub pending_count: u64,
+    pub treasury_bump: u8,
+}
+
+impl CalculatorConfig {
+    // u64 + bool + optional token fee + u16 + u64 + bool + bool + optional prover version
+    pub const LEN: usize = 8 + 1 + 1 + TokenFee::LEN + 2 + 8 + 1 + 1 + 2;
+}
+
+impl TokenFee {
+    pub const LEN: usize = 32 + 8; // pubkey + u64
+}
+
+/// Return data of `GetHistory`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct HistorySnapshot {
+    pub calculation_count: u64,
+    pub pending_count: u64,
+    /// Index of the first calculation in the window
+    pub offset: u32,
+    /// Entries of the window in index order; pruned records are left out
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub index: u64,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: Option<i64>,
+    pub status: CalculationStatus,
+    pub label: Option<String>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CalculationStatus {
+    Pending,
+    AwaitingFinalize,
+    Complete,
+    Local,
+    Failed(FailureReason),
+}
+
+impl HistorySnapshot {
+    // u64 + u64 + u32 + vec length
+    pub const HEADER_LEN: usize = 8 + 8 + 4 + 4;
+}
+
+impl HistoryEntry {
+    // Upper bound: index + operation/operands + optional result + status with
+    // failure reason + optional label
+    pub const LEN: usize = 8 + 8 * 3 + 9 + 2 + 1 + 4 + MAX_LABEL_LEN;
+}
+
+// Largest `GetHistory` window whose snapshot fits in return data
+pub const MAX_HISTORY_LIMIT: u8 = ((MAX_RETURN_DATA - HistorySnapshot::HEADER_LEN) / HistoryEntry::LEN) as u8;
+
+impl From<&CalculationRecord> for HistoryEntry {
+    fn from(calc: &CalculationRecord) -> Self {
+        let status = if let Some(reason) = calc.failure {
+            CalculationStatus::Failed(reason)
+        } else if calc.is_local {
+            CalculationStatus::Local
+        } else if calc.is_complete {
+            CalculationStatus::Complete
+        } else if calc.pending_journal.is_some() {
+            CalculationStatus::AwaitingFinalize
+        } else {
+            CalculationStatus::Pending
+        };
+
+        HistoryEntry {
+            index: calc.index,
+            operation: calc.operation,
+            operand_a: calc.operand_a,
+            operand_b: calc.operand_b,
+            result: calc.result,
+            status,
+            label: calc.label.clone(),
+        }
+    }
+}
+
+/// Payload passed to a result hook program when a calculation completes
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ResultHookData {
+    pub execution_id: String,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: i64,
+}
+
+impl CalculationRecord {
+    // state + index + bump + execution id + operation/operands + optional result
+    // + timestamp + completion flag + callback account list + optional hook
+    // + manual finalize flag + optional pending journal + submitted/completed slots + fees + referral
+    // + expiration slot + retry lineage + optional bounds + optional failure reason + local flag
+    // + memo flag + requester + payer + ... + optional input hash
+    pub const LEN: usize = 32 + 8 + 1 + 4 + MAX_EXECUTION_ID_LEN + 8 * 3 + 9 + 8 + 1
+        + 4 + MAX_CALLBACK_EXTRA_ACCOUNTS * CallbackAccount::LEN + 33
+        + 1 + 1 + 4 + MAX_JOURNAL_LEN + 8 + 9 + 8 + 8 + 33 + 2 + 8
+        + 8 + 1 + 4 + MAX_EXECUTION_ID_LEN + 1 + 1 + 1 + ResultBounds::LEN + 2 + 1
+        + 1 + 32 + 32 + 2 + 1 + 4 + MAX_LABEL_LEN + 1 + 4 + MAX_JOURNAL_LEN
+        + 4 + MAX_EXPRESSION_TOKENS * ExprToken::LEN + 2 * (1 + 4 + MAX_VARIABLE_NAME_LEN) + 33;
+
+    /// Human-readable form of the calculation, `a op b`, `op(a, b)` or the infix expression
+    pub fn describe(&self) -> String {
+        if !self.expression.is_empty() {
+            return format_expression(&self.expression);
+        }
+
+        Operation { op: self.operation, a: self.operand_a, b: self.operand_b }.to_string()
+    }
+
+    /// The guest input frame of the operation or expression; see `encode_single` and `encode_expression`
+    pub fn input(&self) -> Vec<u8> {
+        if !self.expression.is_empty() {
+            return encode_expression(&self.expression);
+        }
+
+        encode_single(self.operation, self.operand_a, self.operand_b)
+    }
+
+    /// Slots between submission and the prover's callback, once it has landed
+    pub fn proving_latency_slots(&self) -> Option<u64> {
+        self.completed_slot
+            .map(|completed_slot| completed_slot.saturating_sub(self.submitted_slot))
+    }
+}
+
+impl ResultBounds {
+    pub const LEN: usize = 8 + 8; // i64 + i64
+
+    pub fn contains(&self, value: i64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl CallbackAccount {
+    pub const LEN: usize = 32 + 1; // pubkey + bool
+}
+
+impl ExprToken {
+    pub const LEN: usize = 1 + 8; // variant + i64
+}
+
+impl RequesterStats {
+    // state + requester + bump + counters + slot
+    pub const LEN: usize = 32 + 32 + 1 + 8 + 8 + 8;
+}
+
+impl Variable {
+    // state + name + bump + value + slot
+    pub const LEN: usize = 32 + 4 + MAX_VARIABLE_NAME_LEN + 1 + 8 + 8;
+}
+
+/// Derives the record PDA for the `index`-th calculation submitted to `state`
+pub fn calculation_address(program_id: &Pubkey, state: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CALCULATION_SEED, state.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Derives the treasury PDA collecting protocol fees for `state`
+pub fn treasury_address(program_id: &Pubkey, state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREASURY_SEED, state.as_ref()], program_id)
+}
+
+/// Derives the PDA holding the variable `name` of `state`
+pub fn variable_address(program_id: &Pubkey, state: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VARIABLE_SEED, state.as_ref(), name.as_bytes()], program_id)
+}
+
+/// Derives the counter PDA for calculations `requester` submitted to `state`
+pub fn requester_stats_address(program_id: &Pubkey, state: &Pubkey, requester: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REQUESTER_SEED, state.as_ref(), requester.as_ref()], program_id)
+}
+
+/// Derives a unique execution ID from `(state pubkey, calculation_count, slot)`
+///
+/// The ID is the hex encoding of the first 8 bytes of the SHA-256 digest, so
+/// it is exactly `MAX_EXECUTION_ID_LEN` bytes long.
+pub fn derive_execution_id(state_key: &Pubkey, calculation_count: u64, slot: u64) -> String {
+    short_hex_id(&hashv(&[
+        state_key.as_ref(),
+        &calculation_count.to_le_bytes(),
+        &slot.to_le_bytes(),
+    ]))
+}
+
+/// Derives the execution ID for the `retry_count`-th retry of `parent_execution_id`
+pub fn derive_retry_execution_id(parent_execution_id: &str, retry_count: u8) -> String {
+    short_hex_id(&hashv(&[parent_execution_id.as_bytes(), &[retry_count]]))
+}
+
+fn short_hex_id(digest: &Hash) -> String {
+    digest.to_bytes()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
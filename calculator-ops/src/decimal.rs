@@ -0,0 +1,170 @@
+//! Fixed-point decimal arithmetic
+//!
+//! A `Decimal` is an `i64` count of `10^-scale` units, so `2.50` is 250 at
+//! scale 2. `apply_decimal` computes the exact result of adding, subtracting,
+//! multiplying or dividing two decimals of any scales, then rounds it to the
+//! requested result scale half to even (banker's rounding), so the same
+//! inputs always give the same result.
+
+use core::fmt;
+
+use crate::{OpError, WideInt, I256, OP_ADD, OP_DIVIDE, OP_MULTIPLY, OP_SUBTRACT};
+
+/// Largest scale of a decimal; `10^18` is the largest power of ten in an `i64`
+pub const MAX_SCALE: u8 = 18;
+
+/// `value * 10^-scale`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    pub value: i64,
+    pub scale: u8,
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(unit) = 10u64.checked_pow(self.scale as u32) else {
+            return write!(f, "{}e-{}", self.value, self.scale);
+        };
+        let sign = if self.value < 0 { "-" } else { "" };
+        let magnitude = self.value.unsigned_abs();
+        match self.scale {
+            0 => write!(f, "{}{}", sign, magnitude),
+            scale => write!(f, "{}{}.{:0width$}", sign, magnitude / unit, magnitude % unit, width = scale as usize),
+        }
+    }
+}
+
+/// Computes `a op b` for add, subtract, multiply and divide, rounded half to even to `scale`
+pub fn apply_decimal(op: i64, a: Decimal, b: Decimal, scale: u8) -> Result<Decimal, OpError> {
+    if a.scale > MAX_SCALE || b.scale > MAX_SCALE || scale > MAX_SCALE {
+        return Err(OpError::InvalidOperand);
+    }
+    let x = I256::from(a.value as i128);
+    let y = I256::from(b.value as i128);
+
+    // The exact result is `numerator / denominator` units of the result
+    // scale. Operands and scales are bounded so far below 2^255 that these
+    // products can't overflow, but they are checked all the same
+    let (numerator, denominator) = match op {
+        OP_ADD | OP_SUBTRACT => {
+            let common = a.scale.max(b.scale);
+            let x = x.checked_mul(power_of_ten(common - a.scale));
+            let y = y.checked_mul(power_of_ten(common - b.scale));
+            let sum = match (x, y) {
+                (Some(x), Some(y)) if op == OP_ADD => x.checked_add(y),
+                (Some(x), Some(y)) => x.checked_sub(y),
+                _ => None,
+            };
+            (sum.and_then(|sum| sum.checked_mul(power_of_ten(scale))), power_of_ten(common))
+        }
+        OP_MULTIPLY => (
+            x.checked_mul(y).and_then(|product| product.checked_mul(power_of_ten(scale))),
+            power_of_ten(a.scale + b.scale),
+        ),
+        OP_DIVIDE if b.value == 0 => return Err(OpError::DivisionByZero),
+        OP_DIVIDE => (
+            x.checked_mul(power_of_ten(b.scale + scale)),
+            y.checked_mul(power_of_ten(a.scale)).ok_or(OpError::Overflow)?,
+        ),
+        _ => return Err(OpError::UnknownOperation),
+    };
+
+    let value = divide_half_even(numerator.ok_or(OpError::Overflow)?, denominator)?;
+    let value = i128::try_from(value).and_then(|value| i64::try_from(value).map_err(|_| OpError::Overflow))?;
+    Ok(Decimal { value, scale })
+}
+
+fn power_of_ten(exponent: u8) -> I256 {
+    // Exponents are at most 3 * MAX_SCALE, far inside the range
+    I256::from(10).checked_pow(exponent as u32).unwrap_or(I256::ZERO)
+}
+
+// `numerator / denominator` rounded to the nearest integer, ties to the even one
+fn divide_half_even(numerator: I256, denominator: I256) -> Result<I256, OpError> {
+    let (numerator, denominator) = if denominator.is_negative() {
+        (numerator.checked_neg(), denominator.checked_neg())
+    } else {
+        (Some(numerator), Some(denominator))
+    };
+    let (Some(numerator), Some(denominator)) = (numerator, denominator) else {
+        return Err(OpError::Overflow);
+    };
+    let quotient = numerator.checked_div(denominator).ok_or(OpError::DivisionByZero)?;
+    let remainder = numerator.checked_rem(denominator).ok_or(OpError::DivisionByZero)?;
+
+    // Truncation rounded toward zero; step away from zero past the halfway
+    // point, or at it when the quotient is odd
+    let twice_remainder = remainder.checked_mul(I256::from(2)).ok_or(OpError::Overflow)?;
+    let twice_remainder = if twice_remainder.is_negative() {
+        twice_remainder.checked_neg().ok_or(OpError::Overflow)?
+    } else {
+        twice_remainder
+    };
+    let odd = quotient.checked_rem(I256::from(2)) != Some(I256::ZERO);
+    if twice_remainder > denominator || (twice_remainder == denominator && odd) {
+        let step = I256::from(if numerator.is_negative() { -1 } else { 1 });
+        return quotient.checked_add(step).ok_or(OpError::Overflow);
+    }
+    Ok(quotient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OP_MODULO;
+
+    fn decimal(value: i64, scale: u8) -> Decimal {
+        Decimal { value, scale }
+    }
+
+    #[test]
+    fn ties_round_to_even() {
+        // 0.5, 1.5 and -2.5 rounded to whole units
+        let half = decimal(5, 1);
+        assert_eq!(apply_decimal(OP_MULTIPLY, half, decimal(1, 0), 0), Ok(decimal(0, 0)));
+        assert_eq!(apply_decimal(OP_MULTIPLY, decimal(15, 1), decimal(1, 0), 0), Ok(decimal(2, 0)));
+        assert_eq!(apply_decimal(OP_MULTIPLY, decimal(-25, 1), decimal(1, 0), 0), Ok(decimal(-2, 0)));
+        // Past the halfway point rounds away from zero whatever the parity
+        assert_eq!(apply_decimal(OP_DIVIDE, decimal(2, 0), decimal(3, 0), 0), Ok(decimal(1, 0)));
+        assert_eq!(apply_decimal(OP_DIVIDE, decimal(-2, 0), decimal(3, 0), 0), Ok(decimal(-1, 0)));
+    }
+
+    #[test]
+    fn multiplies_the_documented_example() {
+        // 2.50 * 1.075 is 2.6875 exactly
+        let (price, rate) = (decimal(250, 2), decimal(1075, 3));
+        assert_eq!(apply_decimal(OP_MULTIPLY, price, rate, 4), Ok(decimal(26875, 4)));
+        assert_eq!(apply_decimal(OP_MULTIPLY, price, rate, 3), Ok(decimal(2688, 3)));
+        assert_eq!(apply_decimal(OP_MULTIPLY, price, rate, 2), Ok(decimal(269, 2)));
+    }
+
+    #[test]
+    fn aligns_mismatched_scales() {
+        // 1.5 + 0.25 and 1.5 - 0.25
+        assert_eq!(apply_decimal(OP_ADD, decimal(15, 1), decimal(25, 2), 2), Ok(decimal(175, 2)));
+        assert_eq!(apply_decimal(OP_SUBTRACT, decimal(15, 1), decimal(25, 2), 3), Ok(decimal(1250, 3)));
+        // 1 / 0.0003 and 0.75 / 2 at a coarser scale than either operand
+        assert_eq!(apply_decimal(OP_DIVIDE, decimal(1, 0), decimal(3, 4), 1), Ok(decimal(33333, 1)));
+        assert_eq!(apply_decimal(OP_DIVIDE, decimal(75, 2), decimal(2, 0), 1), Ok(decimal(4, 1)));
+    }
+
+    #[test]
+    fn rejects_scales_past_the_maximum() {
+        let one = decimal(1, 0);
+        assert_eq!(apply_decimal(OP_ADD, decimal(1, MAX_SCALE + 1), one, 0), Err(OpError::InvalidOperand));
+        assert_eq!(apply_decimal(OP_ADD, one, decimal(1, MAX_SCALE + 1), 0), Err(OpError::InvalidOperand));
+        assert_eq!(apply_decimal(OP_ADD, one, one, MAX_SCALE + 1), Err(OpError::InvalidOperand));
+        assert_eq!(apply_decimal(OP_ADD, one, decimal(1, MAX_SCALE), MAX_SCALE), Ok(decimal(10i64.pow(18) + 1, 18)));
+    }
+
+    #[test]
+    fn overflows_only_when_the_rounded_result_does() {
+        // About 9.22 squared: the exact product passes i64 but the result fits
+        let max = decimal(i64::MAX, 18);
+        assert_eq!(apply_decimal(OP_MULTIPLY, max, max, 0), Ok(decimal(85, 0)));
+        // Rescaling 9.2e18 units up a digit can't fit
+        assert_eq!(apply_decimal(OP_ADD, decimal(i64::MAX, 0), decimal(0, 0), 1), Err(OpError::Overflow));
+        assert_eq!(apply_decimal(OP_DIVIDE, max, decimal(0, 3), 9), Err(OpError::DivisionByZero));
+        assert_eq!(apply_decimal(OP_MODULO, max, max, 0), Err(OpError::UnknownOperation));
+    }
+}
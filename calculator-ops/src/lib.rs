@@ -0,0 +1,343 @@
+//! Calculator operations shared by the ZK guest, the on-chain programs and
+//! the client
+//!
+//! The guest proves results with `apply`, the programs validate submissions
+//! against `is_operation` and the client computes results natively with the
+//! same function, so an operation code means the same thing everywhere.
+//! Every operation uses checked `i64` math and reports why it can't be
+//! computed instead of panicking. `apply_wide` computes the same operations
+//! on `i128` and 256-bit operands, `apply_decimal` the arithmetic ones on
+//! fixed-point decimals, and `statistic` aggregates over vectors. Operation
+//! codes flagged `FLAG_SATURATING` or `FLAG_WRAPPING` saturate or wrap on
+//! overflow instead of failing. Besides arithmetic, the operations cover the
+//! integer square root and logarithm and basis-point shares in three rounding
+//! modes, which fixed-point token math leans on.
+
+#![cfg_attr(not(test), no_std)]
+
+use core::fmt;
+
+mod decimal;
+mod semantics;
+mod statistics;
+mod wide;
+
+pub use decimal::{apply_decimal, Decimal, MAX_SCALE};
+pub use semantics::{split_operation, Semantics, FLAG_SATURATING, FLAG_WRAPPING};
+pub use statistics::{
+    statistic, STATISTICS, STAT_MAX, STAT_MEAN, STAT_MEDIAN, STAT_MIN, STAT_SUM, STAT_VARIANCE,
+};
+pub use wide::{apply_wide, WideInt, I256};
+
+pub const OP_ADD: i64 = 0;
+pub const OP_SUBTRACT: i64 = 1;
+pub const OP_MULTIPLY: i64 = 2;
+pub const OP_DIVIDE: i64 = 3;
+
+/// Not an operation: the first word of an expression input frame
+pub const OP_EXPRESSION: i64 = 4;
+/// Not an operation: the first word of a batch input frame
+pub const OP_BATCH: i64 = 11;
+/// Not an operation: the first word of a wide-arithmetic input frame
+pub const OP_WIDE: i64 = 12;
+/// Not an operation: the first word of a fixed-point decimal input frame
+pub const OP_DECIMAL: i64 = 13;
+/// Not an operation: the first word of a private-operand range frame
+pub const OP_PRIVATE_RANGE: i64 = 14;
+/// Not an operation: the first word of a statistics input frame
+pub const OP_STATISTICS: i64 = 15;
+/// Not an operation: the first word of a chained input frame, which applies
+/// an operation to the result of a previous journal
+pub const OP_CHAINED: i64 = 16;
+
+/// Remainder of `a / b`, with the sign of `a`
+pub const OP_MODULO: i64 = 5;
+/// `a` to the power of `b`; `b` must not be negative
+pub const OP_POWER: i64 = 6;
+/// Absolute value of `a`; `b` is ignored
+pub const OP_ABS: i64 = 7;
+pub const OP_MIN: i64 = 8;
+pub const OP_MAX: i64 = 9;
+/// Greatest common divisor of `a` and `b`, never negative
+pub const OP_GCD: i64 = 10;
+/// Integer square root of `a`, rounded down; `a` must not be negative and `b` is ignored
+pub const OP_ISQRT: i64 = 17;
+/// Base-2 logarithm of `a`, rounded down; `a` must be positive and `b` is ignored
+pub const OP_LOG2: i64 = 18;
+/// `b` basis points of `a`, `a * b / 10_000`, rounded toward negative infinity
+pub const OP_BPS_FLOOR: i64 = 19;
+/// `b` basis points of `a`, rounded toward positive infinity
+pub const OP_BPS_CEIL: i64 = 20;
+/// `b` basis points of `a`, rounded to the nearest integer, ties to the even one
+pub const OP_BPS_ROUND: i64 = 21;
+
+/// Basis points in a whole
+pub const BPS_DENOMINATOR: i64 = 10_000;
+
+/// Every operation a calculation or expression token can use
+pub const OPERATIONS: [i64; 15] = [
+    OP_ADD, OP_SUBTRACT, OP_MULTIPLY, OP_DIVIDE, OP_MODULO, OP_POWER, OP_ABS, OP_MIN, OP_MAX, OP_GCD, OP_ISQRT,
+    OP_LOG2, OP_BPS_FLOOR, OP_BPS_CEIL, OP_BPS_ROUND,
+];
+
+/// Why an operation can't be computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpError {
+    DivisionByZero,
+    /// The result doesn't fit in an `i64`
+    Overflow,
+    UnknownOperation,
+    /// An operand outside the operation's domain, such as a negative exponent
+    /// or the logarithm of zero
+    InvalidOperand,
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OpError::DivisionByZero => "division by zero",
+            OpError::Overflow => "overflows a 64-bit integer",
+            OpError::UnknownOperation => "unknown operation",
+            OpError::InvalidOperand => "operand out of the operation's domain",
+        })
+    }
+}
+
+/// Whether `op` is an operation, with or without a semantics flag
+pub fn is_operation(op: i64) -> bool {
+    split_operation(op).is_some_and(|(op, _)| OPERATIONS.contains(&op))
+}
+
+/// Number of operands `op` takes from an expression stack
+pub fn arity(op: i64) -> usize {
+    match base_operation(op) {
+        OP_ABS | OP_ISQRT | OP_LOG2 => 1,
+        _ => 2,
+    }
+}
+
+/// Computes `a op b`
+pub fn apply(op: i64, a: i64, b: i64) -> Result<i64, OpError> {
+    apply_with_semantics(op, a, b).map(|(result, _)| result)
+}
+
+/// Computes `a op b`, along with the semantics that gave the result:
+/// `Semantics::Checked` unless the operation overflowed and its flag
+/// saturated or wrapped the result
+pub fn apply_with_semantics(op: i64, a: i64, b: i64) -> Result<(i64, Semantics), OpError> {
+    let (op, semantics) = split_operation(op).ok_or(OpError::UnknownOperation)?;
+    match checked(op, a, b) {
+        Err(OpError::Overflow) => semantics::overflowed(op, a, b, semantics)
+            .map(|result| (result, semantics))
+            .ok_or(OpError::Overflow),
+        result => result.map(|result| (result, Semantics::Checked)),
+    }
+}
+
+// `op` without its semantics flags
+fn base_operation(op: i64) -> i64 {
+    split_operation(op).map_or(op, |(op, _)| op)
+}
+
+fn checked(op: i64, a: i64, b: i64) -> Result<i64, OpError> {
+    let result = match op {
+        OP_ADD => a.checked_add(b),
+        OP_SUBTRACT => a.checked_sub(b),
+        OP_MULTIPLY => a.checked_mul(b),
+        OP_DIVIDE | OP_MODULO if b == 0 => return Err(OpError::DivisionByZero),
+        // Only i64::MIN / -1 and its remainder overflow
+        OP_DIVIDE => a.checked_div(b),
+        OP_MODULO => a.checked_rem(b),
+        OP_POWER if b < 0 => return Err(OpError::InvalidOperand),
+        // Exponents past u32::MAX overflow just as u32::MAX does, except for
+        // bases of -1, 0 and 1, whose powers only depend on the parity
+        OP_POWER => a.checked_pow(u32::try_from(b).unwrap_or(u32::MAX - (b & 1 == 0) as u32)),
+        // |i64::MIN| is one past i64::MAX
+        OP_ABS => a.checked_abs(),
+        OP_MIN => Some(a.min(b)),
+        OP_MAX => Some(a.max(b)),
+        OP_GCD => i64::try_from(gcd(a.unsigned_abs(), b.unsigned_abs())).ok(),
+        OP_ISQRT if a < 0 => return Err(OpError::InvalidOperand),
+        // The root of a non-negative i64 is below 2^32
+        OP_ISQRT => Some(isqrt(a as u64) as i64),
+        OP_LOG2 => Some(a.checked_ilog2().ok_or(OpError::InvalidOperand)? as i64),
+        OP_BPS_FLOOR | OP_BPS_CEIL | OP_BPS_ROUND => i64::try_from(basis_points(op, a, b)).ok(),
+        _ => return Err(OpError::UnknownOperation),
+    };
+    result.ok_or(OpError::Overflow)
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// Digit-by-digit square root, two bits at a time, so it stays exact where a
+// float's 53-bit mantissa wouldn't and costs no soft-float cycles in the guest
+fn isqrt(mut n: u64) -> u64 {
+    let mut root = 0;
+    let mut bit = 1u64 << 62;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if n >= root + bit {
+            n -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+// `a * b / BPS_DENOMINATOR` rounded as the basis-point operation `op` says.
+// The product of two i64s always fits an i128, so only the result can overflow
+pub(crate) fn basis_points(op: i64, a: i64, b: i64) -> i128 {
+    let product = a as i128 * b as i128;
+    let denominator = BPS_DENOMINATOR as i128;
+    // Truncated toward zero, so the remainder takes the sign of the product
+    let (quotient, remainder) = (product / denominator, product % denominator);
+    let step = match op {
+        OP_BPS_FLOOR if remainder < 0 => -1,
+        OP_BPS_CEIL if remainder > 0 => 1,
+        OP_BPS_ROUND => {
+            let twice_remainder = 2 * remainder.abs();
+            if twice_remainder > denominator || (twice_remainder == denominator && quotient % 2 != 0) {
+                product.signum()
+            } else {
+                0
+            }
+        }
+        _ => 0,
+    };
+    quotient + step
+}
+
+/// Symbol of an infix operation, or the name of one written as a function
+pub fn symbol(op: i64) -> &'static str {
+    match base_operation(op) {
+        OP_ADD => "+",
+        OP_SUBTRACT => "-",
+        OP_MULTIPLY => "*",
+        OP_DIVIDE => "/",
+        OP_MODULO => "%",
+        OP_POWER => "^",
+        OP_ABS => "abs",
+        OP_MIN => "min",
+        OP_MAX => "max",
+        OP_GCD => "gcd",
+        OP_ISQRT => "isqrt",
+        OP_LOG2 => "log2",
+        OP_BPS_FLOOR => "bps_floor",
+        OP_BPS_CEIL => "bps_ceil",
+        OP_BPS_ROUND => "bps_round",
+        _ => "?",
+    }
+}
+
+/// Whether `op` is written between its operands rather than as a function
+pub fn is_infix(op: i64) -> bool {
+    !matches!(
+        base_operation(op),
+        OP_ABS | OP_MIN | OP_MAX | OP_GCD | OP_ISQRT | OP_LOG2 | OP_BPS_FLOOR | OP_BPS_CEIL | OP_BPS_ROUND
+    )
+}
+
+/// Displays an operation on `a` and `b`: `a + b`, `abs(a)` or `min(a, b)`,
+/// wrapped as in `saturating(a + b)` when the operation is flagged
+pub struct Operation<A, B> {
+    pub op: i64,
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for Operation<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wrapper = match split_operation(self.op) {
+            Some((_, Semantics::Saturating)) => Some("saturating"),
+            Some((_, Semantics::Wrapping)) => Some("wrapping"),
+            _ => None,
+        };
+        if let Some(wrapper) = wrapper {
+            write!(f, "{}(", wrapper)?;
+        }
+        if is_infix(self.op) {
+            write!(f, "{} {} {}", self.a, symbol(self.op), self.b)?;
+        } else if arity(self.op) == 1 {
+            write!(f, "{}({})", symbol(self.op), self.a)?;
+        } else {
+            write!(f, "{}({}, {})", symbol(self.op), self.a, self.b)?;
+        }
+        match wrapper {
+            Some(_) => write!(f, ")"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BPS_MODES: [i64; 3] = [OP_BPS_FLOOR, OP_BPS_CEIL, OP_BPS_ROUND];
+
+    #[test]
+    fn isqrt_rounds_down() {
+        for (n, root) in [(0, 0), (1, 1), (2, 1), (3, 1), (4, 2), (99, 9), (100, 10), (1 << 62, 1 << 31)] {
+            assert_eq!(isqrt(n), root, "isqrt({})", n);
+        }
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        assert_eq!(isqrt((u32::MAX as u64).pow(2)), u32::MAX as u64);
+        assert_eq!(isqrt((u32::MAX as u64).pow(2) - 1), u32::MAX as u64 - 1);
+        assert_eq!(apply(OP_ISQRT, i64::MAX, 0), Ok(3_037_000_499));
+        assert_eq!(apply(OP_ISQRT, -1, 0), Err(OpError::InvalidOperand));
+        assert_eq!(apply(OP_ISQRT, i64::MIN, 0), Err(OpError::InvalidOperand));
+    }
+
+    #[test]
+    fn log2_rounds_down_around_powers_of_two() {
+        assert_eq!(apply(OP_LOG2, 0, 0), Err(OpError::InvalidOperand));
+        assert_eq!(apply(OP_LOG2, -1, 0), Err(OpError::InvalidOperand));
+        assert_eq!(apply(OP_LOG2, 1, 0), Ok(0));
+        for exponent in 1..63 {
+            let power = 1i64 << exponent;
+            assert_eq!(apply(OP_LOG2, power - 1, 0), Ok(exponent - 1), "log2(2^{} - 1)", exponent);
+            assert_eq!(apply(OP_LOG2, power, 0), Ok(exponent), "log2(2^{})", exponent);
+            assert_eq!(apply(OP_LOG2, power + 1, 0), Ok(exponent), "log2(2^{} + 1)", exponent);
+        }
+        assert_eq!(apply(OP_LOG2, i64::MAX, 0), Ok(62));
+    }
+
+    #[test]
+    fn basis_points_round_at_the_i64_limits() {
+        // A whole of the limits is exact in every mode
+        for op in BPS_MODES {
+            assert_eq!(apply(op, i64::MAX, BPS_DENOMINATOR), Ok(i64::MAX));
+            assert_eq!(apply(op, i64::MIN, BPS_DENOMINATOR), Ok(i64::MIN));
+            assert_eq!(apply(op, i64::MAX, 0), Ok(0));
+        }
+        // One basis point: 922337203685477.5807 and -922337203685477.5808
+        let cases = [
+            (OP_BPS_FLOOR, i64::MAX, 922_337_203_685_477),
+            (OP_BPS_CEIL, i64::MAX, 922_337_203_685_478),
+            (OP_BPS_ROUND, i64::MAX, 922_337_203_685_478),
+            (OP_BPS_FLOOR, i64::MIN, -922_337_203_685_478),
+            (OP_BPS_CEIL, i64::MIN, -922_337_203_685_477),
+            (OP_BPS_ROUND, i64::MIN, -922_337_203_685_478),
+        ];
+        for (op, a, result) in cases {
+            assert_eq!(apply(op, a, 1), Ok(result), "{}", Operation { op, a, b: 1 });
+        }
+        // More than a whole, or a negative whole of the minimum, overflows
+        for op in BPS_MODES {
+            assert_eq!(apply(op, i64::MAX, BPS_DENOMINATOR + 1), Err(OpError::Overflow));
+            assert_eq!(apply(op, i64::MIN, -BPS_DENOMINATOR), Err(OpError::Overflow));
+            assert_eq!(apply(op, i64::MIN, i64::MIN), Err(OpError::Overflow));
+            assert_eq!(apply(op, i64::MAX, i64::MIN), Err(OpError::Overflow));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Saturating and wrapping variants of the operations
+//!
+//! An operation code with `FLAG_SATURATING` or `FLAG_WRAPPING` set computes
+//! the same operation, but a result past the `i64` range saturates at the
+//! nearest bound or wraps around modulo 2^64 instead of failing with
+//! `OpError::Overflow`. Division by zero and operands outside an operation's
+//! domain fail under every semantics.
+
+use crate::{
+    basis_points, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE, OP_GCD, OP_MODULO, OP_MULTIPLY,
+    OP_POWER, OP_SUBTRACT,
+};
+
+/// Operation code flag selecting saturating semantics
+pub const FLAG_SATURATING: i64 = 1 << 8;
+/// Operation code flag selecting wrapping semantics
+pub const FLAG_WRAPPING: i64 = 1 << 9;
+
+/// What an operation does with a result that overflows an `i64`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Semantics {
+    /// Fail with `OpError::Overflow`
+    Checked,
+    /// Clamp to `i64::MIN` or `i64::MAX`
+    Saturating,
+    /// Keep the low 64 bits
+    Wrapping,
+}
+
+impl Semantics {
+    /// The flag selecting these semantics in an operation code
+    pub fn flag(self) -> i64 {
+        match self {
+            Semantics::Checked => 0,
+            Semantics::Saturating => FLAG_SATURATING,
+            Semantics::Wrapping => FLAG_WRAPPING,
+        }
+    }
+}
+
+/// Splits an operation code into the operation and its semantics, `None`
+/// when it sets both flags
+pub fn split_operation(op: i64) -> Option<(i64, Semantics)> {
+    let semantics = match op & (FLAG_SATURATING | FLAG_WRAPPING) {
+        0 => Semantics::Checked,
+        FLAG_SATURATING => Semantics::Saturating,
+        FLAG_WRAPPING => Semantics::Wrapping,
+        _ => return None,
+    };
+    Some((op & !(FLAG_SATURATING | FLAG_WRAPPING), semantics))
+}
+
+/// The result of `a op b` under `semantics` once the checked one overflowed
+pub(crate) fn overflowed(op: i64, a: i64, b: i64, semantics: Semantics) -> Option<i64> {
+    match semantics {
+        Semantics::Checked => None,
+        Semantics::Saturating => Some(match op {
+            OP_ADD => a.saturating_add(b),
+            OP_SUBTRACT => a.saturating_sub(b),
+            OP_MULTIPLY => a.saturating_mul(b),
+            OP_DIVIDE => a.saturating_div(b),
+            // i64::MIN % -1 is 0, its remainder just doesn't fit the division
+            OP_MODULO => 0,
+            // Only an odd power of a negative base is negative
+            OP_POWER if a < 0 && b & 1 == 1 => i64::MIN,
+            OP_POWER => i64::MAX,
+            OP_ABS => a.saturating_abs(),
+            OP_GCD => i64::MAX,
+            OP_BPS_FLOOR | OP_BPS_CEIL | OP_BPS_ROUND if basis_points(op, a, b) < 0 => i64::MIN,
+            OP_BPS_FLOOR | OP_BPS_CEIL | OP_BPS_ROUND => i64::MAX,
+            _ => return None,
+        }),
+        Semantics::Wrapping => Some(match op {
+            OP_ADD => a.wrapping_add(b),
+            OP_SUBTRACT => a.wrapping_sub(b),
+            OP_MULTIPLY => a.wrapping_mul(b),
+            OP_DIVIDE => a.wrapping_div(b),
+            OP_MODULO => a.wrapping_rem(b),
+            OP_POWER => wrapping_pow(a, b as u64),
+            OP_ABS => a.wrapping_abs(),
+            // The only GCD past i64::MAX is 2^63, which wraps to i64::MIN
+            OP_GCD => i64::MIN,
+            OP_BPS_FLOOR | OP_BPS_CEIL | OP_BPS_ROUND => basis_points(op, a, b) as i64,
+            _ => return None,
+        }),
+    }
+}
+
+// `a^exponent` modulo 2^64, for any exponent
+fn wrapping_pow(mut base: i64, mut exponent: u64) -> i64 {
+    let mut result: i64 = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
@@ -0,0 +1,113 @@
+//! Aggregates over a vector of `i64`s
+//!
+//! Sums, variances and medians are computed exactly on wider integers and
+//! only the aggregate has to fit an `i64`. Divisions truncate toward zero, as
+//! `OP_DIVIDE` does.
+
+use crate::{OpError, WideInt, I256};
+
+pub const STAT_SUM: i64 = 0;
+/// Arithmetic mean, truncated toward zero
+pub const STAT_MEAN: i64 = 1;
+/// Population variance, truncated toward zero
+pub const STAT_VARIANCE: i64 = 2;
+pub const STAT_MIN: i64 = 3;
+pub const STAT_MAX: i64 = 4;
+/// Middle value, or the truncated mean of the two middle values of an even count
+pub const STAT_MEDIAN: i64 = 5;
+
+/// Every statistic `statistic` computes
+pub const STATISTICS: [i64; 6] = [STAT_SUM, STAT_MEAN, STAT_VARIANCE, STAT_MIN, STAT_MAX, STAT_MEDIAN];
+
+/// Computes `stat` over `values`, which must not be empty; sorts `values` for the median
+pub fn statistic(stat: i64, values: &mut [i64]) -> Result<i64, OpError> {
+    if values.is_empty() {
+        return Err(OpError::InvalidOperand);
+    }
+    // No slice holds enough values to overflow an i128 sum
+    let count = values.len() as i128;
+    let sum: i128 = values.iter().map(|&value| value as i128).sum();
+    let result = match stat {
+        STAT_SUM => sum,
+        STAT_MEAN => sum / count,
+        STAT_VARIANCE => return variance(values, sum),
+        STAT_MIN => values.iter().copied().min().unwrap_or_default() as i128,
+        STAT_MAX => values.iter().copied().max().unwrap_or_default() as i128,
+        STAT_MEDIAN => {
+            values.sort_unstable();
+            let middle = values.len() / 2;
+            if values.len() % 2 == 1 {
+                values[middle] as i128
+            } else {
+                (values[middle - 1] as i128 + values[middle] as i128) / 2
+            }
+        }
+        _ => return Err(OpError::UnknownOperation),
+    };
+    i64::try_from(result).map_err(|_| OpError::Overflow)
+}
+
+// (n * sum of squares - sum^2) / n^2, whose terms can pass i128
+fn variance(values: &[i64], sum: i128) -> Result<i64, OpError> {
+    let count = I256::from(values.len() as i128);
+    let sum = I256::from(sum);
+    let mut sum_of_squares = I256::ZERO;
+    for &value in values {
+        let value = I256::from(value as i128);
+        let square = value.checked_mul(value).ok_or(OpError::Overflow)?;
+        sum_of_squares = sum_of_squares.checked_add(square).ok_or(OpError::Overflow)?;
+    }
+    let numerator = count
+        .checked_mul(sum_of_squares)
+        .zip(sum.checked_mul(sum))
+        .and_then(|(scaled, squared_sum)| scaled.checked_sub(squared_sum));
+    let variance = numerator
+        .zip(count.checked_mul(count))
+        .and_then(|(numerator, denominator)| numerator.checked_div(denominator))
+        .ok_or(OpError::Overflow)?;
+    i128::try_from(variance).and_then(|variance| i64::try_from(variance).map_err(|_| OpError::Overflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_an_empty_vector() {
+        for stat in STATISTICS {
+            assert_eq!(statistic(stat, &mut []), Err(OpError::InvalidOperand));
+        }
+    }
+
+    #[test]
+    fn one_value_is_its_own_aggregate() {
+        for (stat, result) in [(STAT_SUM, -7), (STAT_MEAN, -7), (STAT_VARIANCE, 0), (STAT_MIN, -7), (STAT_MAX, -7)] {
+            assert_eq!(statistic(stat, &mut [-7]), Ok(result));
+        }
+        assert_eq!(statistic(STAT_MEDIAN, &mut [i64::MIN]), Ok(i64::MIN));
+    }
+
+    #[test]
+    fn median_of_an_even_count_truncates_the_middle_mean() {
+        let mut values = [9, 1, 4, 2];
+        assert_eq!(statistic(STAT_MEDIAN, &mut values), Ok(3));
+        // Sorted for the median
+        assert_eq!(values, [1, 2, 4, 9]);
+        assert_eq!(statistic(STAT_MEDIAN, &mut [-3, -2]), Ok(-2));
+        // The middle values' sum passes i64
+        assert_eq!(statistic(STAT_MEDIAN, &mut [i64::MAX, i64::MAX - 2]), Ok(i64::MAX - 1));
+    }
+
+    #[test]
+    fn only_the_aggregate_has_to_fit() {
+        assert_eq!(statistic(STAT_SUM, &mut [i64::MAX, 1]), Err(OpError::Overflow));
+        assert_eq!(statistic(STAT_MEAN, &mut [i64::MAX, i64::MAX, i64::MAX]), Ok(i64::MAX));
+        // Truncated toward zero
+        assert_eq!(statistic(STAT_MEAN, &mut [i64::MIN, 1]), Ok((i64::MIN + 1) / 2));
+        // Squares of large values pass i128, but equal values don't vary
+        assert_eq!(statistic(STAT_VARIANCE, &mut [i64::MIN, i64::MIN]), Ok(0));
+        assert_eq!(statistic(STAT_VARIANCE, &mut [1 << 31, -(1 << 31)]), Ok(1 << 62));
+        assert_eq!(statistic(STAT_VARIANCE, &mut [i64::MIN, i64::MAX]), Err(OpError::Overflow));
+        assert_eq!(statistic(99, &mut [1]), Err(OpError::UnknownOperation));
+    }
+}
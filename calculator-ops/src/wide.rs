@@ -0,0 +1,460 @@
+//! Wide arithmetic for calculations that overflow `i64`
+//!
+//! `apply_wide` computes the operations of `apply` up to `OP_GCD` on `i128`
+//! or on the fixed 256-bit `I256`, with the same checked semantics. Wide operands and
+//! results travel as little-endian two's complement of `WideInt::BYTES`
+//! bytes.
+
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::{OpError, OP_ABS, OP_ADD, OP_DIVIDE, OP_GCD, OP_MAX, OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT};
+
+/// An integer type `apply_wide` computes on
+pub trait WideInt: Copy + Ord + fmt::Display {
+    const ZERO: Self;
+    /// Length of the little-endian encoding
+    const BYTES: usize;
+
+    /// Reads a little-endian two's complement value; `bytes` must be `BYTES` long
+    fn read_le(bytes: &[u8]) -> Self;
+    /// Writes the value as little-endian two's complement; `out` must be `BYTES` long
+    fn write_le(self, out: &mut [u8]);
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    fn checked_neg(self) -> Option<Self>;
+    fn checked_pow(self, exponent: u32) -> Option<Self>;
+    fn is_negative(self) -> bool;
+    /// The value as a `u32` exponent, saturating to `u32::MAX` or one less
+    /// so the parity is kept; the value must not be negative
+    fn exponent(self) -> u32;
+}
+
+impl WideInt for i128 {
+    const ZERO: Self = 0;
+    const BYTES: usize = 16;
+
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut buffer = [0u8; 16];
+        buffer.copy_from_slice(bytes);
+        i128::from_le_bytes(buffer)
+    }
+
+    fn write_le(self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        i128::checked_add(self, rhs)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        i128::checked_sub(self, rhs)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        i128::checked_mul(self, rhs)
+    }
+
+    fn checked_div(self, rhs: Self) -> Option<Self> {
+        i128::checked_div(self, rhs)
+    }
+
+    fn checked_rem(self, rhs: Self) -> Option<Self> {
+        i128::checked_rem(self, rhs)
+    }
+
+    fn checked_neg(self) -> Option<Self> {
+        i128::checked_neg(self)
+    }
+
+    fn checked_pow(self, exponent: u32) -> Option<Self> {
+        i128::checked_pow(self, exponent)
+    }
+
+    fn is_negative(self) -> bool {
+        self < 0
+    }
+
+    fn exponent(self) -> u32 {
+        u32::try_from(self).unwrap_or(u32::MAX - (self & 1 == 0) as u32)
+    }
+}
+
+/// Computes `a op b` on wide integers
+pub fn apply_wide<T: WideInt>(op: i64, a: T, b: T) -> Result<T, OpError> {
+    let result = match op {
+        OP_ADD => a.checked_add(b),
+        OP_SUBTRACT => a.checked_sub(b),
+        OP_MULTIPLY => a.checked_mul(b),
+        OP_DIVIDE | OP_MODULO if b == T::ZERO => return Err(OpError::DivisionByZero),
+        OP_DIVIDE => a.checked_div(b),
+        OP_MODULO => a.checked_rem(b),
+        OP_POWER if b.is_negative() => return Err(OpError::InvalidOperand),
+        OP_POWER => a.checked_pow(b.exponent()),
+        OP_ABS if a.is_negative() => a.checked_neg(),
+        OP_ABS => Some(a),
+        OP_MIN => Some(a.min(b)),
+        OP_MAX => Some(a.max(b)),
+        OP_GCD => gcd(a, b),
+        _ => return Err(OpError::UnknownOperation),
+    };
+    result.ok_or(OpError::Overflow)
+}
+
+// Euclid's algorithm on non-positive values, since every positive value can
+// be negated but the minimum can't be made positive
+fn gcd<T: WideInt>(a: T, b: T) -> Option<T> {
+    let non_positive = |value: T| if value > T::ZERO { value.checked_neg() } else { Some(value) };
+    let (mut a, mut b) = (non_positive(a)?, non_positive(b)?);
+    while b != T::ZERO {
+        // Only the minimum over -1 overflows, and it divides evenly
+        let remainder = a.checked_rem(b).unwrap_or(T::ZERO);
+        (a, b) = (b, remainder);
+    }
+    a.checked_neg()
+}
+
+/// Magnitude of an `I256`, as little-endian 64-bit limbs
+type Limbs = [u64; 4];
+
+/// Fixed 256-bit signed integer, from -2^255 to 2^255 - 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct I256 {
+    // Zero is never negative, so every value has one representation
+    negative: bool,
+    magnitude: Limbs,
+}
+
+impl I256 {
+    pub const ZERO: I256 = I256 { negative: false, magnitude: [0; 4] };
+
+    /// The value with `magnitude` and sign, `None` outside the 256-bit range
+    fn new(negative: bool, magnitude: Limbs) -> Option<I256> {
+        const TOP_BIT: u64 = 1 << 63;
+        if magnitude == [0; 4] {
+            return Some(I256::ZERO);
+        }
+        // The top bit is only set in the magnitude of the minimum, 2^255
+        if magnitude[3] & TOP_BIT != 0 && !(negative && magnitude == [0, 0, 0, TOP_BIT]) {
+            return None;
+        }
+        Some(I256 { negative, magnitude })
+    }
+
+    // Adds values given as sign and magnitude, so subtraction can flip the
+    // sign of the minimum without overflowing
+    fn add_signed(a_negative: bool, a: &Limbs, b_negative: bool, b: &Limbs) -> Option<I256> {
+        if a_negative == b_negative {
+            return I256::new(a_negative, add(a, b)?);
+        }
+        match compare(a, b) {
+            Ordering::Less => I256::new(b_negative, sub(b, a)),
+            _ => I256::new(a_negative, sub(a, b)),
+        }
+    }
+}
+
+impl From<i128> for I256 {
+    fn from(value: i128) -> Self {
+        let magnitude = value.unsigned_abs();
+        I256 { negative: value < 0, magnitude: [magnitude as u64, (magnitude >> 64) as u64, 0, 0] }
+    }
+}
+
+impl TryFrom<I256> for i128 {
+    type Error = OpError;
+
+    fn try_from(value: I256) -> Result<Self, OpError> {
+        let [low, high, 0, 0] = value.magnitude else {
+            return Err(OpError::Overflow);
+        };
+        let magnitude = (high as u128) << 64 | low as u128;
+        if value.negative {
+            0i128.checked_sub_unsigned(magnitude).ok_or(OpError::Overflow)
+        } else {
+            i128::try_from(magnitude).map_err(|_| OpError::Overflow)
+        }
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => compare(&self.magnitude, &other.magnitude),
+            (true, true) => compare(&other.magnitude, &self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for I256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl WideInt for I256 {
+    const ZERO: Self = I256::ZERO;
+    const BYTES: usize = 32;
+
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            let mut buffer = [0u8; 8];
+            buffer.copy_from_slice(chunk);
+            *limb = u64::from_le_bytes(buffer);
+        }
+        let negative = limbs[3] >> 63 == 1;
+        let magnitude = if negative { negate(&limbs) } else { limbs };
+        // Every 256-bit pattern is in range
+        I256::new(negative, magnitude).unwrap_or(I256::ZERO)
+    }
+
+    fn write_le(self, out: &mut [u8]) {
+        let limbs = if self.negative { negate(&self.magnitude) } else { self.magnitude };
+        for (chunk, limb) in out.chunks_exact_mut(8).zip(limbs) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+    }
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        I256::add_signed(self.negative, &self.magnitude, rhs.negative, &rhs.magnitude)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        I256::add_signed(self.negative, &self.magnitude, !rhs.negative, &rhs.magnitude)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        I256::new(self.negative != rhs.negative, mul(&self.magnitude, &rhs.magnitude)?)
+    }
+
+    fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs == I256::ZERO {
+            return None;
+        }
+        let (quotient, _) = div_rem(&self.magnitude, &rhs.magnitude);
+        I256::new(self.negative != rhs.negative, quotient)
+    }
+
+    fn checked_rem(self, rhs: Self) -> Option<Self> {
+        if rhs == I256::ZERO {
+            return None;
+        }
+        let (_, remainder) = div_rem(&self.magnitude, &rhs.magnitude);
+        I256::new(self.negative, remainder)
+    }
+
+    fn checked_neg(self) -> Option<Self> {
+        I256::new(!self.negative, self.magnitude)
+    }
+
+    fn checked_pow(self, mut exponent: u32) -> Option<Self> {
+        let mut result = I256::from(1);
+        let mut base = self;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.checked_mul(base)?;
+            }
+            exponent >>= 1;
+            // Squaring past the last bit could overflow needlessly
+            if exponent > 0 {
+                base = base.checked_mul(base)?;
+            }
+        }
+        Some(result)
+    }
+
+    fn is_negative(self) -> bool {
+        self.negative
+    }
+
+    fn exponent(self) -> u32 {
+        match self.magnitude {
+            [low, 0, 0, 0] => u32::try_from(low).unwrap_or(u32::MAX - (low & 1 == 0) as u32),
+            [low, ..] => u32::MAX - (low & 1 == 0) as u32,
+        }
+    }
+}
+
+impl fmt::Display for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 2^255 has 77 decimal digits
+        let mut digits = [0u8; 78];
+        let mut start = digits.len();
+        let mut rest = self.magnitude;
+        loop {
+            let (quotient, remainder) = div_rem(&rest, &[10, 0, 0, 0]);
+            start -= 1;
+            digits[start] = b'0' + remainder[0] as u8;
+            rest = quotient;
+            if rest == [0; 4] {
+                break;
+            }
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        f.write_str(core::str::from_utf8(&digits[start..]).unwrap_or_default())
+    }
+}
+
+fn compare(a: &Limbs, b: &Limbs) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+fn add(a: &Limbs, b: &Limbs) -> Option<Limbs> {
+    let mut sum = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (partial, first) = a[i].overflowing_add(b[i]);
+        let (partial, second) = partial.overflowing_add(carry as u64);
+        sum[i] = partial;
+        carry = first || second;
+    }
+    (!carry).then_some(sum)
+}
+
+// `a - b`, where `a` is at least `b`
+fn sub(a: &Limbs, b: &Limbs) -> Limbs {
+    let mut difference = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (partial, first) = a[i].overflowing_sub(b[i]);
+        let (partial, second) = partial.overflowing_sub(borrow as u64);
+        difference[i] = partial;
+        borrow = first || second;
+    }
+    difference
+}
+
+// Two's complement of the limbs, modulo 2^256
+fn negate(limbs: &Limbs) -> Limbs {
+    let mut negated = [0u64; 4];
+    let mut carry = true;
+    for i in 0..4 {
+        let (partial, overflowed) = (!limbs[i]).overflowing_add(carry as u64);
+        negated[i] = partial;
+        carry = overflowed;
+    }
+    negated
+}
+
+fn mul(a: &Limbs, b: &Limbs) -> Option<Limbs> {
+    let mut product = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let partial = a[i] as u128 * b[j] as u128 + product[i + j] as u128 + carry;
+            product[i + j] = partial as u64;
+            carry = partial >> 64;
+        }
+        product[i + 4] = carry as u64;
+    }
+    if product[4..] != [0; 4] {
+        return None;
+    }
+    Some([product[0], product[1], product[2], product[3]])
+}
+
+// Long division, one bit at a time; `b` must not be zero. Magnitudes are at
+// most 2^255, so the shifted remainder never overflows
+fn div_rem(a: &Limbs, b: &Limbs) -> (Limbs, Limbs) {
+    let mut quotient = [0u64; 4];
+    let mut remainder = [0u64; 4];
+    for bit in (0..256).rev() {
+        for i in (1..4).rev() {
+            remainder[i] = (remainder[i] << 1) | (remainder[i - 1] >> 63);
+        }
+        remainder[0] = (remainder[0] << 1) | ((a[bit / 64] >> (bit % 64)) & 1);
+        if compare(&remainder, b) != Ordering::Less {
+            remainder = sub(&remainder, b);
+            quotient[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: I256 = I256 { negative: false, magnitude: [u64::MAX, u64::MAX, u64::MAX, u64::MAX >> 1] };
+    const MIN: I256 = I256 { negative: true, magnitude: [0, 0, 0, 1 << 63] };
+
+    fn positive(magnitude: Limbs) -> I256 {
+        I256::new(false, magnitude).unwrap()
+    }
+
+    #[test]
+    fn addition_carries_across_limbs() {
+        let one = I256::from(1);
+        assert_eq!(apply_wide(OP_ADD, I256::from(u64::MAX as i128), one), Ok(positive([0, 1, 0, 0])));
+        assert_eq!(apply_wide(OP_ADD, positive([u64::MAX, u64::MAX, u64::MAX, 0]), one), Ok(positive([0, 0, 0, 1])));
+        assert_eq!(apply_wide(OP_ADD, MAX, one), Err(OpError::Overflow));
+        assert_eq!(apply_wide(OP_ADD, MIN, I256::from(-1)), Err(OpError::Overflow));
+    }
+
+    #[test]
+    fn subtraction_borrows_across_limbs() {
+        let one = I256::from(1);
+        let borrowed = positive([u64::MAX, u64::MAX, u64::MAX, 0]);
+        assert_eq!(apply_wide(OP_SUBTRACT, positive([0, 0, 0, 1]), one), Ok(borrowed));
+        // Crossing zero flips the sign rather than wrapping
+        let below_zero = apply_wide(OP_SUBTRACT, one, positive([0, 0, 1, 0])).unwrap();
+        assert_eq!(below_zero, I256::new(true, [u64::MAX, u64::MAX, 0, 0]).unwrap());
+        assert_eq!(apply_wide(OP_SUBTRACT, MIN, one), Err(OpError::Overflow));
+        assert_eq!(apply_wide(OP_SUBTRACT, I256::from(-1), MAX), Ok(MIN));
+    }
+
+    #[test]
+    fn i128_min_negates_only_when_widened() {
+        assert_eq!(apply_wide(OP_ABS, i128::MIN, 0), Err(OpError::Overflow));
+        assert_eq!(apply_wide(OP_DIVIDE, i128::MIN, -1), Err(OpError::Overflow));
+
+        let negated = apply_wide(OP_ABS, I256::from(i128::MIN), I256::ZERO).unwrap();
+        assert_eq!(negated, positive([0, 1 << 63, 0, 0]));
+        assert_eq!(i128::try_from(negated), Err(OpError::Overflow));
+        assert_eq!(i128::try_from(I256::from(i128::MIN)), Ok(i128::MIN));
+        assert_eq!(apply_wide(OP_ABS, MIN, I256::ZERO), Err(OpError::Overflow));
+    }
+
+    #[test]
+    fn division_by_zero_fails_at_both_widths() {
+        for op in [OP_DIVIDE, OP_MODULO] {
+            assert_eq!(apply_wide(op, 7i128, 0), Err(OpError::DivisionByZero));
+            assert_eq!(apply_wide(op, I256::from(7), I256::ZERO), Err(OpError::DivisionByZero));
+            assert_eq!(apply_wide(op, MIN, I256::ZERO), Err(OpError::DivisionByZero));
+        }
+        assert_eq!(apply_wide(OP_DIVIDE, MIN, I256::from(-1)), Err(OpError::Overflow));
+        assert_eq!(apply_wide(OP_MODULO, MIN, I256::from(-1)), Ok(I256::ZERO));
+    }
+
+    #[test]
+    fn encoding_round_trips_through_twos_complement() {
+        for value in [I256::ZERO, I256::from(-1), I256::from(i128::MIN), MAX, MIN] {
+            let mut bytes = [0u8; 32];
+            value.write_le(&mut bytes);
+            assert_eq!(I256::read_le(&bytes), value);
+        }
+        let mut bytes = [0u8; 32];
+        I256::from(-2).write_le(&mut bytes);
+        assert_eq!(bytes[0], 0xfe);
+        assert!(bytes[1..].iter().all(|&byte| byte == 0xff));
+    }
+
+    #[test]
+    fn display_prints_the_full_range() {
+        assert_eq!(I256::from(i128::MIN).to_string(), i128::MIN.to_string());
+        assert_eq!(
+            MIN.to_string(),
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+"""Type hints for the calculator bindings built from this crate."""
+
+from typing import List, Optional
+
+class CalculatorError(Exception): ...
+class InvalidArgsError(CalculatorError): ...
+class RpcError(CalculatorError): ...
+class SimulationError(CalculatorError): ...
+class TransactionError(CalculatorError): ...
+class CallbackTimeoutError(CalculatorError): ...
+
+class Submission:
+    execution_id: str
+    description: str
+    signature: str
+    state: str
+    index: int
+    record: str
+    requester: str
+
+class Record:
+    execution_id: str
+    description: str
+    state: str
+    index: int
+    record: str
+    status: str
+    result: Optional[int]
+    failure: Optional[str]
+    label: Optional[str]
+    payer: str
+    requester: str
+    submitted_slot: int
+    completed_slot: Optional[int]
+    timestamp: int
+    journal: Optional[bytes]
+
+class Journal:
+    result: Optional[int]
+    failure: Optional[str]
+    input_digest: Optional[str]
+    cycles: Optional[int]
+
+class Client:
+    payer: Optional[str]
+    program_id: str
+    def __init__(
+        self, rpc_url: str = "http://127.0.0.1:8899", keypair: Optional[str] = None, program_id: Optional[str] = None
+    ) -> None: ...
+    def submit(
+        self,
+        state: str,
+        expression: Optional[str] = None,
+        *,
+        rpn: Optional[str] = None,
+        operation: Optional[str] = None,
+        operand_a: Optional[int] = None,
+        operand_b: Optional[int] = None,
+        execution_id: Optional[str] = None,
+    ) -> Submission: ...
+    def wait_for_result(self, submission: Submission, timeout: float = 120.0) -> Record: ...
+    def status(self, execution_id: str) -> Record: ...
+    def history(self, state: str) -> List[Record]: ...
+
+def decode_journal(journal: bytes) -> Journal: ...
@@ -0,0 +1,180 @@
+//! The blocking `Client` Python calls through
+//!
+//! Each call runs the SDK's future to completion on the client's own Tokio
+//! runtime with the GIL released. The keypair is only needed to submit, so
+//! a client without one can still read records and history.
+
+use anyhow::{anyhow, Context};
+use calculator_sdk::{
+    expression, pad_execution_id, parse_operation, records, validate_execution_id, Calculation, CalculatorClient,
+    OPERATION_NAMES,
+};
+use pyo3::prelude::*;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::error;
+use crate::types::{Record, Submission};
+
+/// Calculator program operations over an RPC node
+///
+/// `keypair` pays for and requests submissions; without it the default
+/// Solana CLI keypair is used when it exists. `program_id` targets another
+/// deployment of the calculator program, such as one on a local validator.
+#[pyclass(module = "calculator")]
+pub struct Client {
+    calculator: CalculatorClient,
+    payer: Option<Keypair>,
+    runtime: Runtime,
+}
+
+#[pymethods]
+impl Client {
+    #[new]
+    #[pyo3(signature = (rpc_url = "http://127.0.0.1:8899", keypair = None, program_id = None))]
+    fn new(rpc_url: &str, keypair: Option<PathBuf>, program_id: Option<&str>) -> PyResult<Self> {
+        let payer = match keypair {
+            Some(path) => Some(
+                read_keypair_file(&path)
+                    .map_err(|e| error::invalid(format!("Failed to read keypair {}: {}", path.display(), e)))?,
+            ),
+            None => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".config").join("solana").join("id.json"))
+                .and_then(|path| read_keypair_file(path).ok()),
+        };
+        let program_id = match program_id {
+            Some(program_id) => parse_pubkey("program_id", program_id)?,
+            None => bonsol_calculator_backend::id(),
+        };
+        let rpc = Arc::new(RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()));
+        let runtime = Runtime::new().map_err(|e| error::to_py(anyhow!("Failed to start a Tokio runtime: {}", e)))?;
+        Ok(Client { calculator: CalculatorClient::new(rpc).with_program_id(program_id), payer, runtime })
+    }
+
+    /// Submits a calculation to the calculator `state` and returns once its transaction is confirmed
+    ///
+    /// Give an infix `expression`, space-separated `rpn` tokens, or an
+    /// `operation` name with `operand_a` and, for binary operations,
+    /// `operand_b`. An `execution_id` is generated unless one is given.
+    #[pyo3(signature = (state, expression = None, *, rpn = None, operation = None, operand_a = None, operand_b = None,
+        execution_id = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn submit(
+        &self,
+        py: Python<'_>,
+        state: &str,
+        expression: Option<&str>,
+        rpn: Option<&str>,
+        operation: Option<&str>,
+        operand_a: Option<i64>,
+        operand_b: Option<i64>,
+        execution_id: Option<&str>,
+    ) -> PyResult<Submission> {
+        let state = parse_pubkey("state", state)?;
+        let calculation = calculation(expression, rpn, operation, operand_a, operand_b)?;
+        let payer = self.payer.as_ref().ok_or_else(|| {
+            error::invalid("No keypair to submit with; pass keypair= or create ~/.config/solana/id.json")
+        })?;
+        let submission = py
+            .allow_threads(|| {
+                self.runtime.block_on(self.calculator.submit(payer, &state, &calculation, execution_id))
+            })
+            .map_err(error::to_py)?;
+        Ok(Submission {
+            execution_id: submission.execution_id,
+            description: calculation.describe(),
+            signature: submission.signature.to_string(),
+            state: submission.state.to_string(),
+            index: submission.index,
+            record: submission.record.to_string(),
+            requester: submission.requester.to_string(),
+        })
+    }
+
+    /// Waits up to `timeout` seconds for the callback of `submission`, returning its record
+    ///
+    /// Raises `CallbackTimeoutError` when no callback lands in time.
+    #[pyo3(signature = (submission, timeout = 120.0))]
+    fn wait_for_result(&self, py: Python<'_>, submission: &Submission, timeout: f64) -> PyResult<Record> {
+        let timeout = Duration::try_from_secs_f64(timeout).map_err(|_| error::invalid("timeout must be positive"))?;
+        let submission = calculator_sdk::Submission {
+            signature: submission.signature.parse::<Signature>().map_err(|e| error::invalid(e.to_string()))?,
+            execution_id: submission.execution_id.clone(),
+            state: parse_pubkey("state", &submission.state)?,
+            index: submission.index,
+            record: parse_pubkey("record", &submission.record)?,
+            requester: parse_pubkey("requester", &submission.requester)?,
+        };
+        let calc = py
+            .allow_threads(|| self.runtime.block_on(self.calculator.wait_for_result(&submission, timeout)))
+            .map_err(error::to_py)?;
+        Ok(Record::new(self.calculator.program_id(), &calc))
+    }
+
+    /// The record of the calculation submitted under `execution_id`, in any calculator state
+    fn status(&self, py: Python<'_>, execution_id: &str) -> PyResult<Record> {
+        validate_execution_id(execution_id).map_err(error::to_py)?;
+        let execution_id = pad_execution_id(execution_id);
+        let calculator = &self.calculator;
+        let (_, calc) = py
+            .allow_threads(|| {
+                self.runtime.block_on(records::find_record(calculator.rpc(), calculator.program_id(), &execution_id))
+            })
+            .map_err(error::to_py)?;
+        Ok(Record::new(calculator.program_id(), &calc))
+    }
+
+    /// Every calculation record of `state` that hasn't been pruned, in index order
+    fn history(&self, py: Python<'_>, state: &str) -> PyResult<Vec<Record>> {
+        let state = parse_pubkey("state", state)?;
+        let calcs = py
+            .allow_threads(|| self.runtime.block_on(self.calculator.get_history(&state)))
+            .map_err(error::to_py)?;
+        Ok(calcs.iter().map(|calc| Record::new(self.calculator.program_id(), calc)).collect())
+    }
+
+    /// Public key paying for submissions, if the client has a keypair
+    #[getter]
+    fn payer(&self) -> Option<String> {
+        self.payer.as_ref().map(|payer| payer.pubkey().to_string())
+    }
+
+    #[getter]
+    fn program_id(&self) -> String {
+        self.calculator.program_id().to_string()
+    }
+}
+
+fn parse_pubkey(name: &str, value: &str) -> PyResult<Pubkey> {
+    value.parse().map_err(|_| error::invalid(format!("{} {:?} is not a valid public key", name, value)))
+}
+
+fn calculation(
+    expression: Option<&str>,
+    rpn: Option<&str>,
+    operation: Option<&str>,
+    operand_a: Option<i64>,
+    operand_b: Option<i64>,
+) -> PyResult<Calculation> {
+    let tokens = match (expression, rpn, operation) {
+        (Some(expression), None, None) => expression::parse(expression),
+        (None, Some(rpn), None) => expression::parse_rpn(rpn),
+        (None, None, Some(name)) => {
+            let op_code = parse_operation(name)
+                .ok_or_else(|| error::invalid(format!("Unknown operation {:?}; use {}", name, OPERATION_NAMES)))?;
+            let operand_a = operand_a.ok_or_else(|| error::invalid("operand_a is required"))?;
+            return Ok(Calculation::Single { op_code, operand_a, operand_b: operand_b.unwrap_or(0) });
+        }
+        _ => return Err(error::invalid("Give one of expression, rpn or operation")),
+    };
+    tokens
+        .context("Invalid expression")
+        .map(Calculation::from_tokens)
+        .map_err(|e| error::invalid(format!("{:#}", e)))
+}
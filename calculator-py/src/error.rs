@@ -0,0 +1,45 @@
+//! Python exceptions for SDK failures
+//!
+//! Every failure is a `CalculatorError`; a `ClientError` anywhere in the
+//! `anyhow` chain picks the subclass, the way it picks the client's exit
+//! code, so Python callers can retry RPC failures and not invalid input.
+
+use calculator_sdk::ClientError;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(calculator, CalculatorError, PyException, "A calculator SDK call failed");
+create_exception!(calculator, InvalidArgsError, CalculatorError, "Arguments that can't describe a valid request");
+create_exception!(calculator, RpcError, CalculatorError, "The RPC node failed or could not be reached");
+create_exception!(calculator, SimulationError, CalculatorError, "A transaction failed in simulation and was not sent");
+create_exception!(calculator, TransactionError, CalculatorError, "A transaction was rejected, failed or never landed");
+create_exception!(calculator, CallbackTimeoutError, CalculatorError, "No callback arrived within the timeout");
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("CalculatorError", py.get_type::<CalculatorError>())?;
+    m.add("InvalidArgsError", py.get_type::<InvalidArgsError>())?;
+    m.add("RpcError", py.get_type::<RpcError>())?;
+    m.add("SimulationError", py.get_type::<SimulationError>())?;
+    m.add("TransactionError", py.get_type::<TransactionError>())?;
+    m.add("CallbackTimeoutError", py.get_type::<CallbackTimeoutError>())?;
+    Ok(())
+}
+
+pub fn to_py(error: anyhow::Error) -> PyErr {
+    let message = format!("{:#}", error);
+    match error.chain().find_map(|cause| cause.downcast_ref::<ClientError>()) {
+        Some(ClientError::InvalidArgs(_)) => InvalidArgsError::new_err(message),
+        Some(ClientError::Rpc(_)) => RpcError::new_err(message),
+        Some(ClientError::Simulation(_)) => SimulationError::new_err(message),
+        Some(ClientError::Transaction(_)) => TransactionError::new_err(message),
+        Some(ClientError::CallbackTimeout(_)) => CallbackTimeoutError::new_err(message),
+        None => CalculatorError::new_err(message),
+    }
+}
+
+/// An argument Python passed that can't be used
+pub fn invalid(message: impl Into<String>) -> PyErr {
+    InvalidArgsError::new_err(message.into())
+}
@@ -0,0 +1,26 @@
+//! Python bindings for the calculator SDK
+//!
+//! Exposes a blocking `Client` over `CalculatorClient` for notebooks and
+//! pipelines: it submits calculations, waits for their proven results and
+//! reads records and history, returning plain Python objects. The GIL is
+//! released while it waits on the cluster, so other Python threads keep
+//! running. `decode_journal` decodes a guest journal without a cluster.
+//! Failures are raised as `CalculatorError` subclasses named after the SDK's
+//! `ClientError` categories.
+
+use pyo3::prelude::*;
+
+mod client;
+mod error;
+mod types;
+
+#[pymodule]
+fn calculator(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<client::Client>()?;
+    m.add_class::<types::Submission>()?;
+    m.add_class::<types::Record>()?;
+    m.add_class::<types::Journal>()?;
+    m.add_function(wrap_pyfunction!(types::decode_journal, m)?)?;
+    error::register(m)?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! What the bindings return to Python
+//!
+//! Plain read-only objects with the SDK's fields, public keys and signatures
+//! as base58 strings and statuses as lowercase names, so they convert to
+//! dicts or dataframe rows without Solana types.
+
+use calculator_common::{calculation_address, decode_outcome, GuestJournal};
+use calculator_sdk::{CalculationRecord, CalculationStatus, HistoryEntry};
+use pyo3::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error;
+
+/// A calculation submitted through the calculator program
+#[pyclass(frozen, get_all, module = "calculator")]
+#[derive(Clone)]
+pub struct Submission {
+    /// Padded to the 16 bytes Bonsol expects
+    pub execution_id: String,
+    pub description: String,
+    pub signature: String,
+    pub state: String,
+    pub index: u64,
+    /// Address of the calculation record the callback completes
+    pub record: String,
+    pub requester: String,
+}
+
+#[pymethods]
+impl Submission {
+    fn __repr__(&self) -> String {
+        format!("Submission(execution_id={:?}, description={:?})", self.execution_id, self.description)
+    }
+}
+
+/// A calculation record as the program stores it
+#[pyclass(frozen, get_all, module = "calculator")]
+pub struct Record {
+    pub execution_id: String,
+    pub description: String,
+    pub state: String,
+    pub index: u64,
+    pub record: String,
+    /// `pending`, `awaiting_finalize`, `complete`, `local`, `failed` or `retried`
+    pub status: &'static str,
+    pub result: Option<i64>,
+    /// Why it failed, e.g. `DivisionByZero`
+    pub failure: Option<String>,
+    pub label: Option<String>,
+    pub payer: String,
+    pub requester: String,
+    pub submitted_slot: u64,
+    pub completed_slot: Option<u64>,
+    /// Unix time of the submission
+    pub timestamp: i64,
+    /// The raw guest journal the result was decoded from
+    pub journal: Option<Vec<u8>>,
+}
+
+impl Record {
+    pub fn new(program_id: &Pubkey, calc: &CalculationRecord) -> Self {
+        let (status, failure) = match HistoryEntry::from(calc).status {
+            _ if calc.retried => ("retried", None),
+            CalculationStatus::Pending => ("pending", None),
+            CalculationStatus::AwaitingFinalize => ("awaiting_finalize", None),
+            CalculationStatus::Complete => ("complete", None),
+            CalculationStatus::Local => ("local", None),
+            CalculationStatus::Failed(reason) => ("failed", Some(format!("{:?}", reason))),
+        };
+        let (record, _) = calculation_address(program_id, &calc.state, calc.index);
+        Record {
+            execution_id: calc.execution_id.clone(),
+            description: calc.describe(),
+            state: calc.state.to_string(),
+            index: calc.index,
+            record: record.to_string(),
+            status,
+            result: calc.result,
+            failure,
+            label: calc.label.clone(),
+            payer: calc.payer.to_string(),
+            requester: calc.requester.to_string(),
+            submitted_slot: calc.submitted_slot,
+            completed_slot: calc.completed_slot,
+            timestamp: calc.timestamp,
+            journal: calc.journal.clone(),
+        }
+    }
+}
+
+#[pymethods]
+impl Record {
+    fn __repr__(&self) -> String {
+        format!(
+            "Record(execution_id={:?}, description={:?}, status={:?}, result={})",
+            self.execution_id,
+            self.description,
+            self.status,
+            repr(&self.result)
+        )
+    }
+}
+
+/// A decoded single-result guest journal
+#[pyclass(frozen, get_all, module = "calculator")]
+pub struct Journal {
+    pub result: Option<i64>,
+    /// Why the guest proved the calculation can't be computed, e.g. `DivisionByZero`
+    pub failure: Option<String>,
+    /// Hex SHA-256 digest of the input the guest read; legacy journals carry none
+    pub input_digest: Option<String>,
+    /// zkVM cycles the guest's arithmetic consumed; only metered journals carry them
+    pub cycles: Option<u64>,
+}
+
+#[pymethods]
+impl Journal {
+    fn __repr__(&self) -> String {
+        let (result, failure, cycles) = (repr(&self.result), repr(&self.failure), repr(&self.cycles));
+        format!("Journal(result={}, failure={}, cycles={})", result, failure, cycles)
+    }
+}
+
+// An optional field as Python would print it
+fn repr<T: std::fmt::Debug>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "None".to_string(),
+    }
+}
+
+/// Decodes the journal a guest committed for a single operation or expression
+#[pyfunction]
+pub fn decode_journal(journal: &[u8]) -> PyResult<Journal> {
+    let parsed = GuestJournal::parse(journal).map_err(|e| error::invalid(format!("Invalid journal: {}", e)))?;
+    let outcome = decode_outcome(journal).map_err(|e| error::invalid(format!("Invalid journal: {}", e)))?;
+    let (result, failure) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(reason) => (None, Some(format!("{:?}", reason))),
+    };
+    Ok(Journal {
+        result,
+        failure,
+        input_digest: parsed.input_digest.map(|digest| digest.iter().map(|byte| format!("{:02x}", byte)).collect()),
+        cycles: parsed.cycles,
+    })
+}
@@ -0,0 +1,139 @@
+//! Decoding of Bonsol's execution request and claim accounts
+//!
+//! An execution request lives at `execution_address(requester, execution_id)`
+//! as an `ExecutionRequestV1` flatbuffer until Bonsol settles it, after which
+//! only a single exit code byte is left behind. A prover claiming the request
+//! creates a `ClaimV1` account at `execution_claim_address(execution_account)`.
+
+use anyhow::{anyhow, Context, Result};
+use bonsol_interface::bonsol_schema::{root_as_claim_v1, root_as_execution_request_v1};
+use bonsol_interface::util::{execution_address, execution_claim_address};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+/// Lamports tipped to the prover of every execution; the calculator program tips the same
+pub const EXECUTION_TIP: u64 = 1000;
+
+// Exit codes Bonsol leaves in a settled execution account
+const EXIT_SUCCESS: u8 = 0;
+const EXIT_VERIFY_INPUTS_FAILED: u8 = 1;
+const EXIT_PROVING_ERROR: u8 = 2;
+const EXIT_INPUT_ERROR: u8 = 3;
+const EXIT_EXPIRED: u8 = 4;
+
+/// Where an execution request is in Bonsol's lifecycle
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionLifecycle {
+    /// No execution account exists at the derived address
+    NotFound,
+    /// Waiting for a prover to claim it before `expiry`
+    Requested { expiry: u64 },
+    /// Claimed by a prover who must submit a proof by `block_commitment`
+    Claimed { claimer: Pubkey, claimed_at: u64, block_commitment: u64 },
+    /// Proof verified and the callback delivered
+    Proved,
+    /// Nobody proved it before its expiry slot
+    Expired,
+    /// Settled with a failure exit code
+    Failed(u8),
+}
+
+/// Decoded state of an execution request
+pub struct ExecutionStatus {
+    pub execution_account: Pubkey,
+    pub image_id: Option<String>,
+    pub tip: Option<u64>,
+    pub lifecycle: ExecutionLifecycle,
+}
+
+impl fmt::Display for ExecutionLifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionLifecycle::NotFound => write!(f, "not found"),
+            ExecutionLifecycle::Requested { expiry } => write!(f, "requested (expires at slot {})", expiry),
+            ExecutionLifecycle::Claimed { claimer, claimed_at, block_commitment } => write!(
+                f,
+                "claimed by {} at slot {} (proof due by slot {})",
+                claimer, claimed_at, block_commitment
+            ),
+            ExecutionLifecycle::Proved => write!(f, "proved"),
+            ExecutionLifecycle::Expired => write!(f, "expired"),
+            ExecutionLifecycle::Failed(EXIT_VERIFY_INPUTS_FAILED) => write!(f, "failed (input hash mismatch)"),
+            ExecutionLifecycle::Failed(EXIT_PROVING_ERROR) => write!(f, "failed (proving error)"),
+            ExecutionLifecycle::Failed(EXIT_INPUT_ERROR) => write!(f, "failed (invalid inputs)"),
+            ExecutionLifecycle::Failed(code) => write!(f, "failed (exit code {})", code),
+        }
+    }
+}
+
+/// Fetches and decodes the execution request `requester` made under `execution_id`
+pub async fn fetch_execution_status(client: &RpcClient, requester: &Pubkey, execution_id: &str) -> Result<ExecutionStatus> {
+    let (execution_account, _) = execution_address(requester, execution_id.as_bytes());
+    let mut status = ExecutionStatus {
+        execution_account,
+        image_id: None,
+        tip: None,
+        lifecycle: ExecutionLifecycle::NotFound,
+    };
+
+    let Some(account) = client
+        .get_account_with_commitment(&execution_account, client.commitment())
+        .await
+        .context("Failed to fetch the Bonsol execution account")?
+        .value
+    else {
+        return Ok(status);
+    };
+
+    // Settled requests are shrunk to their exit code
+    if account.data.len() == 1 {
+        status.lifecycle = match account.data[0] {
+            EXIT_SUCCESS => ExecutionLifecycle::Proved,
+            EXIT_EXPIRED => ExecutionLifecycle::Expired,
+            code => ExecutionLifecycle::Failed(code),
+        };
+        return Ok(status);
+    }
+
+    let request = root_as_execution_request_v1(&account.data)
+        .map_err(|e| anyhow!("Failed to decode execution request {}: {}", execution_account, e))?;
+    status.image_id = request.image_id().map(str::to_string);
+    status.tip = Some(request.tip());
+    let expiry = request.max_block_height();
+
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let (claim_account, _) = execution_claim_address(execution_account.as_ref());
+    let claim = client
+        .get_account_with_commitment(&claim_account, client.commitment())
+        .await
+        .context("Failed to fetch the Bonsol claim account")?
+        .value;
+
+    status.lifecycle = match claim {
+        Some(claim) if !claim.data.is_empty() => {
+            let claim = root_as_claim_v1(&claim.data)
+                .map_err(|e| anyhow!("Failed to decode claim {}: {}", claim_account, e))?;
+            let claimer = claim
+                .claimer()
+                .and_then(|claimer| Pubkey::try_from(claimer.bytes()).ok())
+                .ok_or_else(|| anyhow!("Claim {} has no valid claimer", claim_account))?;
+            if claim.block_commitment() >= current_slot {
+                ExecutionLifecycle::Claimed {
+                    claimer,
+                    claimed_at: claim.claimed_at(),
+                    block_commitment: claim.block_commitment(),
+                }
+            } else if expiry < current_slot {
+                ExecutionLifecycle::Expired
+            } else {
+                // A lapsed claim can be taken over by another prover
+                ExecutionLifecycle::Requested { expiry }
+            }
+        }
+        _ if expiry < current_slot => ExecutionLifecycle::Expired,
+        _ => ExecutionLifecycle::Requested { expiry },
+    };
+
+    Ok(status)
+}
@@ -0,0 +1,239 @@
+//! What a submission asks the guest to compute, and the execution IDs it
+//! is submitted under
+
+use anyhow::{bail, Result};
+use calculator_common::constants::{FRAME_VERSION_1, FRAME_VERSION_2, MAX_EXECUTION_ID_LEN, OP_EXPRESSION};
+use calculator_common::{encode_expression, encode_single, format_expression, ExprToken};
+use calculator_ops::{
+    arity, split_operation, OpError, Operation, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE,
+    OP_GCD, OP_ISQRT, OP_LOG2, OP_MAX, OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT,
+};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ClientError;
+
+/// Operation names `parse_operation` accepts, for error messages
+pub const OPERATION_NAMES: &str =
+    "add, subtract, multiply, divide, modulo, power, abs, min, max, gcd, isqrt, log2, bps_floor, bps_ceil or bps_round";
+
+/// What a submission asks the guest to compute
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calculation {
+    /// `operand_a op operand_b`, sent as the guest's single-operation input
+    Single { op_code: i64, operand_a: i64, operand_b: i64 },
+    /// RPN program, sent as an expression input frame
+    Expression(Vec<ExprToken>),
+}
+
+impl Calculation {
+    /// Keeps a lone operation on the single-operation input format
+    pub fn from_tokens(tokens: Vec<ExprToken>) -> Self {
+        match tokens[..] {
+            [ExprToken::Push(operand_a), ExprToken::Push(operand_b), ExprToken::Op(op_code)] if arity(op_code) == 2 => {
+                Calculation::Single { op_code, operand_a, operand_b }
+            }
+            [ExprToken::Push(operand_a), ExprToken::Op(op_code)] if arity(op_code) == 1 => {
+                Calculation::Single { op_code, operand_a, operand_b: 0 }
+            }
+            _ => Calculation::Expression(tokens),
+        }
+    }
+
+    pub fn op_code(&self) -> i64 {
+        match self {
+            Calculation::Single { op_code, .. } => *op_code,
+            Calculation::Expression(_) => OP_EXPRESSION,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Calculation::Single { op_code, operand_a, operand_b } => {
+                Operation { op: *op_code, a: *operand_a, b: *operand_b }.to_string()
+            }
+            Calculation::Expression(tokens) => format_expression(tokens),
+        }
+    }
+
+    /// SHA-256 digest of `input`, the frame Bonsol hashes when verifying inputs
+    pub fn input_hash(&self) -> [u8; 32] {
+        Sha256::digest(self.input()).into()
+    }
+
+    /// Computes the result natively with the guest's checked math, `None`
+    /// where the guest would commit a failure
+    pub fn evaluate(&self) -> Option<i64> {
+        self.compute().ok()
+    }
+
+    /// Checks that the guest can compute the calculation and returns its result
+    ///
+    /// The guest proves overflow, division by zero and other failures, and
+    /// the record is marked failed after the prover has claimed the tip, so
+    /// such calculations are rejected before they are submitted.
+    pub fn validate(&self) -> Result<i64> {
+        self.compute().map_err(|reason| {
+            ClientError::InvalidArgs(format!(
+                "{} cannot be computed: {}; the ZK program would only prove the failure and the tip would be lost",
+                self.describe(),
+                reason
+            ))
+            .into()
+        })
+    }
+
+    fn compute(&self) -> Result<i64, String> {
+        match self {
+            Calculation::Single { op_code, operand_a, operand_b } => apply(*op_code, *operand_a, *operand_b),
+            Calculation::Expression(tokens) => {
+                let mut stack = Vec::with_capacity(tokens.len());
+                for token in tokens {
+                    match *token {
+                        ExprToken::Push(literal) => stack.push(literal),
+                        ExprToken::Op(op_code) => {
+                            // Unary operations take `a` alone
+                            let b = if arity(op_code) == 2 { stack.pop() } else { Some(0) };
+                            let (Some(b), Some(a)) = (b, stack.pop()) else {
+                                return Err("an operator is missing an operand".to_string());
+                            };
+                            stack.push(apply(op_code, a, b)?);
+                        }
+                        ExprToken::Dup => match stack.last() {
+                            Some(&top) => stack.push(top),
+                            None => return Err("dup needs a value on the stack".to_string()),
+                        },
+                        ExprToken::Swap => match stack.len() {
+                            0 | 1 => return Err("swap needs two values on the stack".to_string()),
+                            len => stack.swap(len - 1, len - 2),
+                        },
+                    }
+                }
+                match stack[..] {
+                    [result] => Ok(result),
+                    _ => Err("the expression does not reduce to one result".to_string()),
+                }
+            }
+        }
+    }
+
+    /// Reads back a guest input built by `input`, `None` when it is in neither format
+    pub fn from_input(input: &[u8]) -> Option<Self> {
+        // Versioned frames are one header byte longer than the legacy layout;
+        // single operations and expressions have the same words under both schemas
+        let input = match input.len() % 8 {
+            1 if input[0] == FRAME_VERSION_1 || input[0] == FRAME_VERSION_2 => &input[1..],
+            _ => input,
+        };
+        let words = input
+            .chunks(8)
+            .map(|word| word.try_into().ok().map(i64::from_le_bytes))
+            .collect::<Option<Vec<i64>>>()?;
+        match words[..] {
+            [op_code, operand_a, operand_b] if op_code != OP_EXPRESSION => {
+                Some(Calculation::Single { op_code, operand_a, operand_b })
+            }
+            [OP_EXPRESSION, count, ref tokens @ ..]
+                if usize::try_from(count).ok().and_then(|count| count.checked_mul(2)) == Some(tokens.len()) =>
+            {
+                let tokens = tokens
+                    .chunks(2)
+                    .map(|token| match *token {
+                        [0, literal] => Some(ExprToken::Push(literal)),
+                        [1, op_code] => Some(ExprToken::Op(op_code)),
+                        [2, 0] => Some(ExprToken::Dup),
+                        [3, 0] => Some(ExprToken::Swap),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Calculation::Expression(tokens))
+            }
+            _ => None,
+        }
+    }
+
+    /// The guest input: `op, a, b` as little-endian `i64`s, or an expression
+    /// frame, behind a version 2 header when an operation needs one
+    pub fn input(&self) -> Vec<u8> {
+        match *self {
+            Calculation::Single { op_code, operand_a, operand_b } => encode_single(op_code, operand_a, operand_b),
+            Calculation::Expression(ref tokens) => encode_expression(tokens),
+        }
+    }
+}
+
+/// One checked operation, with why the guest can't compute it
+fn apply(op_code: i64, operand_a: i64, operand_b: i64) -> Result<i64, String> {
+    let operation = Operation { op: op_code, a: operand_a, b: operand_b };
+    calculator_ops::apply(op_code, operand_a, operand_b).map_err(|error| match error {
+        OpError::DivisionByZero => format!("{} divides by zero", operation),
+        OpError::Overflow => format!(
+            "{} overflows a 64-bit integer (results must lie between {} and {})",
+            operation,
+            i64::MIN,
+            i64::MAX
+        ),
+        OpError::UnknownOperation => format!("operation code {} is not supported", op_code),
+        OpError::InvalidOperand => match split_operation(op_code).map(|(op, _)| op) {
+            Some(OP_ISQRT) => format!("{} takes the square root of a negative number", operation),
+            Some(OP_LOG2) => format!("{} takes the logarithm of a number below 1", operation),
+            _ => format!("{} has a negative exponent", operation),
+        },
+    })
+}
+
+/// The operation code for an operation name or symbol, e.g. `mod` or `%`
+pub fn parse_operation(name: &str) -> Option<i64> {
+    let op_code = match name.trim().to_lowercase().as_str() {
+        "add" | "+" => OP_ADD,
+        "subtract" | "sub" | "-" => OP_SUBTRACT,
+        "multiply" | "mul" | "*" => OP_MULTIPLY,
+        "divide" | "div" | "/" => OP_DIVIDE,
+        "modulo" | "mod" | "%" => OP_MODULO,
+        "power" | "pow" | "^" => OP_POWER,
+        "abs" => OP_ABS,
+        "min" => OP_MIN,
+        "max" => OP_MAX,
+        "gcd" => OP_GCD,
+        "isqrt" | "sqrt" => OP_ISQRT,
+        "log2" => OP_LOG2,
+        "bps_floor" | "bps" => OP_BPS_FLOOR,
+        "bps_ceil" => OP_BPS_CEIL,
+        "bps_round" => OP_BPS_ROUND,
+        _ => return None,
+    };
+    Some(op_code)
+}
+
+/// Random ID for a submission: 8 hex digits of the Unix time, then 8 random hex digits
+pub fn generate_execution_id() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    format!("{:08x}{:08x}", now as u32, rand::random::<u32>())
+}
+
+/// Rejects execution IDs Bonsol would truncate or that don't survive a round trip through logs and URLs
+pub fn validate_execution_id(execution_id: &str) -> Result<()> {
+    if execution_id.is_empty() || execution_id.len() > MAX_EXECUTION_ID_LEN {
+        bail!(ClientError::InvalidArgs(format!(
+            "Execution ID {:?} must be 1 to {} bytes long", execution_id, MAX_EXECUTION_ID_LEN
+        )));
+    }
+    if let Some(c) = execution_id.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '-')) {
+        bail!(ClientError::InvalidArgs(format!(
+            "Execution ID {:?} contains {:?}; use letters, digits, '_' or '-'", execution_id, c
+        )));
+    }
+    Ok(())
+}
+
+/// Pads or truncates an execution ID to the `MAX_EXECUTION_ID_LEN` bytes Bonsol expects
+///
+/// Validate user input with `validate_execution_id` first: the truncation is
+/// by byte, so it panics inside a multi-byte character.
+pub fn pad_execution_id(execution_id: &str) -> String {
+    let padded = format!("{:0<width$}", execution_id, width = MAX_EXECUTION_ID_LEN);
+    padded[..MAX_EXECUTION_ID_LEN.min(padded.len())].to_string()
+}
@@ -0,0 +1,177 @@
+use anyhow::{bail, Context, Result};
+use calculator_common::{calculation_address, CalculationRecord, CalculationStatus, HistoryEntry};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::bonsol::{fetch_execution_status, ExecutionStatus};
+use crate::calculation::{generate_execution_id, pad_execution_id, validate_execution_id, Calculation};
+use crate::error::ClientError;
+use crate::{instruction, records};
+
+/// How often `wait_for_result` polls a calculation record by default
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Calculator program operations over an RPC client the caller owns
+///
+/// Transactions are signed by the `Signer`s each call is given and sent with
+/// the RPC client's commitment; nothing is printed. Signers are `Sync` so the
+/// returned futures can run on a multi-threaded runtime, such as a web
+/// server's handlers.
+pub struct CalculatorClient {
+    rpc: Arc<RpcClient>,
+    program_id: Pubkey,
+    poll_interval: Duration,
+}
+
+/// A calculation submitted through the calculator program
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub signature: Signature,
+    /// The execution ID, padded to the 16 bytes Bonsol expects
+    pub execution_id: String,
+    pub state: Pubkey,
+    /// Index of the calculation among the state's records
+    pub index: u64,
+    /// The calculation record the callback completes
+    pub record: Pubkey,
+    /// Requester of the Bonsol execution, the payer of the submission
+    pub requester: Pubkey,
+}
+
+impl CalculatorClient {
+    /// Client of the deployed calculator program
+    pub fn new(rpc: Arc<RpcClient>) -> Self {
+        CalculatorClient { rpc, program_id: bonsol_calculator_backend::id(), poll_interval: DEFAULT_POLL_INTERVAL }
+    }
+
+    /// Targets another deployment of the calculator program, such as one on a local validator
+    pub fn with_program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    /// How often `wait_for_result` polls the calculation record
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// Creates the calculator state account `state`, owned by `payer`
+    ///
+    /// `state` signs as the new account, so it is usually a fresh keypair the
+    /// caller keeps to refer to the calculator by its public key.
+    pub async fn initialize(&self, payer: &(dyn Signer + Sync), state: &(dyn Signer + Sync)) -> Result<Signature> {
+        let instruction = instruction::initialize(&self.program_id, &payer.pubkey(), &state.pubkey())?;
+        self.send(payer, &[state], instruction).await
+    }
+
+    /// Submits `calculation` to the calculator `state`, paid for and requested by `payer`
+    ///
+    /// A given `execution_id` is validated and padded, and one is generated
+    /// when it is `None`. Calculations the guest can't compute are rejected
+    /// before anything is sent, since the prover would claim the tip for
+    /// proving the failure.
+    pub async fn submit(
+        &self,
+        payer: &(dyn Signer + Sync),
+        state: &Pubkey,
+        calculation: &Calculation,
+        execution_id: Option<&str>,
+    ) -> Result<Submission> {
+        calculation.validate()?;
+        let execution_id = match execution_id {
+            Some(execution_id) => {
+                validate_execution_id(execution_id)?;
+                pad_execution_id(execution_id)
+            }
+            None => generate_execution_id(),
+        };
+
+        let calculator_state = records::fetch_state(&self.rpc, state).await?;
+        let requester = payer.pubkey();
+        let index = calculator_state.calculation_count;
+        let instruction =
+            instruction::submit(&self.program_id, state, &calculator_state, &requester, &execution_id, calculation)?;
+        let signature = self.send(payer, &[], instruction).await?;
+
+        let (record, _) = calculation_address(&self.program_id, state, index);
+        Ok(Submission { signature, execution_id, state: *state, index, record, requester })
+    }
+
+    /// Polls the record of `submission` until its callback lands, returning
+    /// it complete, failed or awaiting finalization
+    ///
+    /// Fails with `ClientError::CallbackTimeout` when no callback lands within `timeout`.
+    pub async fn wait_for_result(&self, submission: &Submission, timeout: Duration) -> Result<CalculationRecord> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let Some(calc) = records::fetch_record(&self.rpc, &self.program_id, &submission.record).await? else {
+                bail!("Calculation record {} of {} no longer exists", submission.record, submission.execution_id);
+            };
+            if calc.retried {
+                bail!("Calculation #{} was superseded by a retry; wait for the retry instead", calc.index);
+            }
+            if HistoryEntry::from(&calc).status != CalculationStatus::Pending {
+                return Ok(calc);
+            }
+            if Instant::now() + self.poll_interval > deadline {
+                bail!(ClientError::CallbackTimeout(format!(
+                    "No callback for {} within {}s",
+                    submission.execution_id,
+                    timeout.as_secs()
+                )));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Every calculation record of `state` that hasn't been pruned, in index order
+    pub async fn get_history(&self, state: &Pubkey) -> Result<Vec<CalculationRecord>> {
+        records::fetch_records(&self.rpc, &self.program_id, state).await
+    }
+
+    /// Where the Bonsol execution `requester` made under `execution_id` is in its lifecycle
+    ///
+    /// `execution_id` is the padded ID a `Submission` carries.
+    pub async fn status(&self, requester: &Pubkey, execution_id: &str) -> Result<ExecutionStatus> {
+        fetch_execution_status(&self.rpc, requester, execution_id).await
+    }
+
+    /// Signs `instruction` with the payer and `extra_signers`, then sends and confirms it
+    async fn send(
+        &self,
+        payer: &(dyn Signer + Sync),
+        extra_signers: &[&(dyn Signer + Sync)],
+        instruction: Instruction,
+    ) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash().await.context("Failed to get latest blockhash")?;
+        let mut transaction = Transaction::new_unsigned(Message::new(&[instruction], Some(&payer.pubkey())));
+        {
+            // Not held across the send, which would keep the future from being `Send`
+            let mut signers: Vec<&dyn Signer> = vec![payer];
+            signers.extend(extra_signers.iter().map(|signer| *signer as &dyn Signer));
+            transaction.try_sign(&signers, blockhash).context("Failed to sign transaction")?;
+        }
+        self.rpc
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .context("Failed to send transaction")
+    }
+}
@@ -0,0 +1,35 @@
+//! Failure categories callers can branch on
+//!
+//! Errors are `anyhow` chains so context reads naturally; a `ClientError`
+//! anywhere in the chain says what kind of failure it was, and can be found
+//! with `anyhow::Error::downcast_ref` or by walking `chain()`.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// Arguments or input files that can't describe a valid request
+    InvalidArgs(String),
+    /// The RPC node or its websocket failed or could not be reached
+    Rpc(String),
+    /// A transaction failed in simulation, so it was not sent
+    Simulation(String),
+    /// A transaction was rejected, failed on-chain or never landed
+    Transaction(String),
+    /// No callback arrived within the wait timeout
+    CallbackTimeout(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidArgs(message)
+            | ClientError::Rpc(message)
+            | ClientError::Simulation(message)
+            | ClientError::Transaction(message)
+            | ClientError::CallbackTimeout(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
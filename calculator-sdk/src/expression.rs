@@ -0,0 +1,314 @@
+//! Parsing of infix arithmetic like `17 * (3 + 4)` into the RPN program
+//! the guest evaluates
+//!
+//! Numbers are `i64` literals, optionally negated with a leading `-`; the
+//! operators are `+ - * / %` with the usual precedence and left
+//! associativity, and `^` for powers, which binds tighter and associates to
+//! the right. `abs(x)`, `min(a, b)`, `max(a, b)`, `gcd(a, b)`, `isqrt(x)`,
+//! `log2(x)` and the basis-point shares `bps_floor(a, bps)`, `bps_ceil(a, bps)`
+//! and `bps_round(a, bps)` are written as functions, and parentheses group.
+//!
+//! With `--rpn` the program is written directly as space-separated tokens,
+//! such as `3 dup * 4 swap -`: numbers push themselves, operators and
+//! function names apply to the values on the stack, `dup` copies the top
+//! value and `swap` exchanges the top two.
+
+use anyhow::{bail, Result};
+use calculator_common::constants::{
+    MAX_EXPRESSION_TOKENS, MAX_STACK_DEPTH, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE, OP_GCD,
+    OP_ISQRT, OP_LOG2, OP_MAX, OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT,
+};
+use calculator_common::ExprToken;
+use calculator_ops::{arity, symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lexeme {
+    Number(i64),
+    Op(i64),
+    Function(i64),
+    Open,
+    Close,
+    Comma,
+}
+
+/// A `(` waiting for its `)`, and the arguments seen so far when it opens a function call
+struct Group {
+    function: Option<i64>,
+    arguments: usize,
+}
+
+/// Parses `input` into an RPN program of at most `MAX_EXPRESSION_TOKENS` tokens
+pub fn parse(input: &str) -> Result<Vec<ExprToken>> {
+    let lexemes = lex(input)?;
+
+    // Shunting-yard: operands go straight to the output, operators wait on a
+    // stack until an operator of lower precedence arrives (or of equal
+    // precedence, for left-associative ones)
+    let mut output = Vec::with_capacity(lexemes.len());
+    let mut operators: Vec<Lexeme> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut expect_operand = true;
+    for lexeme in lexemes {
+        if let Some(&Lexeme::Function(function)) = operators.last() {
+            if lexeme != Lexeme::Open {
+                bail!("Expected '(' after {} in {:?}", symbol(function), input);
+            }
+        }
+        match lexeme {
+            Lexeme::Number(value) if expect_operand => {
+                output.push(ExprToken::Push(value));
+                expect_operand = false;
+            }
+            Lexeme::Function(_) if expect_operand => operators.push(lexeme),
+            Lexeme::Open if expect_operand => {
+                // A `(` right after a function name opens its call
+                let function = match operators.last() {
+                    Some(&Lexeme::Function(function)) => Some(function),
+                    _ => None,
+                };
+                operators.push(lexeme);
+                groups.push(Group { function, arguments: 1 });
+            }
+            Lexeme::Op(operation) if !expect_operand => {
+                while let Some(&Lexeme::Op(top)) = operators.last() {
+                    let binds_tighter = precedence(top) > precedence(operation)
+                        || (precedence(top) == precedence(operation) && operation != OP_POWER);
+                    if !binds_tighter {
+                        break;
+                    }
+                    output.push(ExprToken::Op(top));
+                    operators.pop();
+                }
+                operators.push(lexeme);
+                expect_operand = true;
+            }
+            Lexeme::Comma if !expect_operand => {
+                pop_group(&mut operators, &mut output, input)?;
+                match groups.last_mut() {
+                    Some(group) if group.function.is_some() => group.arguments += 1,
+                    _ => bail!("Unexpected ',' outside a function call in {:?}", input),
+                }
+                operators.push(Lexeme::Open);
+                expect_operand = true;
+            }
+            Lexeme::Close if !expect_operand => {
+                pop_group(&mut operators, &mut output, input)?;
+                let Some(group) = groups.pop() else {
+                    bail!("Unbalanced ')' in {:?}", input);
+                };
+                if let Some(function) = group.function {
+                    if group.arguments != arity(function) {
+                        bail!(
+                            "{} takes {} argument(s), got {} in {:?}",
+                            symbol(function),
+                            arity(function),
+                            group.arguments,
+                            input
+                        );
+                    }
+                    // The function's name sits right below its `(`
+                    operators.pop();
+                    output.push(ExprToken::Op(function));
+                }
+            }
+            _ if expect_operand => bail!("Expected a number, a function or '(' in {:?}", input),
+            _ => bail!("Expected an operator, ',' or ')' in {:?}", input),
+        }
+    }
+
+    if expect_operand {
+        bail!("Expression {:?} is incomplete", input);
+    }
+    while let Some(lexeme) = operators.pop() {
+        match lexeme {
+            Lexeme::Op(operation) => output.push(ExprToken::Op(operation)),
+            _ => bail!("Unbalanced '(' in {:?}", input),
+        }
+    }
+
+    if output.len() > MAX_EXPRESSION_TOKENS {
+        bail!(
+            "Expression has {} numbers and operators, at most {} are supported",
+            output.len(),
+            MAX_EXPRESSION_TOKENS
+        );
+    }
+    check_stack(&output, input)?;
+    Ok(output)
+}
+
+/// Parses space-separated RPN tokens into a program of at most `MAX_EXPRESSION_TOKENS` tokens
+pub fn parse_rpn(input: &str) -> Result<Vec<ExprToken>> {
+    let mut output = Vec::new();
+    for word in input.split_whitespace() {
+        let token = match word.to_lowercase().as_str() {
+            "+" => ExprToken::Op(OP_ADD),
+            "-" => ExprToken::Op(OP_SUBTRACT),
+            "*" => ExprToken::Op(OP_MULTIPLY),
+            "/" => ExprToken::Op(OP_DIVIDE),
+            "%" => ExprToken::Op(OP_MODULO),
+            "^" => ExprToken::Op(OP_POWER),
+            "abs" => ExprToken::Op(OP_ABS),
+            "min" => ExprToken::Op(OP_MIN),
+            "max" => ExprToken::Op(OP_MAX),
+            "gcd" => ExprToken::Op(OP_GCD),
+            "isqrt" => ExprToken::Op(OP_ISQRT),
+            "log2" => ExprToken::Op(OP_LOG2),
+            "bps_floor" => ExprToken::Op(OP_BPS_FLOOR),
+            "bps_ceil" => ExprToken::Op(OP_BPS_CEIL),
+            "bps_round" => ExprToken::Op(OP_BPS_ROUND),
+            "dup" => ExprToken::Dup,
+            "swap" => ExprToken::Swap,
+            _ => match word.parse() {
+                Ok(literal) => ExprToken::Push(literal),
+                Err(_) => bail!(
+                    "Unknown RPN token {:?}; use numbers, + - * / % ^, abs, min, max, gcd, isqrt, log2, bps_floor, \
+                     bps_ceil, bps_round, dup or swap",
+                    word
+                ),
+            },
+        };
+        output.push(token);
+    }
+
+    if output.is_empty() || output.len() > MAX_EXPRESSION_TOKENS {
+        bail!("RPN program must have 1 to {} tokens, got {}", MAX_EXPRESSION_TOKENS, output.len());
+    }
+    check_stack(&output, input)?;
+    Ok(output)
+}
+
+/// Checks that a program finds its operands on the stack, stays within
+/// `MAX_STACK_DEPTH` values and leaves exactly one
+fn check_stack(tokens: &[ExprToken], input: &str) -> Result<()> {
+    let mut depth = 0usize;
+    for token in tokens {
+        let (needs, leaves) = match *token {
+            ExprToken::Push(_) => (0, 1),
+            ExprToken::Op(operation) => (arity(operation), 1),
+            ExprToken::Dup => (1, 2),
+            ExprToken::Swap => (2, 2),
+        };
+        if depth < needs {
+            bail!("Not enough values on the stack in {:?}", input);
+        }
+        depth = depth - needs + leaves;
+        if depth > MAX_STACK_DEPTH {
+            bail!("{:?} holds more than {} values on the stack at once", input, MAX_STACK_DEPTH);
+        }
+    }
+    if depth != 1 {
+        bail!("{:?} leaves {} values on the stack, expected 1", input, depth);
+    }
+    Ok(())
+}
+
+/// Moves the operators of the innermost group to the output and pops its `(`
+fn pop_group(operators: &mut Vec<Lexeme>, output: &mut Vec<ExprToken>, input: &str) -> Result<()> {
+    loop {
+        match operators.pop() {
+            Some(Lexeme::Op(top)) => output.push(ExprToken::Op(top)),
+            Some(Lexeme::Open) => return Ok(()),
+            _ => bail!("Unbalanced ')' in {:?}", input),
+        }
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<Lexeme>> {
+    let mut lexemes = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                lexemes.push(Lexeme::Open);
+            }
+            ')' => {
+                chars.next();
+                lexemes.push(Lexeme::Close);
+            }
+            ',' => {
+                chars.next();
+                lexemes.push(Lexeme::Comma);
+            }
+            '+' | '*' | '/' | '%' | '^' => {
+                chars.next();
+                lexemes.push(Lexeme::Op(match c {
+                    '+' => OP_ADD,
+                    '*' => OP_MULTIPLY,
+                    '/' => OP_DIVIDE,
+                    '%' => OP_MODULO,
+                    _ => OP_POWER,
+                }));
+            }
+            '-' => {
+                chars.next();
+                // A minus where an operand is expected negates the literal after it
+                let negates = matches!(
+                    lexemes.last(),
+                    None | Some(Lexeme::Op(_)) | Some(Lexeme::Open) | Some(Lexeme::Comma)
+                );
+                if !negates {
+                    lexemes.push(Lexeme::Op(OP_SUBTRACT));
+                } else if chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    lexemes.push(Lexeme::Number(read_number(&mut chars, true)?));
+                } else {
+                    bail!("Unary '-' is only supported directly before a number");
+                }
+            }
+            '0'..='9' => lexemes.push(Lexeme::Number(read_number(&mut chars, false)?)),
+            'a'..='z' | 'A'..='Z' => {
+                let mut name = String::new();
+                // Names may continue with digits and underscores, as in `log2` and `bps_floor`
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                    name.push(c);
+                    chars.next();
+                }
+                let function = match name.to_lowercase().as_str() {
+                    "abs" => OP_ABS,
+                    "min" => OP_MIN,
+                    "max" => OP_MAX,
+                    "gcd" => OP_GCD,
+                    "isqrt" => OP_ISQRT,
+                    "log2" => OP_LOG2,
+                    "bps_floor" => OP_BPS_FLOOR,
+                    "bps_ceil" => OP_BPS_CEIL,
+                    "bps_round" => OP_BPS_ROUND,
+                    _ => bail!(
+                        "Unknown function {:?}; use abs, min, max, gcd, isqrt, log2, bps_floor, bps_ceil or bps_round",
+                        name
+                    ),
+                };
+                lexemes.push(Lexeme::Function(function));
+            }
+            _ => bail!("Unexpected character {:?} in expression", c),
+        }
+    }
+    Ok(lexemes)
+}
+
+fn read_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>, negative: bool) -> Result<i64> {
+    let mut digits = String::from(if negative { "-" } else { "" });
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    match digits.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("{} does not fit in an i64", digits),
+    }
+}
+
+fn precedence(operation: i64) -> u8 {
+    match operation {
+        OP_POWER => 3,
+        OP_MULTIPLY | OP_DIVIDE | OP_MODULO => 2,
+        _ => 1,
+    }
+}
@@ -0,0 +1,190 @@
+//! Calculator program instructions, with the accounts each one expects
+
+use anyhow::{Context, Result};
+use bonsol_calculator_backend::derive_retry_execution_id;
+use bonsol_interface::util::{deployment_address, execution_address};
+use borsh::BorshSerialize;
+use calculator_common::{
+    calculation_address, requester_stats_address, treasury_address, CalculationRecord, CalculatorInstruction,
+    CalculatorState, MEMO_PROGRAM_ID,
+};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use spl_associated_token_account_client::address::get_associated_token_address;
+
+use crate::calculation::Calculation;
+
+/// `Initialize`, creating the calculator state account `state` owned by `payer`
+///
+/// Both sign: `state` is a fresh keypair the program allocates the account at.
+pub fn initialize(program_id: &Pubkey, payer: &Pubkey, state: &Pubkey) -> Result<Instruction> {
+    let (treasury, _) = treasury_address(program_id, state);
+    Ok(Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::Initialize.try_to_vec()?,
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*state, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(treasury, false),
+        ],
+    ))
+}
+
+/// `SubmitCalculation` or `SubmitExpression` for `calculation`, submitted by
+/// `requester` for itself
+///
+/// The calculation becomes record `calculation_count` of `state`, so
+/// `calculator_state` must be fresh: a submission landing in between takes
+/// the index and this one fails.
+pub fn submit(
+    program_id: &Pubkey,
+    state: &Pubkey,
+    calculator_state: &CalculatorState,
+    requester: &Pubkey,
+    execution_id: &str,
+    calculation: &Calculation,
+) -> Result<Instruction> {
+    submit_sponsored(program_id, state, calculator_state, requester, requester, execution_id, calculation)
+}
+
+/// `submit`, with `payer` covering fees, tip and rent for a calculation
+/// attributed to `requester`
+///
+/// Only `payer` signs; a state with `config.requester_must_sign` set also
+/// needs the requester's signature on the transaction.
+pub fn submit_sponsored(
+    program_id: &Pubkey,
+    state: &Pubkey,
+    calculator_state: &CalculatorState,
+    payer: &Pubkey,
+    requester: &Pubkey,
+    execution_id: &str,
+    calculation: &Calculation,
+) -> Result<Instruction> {
+    let (record, _) = calculation_address(program_id, state, calculator_state.calculation_count);
+    let (treasury, _) = treasury_address(program_id, state);
+    let (requester_stats, _) = requester_stats_address(program_id, state, requester);
+    let (deployment, _) = deployment_address(&calculator_state.image_id);
+    let (execution, _) = execution_address(requester, execution_id.as_bytes());
+
+    let data = match calculation {
+        Calculation::Single { op_code, operand_a, operand_b } => CalculatorInstruction::SubmitCalculation {
+            execution_id: Some(execution_id.to_string()),
+            operation: *op_code,
+            operand_a: *operand_a,
+            operand_b: *operand_b,
+            callback_accounts: Vec::new(),
+            result_hook: None,
+            manual_finalize: false,
+            referrer: None,
+            result_bounds: None,
+            prover_version: None,
+            label: None,
+            operand_a_variable: None,
+            operand_b_variable: None,
+        },
+        Calculation::Expression(tokens) => CalculatorInstruction::SubmitExpression {
+            execution_id: Some(execution_id.to_string()),
+            tokens: tokens.clone(),
+        },
+    }
+    .try_to_vec()?;
+
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*requester, requester == payer),
+        AccountMeta::new(*state, false),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(treasury, false),
+        AccountMeta::new(requester_stats, false),
+        AccountMeta::new_readonly(deployment, false),
+        AccountMeta::new(execution, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(bonsol_interface::ID, false),
+    ];
+    if let Some(ref token_fee) = calculator_state.config.token_fee {
+        if token_fee.amount > 0 {
+            accounts.push(AccountMeta::new(get_associated_token_address(payer, &token_fee.mint), false));
+            accounts.push(AccountMeta::new(get_associated_token_address(&treasury, &token_fee.mint), false));
+            accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+        }
+    }
+
+    Ok(Instruction::new_with_bytes(*program_id, &data, accounts))
+}
+
+/// `RetryCalculation` of `original`, an expired calculation of `state`, paid
+/// for by `payer`
+///
+/// The retry becomes record `calculation_count` of `state`, so
+/// `calculator_state` must be fresh, as for `submit`.
+pub fn retry(
+    program_id: &Pubkey,
+    state: &Pubkey,
+    calculator_state: &CalculatorState,
+    payer: &Pubkey,
+    original: &CalculationRecord,
+) -> Result<Instruction> {
+    let (original_record, _) = calculation_address(program_id, state, original.index);
+    let (record, _) = calculation_address(program_id, state, calculator_state.calculation_count);
+    let (deployment, _) = deployment_address(&calculator_state.image_id);
+    let retry_count = original.retry_count.checked_add(1).context("The calculation was retried too many times")?;
+    let retry_execution_id = derive_retry_execution_id(&original.execution_id, retry_count);
+    let (execution, _) = execution_address(&original.requester, retry_execution_id.as_bytes());
+
+    let data = CalculatorInstruction::RetryCalculation { execution_id: original.execution_id.clone() }.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*state, false),
+        AccountMeta::new(original_record, false),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(original.requester, false),
+        AccountMeta::new_readonly(deployment, false),
+        AccountMeta::new(execution, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(bonsol_interface::ID, false),
+    ];
+    Ok(Instruction::new_with_bytes(*program_id, &data, accounts))
+}
+
+/// `Callback` delivering `journal` for `calc`, signed by `signer`, with the
+/// accounts the program registered in the record's Bonsol callback config
+///
+/// Bonsol sends this once the execution is proven, signed by the execution
+/// request PDA of `calc` (see `execution_address`). Only a `localnet` build
+/// of the program accepts another signer, e.g. a mock prover's keypair.
+pub fn callback(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    calc: &CalculationRecord,
+    journal: Vec<u8>,
+) -> Result<Instruction> {
+    let (record, _) = calculation_address(program_id, &calc.state, calc.index);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new(calc.state, false),
+        AccountMeta::new(record, false),
+    ];
+    accounts.extend(calc.callback_accounts.iter().map(|account| match account.is_writable {
+        true => AccountMeta::new(account.pubkey, false),
+        false => AccountMeta::new_readonly(account.pubkey, false),
+    }));
+    if let Some(hook_program_id) = calc.result_hook {
+        accounts.push(AccountMeta::new_readonly(hook_program_id, false));
+    }
+    if let Some(referrer) = calc.referrer {
+        let (treasury, _) = treasury_address(program_id, &calc.state);
+        accounts.push(AccountMeta::new(treasury, false));
+        accounts.push(AccountMeta::new(referrer, false));
+    }
+    if calc.emit_memo {
+        accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+
+    let data = CalculatorInstruction::Callback { execution_id: calc.execution_id.clone(), journal }.try_to_vec()?;
+    Ok(Instruction::new_with_bytes(*program_id, &data, accounts))
+}
@@ -0,0 +1,31 @@
+//! Building, sending and tracking calculator submissions from Rust
+//!
+//! `CalculatorClient` wraps an RPC client the caller creates and shares, so
+//! a backend can initialize calculators, submit calculations through the
+//! calculator program, wait for their proven results and read their history
+//! without shelling out to the CLI. The instruction builders, record fetching
+//! and Bonsol execution decoding it is made of are public as well, and the
+//! CLI is built on them. `expression` parses infix and RPN arithmetic into
+//! expression programs, and `logs` decodes the events the calculator
+//! program logs, for services following its activity. With the `metrics`
+//! feature, `metrics` exports what such services see to Prometheus.
+
+pub mod bonsol;
+pub mod calculation;
+mod client;
+pub mod error;
+pub mod expression;
+pub mod instruction;
+pub mod logs;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod records;
+
+pub use bonsol::{fetch_execution_status, ExecutionLifecycle, ExecutionStatus, EXECUTION_TIP};
+pub use calculation::{
+    generate_execution_id, pad_execution_id, parse_operation, validate_execution_id, Calculation, OPERATION_NAMES,
+};
+pub use calculator_common::{CalculationRecord, CalculationStatus, CalculatorState, ExprToken, HistoryEntry};
+pub use client::{CalculatorClient, Submission};
+pub use error::ClientError;
+pub use logs::{decode_events, CallbackOutcome, LogEvent};
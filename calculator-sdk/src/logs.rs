@@ -0,0 +1,177 @@
+//! Decoding the calculator program's logs
+//!
+//! The program logs every submission and callback it handles, so the logs
+//! of a confirmed transaction are enough to tell what happened to which
+//! execution, whoever sent it. They arrive from a websocket log
+//! subscription, a Geyser stream or a fetched transaction alike.
+
+use solana_client::rpc_response::RpcLogsResponse;
+
+const LOG_PREFIX: &str = "Program log: ";
+/// Logged by the calculator program when a callback arrives
+pub const CALLBACK_RECEIVED: &str = "Callback received for execution ID: ";
+
+/// How the calculator program handled the callback of an execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallbackOutcome {
+    /// The proven result was committed to the calculation record
+    Completed { description: String, result: i64 },
+    /// The proven result fell outside the submission's bounds
+    OutOfBounds,
+    /// The guest proved the calculation can't be computed, e.g. `DivisionByZero`
+    GuestError(String),
+    /// The journal is parked until an owner runs `FinalizeCalculation`
+    AwaitingFinalize,
+    /// The callback transaction failed
+    Failed(String),
+}
+
+/// A calculator event in one transaction's logs
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEvent {
+    /// A calculation was submitted to Bonsol for proving
+    Submitted { execution_id: String, description: String },
+    /// A calculation was computed by the program itself, without Bonsol
+    ComputedLocally { execution_id: String, description: String },
+    /// An expired execution was resubmitted under a new execution ID
+    Retried { execution_id: String, retry_execution_id: String },
+    Callback {
+        execution_id: String,
+        outcome: CallbackOutcome,
+        /// Slots between submission and callback, as logged by the program
+        latency_slots: Option<u64>,
+        /// zkVM cycles the guest reported, when its journal was metered
+        cycles: Option<u64>,
+    },
+}
+
+impl CallbackOutcome {
+    /// `completed`, `out_of_bounds`, `guest_error`, `awaiting_finalize` or `failed`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallbackOutcome::Completed { .. } => "completed",
+            CallbackOutcome::OutOfBounds => "out_of_bounds",
+            CallbackOutcome::GuestError(_) => "guest_error",
+            CallbackOutcome::AwaitingFinalize => "awaiting_finalize",
+            CallbackOutcome::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Decodes the calculator events in one transaction's logs, in the order they were logged
+pub fn decode_events(logs: &RpcLogsResponse) -> Vec<LogEvent> {
+    let mut events = Vec::new();
+    let messages: Vec<&str> = logs.logs.iter().filter_map(|line| log_message(line)).collect();
+
+    // A failed transaction left no submission behind
+    if logs.err.is_none() {
+        // The execution ID is logged on the line after the calculation
+        let mut described: Option<(bool, &str)> = None;
+        for message in &messages {
+            if let Some(description) = message.strip_prefix("Submitted ZK execution request: ") {
+                described = Some((false, description));
+            } else if let Some(computed) = message.strip_prefix("Computed locally: ") {
+                described = Some((true, computed));
+            } else if let (Some(execution_id), Some((local, description))) =
+                (message.strip_prefix("Execution ID: "), described.take())
+            {
+                let execution_id = execution_id.to_string();
+                let description = description.to_string();
+                events.push(match local {
+                    true => LogEvent::ComputedLocally { execution_id, description },
+                    false => LogEvent::Submitted { execution_id, description },
+                });
+            } else if let Some((execution_id, retry)) = message
+                .strip_prefix("Retrying execution ")
+                .and_then(|rest| rest.split_once(" as "))
+            {
+                let retry_execution_id = retry.split(' ').next().unwrap_or(retry);
+                events.push(LogEvent::Retried {
+                    execution_id: execution_id.to_string(),
+                    retry_execution_id: retry_execution_id.to_string(),
+                });
+            }
+        }
+    }
+
+    let callback = messages.iter().find_map(|message| message.strip_prefix(CALLBACK_RECEIVED));
+    if let Some(execution_id) = callback {
+        let latency_slots = messages
+            .iter()
+            .find_map(|message| message.strip_prefix("Proving latency: "))
+            .and_then(|latency| latency.split(' ').next())
+            .and_then(|slots| slots.parse().ok());
+        events.push(LogEvent::Callback {
+            execution_id: execution_id.to_string(),
+            outcome: parse_outcome(logs),
+            latency_slots,
+            cycles: parse_cycles(logs),
+        });
+    }
+
+    events
+}
+
+/// The message of a `Program log: ` line
+pub fn log_message(line: &str) -> Option<&str> {
+    line.strip_prefix(LOG_PREFIX)
+}
+
+/// The guest cycle count the callback in `logs` reported, if its journal was metered
+pub fn parse_cycles(logs: &RpcLogsResponse) -> Option<u64> {
+    logs.logs
+        .iter()
+        .filter_map(|line| log_message(line))
+        .find_map(|message| message.strip_prefix("Guest cycles: ")?.parse().ok())
+}
+
+/// How the callback in `logs` was handled
+pub fn parse_outcome(logs: &RpcLogsResponse) -> CallbackOutcome {
+    if let Some(err) = &logs.err {
+        return CallbackOutcome::Failed(format!("{:?}", err));
+    }
+
+    for message in logs.logs.iter().filter_map(|line| log_message(line)) {
+        if let Some(completed) = message.strip_prefix("✅ ZK computation completed: ") {
+            if let Some((description, result)) = completed.rsplit_once(" = ") {
+                if let Ok(result) = result.parse() {
+                    return CallbackOutcome::Completed { description: description.to_string(), result };
+                }
+            }
+        }
+        if let Some(reason) = message.strip_prefix("❌ ZK computation failed: ") {
+            return CallbackOutcome::GuestError(reason.to_string());
+        }
+        if message.starts_with("❌ Result ") && message.contains("outside accepted bounds") {
+            return CallbackOutcome::OutOfBounds;
+        }
+        if message.starts_with("Journal stored, awaiting FinalizeCalculation") {
+            return CallbackOutcome::AwaitingFinalize;
+        }
+    }
+
+    CallbackOutcome::Failed("callback was ignored by the calculator program".to_string())
+}
+
+/// Derives the websocket endpoint the way the Solana CLI does: `ws(s)://` on
+/// the next port up when one is given
+pub fn websocket_url(rpc_url: &str) -> String {
+    let (scheme, rest) = if let Some(rest) = rpc_url.strip_prefix("https://") {
+        ("wss://", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        ("ws://", rest)
+    } else {
+        return rpc_url.to_string();
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(split) => rest.split_at(split),
+        None => (rest, ""),
+    };
+    if let Some((host, port)) = authority.rsplit_once(':') {
+        if let Ok(port) = port.parse::<u16>() {
+            return format!("{}{}:{}{}", scheme, host, port.saturating_add(1), path);
+        }
+    }
+    format!("{}{}{}", scheme, authority, path)
+}
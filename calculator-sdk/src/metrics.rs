@@ -0,0 +1,88 @@
+//! Prometheus metrics for services following the calculator
+//!
+//! `serve` installs a recorder exposing every metric at `/metrics` on the
+//! given address. The other functions record one event each and do nothing
+//! until a recorder is installed, so callers record unconditionally and only
+//! pay for it when asked to export. Proving latency is observed both in slots,
+//! as the program logs it, and in seconds where the caller saw the
+//! submission itself. Only built with the `metrics` feature.
+
+use anyhow::{Context, Result};
+use metrics::{counter, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::bonsol::EXECUTION_TIP;
+use crate::logs::{CallbackOutcome, LogEvent};
+
+pub const SUBMISSIONS: &str = "calculator_submissions_total";
+pub const LOCAL_COMPUTATIONS: &str = "calculator_local_computations_total";
+pub const RETRIES: &str = "calculator_retries_total";
+pub const COMPLETIONS: &str = "calculator_completions_total";
+/// Labelled with the `outcome`: `out_of_bounds`, `guest_error` or `failed`
+pub const FAILURES: &str = "calculator_failures_total";
+pub const AWAITING_FINALIZE: &str = "calculator_awaiting_finalize_total";
+pub const TIPS_LAMPORTS: &str = "calculator_tips_lamports_total";
+pub const PROVING_LATENCY_SLOTS: &str = "calculator_proving_latency_slots";
+pub const PROVING_LATENCY_SECONDS: &str = "calculator_proving_latency_seconds";
+pub const GUEST_CYCLES: &str = "calculator_guest_cycles";
+
+const LATENCY_SLOT_BUCKETS: [f64; 10] = [5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0];
+const LATENCY_SECOND_BUCKETS: [f64; 10] = [2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+const CYCLE_BUCKETS: [f64; 6] = [1e3, 1e4, 1e5, 1e6, 1e7, 1e8];
+
+/// Serves the metrics recorded from now on at `http://<listen>/metrics`
+///
+/// Must be called from within a Tokio runtime, which the listener runs on.
+pub fn serve(listen: SocketAddr) -> Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(listen)
+        .set_buckets_for_metric(Matcher::Full(PROVING_LATENCY_SLOTS.to_string()), &LATENCY_SLOT_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(PROVING_LATENCY_SECONDS.to_string()), &LATENCY_SECOND_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(GUEST_CYCLES.to_string()), &CYCLE_BUCKETS)?
+        .install()
+        .with_context(|| format!("Failed to serve metrics on {}", listen))
+}
+
+/// Records a calculator event decoded from a transaction's logs
+pub fn observe(event: &LogEvent) {
+    match event {
+        LogEvent::Submitted { .. } => submitted(EXECUTION_TIP),
+        LogEvent::ComputedLocally { .. } => counter!(LOCAL_COMPUTATIONS).increment(1),
+        LogEvent::Retried { .. } => {
+            // The retry is a new execution request with a tip of its own
+            counter!(RETRIES).increment(1);
+            counter!(TIPS_LAMPORTS).increment(EXECUTION_TIP);
+        }
+        LogEvent::Callback { outcome, latency_slots, cycles, .. } => callback(outcome, *latency_slots, *cycles),
+    }
+}
+
+/// Records an execution request submitted with a `tip_lamports` tip
+pub fn submitted(tip_lamports: u64) {
+    counter!(SUBMISSIONS).increment(1);
+    counter!(TIPS_LAMPORTS).increment(tip_lamports);
+}
+
+/// Records a callback, with the proving latency in slots and guest cycles when known
+pub fn callback(outcome: &CallbackOutcome, latency_slots: Option<u64>, cycles: Option<u64>) {
+    match outcome {
+        CallbackOutcome::Completed { .. } => counter!(COMPLETIONS).increment(1),
+        CallbackOutcome::AwaitingFinalize => counter!(AWAITING_FINALIZE).increment(1),
+        CallbackOutcome::OutOfBounds | CallbackOutcome::GuestError(_) | CallbackOutcome::Failed(_) => {
+            counter!(FAILURES, "outcome" => outcome.name()).increment(1)
+        }
+    }
+    if let Some(slots) = latency_slots {
+        histogram!(PROVING_LATENCY_SLOTS).record(slots as f64);
+    }
+    if let Some(cycles) = cycles {
+        histogram!(GUEST_CYCLES).record(cycles as f64);
+    }
+}
+
+/// Records the wall-clock time from seeing a submission to seeing its callback
+pub fn callback_latency(elapsed: Duration) {
+    histogram!(PROVING_LATENCY_SECONDS).record(elapsed.as_secs_f64());
+}
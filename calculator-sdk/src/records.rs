@@ -0,0 +1,113 @@
+//! Reading calculator state accounts and their calculation records
+
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_common::{calculation_address, codec, CalculationRecord, CalculatorState};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::ClientError;
+
+// Largest batch accepted by getMultipleAccounts
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Fetches and decodes the calculator state account `state`
+pub async fn fetch_state(client: &RpcClient, state: &Pubkey) -> Result<CalculatorState> {
+    let state_data = client
+        .get_account_data(state)
+        .await
+        .with_context(|| format!("Failed to fetch calculator state {}", state))?;
+    codec::decode(&state_data).map_err(|e| anyhow!("{} is not a calculator state account: {}", state, e))
+}
+
+/// Fetches every calculation record of `state` that hasn't been pruned, in index order
+pub async fn fetch_records(client: &RpcClient, program_id: &Pubkey, state: &Pubkey) -> Result<Vec<CalculationRecord>> {
+    let calculator_state = fetch_state(client, state).await?;
+
+    let addresses: Vec<Pubkey> = (0..calculator_state.calculation_count)
+        .map(|index| calculation_address(program_id, state, index).0)
+        .collect();
+
+    let mut records = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = client
+            .get_multiple_accounts(chunk)
+            .await
+            .context("Failed to fetch calculation records")?;
+        for account in accounts.into_iter().flatten() {
+            // Pruned records are closed and come back empty
+            if account.owner != *program_id {
+                continue;
+            }
+            let calc: CalculationRecord = codec::decode(&account.data)
+                .map_err(|e| anyhow!("Failed to decode calculation record: {}", e))?;
+            records.push(calc);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Fetches the calculation record at `record`, `None` once it has been pruned
+pub async fn fetch_record(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    record: &Pubkey,
+) -> Result<Option<CalculationRecord>> {
+    let account = client
+        .get_account_with_commitment(record, client.commitment())
+        .await
+        .with_context(|| format!("Failed to fetch calculation record {}", record))?
+        .value;
+    match account {
+        Some(account) if account.owner == *program_id => codec::decode(&account.data)
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to decode calculation record {}: {}", record, e)),
+        _ => Ok(None),
+    }
+}
+
+/// Finds the record of `execution_id` across every calculator state, with the state it belongs to
+pub async fn find_record(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    execution_id: &str,
+) -> Result<(Pubkey, CalculationRecord)> {
+    // The execution ID follows the state, index and bump; Borsh prefixes strings with their length
+    let offset = codec::LEN_PREFIX + 32 + 8 + 1;
+    let mut needle = (execution_id.len() as u32).to_le_bytes().to_vec();
+    needle.extend_from_slice(execution_id.as_bytes());
+
+    let accounts = client
+        .get_program_accounts_with_config(
+            program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, &needle))]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .await
+        .context("Failed to search calculation records")?;
+
+    let mut found: Vec<CalculationRecord> = accounts
+        .iter()
+        .filter_map(|(_, account)| codec::decode::<CalculationRecord>(&account.data).ok())
+        .filter(|calc| calc.execution_id == execution_id)
+        .collect();
+    match found.len() {
+        0 => bail!("No calculation with execution ID {}", execution_id),
+        1 => {
+            let calc = found.remove(0);
+            Ok((calc.state, calc))
+        }
+        n => bail!(ClientError::InvalidArgs(format!(
+            "{} calculations share execution ID {}; look it up in its state account instead", n, execution_id
+        ))),
+    }
+}
@@ -0,0 +1,266 @@
+//! What the dashboard shows, updated from decoded events
+//!
+//! Counters cover what was seen since the dashboard started. The history
+//! holds the newest calculations first, up to `--history` of them, each
+//! updated in place when its callback or retry arrives. The selection is
+//! `None` while the table follows the newest row; once the operator scrolls
+//! it sticks to the row they picked as new ones arrive above it.
+
+use calculator_sdk::logs::{decode_events, CallbackOutcome, LogEvent};
+use calculator_sdk::{CalculationRecord, CalculationStatus, HistoryEntry};
+use chrono::{DateTime, Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::widgets::TableState;
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::source::Update;
+
+/// Proving latencies kept for the sparkline
+const LATENCY_WINDOW: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pending,
+    Local,
+    Retried,
+    Completed,
+    OutOfBounds,
+    GuestError,
+    AwaitingFinalize,
+    Failed,
+}
+
+/// A row of the history table
+pub struct Entry {
+    pub slot: u64,
+    pub time: DateTime<Local>,
+    pub execution_id: String,
+    pub description: String,
+    pub status: Status,
+    pub result: Option<i64>,
+    /// Why it failed, or the execution ID it was retried as
+    pub detail: Option<String>,
+    pub latency_slots: Option<u64>,
+    /// When the dashboard saw the submission, for the age of pending rows
+    pub seen: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct Counts {
+    pub submitted: u64,
+    pub local: u64,
+    pub retried: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub awaiting_finalize: u64,
+}
+
+pub struct App {
+    /// Program or state account watched, and where from
+    pub account: String,
+    pub source: String,
+    pub connection: Result<(), String>,
+    pub last_slot: u64,
+    pub counts: Counts,
+    /// Execution IDs waiting for their callback
+    pub pending: HashSet<String>,
+    pub latencies: VecDeque<u64>,
+    pub history: VecDeque<Entry>,
+    pub table: TableState,
+    capacity: usize,
+}
+
+impl App {
+    pub fn new(account: String, source: String, capacity: usize) -> Self {
+        App {
+            account,
+            source,
+            connection: Err("Connecting…".to_string()),
+            last_slot: 0,
+            counts: Counts::default(),
+            pending: HashSet::new(),
+            latencies: VecDeque::with_capacity(LATENCY_WINDOW),
+            history: VecDeque::with_capacity(capacity),
+            table: TableState::default(),
+            capacity,
+        }
+    }
+
+    /// Fills the history with calculations recorded before the dashboard started, in index order
+    pub fn seed(&mut self, records: &[CalculationRecord]) {
+        for calc in records {
+            let (status, detail) = match HistoryEntry::from(calc).status {
+                _ if calc.retried => (Status::Retried, None),
+                CalculationStatus::Pending => (Status::Pending, None),
+                CalculationStatus::AwaitingFinalize => (Status::AwaitingFinalize, None),
+                CalculationStatus::Complete => (Status::Completed, None),
+                CalculationStatus::Local => (Status::Local, None),
+                CalculationStatus::Failed(reason) => (Status::Failed, Some(format!("{:?}", reason))),
+            };
+            if status == Status::Pending {
+                self.pending.insert(calc.execution_id.clone());
+            }
+            let latency_slots = calc.completed_slot.map(|slot| slot.saturating_sub(calc.submitted_slot));
+            if let (Some(latency), false) = (latency_slots, calc.is_local) {
+                self.record_latency(latency);
+            }
+            self.push(Entry {
+                slot: calc.submitted_slot,
+                time: Local.timestamp_opt(calc.timestamp, 0).single().unwrap_or_else(Local::now),
+                execution_id: calc.execution_id.clone(),
+                description: calc.describe(),
+                status,
+                result: calc.result,
+                detail,
+                latency_slots,
+                seen: None,
+            });
+        }
+    }
+
+    pub fn update(&mut self, update: Update) {
+        match update {
+            Update::Connected => self.connection = Ok(()),
+            Update::Disconnected(reason) => self.connection = Err(reason),
+            Update::Logs(response) => {
+                self.last_slot = self.last_slot.max(response.context.slot);
+                for event in decode_events(&response.value) {
+                    self.apply(response.context.slot, event);
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, slot: u64, event: LogEvent) {
+        match event {
+            LogEvent::Submitted { execution_id, description } => {
+                self.counts.submitted += 1;
+                self.pending.insert(execution_id.clone());
+                self.push(Entry::new(slot, execution_id, description, Status::Pending));
+            }
+            LogEvent::ComputedLocally { execution_id, description } => {
+                self.counts.local += 1;
+                self.push(Entry::new(slot, execution_id, description, Status::Local));
+            }
+            LogEvent::Retried { execution_id, retry_execution_id } => {
+                self.counts.retried += 1;
+                self.pending.remove(&execution_id);
+                self.pending.insert(retry_execution_id.clone());
+                let mut description = String::new();
+                if let Some(entry) = self.find(&execution_id) {
+                    entry.status = Status::Retried;
+                    entry.detail = Some(format!("as {}", retry_execution_id));
+                    description = entry.description.clone();
+                }
+                self.push(Entry::new(slot, retry_execution_id, description, Status::Pending));
+            }
+            LogEvent::Callback { execution_id, outcome, latency_slots, .. } => {
+                self.pending.remove(&execution_id);
+                let (status, result, detail) = match &outcome {
+                    CallbackOutcome::Completed { result, .. } => (Status::Completed, Some(*result), None),
+                    CallbackOutcome::OutOfBounds => (Status::OutOfBounds, None, None),
+                    CallbackOutcome::GuestError(reason) => (Status::GuestError, None, Some(reason.clone())),
+                    CallbackOutcome::AwaitingFinalize => (Status::AwaitingFinalize, None, None),
+                    CallbackOutcome::Failed(reason) => (Status::Failed, None, Some(reason.clone())),
+                };
+                match status {
+                    Status::Completed => self.counts.completed += 1,
+                    Status::AwaitingFinalize => self.counts.awaiting_finalize += 1,
+                    _ => self.counts.failed += 1,
+                }
+                if let Some(latency) = latency_slots {
+                    self.record_latency(latency);
+                }
+                match self.find(&execution_id) {
+                    Some(entry) => {
+                        entry.status = status;
+                        entry.result = result;
+                        entry.detail = detail;
+                        entry.latency_slots = latency_slots;
+                    }
+                    None => {
+                        // Submitted before the dashboard started
+                        let description = match outcome {
+                            CallbackOutcome::Completed { description, .. } => description,
+                            _ => String::new(),
+                        };
+                        let entry = Entry::new(slot, execution_id, description, status);
+                        self.push(Entry { result, detail, latency_slots, seen: None, ..entry });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles a key press, returning whether the dashboard should quit
+    pub fn key(&mut self, key: KeyEvent) -> bool {
+        let last = self.history.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table.select(Some(self.table.selected().map_or(0, |row| row + 1).min(last)));
+            }
+            KeyCode::Up | KeyCode::Char('k') => match self.table.selected() {
+                Some(0) | None => self.table.select(None),
+                Some(row) => self.table.select(Some(row - 1)),
+            },
+            KeyCode::PageDown => self.table.select(Some(self.table.selected().map_or(0, |row| row + 20).min(last))),
+            KeyCode::PageUp => self.table.select(self.table.selected().and_then(|row| row.checked_sub(20))),
+            KeyCode::Home | KeyCode::Char('g') => self.table.select(None),
+            KeyCode::End | KeyCode::Char('G') => self.table.select(Some(last)),
+            _ => {}
+        }
+        false
+    }
+
+    /// Whether the selection is following the newest row
+    pub fn following(&self) -> bool {
+        self.table.selected().is_none()
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.history.push_front(entry);
+        self.history.truncate(self.capacity);
+        // Keep a scrolled selection on the row it was on
+        if let Some(row) = self.table.selected() {
+            self.table.select(Some((row + 1).min(self.history.len() - 1)));
+        }
+    }
+
+    fn find(&mut self, execution_id: &str) -> Option<&mut Entry> {
+        self.history.iter_mut().find(|entry| entry.execution_id == execution_id)
+    }
+
+    fn record_latency(&mut self, latency_slots: u64) {
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency_slots);
+    }
+}
+
+impl Entry {
+    fn new(slot: u64, execution_id: String, description: String, status: Status) -> Self {
+        Entry {
+            slot,
+            time: Local::now(),
+            execution_id,
+            description,
+            status,
+            result: None,
+            detail: None,
+            latency_slots: None,
+            seen: Some(Instant::now()),
+        }
+    }
+
+    /// How long a pending calculation has been waiting since the dashboard saw it
+    pub fn age(&self) -> Option<Duration> {
+        match self.status {
+            Status::Pending => self.seen.map(|seen| seen.elapsed()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Streaming transactions from a Yellowstone gRPC (Geyser) endpoint
+//!
+//! Each confirmed transaction mentioning the watched account is turned into
+//! the logs response the websocket would have delivered, so both sources
+//! feed the dashboard the same way. The gRPC client is only built with the
+//! `geyser` feature.
+
+use anyhow::Result;
+use futures_util::stream::BoxStream;
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+
+use crate::source::Source;
+
+/// Streams confirmed transactions mentioning `source.account` from `--geyser-url`
+#[cfg(feature = "geyser")]
+pub async fn subscribe(source: &Source) -> Result<BoxStream<'static, Response<RpcLogsResponse>>> {
+    use anyhow::{bail, Context};
+    use futures_util::{future, StreamExt};
+    use std::collections::HashMap;
+    use tonic::transport::ClientTlsConfig;
+    use yellowstone_grpc_client::GeyserGrpcClient;
+    use yellowstone_grpc_proto::prelude::{CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions};
+
+    let Some(url) = &source.geyser_url else {
+        bail!("Nothing to subscribe to without --geyser-url");
+    };
+    let mut client = GeyserGrpcClient::build_from_shared(url.clone())
+        .with_context(|| format!("Invalid Geyser endpoint {}", url))?
+        .x_token(source.geyser_token.clone())
+        .context("Invalid --geyser-token")?
+        .tls_config(ClientTlsConfig::new().with_native_roots())
+        .context("Failed to configure TLS for the Geyser endpoint")?
+        .connect()
+        .await
+        .with_context(|| format!("Failed to connect to Geyser endpoint {}", url))?;
+
+    let request = SubscribeRequest {
+        transactions: HashMap::from([(
+            "calculator".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                account_include: vec![source.account.to_string()],
+                ..Default::default()
+            },
+        )]),
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    };
+    let (requests, updates) = client
+        .subscribe_with_request(Some(request))
+        .await
+        .context("Failed to subscribe to transactions over Geyser")?;
+
+    // The request sink keeps the subscription open for as long as the stream is read
+    Ok(updates
+        .take_while(|update| future::ready(update.is_ok()))
+        .filter_map(move |update| {
+            let _requests = &requests;
+            future::ready(update.ok().and_then(convert::logs_response))
+        })
+        .boxed())
+}
+
+#[cfg(not(feature = "geyser"))]
+pub async fn subscribe(_source: &Source) -> Result<BoxStream<'static, Response<RpcLogsResponse>>> {
+    anyhow::bail!("--geyser-url needs the dashboard built with the `geyser` feature: cargo run --features geyser")
+}
+
+#[cfg(feature = "geyser")]
+mod convert {
+    use solana_client::rpc_response::{Response, RpcLogsResponse, RpcResponseContext};
+    use solana_sdk::{signature::Signature, transaction::TransactionError};
+    use yellowstone_grpc_proto::prelude::{subscribe_update::UpdateOneof, SubscribeUpdate};
+
+    /// The logs response the websocket would have delivered for a transaction update
+    pub fn logs_response(update: SubscribeUpdate) -> Option<Response<RpcLogsResponse>> {
+        let Some(UpdateOneof::Transaction(update)) = update.update_oneof else {
+            // Pings and updates of other kinds
+            return None;
+        };
+        let transaction = update.transaction?;
+        let meta = transaction.meta?;
+        let signature = Signature::try_from(transaction.signature.as_slice()).ok()?;
+        // Errors travel bincode-encoded, as the validator stores them
+        let err = meta
+            .err
+            .and_then(|err| bincode::deserialize::<TransactionError>(&err.err).ok());
+        Some(Response {
+            context: RpcResponseContext::new(update.slot),
+            value: RpcLogsResponse {
+                signature: signature.to_string(),
+                err,
+                logs: meta.log_messages,
+            },
+        })
+    }
+}
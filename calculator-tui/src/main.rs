@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use calculator_sdk::logs::websocket_url;
+use calculator_sdk::records;
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use futures_util::StreamExt;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+mod app;
+mod geyser;
+mod source;
+mod ui;
+
+use app::App;
+use source::Source;
+
+#[derive(Parser)]
+#[command(name = "calculator-tui")]
+#[command(about = "Terminal dashboard of the calculator program's live submissions and callbacks")]
+struct Cli {
+    /// RPC URL for the Solana cluster
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// Websocket URL to subscribe on [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Yellowstone gRPC endpoint to stream transactions from instead of the websocket (`geyser` feature)
+    #[arg(long)]
+    geyser_url: Option<String>,
+
+    /// Access token for --geyser-url, sent as `x-token`
+    #[arg(long, requires = "geyser_url")]
+    geyser_token: Option<String>,
+
+    /// Calculator program to watch [default: the deployed calculator program]
+    #[arg(long)]
+    program_id: Option<Pubkey>,
+
+    /// Only show activity of this calculator state account, starting from its recorded history
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Calculations kept in the history table
+    #[arg(long, default_value_t = 1000)]
+    history: usize,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let program_id = cli.program_id.unwrap_or_else(bonsol_calculator_backend::id);
+    let source = Source {
+        ws_url: cli.ws_url.unwrap_or_else(|| websocket_url(&cli.rpc_url)),
+        geyser_url: cli.geyser_url,
+        geyser_token: cli.geyser_token,
+        account: cli.state.unwrap_or(program_id),
+    };
+    let mut app = App::new(source.account.to_string(), source.url().to_string(), cli.history.max(1));
+
+    // Subscribe first, so nothing recorded while the history loads is missed
+    let (sender, mut updates) = mpsc::channel(1024);
+    tokio::spawn(source::run(source, sender));
+    if let Some(state) = &cli.state {
+        let rpc = RpcClient::new_with_commitment(cli.rpc_url.clone(), CommitmentConfig::confirmed());
+        let records = records::fetch_records(&rpc, &program_id, state)
+            .await
+            .with_context(|| format!("Failed to load the history of {}", state))?;
+        app.seed(&records);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = run_dashboard(&mut terminal, &mut app, &mut updates).await;
+    ratatui::restore();
+    result
+}
+
+async fn run_dashboard(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    updates: &mut mpsc::Receiver<source::Update>,
+) -> Result<()> {
+    let mut keys = EventStream::new();
+    // Redraws at least once a second, so the age of pending calculations keeps counting
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        terminal.draw(|frame| ui::draw(frame, app)).context("Failed to draw the dashboard")?;
+        tokio::select! {
+            Some(update) = updates.recv() => app.update(update),
+            event = keys.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    if app.key(key) {
+                        return Ok(());
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e).context("Failed to read the terminal"),
+                None => return Ok(()),
+            },
+            _ = tick.tick() => {}
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Feeding the dashboard confirmed transactions
+//!
+//! Transactions mentioning the watched account arrive from an RPC websocket
+//! log subscription, or with `--geyser-url` from a Yellowstone gRPC stream.
+//! The subscription runs on its own task and is re-established whenever it
+//! fails or closes, so the dashboard survives a node restart; what happened
+//! in between is missed, and the connection line says so.
+
+use anyhow::{bail, Context, Result};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::geyser;
+
+/// Wait before resubscribing after the subscription fails or closes
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Where transactions come from
+pub struct Source {
+    pub ws_url: String,
+    pub geyser_url: Option<String>,
+    #[cfg_attr(not(feature = "geyser"), allow(dead_code))]
+    pub geyser_token: Option<String>,
+    pub account: Pubkey,
+}
+
+pub enum Update {
+    Connected,
+    /// Why the subscription ended; it is retried after `RESUBSCRIBE_DELAY`
+    Disconnected(String),
+    Logs(Response<RpcLogsResponse>),
+}
+
+impl Source {
+    /// Endpoint transactions are streamed from, for the title bar
+    pub fn url(&self) -> &str {
+        self.geyser_url.as_deref().unwrap_or(&self.ws_url)
+    }
+}
+
+/// Sends `updates` until the dashboard stops reading them
+pub async fn run(source: Source, updates: mpsc::Sender<Update>) {
+    loop {
+        let reason = match forward(&source, &updates).await {
+            Ok(()) => "Subscription closed".to_string(),
+            Err(e) => format!("{:#}", e),
+        };
+        if updates.send(Update::Disconnected(reason)).await.is_err() {
+            return;
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn forward(source: &Source, updates: &mpsc::Sender<Update>) -> Result<()> {
+    if source.geyser_url.is_some() {
+        let logs = geyser::subscribe(source).await?;
+        return forward_stream(logs, updates).await;
+    }
+    let pubsub = PubsubClient::new(&source.ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to websocket {}", source.ws_url))?;
+    let (logs, unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![source.account.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+    let result = forward_stream(logs, updates).await;
+    unsubscribe().await;
+    result
+}
+
+async fn forward_stream(
+    mut logs: BoxStream<'_, Response<RpcLogsResponse>>,
+    updates: &mpsc::Sender<Update>,
+) -> Result<()> {
+    if updates.send(Update::Connected).await.is_err() {
+        return Ok(());
+    }
+    while let Some(response) = logs.next().await {
+        if updates.send(Update::Logs(response)).await.is_err() {
+            return Ok(());
+        }
+    }
+    bail!("Transaction stream closed")
+}
@@ -0,0 +1,142 @@
+//! Drawing the dashboard
+//!
+//! A title bar with the watched account and connection, a line of counters,
+//! a sparkline of recent proving latencies in slots, the history table and
+//! a line of key bindings.
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Sparkline, Table};
+use ratatui::Frame;
+
+use crate::app::{App, Entry, Status};
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [title, counts, latency, history, keys] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Min(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    draw_title(frame, app, title);
+    draw_counts(frame, app, counts);
+    draw_latency(frame, app, latency);
+    draw_history(frame, app, history);
+    let keys_line = match app.following() {
+        true => " q quit  ↑/↓ scroll  PgUp/PgDn page  G oldest",
+        false => " q quit  ↑/↓ scroll  PgUp/PgDn page  g follow newest  G oldest",
+    };
+    frame.render_widget(Paragraph::new(keys_line).dark_gray(), keys);
+}
+
+fn draw_title(frame: &mut Frame, app: &App, area: Rect) {
+    let connection = match &app.connection {
+        Ok(()) => Span::from("● live").green(),
+        Err(reason) => Span::from(format!("○ {}", reason)).red(),
+    };
+    let line = Line::from(vec![
+        Span::from(" Calculator ").bold().reversed(),
+        Span::from(format!(" {}  via {}  ", app.account, app.source)),
+        connection,
+    ]);
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+fn draw_counts(frame: &mut Frame, app: &App, area: Rect) {
+    let counts = &app.counts;
+    let mut spans = Vec::new();
+    let mut counter = |label: &str, value: u64, color: Color| {
+        spans.push(Span::from(format!(" {} ", label)));
+        spans.push(Span::from(value.to_string()).fg(color).bold());
+        spans.push(Span::from("  "));
+    };
+    counter("Submitted", counts.submitted, Color::White);
+    counter("Pending", app.pending.len() as u64, Color::Yellow);
+    counter("Completed", counts.completed, Color::Green);
+    counter("Failed", counts.failed, Color::Red);
+    counter("Awaiting finalize", counts.awaiting_finalize, Color::Magenta);
+    counter("Local", counts.local, Color::Blue);
+    counter("Retried", counts.retried, Color::Cyan);
+    spans.push(Span::from(format!(" slot {}", app.last_slot)).dark_gray());
+    let block = Block::bordered().title(" Since start ");
+    frame.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+}
+
+fn draw_latency(frame: &mut Frame, app: &App, area: Rect) {
+    let latencies: Vec<u64> = app.latencies.iter().copied().collect();
+    let title = match (latencies.iter().min(), latencies.iter().max()) {
+        (Some(min), Some(max)) => {
+            let average = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
+            format!(
+                " Proving latency, last {} callbacks: min {} / avg {:.0} / max {} slots ",
+                latencies.len(),
+                min,
+                average,
+                max
+            )
+        }
+        _ => " Proving latency: no callbacks yet ".to_string(),
+    };
+    // The newest latencies, as many as fit
+    let width = area.width.saturating_sub(2) as usize;
+    let shown = &latencies[latencies.len().saturating_sub(width)..];
+    let sparkline = Sparkline::default().block(Block::bordered().title(title)).data(shown).cyan();
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_history(frame: &mut Frame, app: &mut App, area: Rect) {
+    let header = Row::new(["Time", "Slot", "Execution ID", "Calculation", "Status", "Result", "Latency"])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+    let rows = app.history.iter().map(row);
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(16),
+        Constraint::Min(20),
+        Constraint::Length(18),
+        Constraint::Length(22),
+        Constraint::Length(10),
+    ];
+    let title = format!(" History ({} shown) ", app.history.len());
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(table, area, &mut app.table);
+}
+
+fn row(entry: &Entry) -> Row<'static> {
+    let (status, color) = match entry.status {
+        Status::Pending => ("⏳ pending", Color::Yellow),
+        Status::Local => ("🖩 local", Color::Blue),
+        Status::Retried => ("🔁 retried", Color::Cyan),
+        Status::Completed => ("✅ completed", Color::Green),
+        Status::OutOfBounds => ("❌ out of bounds", Color::Red),
+        Status::GuestError => ("❌ guest error", Color::Red),
+        Status::AwaitingFinalize => ("📥 awaiting final", Color::Magenta),
+        Status::Failed => ("❌ failed", Color::Red),
+    };
+    let result = match (entry.result, &entry.detail) {
+        (Some(result), _) => result.to_string(),
+        (None, Some(detail)) => detail.clone(),
+        (None, None) => String::new(),
+    };
+    let latency = match (entry.latency_slots, entry.age()) {
+        (Some(slots), _) => format!("{} slots", slots),
+        (None, Some(age)) => format!("{}s…", age.as_secs()),
+        (None, None) => String::new(),
+    };
+    Row::new([
+        Cell::from(entry.time.format("%H:%M:%S").to_string()),
+        Cell::from(entry.slot.to_string()),
+        Cell::from(entry.execution_id.clone()),
+        Cell::from(entry.description.clone()),
+        Cell::from(status).fg(color),
+        Cell::from(result),
+        Cell::from(latency),
+    ])
+}
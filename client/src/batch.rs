@@ -0,0 +1,242 @@
+//! Batch submission of calculations from a JSON or CSV file
+//!
+//! Each entry becomes its own Bonsol execution with a generated execution ID;
+//! as many execute instructions as fit are packed into each transaction.
+
+use anyhow::{bail, Context, Result};
+use calculator_sdk::{parse_operation, Calculation, OPERATION_NAMES};
+use clap::Args;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::{
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signer,
+};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::submit::execute_instruction;
+use crate::error::ClientError;
+use crate::journal;
+use crate::{emit, lookup, send_instructions, Cli};
+
+#[derive(Args)]
+pub struct SubmitBatchArgs {
+    /// JSON array of {"operation", "a", "b"} objects, or CSV with `operation,a,b` rows
+    #[arg(long)]
+    file: PathBuf,
+
+    /// Expiration in slots from current slot
+    #[arg(long, default_value = "1000")]
+    expiration_slots: u64,
+
+    /// Have Bonsol check the SHA-256 digest of each entry's input before proving
+    #[arg(long)]
+    verify_input_hash: bool,
+
+    /// Most execute instructions packed into one transaction
+    #[arg(long, default_value = "4")]
+    max_per_transaction: usize,
+
+    /// Most transactions in flight at once
+    #[arg(long, default_value = "4")]
+    concurrency: usize,
+}
+
+/// One calculation of a batch file
+#[derive(Deserialize)]
+struct BatchEntry {
+    operation: String,
+    a: i64,
+    b: i64,
+}
+
+/// Outcome of one batch entry
+#[derive(Serialize)]
+struct EntryReport {
+    index: usize,
+    execution_id: String,
+    calculation: String,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &SubmitBatchArgs) -> Result<()> {
+    if args.max_per_transaction == 0 || args.concurrency == 0 {
+        bail!(ClientError::InvalidArgs("--max-per-transaction and --concurrency must be at least 1".to_string()));
+    }
+
+    let entries = read_entries(&args.file)?;
+    say!("📂 Loaded {} calculation(s) from {}", entries.len(), args.file.display());
+    cli.deployment.validate(client).await?;
+
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let expiration = current_slot + args.expiration_slots;
+    let id_prefix = execution_id_prefix();
+
+    // Build every instruction up front so a bad entry fails before anything is sent
+    let mut reports = Vec::with_capacity(entries.len());
+    let mut instructions = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let calculation = entry
+            .to_calculation()
+            .and_then(|calculation| calculation.validate().map(|_| calculation))
+            .with_context(|| format!("Invalid entry #{}", index))?;
+        let execution_id = format!("{}{:08}", id_prefix, index);
+        instructions.push(execute_instruction(
+            &cli.deployment,
+            &payer.pubkey(),
+            &execution_id,
+            &calculation,
+            expiration,
+            args.verify_input_hash,
+        )?);
+        reports.push(EntryReport {
+            index,
+            execution_id,
+            calculation: calculation.describe(),
+            signature: None,
+            error: None,
+        });
+    }
+
+    // Split the entries into transactions
+    let budget = cli.budget.template();
+    let tables = lookup::load(client, &cli.lookup_tables).await?;
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < instructions.len() {
+        let end = pack(&payer.pubkey(), &budget, &instructions[start..], &tables, args.max_per_transaction)? + start;
+        ranges.push(start..end);
+        start = end;
+    }
+
+    // Confirmation prompts can't be answered for several transactions at once
+    let concurrency = if cli.simulate && !cli.yes { 1 } else { args.concurrency };
+    say!("\n📦 Sending {} transaction(s), up to {} at a time", ranges.len(), concurrency);
+    let instructions = &instructions;
+    let results: Vec<_> = stream::iter(ranges)
+        .map(|range| async move {
+            let result = send_instructions(client, cli, payer, &[], &instructions[range.clone()]).await;
+            (range, result)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    for (range, result) in results {
+        for report in &mut reports[range] {
+            match &result {
+                Ok(signature) => {
+                    report.signature = Some(signature.to_string());
+                    journal::record(
+                        client,
+                        &report.execution_id,
+                        &payer.pubkey(),
+                        signature,
+                        "direct-bonsol",
+                        &report.calculation,
+                    )
+                    .await;
+                }
+                Err(e) => report.error = Some(format!("{:#}", e)),
+            }
+        }
+    }
+
+    let failed = reports.iter().filter(|report| report.error.is_some()).count();
+    say!("\n📊 Batch Summary: {} submitted, {} failed", reports.len() - failed, failed);
+    for report in &reports {
+        match (&report.signature, &report.error) {
+            (Some(signature), _) => say!("   #{} [{}] {} -> {}", report.index, report.execution_id, report.calculation, signature),
+            (_, Some(error)) => say!("   #{} [{}] {} ❌ {}", report.index, report.execution_id, report.calculation, error),
+            _ => {}
+        }
+    }
+
+    emit(&reports)
+}
+
+impl BatchEntry {
+    fn to_calculation(&self) -> Result<Calculation> {
+        let Some(op_code) = parse_operation(&self.operation) else {
+            bail!(ClientError::InvalidArgs(format!(
+                "Invalid operation {:?}. Use: {}",
+                self.operation.trim(),
+                OPERATION_NAMES
+            )));
+        };
+        Ok(Calculation::Single { op_code, operand_a: self.a, operand_b: self.b })
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<BatchEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let is_csv = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    if !is_csv {
+        return serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {} as a JSON array of entries", path.display()));
+    }
+
+    let mut entries = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [operation, a, b] = fields[..] else {
+            bail!(ClientError::InvalidArgs(format!("Line {}: expected `operation,a,b`", line_number + 1)));
+        };
+        // Skip a header row
+        if line_number == 0 && a.parse::<i64>().is_err() {
+            continue;
+        }
+        entries.push(BatchEntry {
+            operation: operation.to_string(),
+            a: a.parse().with_context(|| format!("Line {}: invalid operand {:?}", line_number + 1, a))?,
+            b: b.parse().with_context(|| format!("Line {}: invalid operand {:?}", line_number + 1, b))?,
+        });
+    }
+    Ok(entries)
+}
+
+/// First 8 bytes of the batch's execution IDs, unique per second
+fn execution_id_prefix() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    format!("{:08x}", now as u32)
+}
+
+/// Number of leading `instructions` that fit in one transaction after `budget`
+fn pack(
+    payer: &Pubkey,
+    budget: &[Instruction],
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+    max: usize,
+) -> Result<usize> {
+    let mut count = 0;
+    while count < instructions.len().min(max) {
+        let packed: Vec<Instruction> = budget.iter().chain(&instructions[..=count]).cloned().collect();
+        if lookup::transaction_size(payer, &packed, tables)? > PACKET_DATA_SIZE {
+            break;
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        bail!("A single execute instruction does not fit in a transaction");
+    }
+    Ok(count)
+}
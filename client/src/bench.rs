@@ -0,0 +1,394 @@
+//! Load generator measuring end-to-end proving latency
+//!
+//! Submits random single operations directly to Bonsol, listens for their
+//! callbacks on one log subscription, and reports latency percentiles from
+//! the start of each submission to its callback, the failure rate, and what
+//! the submissions cost the payer. Metered journals also give the mean zkVM
+//! cycles per operation, to catch proving-cost regressions between images.
+//! With `--metrics-listen`, submissions, tips, callbacks and latencies are
+//! exported to Prometheus while the benchmark runs.
+
+use anyhow::{bail, Context, Result};
+use calculator_ops::symbol;
+use calculator_sdk::{generate_execution_id, metrics, Calculation};
+use clap::Args;
+use futures_util::{stream, StreamExt};
+use rand::Rng;
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{native_token::lamports_to_sol, signature::Signer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::error::ClientError;
+use crate::geyser::{self, GeyserArgs};
+use crate::reconcile::{self, Verdict};
+use crate::submit::{execute_instruction, EXECUTION_TIP, OP_ADD, OP_DIVIDE, OP_MULTIPLY, OP_SUBTRACT};
+use crate::wait::{self, CallbackOutcome};
+use crate::{emit, send_instruction, Cli};
+
+// Operands are drawn from -OPERAND_RANGE..=OPERAND_RANGE so no operation overflows
+const OPERAND_RANGE: i64 = 1_000_000;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Calculations to submit
+    #[arg(long, default_value = "10")]
+    count: usize,
+
+    /// Submissions in flight at once
+    #[arg(long, default_value = "4")]
+    concurrency: usize,
+
+    /// Expiration in slots from current slot for each submission
+    #[arg(long, default_value = "1000")]
+    expiration_slots: u64,
+
+    /// Seconds to keep waiting for callbacks after the last submission
+    #[arg(long, default_value = "600")]
+    timeout: u64,
+
+    /// Websocket URL for callback notifications [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    #[command(flatten)]
+    geyser: GeyserArgs,
+
+    /// Write one row per calculation to this CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Write the checks of proven results against the native computation to this JSON file
+    #[arg(long)]
+    reconcile_report: Option<PathBuf>,
+
+    /// Serve Prometheus metrics of the benchmark at `http://<addr>/metrics` while it runs
+    #[arg(long)]
+    metrics_listen: Option<SocketAddr>,
+}
+
+/// What happened to one benchmark calculation
+#[derive(Serialize)]
+struct Sample {
+    index: usize,
+    execution_id: String,
+    calculation: String,
+    expected: Option<i64>,
+    signature: Option<String>,
+    /// `completed`, `mismatch`, `out_of_bounds`, `awaiting_finalize`, `failed`, `send_failed` or `timed_out`
+    outcome: &'static str,
+    result: Option<i64>,
+    /// Seconds from the start of the submission to its callback
+    latency_secs: Option<f64>,
+    /// zkVM cycles the guest's arithmetic consumed, from a metered journal
+    cycles: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    count: usize,
+    concurrency: usize,
+    completed: usize,
+    failed: usize,
+    /// Proven results differing from the native computation, counted in `failed`
+    mismatched: usize,
+    failure_rate: f64,
+    p50_secs: Option<f64>,
+    p95_secs: Option<f64>,
+    p99_secs: Option<f64>,
+    /// Mean guest cycles of each operation's metered callbacks, keyed by its symbol
+    mean_cycles: BTreeMap<&'static str, u64>,
+    /// Lamports the payer spent on the submissions: fees, tips and rent
+    total_cost_lamports: u64,
+    elapsed_secs: f64,
+    samples: Vec<Sample>,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &BenchArgs) -> Result<()> {
+    if args.count == 0 || args.concurrency == 0 {
+        bail!(ClientError::InvalidArgs("--count and --concurrency must be at least 1".to_string()));
+    }
+
+    cli.deployment.validate(client).await?;
+    if let Some(listen) = args.metrics_listen {
+        metrics::serve(listen)?;
+        say!("📈 Serving metrics on http://{}/metrics", listen);
+    }
+    let calculations = random_calculations(args.count);
+    let execution_ids: Vec<String> = (0..args.count).map(|_| generate_execution_id()).collect();
+    let index_of: HashMap<&str, usize> =
+        execution_ids.iter().enumerate().map(|(index, id)| (id.as_str(), index)).collect();
+
+    // Subscribe before sending so no callback can slip past
+    let pubsub = match args.geyser.is_set() {
+        true => None,
+        false => {
+            let ws_url = args.ws_url.clone().unwrap_or_else(|| wait::websocket_url(&client.url()));
+            Some(wait::connect(&ws_url).await?)
+        }
+    };
+    let mut subscription = match &pubsub {
+        Some(pubsub) => wait::subscribe(pubsub, &cli.deployment.callback_program).await?,
+        None => geyser::subscribe(&args.geyser, &cli.deployment.callback_program).await?,
+    };
+
+    let balance_before = client.get_balance(&payer.pubkey()).await.context("Failed to get the payer's balance")?;
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let expiration = current_slot + args.expiration_slots;
+    say!("🏁 Submitting {} calculation(s), {} at a time", args.count, args.concurrency);
+
+    let benchmark_started = Instant::now();
+    let calculations = &calculations;
+    let execution_ids = &execution_ids;
+    let mut sends = stream::iter(0..args.count)
+        .map(|index| async move {
+            let started = Instant::now();
+            let result = async {
+                let instruction = execute_instruction(
+                    &cli.deployment,
+                    &payer.pubkey(),
+                    &execution_ids[index],
+                    &calculations[index],
+                    expiration,
+                    false,
+                )?;
+                send_instruction(client, cli, payer, &[], instruction).await
+            }
+            .await;
+            (index, started, result)
+        })
+        .buffer_unordered(args.concurrency);
+
+    let mut started: Vec<Option<Instant>> = vec![None; args.count];
+    let mut signatures: Vec<Option<String>> = vec![None; args.count];
+    let mut send_errors: Vec<Option<String>> = vec![None; args.count];
+    let mut callbacks: HashMap<usize, (Instant, CallbackOutcome, Option<u64>)> = HashMap::new();
+    let mut awaiting: HashSet<usize> = HashSet::new();
+    let mut sends_done = false;
+    let mut balance_after = balance_before;
+    // Only armed once every submission has been sent
+    let mut deadline = tokio::time::Instant::now() + Duration::from_secs(365 * 24 * 60 * 60);
+
+    while !(sends_done && awaiting.is_empty()) {
+        tokio::select! {
+            sent = sends.next(), if !sends_done => match sent {
+                Some((index, send_started, result)) => {
+                    started[index] = Some(send_started);
+                    match result {
+                        Ok(signature) => {
+                            signatures[index] = Some(signature.to_string());
+                            metrics::submitted(EXECUTION_TIP);
+                            match callbacks.get(&index) {
+                                Some((callback_at, _, _)) => metrics::callback_latency(*callback_at - send_started),
+                                None => {
+                                    awaiting.insert(index);
+                                }
+                            }
+                        }
+                        Err(e) => send_errors[index] = Some(format!("{:#}", e)),
+                    }
+                }
+                None => {
+                    sends_done = true;
+                    balance_after = client
+                        .get_balance(&payer.pubkey())
+                        .await
+                        .context("Failed to get the payer's balance")?;
+                    deadline = tokio::time::Instant::now() + Duration::from_secs(args.timeout);
+                    say!("⏳ All submissions sent; waiting for {} callback(s)...", awaiting.len());
+                }
+            },
+            callback = subscription.next_metered_callback() => {
+                let (execution_id, outcome, cycles) = callback?;
+                // Callbacks of executions submitted elsewhere don't count
+                let Some(&index) = index_of.get(execution_id.as_str()) else {
+                    continue;
+                };
+                metrics::callback(&outcome, None, cycles);
+                if let Some(started) = started[index] {
+                    metrics::callback_latency(started.elapsed());
+                }
+                callbacks.insert(index, (Instant::now(), outcome, cycles));
+                awaiting.remove(&index);
+                say!("   {}/{} callbacks", callbacks.len(), args.count);
+            },
+            _ = tokio::time::sleep_until(deadline) => {
+                say!("⌛ Gave up on {} callback(s) after {}s", awaiting.len(), args.timeout);
+                break;
+            }
+        }
+    }
+    drop(sends);
+    subscription.close().await;
+    let elapsed = benchmark_started.elapsed();
+
+    let mut samples = Vec::with_capacity(args.count);
+    let mut reconciliations = Vec::new();
+    for (index, calculation) in calculations.iter().enumerate() {
+        let expected = calculation.evaluate();
+        let mut sample = Sample {
+            index,
+            execution_id: execution_ids[index].clone(),
+            calculation: calculation.describe(),
+            expected,
+            signature: signatures[index].clone(),
+            outcome: "timed_out",
+            result: None,
+            latency_secs: None,
+            cycles: None,
+            error: send_errors[index].clone(),
+        };
+        if sample.error.is_some() {
+            sample.outcome = "send_failed";
+        } else if let Some((callback_at, outcome, cycles)) = callbacks.remove(&index) {
+            sample.latency_secs = started[index].map(|started| (callback_at - started).as_secs_f64());
+            sample.cycles = cycles;
+            let reconciliation = reconcile::reconcile(&execution_ids[index], calculation, &outcome);
+            let verdict = reconciliation.verdict;
+            reconciliations.push(reconciliation);
+            sample.outcome = match outcome {
+                CallbackOutcome::Completed { result, .. } => {
+                    sample.result = Some(result);
+                    if verdict == Verdict::Match { "completed" } else { "mismatch" }
+                }
+                CallbackOutcome::OutOfBounds => "out_of_bounds",
+                CallbackOutcome::GuestError(reason) => {
+                    sample.error = Some(reason);
+                    "guest_error"
+                }
+                CallbackOutcome::AwaitingFinalize => "awaiting_finalize",
+                CallbackOutcome::Failed(reason) => {
+                    sample.error = Some(reason);
+                    "failed"
+                }
+            };
+        }
+        samples.push(sample);
+    }
+
+    let mut latencies: Vec<f64> = samples.iter().filter_map(|sample| sample.latency_secs).collect();
+    latencies.sort_by(f64::total_cmp);
+    let completed = samples.iter().filter(|sample| sample.outcome == "completed").count();
+    let failed = args.count - completed;
+    let mismatched = samples.iter().filter(|sample| sample.outcome == "mismatch").count();
+    let report = BenchReport {
+        count: args.count,
+        concurrency: args.concurrency,
+        completed,
+        failed,
+        mismatched,
+        failure_rate: failed as f64 / args.count as f64,
+        p50_secs: percentile(&latencies, 50.0),
+        p95_secs: percentile(&latencies, 95.0),
+        p99_secs: percentile(&latencies, 99.0),
+        mean_cycles: mean_cycles(calculations, &samples),
+        total_cost_lamports: balance_before.saturating_sub(balance_after),
+        elapsed_secs: elapsed.as_secs_f64(),
+        samples,
+    };
+
+    say!("\n📊 Benchmark of {} calculation(s) in {:.1}s:", report.count, report.elapsed_secs);
+    say!("   Completed: {}  Failed: {} ({:.1}%)", report.completed, report.failed, report.failure_rate * 100.0);
+    let format_latency = |latency: Option<f64>| latency.map_or_else(|| "-".to_string(), |secs| format!("{:.1}s", secs));
+    say!("   Latency p50: {}  p95: {}  p99: {}",
+             format_latency(report.p50_secs), format_latency(report.p95_secs), format_latency(report.p99_secs));
+    if !report.mean_cycles.is_empty() {
+        let means: Vec<String> =
+            report.mean_cycles.iter().map(|(symbol, cycles)| format!("{} {}", symbol, cycles)).collect();
+        say!("   Mean guest cycles: {}", means.join("  "));
+    }
+    say!("   Total cost: {} lamports ({} SOL)",
+             report.total_cost_lamports, lamports_to_sol(report.total_cost_lamports));
+    for sample in report.samples.iter().filter(|sample| sample.outcome != "completed") {
+        say!("   #{} [{}] {}: {}{}", sample.index, sample.execution_id, sample.calculation, sample.outcome,
+                 sample.error.as_ref().map_or_else(String::new, |error| format!(" ({})", error)));
+    }
+
+    if report.mismatched > 0 {
+        say!("   🚨 {} proven result(s) differ from the native computation", report.mismatched);
+    }
+    if let Some(path) = &args.reconcile_report {
+        reconcile::write_report(path, &reconciliations)?;
+    }
+    if let Some(path) = &args.csv {
+        write_csv(path, &report.samples)?;
+        say!("📝 Wrote {} row(s) to {}", report.samples.len(), path.display());
+    }
+
+    emit(&report)
+}
+
+/// `count` random single operations that the guest can compute
+fn random_calculations(count: usize) -> Vec<Calculation> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let op_code = [OP_ADD, OP_SUBTRACT, OP_MULTIPLY, OP_DIVIDE][rng.gen_range(0..4)];
+            let operand_a = rng.gen_range(-OPERAND_RANGE..=OPERAND_RANGE);
+            let mut operand_b = rng.gen_range(-OPERAND_RANGE..=OPERAND_RANGE);
+            if op_code == OP_DIVIDE && operand_b == 0 {
+                operand_b = 1;
+            }
+            Calculation::Single { op_code, operand_a, operand_b }
+        })
+        .collect()
+}
+
+/// Mean cycle count of the metered samples of each single operation
+fn mean_cycles(calculations: &[Calculation], samples: &[Sample]) -> BTreeMap<&'static str, u64> {
+    let mut totals: BTreeMap<&'static str, (u64, u64)> = BTreeMap::new();
+    for (calculation, sample) in calculations.iter().zip(samples) {
+        if let (Calculation::Single { op_code, .. }, Some(cycles)) = (calculation, sample.cycles) {
+            let (total, count) = totals.entry(symbol(*op_code)).or_default();
+            *total = total.saturating_add(cycles);
+            *count += 1;
+        }
+    }
+    totals.into_iter().map(|(symbol, (total, count))| (symbol, total / count)).collect()
+}
+
+/// Nearest-rank percentile of sorted `values`
+fn percentile(values: &[f64], percentile: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let rank = (percentile / 100.0 * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
+
+fn write_csv(path: &PathBuf, samples: &[Sample]) -> Result<()> {
+    let mut file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    writeln!(file, "index,execution_id,calculation,expected,signature,outcome,result,latency_secs,cycles,error")?;
+    for sample in samples {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{},{}",
+            sample.index,
+            sample.execution_id,
+            csv_field(&sample.calculation),
+            optional(sample.expected.map(|expected| expected.to_string())),
+            optional(sample.signature.clone()),
+            sample.outcome,
+            optional(sample.result.map(|result| result.to_string())),
+            optional(sample.latency_secs.map(|latency| format!("{:.3}", latency))),
+            optional(sample.cycles.map(|cycles| cycles.to_string())),
+            csv_field(sample.error.as_deref().unwrap_or_default()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Quotes a field containing a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
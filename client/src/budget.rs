@@ -0,0 +1,145 @@
+//! Compute budget instructions prepended to every transaction
+//!
+//! With `--priority-fee` and no `--compute-unit-limit`, the limit is
+//! estimated by simulating the transaction, since the fee is charged on the
+//! requested limit rather than the units actually consumed.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_program::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use crate::error::ClientError;
+use crate::lookup;
+
+/// Most compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+// Headroom added to the simulated consumption, in percent
+const ESTIMATE_MARGIN_PERCENT: u64 = 20;
+
+#[derive(Args)]
+pub struct BudgetArgs {
+    /// Priority fee in micro-lamports per compute unit
+    #[arg(long, global = true)]
+    priority_fee: Option<u64>,
+
+    /// Compute unit limit requested per transaction [default: estimated by simulation when --priority-fee is set]
+    #[arg(long, global = true)]
+    compute_unit_limit: Option<u32>,
+}
+
+impl BudgetArgs {
+    /// Budget instructions to prepend to `instructions`, empty when no flag is set
+    pub async fn instructions(
+        &self,
+        client: &RpcClient,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        tables: &[AddressLookupTableAccount],
+    ) -> Result<Vec<Instruction>> {
+        let limit = self.compute_unit_limit(client, payer, instructions, tables).await?;
+        Ok(self.build(limit))
+    }
+
+    /// The compute unit limit to request for `instructions`, estimated when
+    /// only `--priority-fee` is set
+    pub async fn compute_unit_limit(
+        &self,
+        client: &RpcClient,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        tables: &[AddressLookupTableAccount],
+    ) -> Result<Option<u32>> {
+        Ok(match (self.compute_unit_limit, self.priority_fee) {
+            (Some(limit), _) => Some(limit),
+            (None, Some(_)) => Some(estimate_compute_units(client, payer, instructions, tables).await?),
+            (None, None) => None,
+        })
+    }
+
+    /// Lamports `--priority-fee` adds to a transaction requesting `limit` compute units
+    pub fn priority_fee_lamports(&self, limit: u32) -> u64 {
+        // The price is in micro-lamports per unit, rounded up like the runtime does
+        self.priority_fee
+            .map_or(0, |price| (price as u128 * limit as u128).div_ceil(1_000_000) as u64)
+    }
+
+    /// Budget instructions of the same size `instructions` would return, for
+    /// sizing transactions before estimating
+    pub fn template(&self) -> Vec<Instruction> {
+        let limit = match (self.compute_unit_limit, self.priority_fee) {
+            (None, None) => None,
+            (limit, _) => Some(limit.unwrap_or(MAX_COMPUTE_UNIT_LIMIT)),
+        };
+        self.build(limit)
+    }
+
+    fn build(&self, limit: Option<u32>) -> Vec<Instruction> {
+        let mut budget = Vec::with_capacity(2);
+        if let Some(limit) = limit {
+            budget.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+        }
+        if let Some(priority_fee) = self.priority_fee {
+            budget.push(ComputeBudgetInstruction::set_compute_unit_price(priority_fee));
+        }
+        budget
+    }
+}
+
+/// Simulates `instructions` under the maximum limit and returns the units
+/// consumed plus `ESTIMATE_MARGIN_PERCENT`
+async fn estimate_compute_units(
+    client: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> Result<u32> {
+    let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)];
+    simulated.extend_from_slice(instructions);
+    // Unsigned, with the blockhash replaced by the simulation
+    let message = lookup::compile(payer, &simulated, tables, Hash::default())?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message,
+    };
+
+    let simulation = client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(client.commitment()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .context("Failed to simulate transaction for compute unit estimation")?
+        .value;
+
+    if let Some(err) = simulation.err {
+        bail!(ClientError::Simulation(format!(
+            "Simulation failed while estimating compute units: {:?} {:?}",
+            err,
+            simulation.logs.unwrap_or_default()
+        )));
+    }
+    let consumed = simulation
+        .units_consumed
+        .ok_or_else(|| anyhow!("Simulation did not report consumed compute units"))?;
+
+    let limit = consumed.saturating_mul(100 + ESTIMATE_MARGIN_PERCENT) / 100;
+    let limit = limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32;
+    say!("⛽ Estimated compute units: {} (simulated {})", limit, consumed);
+    Ok(limit)
+}
@@ -0,0 +1,418 @@
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_common::constants::{
+    JOURNAL_STATUS_OK, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE, OP_GCD, OP_ISQRT,
+    OP_LOG2, OP_MAX, OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT,
+};
+use calculator_common::{
+    calculation_address, decode_journal, decode_outcome, treasury_address, CalculationRecord, CalculationStatus,
+    GuestJournal, HistoryEntry,
+};
+use calculator_ops::{split_operation, Semantics};
+use calculator_sdk::{fetch_execution_status, instruction, pad_execution_id, records, validate_execution_id};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::hash::hash;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair, Signer},
+};
+
+use crate::calculators;
+use crate::error::ClientError;
+use crate::{emit, send_instruction, signer, Cli};
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Where to save the new state account's keypair
+    #[arg(long)]
+    state_keypair: Option<String>,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Execution ID of the calculation
+    execution_id: String,
+
+    /// Calculator state account; when given, the calculation record is shown too
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Account that requested the execution [default: the record's requester, else the payer]
+    #[arg(long)]
+    requester: Option<Pubkey>,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Calculator state account [default: from the address book]
+    #[arg(long)]
+    state: Option<Pubkey>,
+}
+
+#[derive(Args)]
+pub struct SimulateCallbackArgs {
+    /// Calculator state account [default: found by searching every record for the execution ID]
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Execution ID of the pending calculation
+    #[arg(long)]
+    execution_id: String,
+
+    /// Result to report, encoded the way the ZK program commits it
+    #[arg(long, required_unless_present = "journal")]
+    result: Option<i64>,
+
+    /// Raw journal bytes in hex instead of an encoded --result, to exercise malformed journals
+    #[arg(long, conflicts_with = "result")]
+    journal: Option<String>,
+}
+
+/// `--output json` view of a calculation record
+#[derive(Serialize)]
+struct CalculationReport {
+    index: u64,
+    execution_id: String,
+    description: String,
+    operation: i64,
+    operand_a: i64,
+    operand_b: i64,
+    result: Option<i64>,
+    status: String,
+    label: Option<String>,
+    requester: String,
+    /// Unix timestamp of the submission
+    timestamp: i64,
+    submitted_slot: u64,
+    completed_slot: Option<u64>,
+}
+
+impl From<&CalculationRecord> for CalculationReport {
+    fn from(calc: &CalculationRecord) -> Self {
+        CalculationReport {
+            index: calc.index,
+            execution_id: calc.execution_id.clone(),
+            description: calc.describe(),
+            operation: calc.operation,
+            operand_a: calc.operand_a,
+            operand_b: calc.operand_b,
+            result: calc.result,
+            status: format!("{:?}", HistoryEntry::from(calc).status),
+            label: calc.label.clone(),
+            requester: calc.requester.to_string(),
+            timestamp: calc.timestamp,
+            submitted_slot: calc.submitted_slot,
+            completed_slot: calc.completed_slot,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StatusReport {
+    execution_id: String,
+    calculation: Option<CalculationReport>,
+    requester: Option<String>,
+    execution_account: Option<String>,
+    image_id: Option<String>,
+    tip: Option<u64>,
+    /// Bonsol lifecycle state; absent for calculations computed on-chain
+    status: Option<String>,
+}
+
+pub async fn init(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &InitArgs) -> Result<()> {
+    let program_id = bonsol_calculator_backend::id();
+    let state = Keypair::new();
+    let (treasury, _) = treasury_address(&program_id, &state.pubkey());
+
+    say!("\n🏗️ Initializing calculator state...");
+    say!("📍 State account: {}", state.pubkey());
+    say!("🏦 Treasury: {}", treasury);
+
+    let instruction = instruction::initialize(&program_id, &payer.pubkey(), &state.pubkey())?;
+
+    let signature = send_instruction(client, cli, payer, &[&state], instruction).await?;
+
+    if let Some(path) = &args.state_keypair {
+        write_keypair_file(&state, path)
+            .map_err(|e| anyhow!("Failed to write state keypair to {}: {}", path, e))?;
+        say!("💾 State keypair saved to {}", path);
+    }
+
+    emit(&json!({
+        "signature": signature.to_string(),
+        "state": state.pubkey().to_string(),
+        "treasury": treasury.to_string(),
+        "state_keypair": args.state_keypair,
+    }))
+}
+
+pub async fn status(client: &RpcClient, cli: &Cli, args: &StatusArgs) -> Result<()> {
+    let mut requester = args.requester;
+    validate_execution_id(&args.execution_id)?;
+    // Executions submitted straight to Bonsol carry the 16-byte padded ID
+    let mut execution_id = pad_execution_id(&args.execution_id);
+    let mut report = StatusReport {
+        execution_id: execution_id.clone(),
+        calculation: None,
+        requester: None,
+        execution_account: None,
+        image_id: None,
+        tip: None,
+        status: None,
+    };
+
+    let record = match calculators::resolve_state(cli, args.state)? {
+        Some(state) => {
+            let records = records::fetch_records(client, &bonsol_calculator_backend::id(), &state).await?;
+            match records.into_iter().find(|calc| calc.execution_id == args.execution_id) {
+                Some(calc) => Some(calc),
+                None if args.state.is_some() => {
+                    bail!("No calculation with execution ID {} in {}", args.execution_id, state);
+                }
+                // Executions submitted directly to Bonsol have no record in the default calculator
+                None => {
+                    say!("No calculation with execution ID {} in {}; checking Bonsol only", args.execution_id, state);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    if let Some(calc) = &record {
+
+        print_record(calc);
+        if let Some(ref label) = calc.label {
+            say!("   Label: {}", label);
+        }
+        if let Some(latency) = calc.proving_latency_slots() {
+            say!("   Proving latency: {} slots", latency);
+        }
+        report.execution_id = calc.execution_id.clone();
+        report.calculation = Some(CalculationReport::from(calc));
+        if calc.is_local {
+            say!("   Computed on-chain, no Bonsol execution");
+            return emit(&report);
+        }
+
+        requester.get_or_insert(calc.requester);
+        execution_id = calc.execution_id.clone();
+    }
+
+    let requester = match requester {
+        Some(requester) => requester,
+        None => signer::load(cli).await?.pubkey(),
+    };
+    let status = fetch_execution_status(client, &requester, &execution_id).await?;
+
+    say!("⚡ Bonsol execution {} requested by {}", execution_id, requester);
+    say!("   Execution account: {}", status.execution_account);
+    if let Some(ref image_id) = status.image_id {
+        say!("   Image ID: {}", image_id);
+    }
+    if let Some(tip) = status.tip {
+        say!("   Tip: {} lamports", tip);
+    }
+    say!("   Status: {}", status.lifecycle);
+
+    report.requester = Some(requester.to_string());
+    report.execution_account = Some(status.execution_account.to_string());
+    report.image_id = status.image_id;
+    report.tip = status.tip;
+    report.status = Some(status.lifecycle.to_string());
+    emit(&report)
+}
+
+pub async fn history(client: &RpcClient, cli: &Cli, args: &HistoryArgs) -> Result<()> {
+    let Some(state) = calculators::resolve_state(cli, args.state)? else {
+        bail!(ClientError::InvalidArgs("--state or --calculator is required".to_string()));
+    };
+    let records = records::fetch_records(client, &bonsol_calculator_backend::id(), &state).await?;
+    if records.is_empty() {
+        say!("No calculations recorded for {}", state);
+    } else {
+        say!("📜 {} calculation(s) recorded for {}:", records.len(), state);
+    }
+    if !records.is_empty() {
+        print_table(&records);
+    }
+
+    let calculations: Vec<CalculationReport> = records.iter().map(CalculationReport::from).collect();
+    emit(&json!({
+        "state": state.to_string(),
+        "calculations": calculations,
+    }))
+}
+
+pub async fn simulate_callback(
+    client: &RpcClient,
+    cli: &Cli,
+    payer: &dyn Signer,
+    args: &SimulateCallbackArgs,
+) -> Result<()> {
+    let program_id = bonsol_calculator_backend::id();
+    validate_execution_id(&args.execution_id)?;
+    let execution_id = pad_execution_id(&args.execution_id);
+    let (state, calc) = match calculators::resolve_state(cli, args.state)? {
+        Some(state) => {
+            let records = records::fetch_records(client, &program_id, &state).await?;
+            let Some(calc) = records.into_iter().find(|calc| calc.execution_id == execution_id) else {
+                bail!("No calculation with execution ID {} in {}", execution_id, state);
+            };
+            (state, calc)
+        }
+        None => records::find_record(client, &program_id, &execution_id).await?,
+    };
+    if calc.is_complete || calc.pending_journal.is_some() {
+        bail!("Calculation #{} already received its callback", calc.index);
+    }
+    if calc.retried {
+        bail!("Calculation #{} was superseded by a retry; simulate the retry's callback instead", calc.index);
+    }
+
+    // Same layout the ZK program commits: a version 1 journal over the record's input frame,
+    // the expression frame for expressions
+    let journal = match (&args.journal, args.result) {
+        (Some(journal), _) => hex::decode(journal).context("--journal must be hex")?,
+        (None, Some(result)) => {
+            // The callback rejects journals bound to any other input than the record's
+            let input_digest = Some(calc.input_hash.unwrap_or_else(|| hash(&calc.input()).to_bytes()));
+            GuestJournal { status: JOURNAL_STATUS_OK, result, input_digest, cycles: None, semantics: None }.encode()
+        }
+        (None, None) => bail!(ClientError::InvalidArgs("Either --result or --journal is required".to_string())),
+    };
+    match decode_outcome(&journal) {
+        Ok(Ok(result)) => say!("📜 Journal {} decodes to {}", hex::encode(&journal), result),
+        Ok(Err(reason)) => say!(
+            "📜 Journal {} reports {:?}; the callback should mark the calculation failed",
+            hex::encode(&journal),
+            reason
+        ),
+        Err(e) => say!("📜 Journal {} does not decode ({}); the callback should fail", hex::encode(&journal), e),
+    }
+    say!("\n🧪 Simulating callback for calculation #{} ({}) of state {}", calc.index, calc.describe(), state);
+
+    // Only Bonsol can sign as the execution request, so this lands on a `localnet` build of the program,
+    // which lets the payer stand in for Bonsol
+    let instruction = instruction::callback(&program_id, &payer.pubkey(), &calc, journal.clone())?;
+    let (record, _) = calculation_address(&program_id, &state, calc.index);
+    let signature = send_instruction(client, cli, payer, &[], instruction).await?;
+
+    emit(&json!({
+        "signature": signature.to_string(),
+        "execution_id": execution_id,
+        "state": state.to_string(),
+        "record": record.to_string(),
+        "journal": hex::encode(&journal),
+        "result": decode_journal(&journal).ok(),
+    }))
+}
+
+fn print_record(calc: &CalculationRecord) {
+    let entry = HistoryEntry::from(calc);
+    let result = match calc.result {
+        Some(result) => result.to_string(),
+        None => "?".to_string(),
+    };
+    say!("   #{} [{}] {} = {} ({:?})",
+             calc.index, calc.execution_id, calc.describe(), result, entry.status);
+}
+
+/// Prints `records` as a table with one row per calculation
+fn print_table(records: &[CalculationRecord]) {
+    const HEADER: [&str; 9] = [
+        "#", "Execution ID", "Operation", "Operands", "Result", "Status", "Submitted (UTC)", "Slot", "Completed",
+    ];
+
+    let rows: Vec<[String; 9]> = records
+        .iter()
+        .map(|calc| {
+            let (operation, operands) = if calc.expression.is_empty() {
+                (operation_name(calc.operation), format!("{}, {}", calc.operand_a, calc.operand_b))
+            } else {
+                ("expression".to_string(), calc.describe())
+            };
+            [
+                calc.index.to_string(),
+                calc.execution_id.clone(),
+                operation,
+                operands,
+                calc.result.map_or_else(|| "-".to_string(), |result| result.to_string()),
+                status_label(&HistoryEntry::from(calc).status),
+                format_timestamp(calc.timestamp),
+                calc.submitted_slot.to_string(),
+                calc.completed_slot.map_or_else(|| "-".to_string(), |slot| slot.to_string()),
+            ]
+        })
+        .collect();
+
+    let mut widths = HEADER.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[&str]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        say!("  {}", padded.join("  ").trim_end());
+    };
+    line(&HEADER);
+    let rules: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    line(&rules.iter().map(String::as_str).collect::<Vec<_>>());
+    for row in &rows {
+        line(&row.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+}
+
+fn operation_name(operation: i64) -> String {
+    let Some((operation, semantics)) = split_operation(operation) else {
+        return "unknown".to_string();
+    };
+    let name = match operation {
+        OP_ADD => "add",
+        OP_SUBTRACT => "subtract",
+        OP_MULTIPLY => "multiply",
+        OP_DIVIDE => "divide",
+        OP_MODULO => "modulo",
+        OP_POWER => "power",
+        OP_ABS => "abs",
+        OP_MIN => "min",
+        OP_MAX => "max",
+        OP_GCD => "gcd",
+        OP_ISQRT => "isqrt",
+        OP_LOG2 => "log2",
+        OP_BPS_FLOOR => "bps_floor",
+        OP_BPS_CEIL => "bps_ceil",
+        OP_BPS_ROUND => "bps_round",
+        _ => "unknown",
+    };
+    match semantics {
+        Semantics::Checked => name.to_string(),
+        Semantics::Saturating => format!("saturating {}", name),
+        Semantics::Wrapping => format!("wrapping {}", name),
+    }
+}
+
+fn status_label(status: &CalculationStatus) -> String {
+    match status {
+        CalculationStatus::Pending => "pending".to_string(),
+        CalculationStatus::AwaitingFinalize => "awaiting finalize".to_string(),
+        CalculationStatus::Complete => "complete".to_string(),
+        CalculationStatus::Local => "local".to_string(),
+        CalculationStatus::Failed(reason) => format!("failed ({:?})", reason),
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    match DateTime::<Utc>::from_timestamp(timestamp, 0) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => timestamp.to_string(),
+    }
+}
@@ -0,0 +1,204 @@
+//! Address book of named calculator state accounts
+//!
+//! Entries live in `calculators.json` in the config directory
+//! (`$XDG_CONFIG_HOME/bonsol-calculator`, else `~/.config/bonsol-calculator`).
+//! Commands taking `--state` fall back to `--calculator <NAME>`, then to the
+//! entry picked with `calculators use`.
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::ClientError;
+use crate::{config_dir, emit, Cli};
+
+const ADDRESS_BOOK_FILE: &str = "calculators.json";
+
+#[derive(Args)]
+pub struct CalculatorsArgs {
+    #[command(subcommand)]
+    command: CalculatorsCommand,
+}
+
+#[derive(Subcommand)]
+enum CalculatorsCommand {
+    /// Name a calculator state account
+    Add(AddArgs),
+    /// List the named calculators
+    List,
+    /// Use a named calculator whenever --state and --calculator are omitted
+    Use {
+        name: String,
+    },
+    /// Forget a named calculator
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Args)]
+struct AddArgs {
+    name: String,
+
+    /// Calculator state account
+    #[arg(long)]
+    state: Pubkey,
+
+    /// Calculator program owning the state account [default: the program this client is built for]
+    #[arg(long)]
+    program_id: Option<Pubkey>,
+
+    /// Replace an existing entry of the same name
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct AddressBook {
+    /// Entry used when neither --state nor --calculator is given
+    default: Option<String>,
+    calculators: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    state: String,
+    program_id: String,
+}
+
+/// The state account a command runs against: `explicit` (its `--state`), else
+/// `--calculator`, else the address book's default
+pub fn resolve_state(cli: &Cli, explicit: Option<Pubkey>) -> Result<Option<Pubkey>> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+
+    let book = AddressBook::load()?;
+    let name = match &cli.calculator {
+        Some(name) => name,
+        None => match &book.default {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    let Some(entry) = book.calculators.get(name) else {
+        bail!(ClientError::InvalidArgs(format!("No calculator named {:?}; add it with `calculators add`", name)));
+    };
+
+    let program_id = parse_pubkey(&entry.program_id)?;
+    if program_id != bonsol_calculator_backend::id() {
+        bail!(ClientError::InvalidArgs(format!(
+            "Calculator {:?} belongs to program {}, but this client is built for {}",
+            name,
+            program_id,
+            bonsol_calculator_backend::id()
+        )));
+    }
+    let state = parse_pubkey(&entry.state)?;
+    if cli.calculator.is_none() {
+        say!("📒 Using calculator {:?} ({})", name, state);
+    }
+    Ok(Some(state))
+}
+
+pub async fn run(client: &RpcClient, args: &CalculatorsArgs) -> Result<()> {
+    let mut book = AddressBook::load()?;
+    match &args.command {
+        CalculatorsCommand::Add(args) => {
+            if book.calculators.contains_key(&args.name) && !args.force {
+                bail!(ClientError::InvalidArgs(format!(
+                    "A calculator named {:?} already exists; pass --force to replace it", args.name
+                )));
+            }
+            let program_id = args.program_id.unwrap_or_else(bonsol_calculator_backend::id);
+            let account = client
+                .get_account(&args.state)
+                .await
+                .with_context(|| format!("Failed to fetch calculator state {}", args.state))?;
+            if account.owner != program_id {
+                bail!("{} is owned by {}, not the calculator program {}", args.state, account.owner, program_id);
+            }
+
+            book.calculators.insert(
+                args.name.clone(),
+                Entry {
+                    state: args.state.to_string(),
+                    program_id: program_id.to_string(),
+                },
+            );
+            // The first calculator added becomes the default
+            book.default.get_or_insert_with(|| args.name.clone());
+            book.save()?;
+            say!("📒 Added calculator {:?}: {}", args.name, args.state);
+        }
+        CalculatorsCommand::List => {
+            if book.calculators.is_empty() {
+                say!("No calculators; add one with `calculators add <NAME> --state <STATE>`");
+            }
+            for (name, entry) in &book.calculators {
+                let marker = if book.default.as_ref() == Some(name) { "*" } else { " " };
+                say!("{} {:<16} {}  (program {})", marker, name, entry.state, entry.program_id);
+            }
+        }
+        CalculatorsCommand::Use { name } => {
+            if !book.calculators.contains_key(name) {
+                bail!(ClientError::InvalidArgs(format!(
+                    "No calculator named {:?}; add it with `calculators add`", name
+                )));
+            }
+            book.default = Some(name.clone());
+            book.save()?;
+            say!("📒 Commands now default to calculator {:?}", name);
+        }
+        CalculatorsCommand::Remove { name } => {
+            if book.calculators.remove(name).is_none() {
+                bail!(ClientError::InvalidArgs(format!("No calculator named {:?}", name)));
+            }
+            if book.default.as_ref() == Some(name) {
+                book.default = None;
+            }
+            book.save()?;
+            say!("📒 Removed calculator {:?}", name);
+        }
+    }
+
+    emit(&json!({
+        "default": book.default,
+        "calculators": book.calculators,
+    }))
+}
+
+impl AddressBook {
+    fn load() -> Result<Self> {
+        let path = address_book_path()?;
+        if !path.exists() {
+            return Ok(AddressBook::default());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = address_book_path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn address_book_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(ADDRESS_BOOK_FILE))
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey> {
+    Pubkey::from_str(pubkey).with_context(|| format!("Invalid address {:?} in the address book", pubkey))
+}
@@ -0,0 +1,365 @@
+//! Decoding of Bonsol instruction data
+//!
+//! Bonsol instructions are a `ChannelInstruction` flatbuffer; execute
+//! instructions nest an `ExecutionRequestV1` holding the image, inputs, tip,
+//! expiry and callback configuration. `decode` reads them from a blob or
+//! from every Bonsol instruction of a transaction, CPIs included.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bonsol_interface::bonsol_schema::{
+    root_as_channel_instruction, root_as_execution_request_v1, ChannelInstructionIxType, ExecutionRequestV1,
+};
+use calculator_sdk::Calculation;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{bs58, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiInstruction, UiTransactionEncoding};
+use std::str::FromStr;
+
+use crate::error::ClientError;
+use crate::submit::CALCULATOR_IMAGE_ID;
+use crate::emit;
+
+#[derive(Args)]
+pub struct DecodeArgs {
+    /// Bonsol instruction data, or the signature of a transaction whose Bonsol instructions to decode
+    data: String,
+
+    /// Encoding of the instruction data
+    #[arg(long, value_enum, default_value = "auto")]
+    encoding: Encoding,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    /// A transaction signature, else base58, else base64
+    Auto,
+    Base58,
+    Base64,
+    Hex,
+}
+
+#[derive(Serialize)]
+struct DecodeReport {
+    signature: Option<String>,
+    instructions: Vec<DecodedInstruction>,
+}
+
+/// One decoded Bonsol instruction
+#[derive(Serialize)]
+pub struct DecodedInstruction {
+    /// Position in the transaction, e.g. `2` or `1.0` for the first CPI of instruction 1
+    location: Option<String>,
+    instruction_type: String,
+    /// Set for execute instructions
+    execution_request: Option<ExecutionRequestReport>,
+}
+
+#[derive(Serialize)]
+struct ExecutionRequestReport {
+    execution_id: Option<String>,
+    image_id: Option<String>,
+    /// Whether `image_id` is the calculator guest
+    calculator_image: bool,
+    tip: u64,
+    /// Slot after which provers may no longer claim the request
+    expiry: u64,
+    verify_input_hash: bool,
+    /// Hex SHA-256 digest Bonsol checks the inputs against
+    input_digest: Option<String>,
+    forward_output: bool,
+    prover_version: String,
+    inputs: Vec<InputReport>,
+    callback: Option<CallbackReport>,
+}
+
+#[derive(Serialize)]
+struct InputReport {
+    input_type: String,
+    /// Hex input data
+    data: String,
+    /// The data as text, for URL and private inputs
+    text: Option<String>,
+    /// The calculation a calculator input encodes
+    calculation: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CallbackReport {
+    program_id: Option<String>,
+    /// Hex prefix of the callback instruction data
+    instruction_prefix: String,
+    extra_accounts: Vec<CallbackAccountReport>,
+}
+
+#[derive(Serialize)]
+struct CallbackAccountReport {
+    pubkey: String,
+    writable: bool,
+}
+
+pub async fn run(client: &RpcClient, args: &DecodeArgs) -> Result<()> {
+    if args.encoding == Encoding::Auto {
+        if let Ok(signature) = Signature::from_str(&args.data) {
+            return decode_transaction(client, &signature).await;
+        }
+    }
+
+    let data = decode_blob(&args.data, args.encoding)?;
+    let decoded = decode_instruction(&data, None)?;
+    print(&decoded);
+    emit(&DecodeReport { signature: None, instructions: vec![decoded] })
+}
+
+/// Decodes every Bonsol instruction `signature` executed, top-level and CPI
+async fn decode_transaction(client: &RpcClient, signature: &Signature) -> Result<()> {
+    let transaction = fetch_transaction(client, signature).await?;
+    let blobs: Vec<_> = transaction.instructions_of(&bonsol_interface::ID).collect();
+    if blobs.is_empty() {
+        bail!(ClientError::InvalidArgs(format!("Transaction {} has no Bonsol instructions", signature)));
+    }
+
+    let mut instructions = Vec::with_capacity(blobs.len());
+    for instruction in blobs {
+        let decoded = decode_instruction(&instruction.data, Some(instruction.location.clone()))?;
+        print(&decoded);
+        instructions.push(decoded);
+    }
+    emit(&DecodeReport { signature: Some(signature.to_string()), instructions })
+}
+
+/// A confirmed transaction's account keys and instructions, CPIs included
+pub struct FetchedTransaction {
+    /// Static keys followed by those loaded from lookup tables
+    // Only `replay` looks up accounts
+    #[cfg_attr(not(feature = "prove-local"), allow(dead_code))]
+    pub keys: Vec<Pubkey>,
+    pub instructions: Vec<FetchedInstruction>,
+}
+
+pub struct FetchedInstruction {
+    /// Position in the transaction, e.g. `2` or `1.0` for the first CPI of instruction 1
+    pub location: String,
+    pub program_id: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl FetchedTransaction {
+    /// The instructions, top-level and CPI, that invoked `program_id`
+    pub fn instructions_of<'a>(&'a self, program_id: &'a Pubkey) -> impl Iterator<Item = &'a FetchedInstruction> {
+        self.instructions.iter().filter(move |instruction| instruction.program_id == *program_id)
+    }
+}
+
+/// Fetches `signature` with every instruction it executed, top-level and CPI
+pub async fn fetch_transaction(client: &RpcClient, signature: &Signature) -> Result<FetchedTransaction> {
+    let confirmed = client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(client.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .with_context(|| format!("Failed to fetch transaction {}", signature))?
+        .transaction;
+    let transaction = confirmed
+        .transaction
+        .decode()
+        .with_context(|| format!("Failed to decode transaction {}", signature))?;
+
+    // Instructions index the static keys followed by those loaded from lookup tables
+    let mut keys = transaction.message.static_account_keys().to_vec();
+    let meta = confirmed.meta;
+    if let Some(OptionSerializer::Some(loaded)) = meta.as_ref().map(|meta| &meta.loaded_addresses) {
+        for key in loaded.writable.iter().chain(&loaded.readonly) {
+            keys.push(Pubkey::from_str(key).with_context(|| format!("Invalid loaded address {}", key))?);
+        }
+    }
+    let program_id = |index: u8| keys.get(index as usize).copied().unwrap_or_default();
+
+    let mut instructions = Vec::new();
+    for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+        instructions.push(FetchedInstruction {
+            location: index.to_string(),
+            program_id: program_id(instruction.program_id_index),
+            data: instruction.data.clone(),
+        });
+    }
+    if let Some(OptionSerializer::Some(inner)) = meta.as_ref().map(|meta| &meta.inner_instructions) {
+        for group in inner {
+            for (position, instruction) in group.instructions.iter().enumerate() {
+                if let UiInstruction::Compiled(instruction) = instruction {
+                    let data = bs58::decode(&instruction.data)
+                        .into_vec()
+                        .context("Failed to decode inner instruction data")?;
+                    instructions.push(FetchedInstruction {
+                        location: format!("{}.{}", group.index, position),
+                        program_id: program_id(instruction.program_id_index),
+                        data,
+                    });
+                }
+            }
+        }
+    }
+    Ok(FetchedTransaction { keys, instructions })
+}
+
+fn decode_blob(data: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    let decoded = match encoding {
+        Encoding::Auto => bs58::decode(data).into_vec().ok().or_else(|| BASE64.decode(data).ok()),
+        Encoding::Base58 => bs58::decode(data).into_vec().ok(),
+        Encoding::Base64 => BASE64.decode(data).ok(),
+        Encoding::Hex => hex::decode(data).ok(),
+    };
+    decoded.ok_or_else(|| {
+        ClientError::InvalidArgs(format!("{:?} is not a signature or instruction data in the given encoding", data))
+            .into()
+    })
+}
+
+/// Decodes the data of one Bonsol instruction found at `location`
+pub fn decode_instruction(data: &[u8], location: Option<String>) -> Result<DecodedInstruction> {
+    let instruction = root_as_channel_instruction(data)
+        .map_err(|e| ClientError::InvalidArgs(format!("Not a Bonsol instruction: {}", e)))?;
+    let ix_type = instruction.ix_type();
+    let instruction_type = ix_type.variant_name().map_or_else(|| format!("Unknown({})", ix_type.0), str::to_string);
+    if ix_type != ChannelInstructionIxType::ExecuteV1 {
+        return Ok(DecodedInstruction { location, instruction_type, execution_request: None });
+    }
+
+    let nested = instruction
+        .execute_v1()
+        .ok_or_else(|| ClientError::InvalidArgs("Execute instruction carries no execution request".to_string()))?;
+    let request = root_as_execution_request_v1(nested.bytes())
+        .map_err(|e| ClientError::InvalidArgs(format!("Invalid execution request: {}", e)))?;
+    Ok(DecodedInstruction {
+        location,
+        instruction_type,
+        execution_request: Some(execution_request(&request)),
+    })
+}
+
+/// The execution request of a Bonsol execute instruction, `None` for any other instruction
+#[cfg_attr(not(feature = "prove-local"), allow(dead_code))]
+pub fn execution_request_of(data: &[u8]) -> Option<ExecutionRequestV1<'_>> {
+    let instruction = root_as_channel_instruction(data).ok()?;
+    if instruction.ix_type() != ChannelInstructionIxType::ExecuteV1 {
+        return None;
+    }
+    root_as_execution_request_v1(instruction.execute_v1()?.bytes()).ok()
+}
+
+fn execution_request(request: &ExecutionRequestV1) -> ExecutionRequestReport {
+    let inputs = request
+        .input()
+        .map(|inputs| {
+            inputs
+                .iter()
+                .map(|input| {
+                    let input_type = input.input_type();
+                    let data = input.data().map(|data| data.bytes()).unwrap_or_default();
+                    let calculation = Calculation::from_input(data).map(|calculation| calculation.describe());
+                    InputReport {
+                        input_type: input_type
+                            .variant_name()
+                            .map_or_else(|| format!("Unknown({})", input_type.0), str::to_string),
+                        data: hex::encode(data),
+                        // URLs and private inputs are text; calculator inputs never are
+                        text: calculation
+                            .is_none()
+                            .then(|| std::str::from_utf8(data).ok().map(str::to_string))
+                            .flatten(),
+                        calculation,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let callback = request.callback_program_id().map(|program_id| CallbackReport {
+        program_id: Pubkey::try_from(program_id.bytes()).ok().map(|program_id| program_id.to_string()),
+        instruction_prefix: request
+            .callback_instruction_prefix()
+            .map(|prefix| hex::encode(prefix.bytes()))
+            .unwrap_or_default(),
+        extra_accounts: request
+            .callback_extra_accounts()
+            .map(|accounts| {
+                accounts
+                    .iter()
+                    .map(|account| CallbackAccountReport {
+                        pubkey: Pubkey::new_from_array(account.pubkey().into()).to_string(),
+                        writable: account.writable() != 0,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    });
+
+    let prover_version = request.prover_version();
+    ExecutionRequestReport {
+        execution_id: request.execution_id().map(str::to_string),
+        image_id: request.image_id().map(str::to_string),
+        calculator_image: request.image_id() == Some(CALCULATOR_IMAGE_ID),
+        tip: request.tip(),
+        expiry: request.max_block_height(),
+        verify_input_hash: request.verify_input_hash(),
+        input_digest: request.input_digest().map(|digest| hex::encode(digest.bytes())),
+        forward_output: request.forward_output(),
+        prover_version: prover_version
+            .variant_name()
+            .map_or_else(|| format!("Unknown({})", prover_version.0), str::to_string),
+        inputs,
+        callback,
+    }
+}
+
+/// Pretty-prints a decoded instruction
+pub fn print(decoded: &DecodedInstruction) {
+    match &decoded.location {
+        Some(location) => say!("\n🧾 Bonsol {} instruction at #{}:", decoded.instruction_type, location),
+        None => say!("\n🧾 Bonsol {} instruction:", decoded.instruction_type),
+    }
+    let Some(request) = &decoded.execution_request else {
+        return;
+    };
+
+    say!("   Execution ID: {}", request.execution_id.as_deref().unwrap_or("-"));
+    say!("   Image ID: {}{}",
+             request.image_id.as_deref().unwrap_or("-"),
+             if request.calculator_image { " (calculator)" } else { "" });
+    say!("   Tip: {} lamports", request.tip);
+    say!("   Expires at slot: {}", request.expiry);
+    match &request.input_digest {
+        Some(digest) if request.verify_input_hash => say!("   Input hash verified against: {}", digest),
+        _ => say!("   Input hash verified: no"),
+    }
+    say!("   Forward output: {}", if request.forward_output { "yes" } else { "no" });
+    say!("   Prover version: {}", request.prover_version);
+    say!("   Inputs:");
+    for (index, input) in request.inputs.iter().enumerate() {
+        let shown = match (&input.calculation, &input.text) {
+            (Some(calculation), _) => format!("{} ({} bytes)", calculation, input.data.len() / 2),
+            (None, Some(text)) => text.clone(),
+            (None, None) => input.data.clone(),
+        };
+        say!("     [{}] {}: {}", index, input.input_type, shown);
+    }
+    match &request.callback {
+        Some(callback) => {
+            say!("   Callback program: {}", callback.program_id.as_deref().unwrap_or("-"));
+            say!("   Callback instruction prefix: {}", callback.instruction_prefix);
+            for account in &callback.extra_accounts {
+                say!("     {} ({})", account.pubkey, if account.writable { "writable" } else { "readonly" });
+            }
+        }
+        None => say!("   Callback: none"),
+    }
+}
+
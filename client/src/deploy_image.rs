@@ -0,0 +1,266 @@
+//! Building, publishing and deploying a calculator guest image
+//!
+//! The guest is built with `bonsol build`, which compiles it in risc0's
+//! docker image so the same source always yields the same image ID. The ID
+//! is recomputed from the ELF and must match the manifest before the ELF is
+//! published where provers can download it and the Bonsol deployment is
+//! sent. The deployed image then becomes the default `--image-id` (see
+//! `deployment`).
+
+use anyhow::{anyhow, bail, Context, Result};
+use bonsol_interface::bonsol_schema::ProgramInputType;
+use bonsol_interface::instructions::deploy_v1;
+use bonsol_interface::util::deployment_address;
+use clap::{Args, ValueEnum};
+use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
+use object_store::{path::Path as ObjectPath, ObjectStore, PutPayload};
+use risc0_zkvm::compute_image_id;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Signer;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::deployment::DeployedImage;
+use crate::error::ClientError;
+use crate::{emit, send_instruction, Cli};
+
+#[derive(Args)]
+pub struct DeployImageArgs {
+    /// Where the ELF is published for provers to download
+    #[arg(long, value_enum)]
+    storage: Storage,
+
+    /// Guest crate to build
+    #[arg(long, default_value = "../zk-program")]
+    zk_program: PathBuf,
+
+    /// Deploy the image the guest's manifest.json describes instead of rebuilding it
+    #[arg(long)]
+    skip_build: bool,
+
+    /// S3 bucket to upload to; credentials, region and endpoint come from the AWS_* environment variables
+    #[arg(long, required_if_eq("storage", "s3"))]
+    bucket: Option<String>,
+
+    /// URL the ELF is PUT to, e.g. a local HTTP server or a presigned S3 URL
+    #[arg(long, required_if_eq("storage", "http"))]
+    upload_url: Option<String>,
+
+    /// Arweave wallet keyfile the Irys CLI pays for the upload with
+    #[arg(long, required_if_eq("storage", "arweave"))]
+    arweave_wallet: Option<PathBuf>,
+
+    /// URL provers download the ELF from [default: derived from the storage backend]
+    #[arg(long)]
+    url: Option<String>,
+
+    /// Deploy without making the image the default --image-id
+    #[arg(long)]
+    no_save: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Storage {
+    /// An S3 bucket, or an S3-compatible store through AWS_ENDPOINT
+    S3,
+    /// Arweave, through the Irys CLI
+    Arweave,
+    /// An HTTP PUT to --upload-url
+    Http,
+}
+
+/// The manifest `bonsol build` writes next to the guest's Cargo.toml
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    name: String,
+    /// ELF path, relative to the guest crate
+    binary_path: PathBuf,
+    image_id: String,
+    input_order: Vec<String>,
+    size: u64,
+}
+
+/// `--output json` report of a deployment
+#[derive(Serialize)]
+struct DeployImageReport {
+    image_id: String,
+    name: String,
+    size: u64,
+    /// `None` when the image was already deployed and --url wasn't given
+    url: Option<String>,
+    deployment: String,
+    /// `None` when the image was already deployed
+    signature: Option<String>,
+    /// `deployment.json` the image was saved to as the default --image-id
+    saved_to: Option<String>,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &DeployImageArgs) -> Result<()> {
+    if !args.skip_build {
+        build(&args.zk_program).await?;
+    }
+    let manifest_path = args.zk_program.join("manifest.json");
+    let manifest: Manifest = serde_json::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {}; build the guest first", manifest_path.display()))?,
+    )
+    .with_context(|| format!("{} is not a Bonsol manifest", manifest_path.display()))?;
+
+    let elf_path = args.zk_program.join(&manifest.binary_path);
+    let elf = std::fs::read(&elf_path).with_context(|| format!("Failed to read guest ELF {}", elf_path.display()))?;
+    if elf.len() as u64 != manifest.size {
+        bail!(
+            "{} is {} bytes but the manifest records {}; rebuild the guest",
+            elf_path.display(),
+            elf.len(),
+            manifest.size
+        );
+    }
+    let image_id = hex::encode(
+        compute_image_id(&elf)
+            .map_err(|e| anyhow!("Failed to compute the image ID of {}: {}", elf_path.display(), e))?
+            .as_bytes(),
+    );
+    if image_id != manifest.image_id {
+        bail!(
+            "{} has image ID {}, but the manifest records {}; rebuild the guest without --skip-build",
+            elf_path.display(),
+            image_id,
+            manifest.image_id
+        );
+    }
+    say!("🖼️ Image ID: {} ({}, {} bytes)", image_id, manifest.name, manifest.size);
+    let inputs = manifest
+        .input_order
+        .iter()
+        .map(|input| match input.as_str() {
+            "Public" => Ok(ProgramInputType::Public),
+            "Private" => Ok(ProgramInputType::Private),
+            other => Err(anyhow!("Unsupported input type {:?} in {}", other, manifest_path.display())),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (deployment, _) = deployment_address(&image_id);
+    let deployed = client
+        .get_account_with_commitment(&deployment, client.commitment())
+        .await
+        .context("Failed to fetch the deployment account")?
+        .value;
+    let mut report = DeployImageReport {
+        image_id: image_id.clone(),
+        name: manifest.name.clone(),
+        size: manifest.size,
+        url: args.url.clone(),
+        deployment: deployment.to_string(),
+        signature: None,
+        saved_to: None,
+    };
+
+    match deployed {
+        Some(account) if account.owner == bonsol_interface::ID => {
+            // Bonsol deployments are immutable, so the URL provers use stays the one first deployed
+            say!("✅ Image {} is already deployed to Bonsol ({})", image_id, deployment);
+        }
+        Some(account) => bail!("Deployment account {} is owned by {}, not Bonsol", deployment, account.owner),
+        None => {
+            let key = format!("{}-{}", manifest.name, image_id);
+            let url = publish(args, &key, &elf_path, elf).await?;
+            say!("📤 ELF published at {}", url);
+
+            let instruction = deploy_v1(&payer.pubkey(), &image_id, manifest.size, &manifest.name, &url, inputs)
+                .context("Failed to create deployment instruction")?;
+            report.url = Some(url);
+            let signature = send_instruction(client, cli, payer, &[], instruction).await?;
+            say!("🚀 Deployed image {} to {}", image_id, deployment);
+            report.signature = Some(signature.to_string());
+        }
+    }
+
+    if !args.no_save {
+        let deployed = DeployedImage { image_id: image_id.clone(), url: report.url.clone(), rpc_url: client.url() };
+        let path = deployed.save()?;
+        say!("💾 Direct submissions now default to image {} (saved to {})", image_id, path.display());
+        report.saved_to = Some(path.display().to_string());
+    }
+    say!("ℹ️ Calculator-program submissions prove their state's image until its owner sends SetImageId");
+    emit(&report)
+}
+
+/// Builds the guest in risc0's docker image, writing its manifest.json
+async fn build(zk_program: &Path) -> Result<()> {
+    say!("🔨 Building {} with bonsol build...", zk_program.display());
+    let status = Command::new("bonsol")
+        .arg("build")
+        .arg("--zk-program-path")
+        .arg(zk_program)
+        .status()
+        .await
+        .context("Failed to run bonsol build; install the Bonsol CLI or pass --skip-build")?;
+    if !status.success() {
+        bail!("bonsol build {} failed with {}", zk_program.display(), status);
+    }
+    Ok(())
+}
+
+/// Uploads the ELF to the storage backend, returning the URL provers download it from
+async fn publish(args: &DeployImageArgs, key: &str, elf_path: &Path, elf: Vec<u8>) -> Result<String> {
+    match args.storage {
+        Storage::S3 => {
+            let bucket = args.bucket.as_deref().unwrap_or_default();
+            let builder = AmazonS3Builder::from_env().with_bucket_name(bucket);
+            let region =
+                builder.get_config_value(&AmazonS3ConfigKey::Region).unwrap_or_else(|| "us-east-1".to_string());
+            let store = builder.build().context("Failed to configure the S3 client")?;
+            store
+                .put(&ObjectPath::from(key), PutPayload::from(elf))
+                .await
+                .with_context(|| format!("Failed to upload {} to bucket {}", key, bucket))?;
+            Ok(args.url.clone().unwrap_or_else(|| format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key)))
+        }
+        Storage::Arweave => {
+            let wallet = args.arweave_wallet.as_deref().unwrap_or(Path::new(""));
+            let output = Command::new("irys")
+                .arg("upload")
+                .arg(elf_path)
+                .args(["-n", "mainnet", "-t", "arweave", "-w"])
+                .arg(wallet)
+                .output()
+                .await
+                .context("Failed to run the Irys CLI; install it with `npm install -g @irys/cli`")?;
+            if !output.status.success() {
+                bail!("irys upload failed with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+            }
+            // The CLI reports "Uploaded to https://gateway.irys.xyz/<id>"
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let uploaded = stdout.split_whitespace().find(|word| word.starts_with("https://")).map(str::to_string);
+            args.url
+                .clone()
+                .or(uploaded)
+                .ok_or_else(|| anyhow!("irys upload did not report a URL; pass --url. Output: {}", stdout.trim()))
+        }
+        Storage::Http => {
+            let upload_url = args.upload_url.as_deref().unwrap_or_default();
+            reqwest::Client::new()
+                .put(upload_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                .body(elf)
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload the ELF to {}", upload_url))?
+                .error_for_status()
+                .map_err(|e| anyhow!("Upload to {} was refused: {}", upload_url, e))?;
+            match &args.url {
+                Some(url) => Ok(url.clone()),
+                // A presigned URL's signature is only good for the upload
+                None => {
+                    let mut url = reqwest::Url::parse(upload_url)
+                        .map_err(|e| ClientError::InvalidArgs(format!("--upload-url {:?}: {}", upload_url, e)))?;
+                    url.set_query(None);
+                    Ok(url.to_string())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! The guest image and callback program direct Bonsol submissions target
+//!
+//! Both default to the calculator's own and can be overridden to try a
+//! rebuilt guest or a fork of the callback program. Once `deploy-image` has
+//! deployed a guest, the image it records in `deployment.json` in the config
+//! directory becomes the default instead. Before anything is sent, the image
+//! must be deployed to Bonsol on the target cluster and the callback program
+//! must be an executable account there, since Bonsol would otherwise take the
+//! tip for an execution no prover can run.
+
+use anyhow::{bail, Context, Result};
+use bonsol_interface::util::deployment_address;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config_dir;
+use crate::error::ClientError;
+use crate::submit::{CALCULATOR_IMAGE_ID, CALLBACK_PROGRAM_ID};
+
+const DEPLOYMENT_FILE: &str = "deployment.json";
+
+#[derive(Args)]
+pub struct DeploymentArgs {
+    /// Image ID of the guest Bonsol should prove direct submissions with
+    #[arg(long, default_value_t = default_image_id().to_string(), global = true)]
+    pub image_id: String,
+
+    /// Program Bonsol calls back with the proven result of direct submissions
+    #[arg(long, default_value = CALLBACK_PROGRAM_ID, global = true)]
+    pub callback_program: Pubkey,
+}
+
+/// The image `deploy-image` last deployed, as recorded in `deployment.json`
+#[derive(Serialize, Deserialize)]
+pub struct DeployedImage {
+    pub image_id: String,
+    /// Where provers download the ELF from, when this client published it
+    pub url: Option<String>,
+    /// RPC URL of the cluster the image was deployed to
+    pub rpc_url: String,
+}
+
+impl DeployedImage {
+    pub fn load() -> Result<Option<Self>> {
+        let path = deployment_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let deployed = serde_json::from_str(&text).with_context(|| format!("{} is not valid JSON", path.display()))?;
+        Ok(Some(deployed))
+    }
+
+    // Only `deploy-image` records deployments
+    #[cfg_attr(not(feature = "deploy-image"), allow(dead_code))]
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = deployment_path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+fn deployment_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(DEPLOYMENT_FILE))
+}
+
+/// The image direct submissions target without `--image-id`: the last one
+/// deployed with `deploy-image`, else the calculator's built-in image
+fn default_image_id() -> &'static str {
+    static DEFAULT: OnceLock<String> = OnceLock::new();
+    DEFAULT.get_or_init(|| match DeployedImage::load() {
+        Ok(Some(deployed)) => deployed.image_id,
+        Ok(None) => CALCULATOR_IMAGE_ID.to_string(),
+        Err(e) => {
+            eprintln!("⚠️ Ignoring the deployed image: {:#}", e);
+            CALCULATOR_IMAGE_ID.to_string()
+        }
+    })
+}
+
+impl DeploymentArgs {
+    /// Whether the image or callback program was overridden
+    pub fn is_overridden(&self) -> bool {
+        self.image_id != default_image_id() || self.callback_program.to_string() != CALLBACK_PROGRAM_ID
+    }
+
+    /// Checks that the image is deployed to Bonsol and the callback program is executable
+    pub async fn validate(&self, client: &RpcClient) -> Result<()> {
+        if self.image_id.len() != 64 || hex::decode(&self.image_id).is_err() {
+            bail!(ClientError::InvalidArgs(format!(
+                "--image-id {:?} is not an image ID; expected 64 hex characters",
+                self.image_id
+            )));
+        }
+
+        let (deployment, _) = deployment_address(&self.image_id);
+        let callback = self.callback_program;
+        let accounts = client
+            .get_multiple_accounts(&[deployment, callback])
+            .await
+            .context("Failed to fetch the image deployment and callback program")?;
+
+        match &accounts[0] {
+            Some(account) if account.owner == bonsol_interface::ID => {}
+            Some(account) => bail!(ClientError::InvalidArgs(format!(
+                "Deployment account {} of image {} is owned by {}, not Bonsol",
+                deployment, self.image_id, account.owner
+            ))),
+            None => bail!(ClientError::InvalidArgs(format!(
+                "Image {} is not deployed to Bonsol on this cluster (no deployment account {}); \
+                 deploy it with `bonsol deploy` or pass the --image-id of a deployed guest",
+                self.image_id, deployment
+            ))),
+        }
+        match &accounts[1] {
+            Some(account) if account.executable => {}
+            Some(_) => bail!(ClientError::InvalidArgs(format!(
+                "Callback program {} is not executable; pass the address of a deployed program",
+                callback
+            ))),
+            None => bail!(ClientError::InvalidArgs(format!(
+                "Callback program {} does not exist on this cluster",
+                callback
+            ))),
+        }
+
+        if self.is_overridden() {
+            say!("🎯 Targeting image {} with callback program {}", self.image_id, callback);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+//! Exit codes scripts can branch on
+//!
+//! Errors stay `anyhow` chains so context reads naturally; a `ClientError`
+//! anywhere in the chain decides the exit code. Chains without one that
+//! contain an RPC or websocket error count as RPC failures, and anything else
+//! exits with 1.
+
+use solana_client::client_error::ClientError as RpcError;
+use solana_client::nonblocking::pubsub_client::PubsubClientError;
+
+pub use calculator_sdk::ClientError;
+
+/// Exit code of failures outside every category
+pub const EXIT_FAILURE: u8 = 1;
+
+const EXIT_RPC: u8 = 3;
+
+/// Exit code for `error`, see the module docs
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    match error.chain().find_map(|cause| cause.downcast_ref::<ClientError>()) {
+        Some(ClientError::InvalidArgs(_)) => 2,
+        Some(ClientError::Rpc(_)) => EXIT_RPC,
+        Some(ClientError::Simulation(_)) => 4,
+        Some(ClientError::Transaction(_)) => 5,
+        Some(ClientError::CallbackTimeout(_)) => 6,
+        None if error.chain().any(|cause| cause.is::<RpcError>() || cause.is::<PubsubClientError>()) => EXIT_RPC,
+        None => EXIT_FAILURE,
+    }
+}
@@ -0,0 +1,297 @@
+//! What a submission will cost, without sending it
+//!
+//! The transaction fee comes from `getFeeForMessage`, the rent from
+//! simulating the submission and pricing every account it would create, and
+//! the priority fee from the compute unit limit `--priority-fee` would request.
+
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_sdk::{generate_execution_id, records, Calculation};
+use clap::Args;
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_program::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::{
+    account::Account,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::VersionedMessage,
+    native_token::lamports_to_sol,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use crate::budget::MAX_COMPUTE_UNIT_LIMIT;
+use crate::calculators;
+use crate::error::ClientError;
+use crate::submit::{create_execution_via_calculator_program, execute_instruction, CalculationArgs, EXECUTION_TIP};
+use crate::{emit, lookup, signer, Cli};
+
+// Expiration of the estimated submission; it is never sent, so any will do
+const EXPIRATION_SLOTS: u64 = 1000;
+
+#[derive(Args)]
+pub struct EstimateArgs {
+    #[command(flatten)]
+    calculation: CalculationArgs,
+
+    /// Execution method: "calculator-program" or "direct-bonsol"
+    #[arg(long, default_value = "direct-bonsol")]
+    method: String,
+
+    /// Calculator state account, required by the calculator-program method [default: from the address book]
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Number of calculations to extrapolate the cost to
+    #[arg(long, default_value = "1")]
+    batch_size: usize,
+
+    /// Calculations packed into one transaction when extrapolating, as with `submit-batch`
+    #[arg(long, default_value = "4")]
+    max_per_transaction: usize,
+}
+
+/// Lamports paid for one calculation or a whole batch
+#[derive(Serialize, Clone, Copy)]
+struct Cost {
+    transaction_fee: u64,
+    priority_fee: u64,
+    tip: u64,
+    /// Calculator program fee paid to its treasury (calculator-program only)
+    protocol_fee: u64,
+    rent: u64,
+    total: u64,
+}
+
+#[derive(Serialize)]
+struct EstimateReport {
+    method: String,
+    calculation: String,
+    compute_unit_limit: Option<u32>,
+    per_calculation: Cost,
+    batch_size: usize,
+    transactions: usize,
+    batch: Cost,
+    /// SPL token fee per calculation, charged on top of the lamports
+    token_fee: Option<TokenFeeReport>,
+}
+
+#[derive(Serialize)]
+struct TokenFeeReport {
+    mint: String,
+    amount: u64,
+}
+
+impl Cost {
+    fn new(transaction_fee: u64, priority_fee: u64, tip: u64, protocol_fee: u64, rent: u64) -> Self {
+        Cost {
+            transaction_fee,
+            priority_fee,
+            tip,
+            protocol_fee,
+            rent,
+            total: transaction_fee + priority_fee + tip + protocol_fee + rent,
+        }
+    }
+
+    /// The cost of `calculations` calculations sent in `transactions` transactions
+    fn scale(&self, calculations: usize, transactions: usize) -> Self {
+        let (calculations, transactions) = (calculations as u64, transactions as u64);
+        // Every transaction pays the base fee once; the rest grows with the calculations
+        Cost::new(
+            self.transaction_fee * transactions,
+            self.priority_fee * calculations,
+            self.tip * calculations,
+            self.protocol_fee * calculations,
+            self.rent * calculations,
+        )
+    }
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, args: &EstimateArgs) -> Result<()> {
+    if args.batch_size == 0 || args.max_per_transaction == 0 {
+        bail!(ClientError::InvalidArgs("--batch-size and --max-per-transaction must be at least 1".to_string()));
+    }
+
+    let payer = signer::load(cli).await?.pubkey();
+    let calculation = args.calculation.to_calculation()?;
+    let execution_id = generate_execution_id();
+
+    let (instruction, protocol_fee, token_fee) = match args.method.as_str() {
+        "calculator-program" => {
+            let state = calculators::resolve_state(cli, args.state)?;
+            let (instruction, _) =
+                create_execution_via_calculator_program(client, state, &payer, &execution_id, &calculation).await?;
+            let state = state.context("--state is required with the calculator-program method")?;
+            let calculator_state = records::fetch_state(client, &state).await?;
+            let token_fee = calculator_state
+                .config
+                .token_fee
+                .filter(|token_fee| token_fee.amount > 0)
+                .map(|token_fee| TokenFeeReport { mint: token_fee.mint.to_string(), amount: token_fee.amount });
+            (instruction, calculator_state.config.fee_lamports, token_fee)
+        }
+        "direct-bonsol" => {
+            let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+            let expiration = current_slot + EXPIRATION_SLOTS;
+            let instruction =
+                execute_instruction(&cli.deployment, &payer, &execution_id, &calculation, expiration, false)?;
+            (instruction, 0, None)
+        }
+        _ => bail!(ClientError::InvalidArgs("Invalid method. Use 'calculator-program' or 'direct-bonsol'".to_string())),
+    };
+
+    let (per_calculation, compute_unit_limit) = cost(client, cli, &payer, instruction, protocol_fee).await?;
+    let transactions = args.batch_size.div_ceil(args.max_per_transaction);
+    let batch = per_calculation.scale(args.batch_size, transactions);
+
+    say!("\n💸 Estimated cost of {} ({}):", calculation.describe(), args.method);
+    say!("   {:<18} {:>16} {:>22}", "", "per calculation", format!("{} in {} tx", args.batch_size, transactions));
+    let rows = [
+        ("Transaction fee", per_calculation.transaction_fee, batch.transaction_fee),
+        ("Priority fee", per_calculation.priority_fee, batch.priority_fee),
+        ("Tip", per_calculation.tip, batch.tip),
+        ("Protocol fee", per_calculation.protocol_fee, batch.protocol_fee),
+        ("Rent", per_calculation.rent, batch.rent),
+        ("Total", per_calculation.total, batch.total),
+    ];
+    for (label, single, batch) in rows {
+        say!("   {:<18} {:>16} {:>22}", label, single, batch);
+    }
+    say!("   Total: {} SOL per calculation, {} SOL for {}",
+             lamports_to_sol(per_calculation.total), lamports_to_sol(batch.total), args.batch_size);
+    if let Some(limit) = compute_unit_limit {
+        say!("   Compute unit limit: {}", limit);
+    }
+    if let Some(token_fee) = &token_fee {
+        say!("   🪙 Plus a token fee of {} of mint {} per calculation", token_fee.amount, token_fee.mint);
+    }
+
+    emit(&EstimateReport {
+        method: args.method.clone(),
+        calculation: calculation.describe(),
+        compute_unit_limit,
+        per_calculation,
+        batch_size: args.batch_size,
+        transactions,
+        batch,
+        token_fee,
+    })
+}
+
+/// Lamports one direct Bonsol submission of `calculation` by `payer` costs in total
+pub async fn submission_cost(client: &RpcClient, cli: &Cli, payer: &Pubkey, calculation: &Calculation) -> Result<u64> {
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let expiration = current_slot + EXPIRATION_SLOTS;
+    let execution_id = generate_execution_id();
+    let instruction = execute_instruction(&cli.deployment, payer, &execution_id, calculation, expiration, false)?;
+    let (cost, _) = cost(client, cli, payer, instruction, 0).await?;
+    Ok(cost.total)
+}
+
+/// The cost of sending `instruction` on its own, and the compute unit limit it would request
+async fn cost(
+    client: &RpcClient,
+    cli: &Cli,
+    payer: &Pubkey,
+    instruction: Instruction,
+    protocol_fee: u64,
+) -> Result<(Cost, Option<u32>)> {
+    let instructions = [instruction];
+    let tables = lookup::load(client, &cli.lookup_tables).await?;
+    let compute_unit_limit = cli.budget.compute_unit_limit(client, payer, &instructions, &tables).await?;
+    let priority_fee = compute_unit_limit.map_or(0, |limit| cli.budget.priority_fee_lamports(limit));
+
+    // The priority fee is reported on its own, so price the message without budget instructions
+    let blockhash = client.get_latest_blockhash().await.context("Failed to get latest blockhash")?;
+    let message = lookup::compile(payer, &instructions, &tables, blockhash)?;
+    let transaction_fee = fee_for_message(client, &message).await?;
+    let rent = rent_for_created_accounts(client, payer, &instructions, &tables).await?;
+
+    Ok((Cost::new(transaction_fee, priority_fee, EXECUTION_TIP, protocol_fee, rent), compute_unit_limit))
+}
+
+async fn fee_for_message(client: &RpcClient, message: &VersionedMessage) -> Result<u64> {
+    let fee = match message {
+        VersionedMessage::Legacy(message) => client.get_fee_for_message(message).await,
+        VersionedMessage::V0(message) => client.get_fee_for_message(message).await,
+    };
+    fee.context("Failed to get the fee for the message")
+}
+
+/// Simulates `instructions` and sums the rent-exempt minimum of every
+/// writable account that doesn't exist yet but would afterwards
+async fn rent_for_created_accounts(
+    client: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> Result<u64> {
+    let mut candidates: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|instruction| &instruction.accounts) {
+        if meta.is_writable && !meta.is_signer && !candidates.contains(&meta.pubkey) {
+            candidates.push(meta.pubkey);
+        }
+    }
+    let existing = client
+        .get_multiple_accounts(&candidates)
+        .await
+        .context("Failed to fetch the submission's accounts")?;
+    let missing: Vec<Pubkey> = candidates
+        .into_iter()
+        .zip(existing)
+        .filter_map(|(address, account)| account.is_none().then_some(address))
+        .collect();
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)];
+    simulated.extend_from_slice(instructions);
+    // Unsigned, with the blockhash replaced by the simulation
+    let message = lookup::compile(payer, &simulated, tables, Hash::default())?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message,
+    };
+    let simulation = client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(client.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: missing.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .context("Failed to simulate the submission")?
+        .value;
+    if let Some(err) = simulation.err {
+        bail!(ClientError::Simulation(format!(
+            "Simulation failed while estimating rent: {:?} {:?}",
+            err,
+            simulation.logs.unwrap_or_default()
+        )));
+    }
+
+    let mut rent = 0;
+    for account in simulation.accounts.unwrap_or_default().into_iter().flatten() {
+        let account: Account = account
+            .decode()
+            .ok_or_else(|| anyhow!("Simulation returned an account that could not be decoded"))?;
+        rent += client
+            .get_minimum_balance_for_rent_exemption(account.data.len())
+            .await
+            .context("Failed to get the rent-exempt minimum")?;
+    }
+    Ok(rent)
+}
@@ -0,0 +1,64 @@
+//! Links to sent transactions on block explorers
+//!
+//! The cluster is told apart by its genesis hash, fetched once per run, so
+//! links to the public clusters use the explorers' own cluster names. Any
+//! other cluster, such as a local validator, gets Explorer's and Solscan's
+//! custom-URL form pointing at the RPC endpoint in use; XRAY only indexes
+//! the public clusters.
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Signature;
+use tokio::sync::OnceCell;
+
+use crate::{json_output, Cli};
+
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+const TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+static CLUSTER: OnceCell<Cluster> = OnceCell::const_new();
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+    /// A local validator or any other cluster, reached through the RPC URL
+    Custom,
+}
+
+/// Prints links to `signature` on the explorers that can show it, unless `--no-links` is set
+pub async fn print_links(client: &RpcClient, cli: &Cli, signature: &Signature) {
+    // Under `--output json` the links wouldn't be printed anyway
+    if cli.no_links || json_output() {
+        return;
+    }
+    let cluster = *CLUSTER.get_or_init(|| detect(client)).await;
+    // Explorer and Solscan name clusters the same way
+    let query = match cluster {
+        Cluster::Mainnet => String::new(),
+        Cluster::Devnet => "?cluster=devnet".to_string(),
+        Cluster::Testnet => "?cluster=testnet".to_string(),
+        Cluster::Custom => format!("?cluster=custom&customUrl={}", urlencoding::encode(&client.url())),
+    };
+    say!("🔗 Explorer: https://explorer.solana.com/tx/{}{}", signature, query);
+    say!("🔗 Solscan: https://solscan.io/tx/{}{}", signature, query);
+    match cluster {
+        Cluster::Mainnet => say!("🔗 XRAY: https://xray.helius.xyz/tx/{}", signature),
+        Cluster::Devnet => say!("🔗 XRAY: https://xray.helius.xyz/tx/{}?network=devnet", signature),
+        Cluster::Testnet | Cluster::Custom => {}
+    }
+}
+
+/// The cluster `client` talks to; a custom one when the genesis hash can't be fetched
+async fn detect(client: &RpcClient) -> Cluster {
+    let Ok(genesis_hash) = client.get_genesis_hash().await else {
+        return Cluster::Custom;
+    };
+    match genesis_hash.to_string().as_str() {
+        MAINNET_GENESIS_HASH => Cluster::Mainnet,
+        DEVNET_GENESIS_HASH => Cluster::Devnet,
+        TESTNET_GENESIS_HASH => Cluster::Testnet,
+        _ => Cluster::Custom,
+    }
+}
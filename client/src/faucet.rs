@@ -0,0 +1,165 @@
+//! `--airdrop`: topping up the payer from the cluster's faucet
+//!
+//! The payer is topped up to what a few submissions cost, as priced by the
+//! cost estimator, and left alone when it already holds that much. Failed
+//! airdrops are retried with backoff. When the faucet is rate-limiting or
+//! keeps failing, the client prints a URL and QR code to fund the payer by
+//! hand and, in a terminal, waits for the funds to arrive.
+
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_sdk::Calculation;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    native_token::{lamports_to_sol, LAMPORTS_PER_SOL},
+    pubkey::Pubkey,
+};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use crate::estimate;
+use crate::submit::OP_ADD;
+use crate::Cli;
+
+/// Submissions the payer is funded for, so a session doesn't stall after the first
+const SUBMISSIONS_FUNDED: u64 = 5;
+
+/// Cost assumed per submission when it can't be estimated, e.g. before the payer exists
+const FALLBACK_SUBMISSION_COST: u64 = LAMPORTS_PER_SOL / 20;
+
+/// Most lamports devnet's faucet hands out per request
+const MAX_AIRDROP: u64 = 2 * LAMPORTS_PER_SOL;
+
+const MAX_AIRDROP_ATTEMPTS: u32 = 4;
+
+/// Backoff before the second attempt, doubled for each one after
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for funds sent by hand
+const MANUAL_FUNDING_TIMEOUT: Duration = Duration::from_secs(300);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Web faucet for devnet and testnet
+const WEB_FAUCET_URL: &str = "https://faucet.solana.com";
+
+/// Tops up `payer` until it can pay for `SUBMISSIONS_FUNDED` submissions
+pub async fn fund(client: &RpcClient, cli: &Cli, payer: &Pubkey) -> Result<()> {
+    let required = required_lamports(client, cli, payer).await;
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 1..=MAX_AIRDROP_ATTEMPTS {
+        let balance = get_balance(client, payer).await?;
+        if balance >= required {
+            say!("✅ Payer holds {} SOL, enough for {} submissions", lamports_to_sol(balance), SUBMISSIONS_FUNDED);
+            return Ok(());
+        }
+
+        let amount = (required - balance).min(MAX_AIRDROP);
+        say!("💸 Requesting an airdrop of {} SOL (attempt {}/{})...",
+                 lamports_to_sol(amount), attempt, MAX_AIRDROP_ATTEMPTS);
+        match airdrop(client, payer, amount).await {
+            Ok(()) => say!("✅ Airdrop confirmed!"),
+            Err(e) if is_rate_limited(&e) => {
+                say!("⚠️ The faucet is rate-limiting requests: {:#}", e);
+                break;
+            }
+            Err(e) => {
+                say!("⚠️ Airdrop failed: {:#}", e);
+                if attempt < MAX_AIRDROP_ATTEMPTS {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+
+    let balance = get_balance(client, payer).await?;
+    if balance >= required {
+        return Ok(());
+    }
+    fund_by_hand(client, payer, required, balance).await
+}
+
+/// Lamports `SUBMISSIONS_FUNDED` direct Bonsol submissions cost
+async fn required_lamports(client: &RpcClient, cli: &Cli, payer: &Pubkey) -> u64 {
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 2, operand_b: 12 };
+    let per_submission = match estimate::submission_cost(client, cli, payer, &calculation).await {
+        Ok(cost) => cost,
+        Err(e) => {
+            say!("⚠️ Could not estimate what a submission costs ({:#}); assuming {} SOL",
+                     e, lamports_to_sol(FALLBACK_SUBMISSION_COST));
+            FALLBACK_SUBMISSION_COST
+        }
+    };
+    per_submission * SUBMISSIONS_FUNDED
+}
+
+/// Requests `amount` lamports for `payer` and waits for the airdrop to confirm
+async fn airdrop(client: &RpcClient, payer: &Pubkey, amount: u64) -> Result<()> {
+    let signature = client.request_airdrop(payer, amount).await?;
+    let started = Instant::now();
+    while started.elapsed() < CONFIRMATION_TIMEOUT {
+        if client.confirm_transaction(&signature).await? {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Err(anyhow!("Airdrop {} was not confirmed within {:?}", signature, CONFIRMATION_TIMEOUT))
+}
+
+/// Whether the faucet refused the request for coming too often, where retrying soon won't help
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error).to_lowercase();
+    ["429", "too many requests", "rate limit", "limit reached"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Prints how to fund `payer` by hand, then waits for the funds when someone is there to send them
+async fn fund_by_hand(client: &RpcClient, payer: &Pubkey, required: u64, balance: u64) -> Result<()> {
+    let shortfall = required - balance;
+    // Solana Pay transfer request, understood by most wallets
+    let uri = format!(
+        "solana:{}?amount={}&label={}",
+        payer,
+        lamports_to_sol(shortfall),
+        urlencoding::encode("Bonsol Calculator payer")
+    );
+
+    // On stderr, so it shows under `--output json` too
+    eprintln!("\n🚰 The faucet could not fund the payer. Send it at least {} SOL:", lamports_to_sol(shortfall));
+    eprintln!("   Web faucet (devnet/testnet): {}", WEB_FAUCET_URL);
+    eprintln!("   From another wallet: solana transfer {} {}", payer, lamports_to_sol(shortfall));
+    if let Ok(code) = QrCode::new(uri.as_bytes()) {
+        let image = code
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .quiet_zone(true)
+            .build();
+        eprintln!("{}", image);
+    }
+    eprintln!("   {}", uri);
+
+    if !std::io::stdin().is_terminal() {
+        bail!("Payer {} holds {} lamports but needs {}", payer, balance, required);
+    }
+    eprintln!("⏳ Waiting up to {}s for the funds to arrive (Ctrl-C to stop)...", MANUAL_FUNDING_TIMEOUT.as_secs());
+    let started = Instant::now();
+    while started.elapsed() < MANUAL_FUNDING_TIMEOUT {
+        tokio::time::sleep(POLL_INTERVAL * 4).await;
+        let balance = get_balance(client, payer).await?;
+        if balance >= required {
+            say!("✅ Funds arrived; payer holds {} SOL", lamports_to_sol(balance));
+            return Ok(());
+        }
+    }
+    bail!("Payer {} still holds less than the {} lamports needed", payer, required)
+}
+
+async fn get_balance(client: &RpcClient, payer: &Pubkey) -> Result<u64> {
+    client.get_balance(payer).await.context("Failed to get the payer's balance")
+}
@@ -0,0 +1,134 @@
+//! Callback detection through a Yellowstone gRPC (Geyser) endpoint
+//!
+//! With `--geyser-url`, `watch` and `bench` stream confirmed transactions
+//! mentioning the subscribed account from a Yellowstone gRPC endpoint instead
+//! of an RPC websocket log subscription, which drops notifications under
+//! heavy load. Each transaction is turned into the logs response the
+//! websocket would have delivered, so callbacks are parsed the same way.
+//! The gRPC client is only built with the `geyser` feature.
+
+use anyhow::Result;
+use clap::Args;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::wait::CallbackSubscription;
+
+#[derive(Args)]
+pub struct GeyserArgs {
+    /// Yellowstone gRPC endpoint to detect callbacks through instead of the RPC websocket (`geyser` feature)
+    #[arg(long)]
+    geyser_url: Option<String>,
+
+    /// Access token for --geyser-url, sent as `x-token`
+    #[arg(long, requires = "geyser_url")]
+    geyser_token: Option<String>,
+}
+
+impl GeyserArgs {
+    pub fn is_set(&self) -> bool {
+        self.geyser_url.is_some()
+    }
+
+    /// Endpoint callbacks are detected through, for progress messages
+    pub fn url(&self) -> Option<&str> {
+        self.geyser_url.as_deref()
+    }
+}
+
+/// Streams confirmed transactions mentioning `account` from `--geyser-url`
+#[cfg(feature = "geyser")]
+pub async fn subscribe(args: &GeyserArgs, account: &Pubkey) -> Result<CallbackSubscription<'static>> {
+    use anyhow::{bail, Context};
+    use futures_util::{future, StreamExt};
+    use std::collections::HashMap;
+    use tonic::transport::ClientTlsConfig;
+    use yellowstone_grpc_client::GeyserGrpcClient;
+    use yellowstone_grpc_proto::prelude::{
+        CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions,
+    };
+
+    let Some(url) = &args.geyser_url else {
+        bail!("Nothing to subscribe to without --geyser-url");
+    };
+    let mut client = GeyserGrpcClient::build_from_shared(url.clone())
+        .with_context(|| format!("Invalid Geyser endpoint {}", url))?
+        .x_token(args.geyser_token.clone())
+        .context("Invalid --geyser-token")?
+        .tls_config(ClientTlsConfig::new().with_native_roots())
+        .context("Failed to configure TLS for the Geyser endpoint")?
+        .connect()
+        .await
+        .with_context(|| format!("Failed to connect to Geyser endpoint {}", url))?;
+
+    let request = SubscribeRequest {
+        transactions: HashMap::from([(
+            "calculator".to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                account_include: vec![account.to_string()],
+                ..Default::default()
+            },
+        )]),
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    };
+    let (requests, updates) = client
+        .subscribe_with_request(Some(request))
+        .await
+        .context("Failed to subscribe to transactions over Geyser")?;
+
+    let logs = updates
+        .inspect(|update| {
+            if let Err(status) = update {
+                say!("⚠️ Geyser stream failed: {}", status);
+            }
+        })
+        .take_while(|update| future::ready(update.is_ok()))
+        .filter_map(|update| future::ready(update.ok().and_then(convert::logs_response)))
+        .boxed();
+    // The request sink keeps the subscription open until the subscription is closed
+    Ok(CallbackSubscription::new(
+        logs,
+        Box::new(move || {
+            drop(requests);
+            Box::pin(future::ready(()))
+        }),
+    ))
+}
+
+#[cfg(not(feature = "geyser"))]
+pub async fn subscribe(_args: &GeyserArgs, _account: &Pubkey) -> Result<CallbackSubscription<'static>> {
+    anyhow::bail!(crate::error::ClientError::InvalidArgs(
+        "--geyser-url needs the client built with the `geyser` feature: cargo run --features geyser -- ...".to_string()
+    ))
+}
+
+#[cfg(feature = "geyser")]
+mod convert {
+    use solana_client::rpc_response::{Response, RpcLogsResponse, RpcResponseContext};
+    use solana_sdk::{signature::Signature, transaction::TransactionError};
+    use yellowstone_grpc_proto::prelude::{subscribe_update::UpdateOneof, SubscribeUpdate};
+
+    /// The logs response the websocket would have delivered for a transaction update
+    pub fn logs_response(update: SubscribeUpdate) -> Option<Response<RpcLogsResponse>> {
+        let Some(UpdateOneof::Transaction(update)) = update.update_oneof else {
+            // Pings and updates of other kinds
+            return None;
+        };
+        let transaction = update.transaction?;
+        let meta = transaction.meta?;
+        let signature = Signature::try_from(transaction.signature.as_slice()).ok()?;
+        // Errors travel bincode-encoded, as the validator stores them
+        let err = meta
+            .err
+            .and_then(|err| bincode::deserialize::<TransactionError>(&err.err).ok());
+        Some(Response {
+            context: RpcResponseContext::new(update.slot),
+            value: RpcLogsResponse {
+                signature: signature.to_string(),
+                err,
+                logs: meta.log_messages,
+            },
+        })
+    }
+}
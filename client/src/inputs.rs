@@ -0,0 +1,137 @@
+//! Where the prover finds the inputs of a direct Bonsol submission
+//!
+//! By default the calculation travels inside the execute instruction as one
+//! public input. `--input-url` has the prover download it instead, and
+//! `--private-operand-a` moves operand A into a private input that only the
+//! prover claiming the execution can fetch. Bonsol hands the inputs to the
+//! guest in order, so the guest reads the same bytes in every case.
+
+use std::iter;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bonsol_interface::instructions::InputRef;
+use calculator_common::version_header;
+use calculator_sdk::Calculation;
+use clap::Args;
+use serde::Serialize;
+
+use crate::error::ClientError;
+
+#[derive(Args)]
+pub struct InputArgs {
+    /// Send operand A as a private input served at --input-url (single operations only)
+    #[arg(long)]
+    private_operand_a: bool,
+
+    /// URL the prover fetches the input from instead of reading it from the instruction
+    #[arg(long)]
+    input_url: Option<String>,
+
+    /// Upload the payload with an HTTP PUT before submitting, e.g. to a presigned S3 URL
+    #[arg(long)]
+    upload_url: Option<String>,
+}
+
+/// One input of an execute instruction
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// Data carried in the instruction
+    Public(Vec<u8>),
+    /// URL of data anyone can fetch
+    Url(String),
+    /// URL of data released only to the claiming prover
+    Private(String),
+}
+
+/// `--output json` description of one input
+#[derive(Serialize)]
+pub struct InputReport {
+    /// `public`, `url` or `private`
+    kind: &'static str,
+    /// Hex data of a public input, the URL otherwise
+    value: String,
+}
+
+impl Input {
+    pub fn as_input_ref(&self) -> InputRef<'_> {
+        match self {
+            Input::Public(data) => InputRef::public(data),
+            Input::Url(url) => InputRef::url(url.as_bytes()),
+            Input::Private(url) => InputRef::private(url.as_bytes()),
+        }
+    }
+
+    pub fn report(&self) -> InputReport {
+        match self {
+            Input::Public(data) => InputReport { kind: "public", value: hex::encode(data) },
+            Input::Url(url) => InputReport { kind: "url", value: url.clone() },
+            Input::Private(url) => InputReport { kind: "private", value: url.clone() },
+        }
+    }
+}
+
+impl InputArgs {
+    /// Whether the inputs leave the instruction, which only direct Bonsol submissions support
+    pub fn is_remote(&self) -> bool {
+        self.private_operand_a || self.input_url.is_some() || self.upload_url.is_some()
+    }
+}
+
+/// Builds the inputs for `calculation`, uploading the hosted payload first
+/// when `--upload-url` is given
+pub async fn prepare(args: &InputArgs, calculation: &Calculation) -> Result<Vec<Input>> {
+    let Some(input_url) = &args.input_url else {
+        if args.private_operand_a {
+            bail!(ClientError::InvalidArgs(
+                "--private-operand-a needs --input-url, where the prover fetches operand A".to_string()
+            ));
+        }
+        if args.upload_url.is_some() {
+            bail!(ClientError::InvalidArgs(
+                "--upload-url needs --input-url, where the prover fetches the uploaded payload".to_string()
+            ));
+        }
+        return Ok(vec![Input::Public(calculation.input())]);
+    };
+
+    let (payload, inputs) = match (args.private_operand_a, calculation) {
+        (true, Calculation::Single { op_code, operand_a, operand_b }) => (
+            operand_a.to_le_bytes().to_vec(),
+            vec![
+                // The guest reads the inputs as one frame, so any version header leads the first
+                Input::Public(version_header(iter::once(*op_code)).into_iter().chain(op_code.to_le_bytes()).collect()),
+                Input::Private(input_url.clone()),
+                Input::Public(operand_b.to_le_bytes().to_vec()),
+            ],
+        ),
+        (true, Calculation::Expression(_)) => {
+            bail!(ClientError::InvalidArgs(
+                "--private-operand-a only applies to single operations, not expressions".to_string()
+            ))
+        }
+        (false, _) => (calculation.input(), vec![Input::Url(input_url.clone())]),
+    };
+
+    match &args.upload_url {
+        Some(upload_url) => {
+            upload(upload_url, payload).await?;
+            say!("☁️ Uploaded the input payload to {}", upload_url);
+        }
+        None => say!("🌐 The payload must be served at {}: {}", input_url, hex::encode(&payload)),
+    }
+    Ok(inputs)
+}
+
+/// PUTs `payload` to `url`; plain HTTP endpoints and presigned S3 URLs accept the same request
+async fn upload(url: &str, payload: Vec<u8>) -> Result<()> {
+    reqwest::Client::new()
+        .put(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to upload the input payload to {}", url))?
+        .error_for_status()
+        .map_err(|e| anyhow!("Upload to {} was refused: {}", url, e))?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! On-disk journal of submissions, so waiting survives a restart
+//!
+//! Every submission is appended to `submissions.json` in the config directory
+//! with its signature, slot and status. `journal list` shows them and
+//! `journal resume-wait` waits for the callbacks of those still pending,
+//! first asking Bonsol about any that settled while nobody was listening.
+
+use anyhow::{bail, Context, Result};
+use calculator_sdk::{fetch_execution_status, ExecutionLifecycle};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::ClientError;
+use crate::wait::{self, CallbackOutcome};
+use crate::{config_dir, emit};
+
+const JOURNAL_FILE: &str = "submissions.json";
+
+/// Status of a submission whose callback hasn't been seen
+const PENDING: &str = "pending";
+
+#[derive(Args)]
+pub struct JournalArgs {
+    #[command(subcommand)]
+    command: JournalCommand,
+}
+
+#[derive(Subcommand)]
+enum JournalCommand {
+    /// List journaled submissions, oldest first
+    List {
+        /// Only list submissions still waiting for their callback
+        #[arg(long)]
+        pending: bool,
+    },
+    /// Wait for the callbacks of pending submissions, e.g. after the client was interrupted
+    ResumeWait(ResumeWaitArgs),
+}
+
+#[derive(Args)]
+struct ResumeWaitArgs {
+    /// Websocket URL for callback notifications [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Seconds to wait for the remaining callbacks before giving up
+    #[arg(long, default_value = "600")]
+    timeout: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Journal {
+    submissions: Vec<Entry>,
+}
+
+/// One journaled submission
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    /// Execution ID as Bonsol knows it
+    execution_id: String,
+    requester: String,
+    signature: String,
+    /// Slot the submission landed in, when the cluster reported it
+    slot: Option<u64>,
+    method: String,
+    calculation: String,
+    /// RFC 3339 time the submission was sent
+    submitted_at: String,
+    /// `pending`, then how the callback was handled or why none will come:
+    /// `completed`, `out_of_bounds`, `awaiting_finalize`, `failed`, `proved`,
+    /// `expired` or `not_found`
+    status: String,
+    result: Option<i64>,
+    error: Option<String>,
+}
+
+/// Journals a submission that landed; failing to write the journal only warns,
+/// since the transaction was sent either way
+pub async fn record(
+    client: &RpcClient,
+    execution_id: &str,
+    requester: &Pubkey,
+    signature: &Signature,
+    method: &str,
+    calculation: &str,
+) {
+    let slot = client
+        .get_signature_statuses(&[*signature])
+        .await
+        .ok()
+        .and_then(|statuses| statuses.value.into_iter().next().flatten())
+        .map(|status| status.slot);
+    let entry = Entry {
+        execution_id: execution_id.to_string(),
+        requester: requester.to_string(),
+        signature: signature.to_string(),
+        slot,
+        method: method.to_string(),
+        calculation: calculation.to_string(),
+        submitted_at: chrono::Utc::now().to_rfc3339(),
+        status: PENDING.to_string(),
+        result: None,
+        error: None,
+    };
+    let saved = Journal::load().and_then(|mut journal| {
+        journal.submissions.push(entry);
+        journal.save()
+    });
+    if let Err(e) = saved {
+        say!("⚠️ Failed to journal submission {}: {:#}", execution_id, e);
+    }
+}
+
+/// Records how the callback of `execution_id` was handled
+pub fn update(execution_id: &str, outcome: &CallbackOutcome) {
+    let saved = Journal::load().and_then(|mut journal| {
+        if let Some(entry) = journal.pending_mut(execution_id) {
+            entry.set_outcome(outcome);
+            journal.save()?;
+        }
+        Ok(())
+    });
+    if let Err(e) = saved {
+        say!("⚠️ Failed to update the journal for {}: {:#}", execution_id, e);
+    }
+}
+
+pub async fn run(client: &RpcClient, args: &JournalArgs) -> Result<()> {
+    match &args.command {
+        JournalCommand::List { pending } => {
+            let journal = Journal::load()?;
+            let entries: Vec<Entry> = journal
+                .submissions
+                .into_iter()
+                .filter(|entry| !pending || entry.status == PENDING)
+                .collect();
+            if entries.is_empty() {
+                say!("No journaled submissions");
+            }
+            for entry in &entries {
+                print_entry(entry);
+            }
+            emit(&entries)
+        }
+        JournalCommand::ResumeWait(args) => resume_wait(client, args).await,
+    }
+}
+
+async fn resume_wait(client: &RpcClient, args: &ResumeWaitArgs) -> Result<()> {
+    let mut journal = Journal::load()?;
+    if journal.submissions.iter().all(|entry| entry.status != PENDING) {
+        say!("No pending submissions");
+        return emit(&Vec::<Entry>::new());
+    }
+
+    // Subscribe before checking Bonsol so a callback landing in between isn't missed
+    let ws_url = args.ws_url.clone().unwrap_or_else(|| wait::websocket_url(&client.url()));
+    let pubsub = wait::connect(&ws_url).await?;
+    let mut subscription = wait::subscribe(&pubsub, &bonsol_calculator_backend::id()).await?;
+
+    let mut touched = Vec::new();
+    let mut awaiting: HashMap<String, usize> = HashMap::new();
+    for (index, entry) in journal.submissions.iter_mut().enumerate() {
+        if entry.status != PENDING {
+            continue;
+        }
+        touched.push(index);
+        let requester = Pubkey::from_str(&entry.requester)
+            .with_context(|| format!("Invalid requester {:?} in the journal", entry.requester))?;
+        let status = fetch_execution_status(client, &requester, &entry.execution_id).await?;
+        match status.lifecycle {
+            ExecutionLifecycle::Requested { .. } | ExecutionLifecycle::Claimed { .. } => {
+                awaiting.insert(entry.execution_id.clone(), index);
+            }
+            // Settled while nobody was listening; the callback's outcome is in its logs
+            ExecutionLifecycle::Proved => entry.status = "proved".to_string(),
+            ExecutionLifecycle::Expired => entry.status = "expired".to_string(),
+            ExecutionLifecycle::NotFound => entry.status = "not_found".to_string(),
+            ExecutionLifecycle::Failed(_) => {
+                entry.status = "failed".to_string();
+                entry.error = Some(status.lifecycle.to_string());
+            }
+        }
+        if entry.status != PENDING {
+            print_entry(entry);
+        }
+    }
+    journal.save()?;
+
+    say!("⏳ Waiting for {} callback(s) (up to {}s)...", awaiting.len(), args.timeout);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(args.timeout);
+    while !awaiting.is_empty() {
+        tokio::select! {
+            callback = subscription.next_callback() => {
+                let (execution_id, outcome) = callback?;
+                let Some(index) = awaiting.remove(&execution_id) else {
+                    continue;
+                };
+                let entry = &mut journal.submissions[index];
+                entry.set_outcome(&outcome);
+                print_entry(entry);
+                journal.save()?;
+            }
+            _ = tokio::time::sleep_until(deadline) => break,
+        }
+    }
+    subscription.close().await;
+
+    let entries: Vec<Entry> = touched.into_iter().map(|index| journal.submissions[index].clone()).collect();
+    emit(&entries)?;
+    if !awaiting.is_empty() {
+        bail!(ClientError::CallbackTimeout(format!(
+            "{} submission(s) still pending after {}s; run `journal resume-wait` again later",
+            awaiting.len(),
+            args.timeout
+        )));
+    }
+    Ok(())
+}
+
+impl Journal {
+    fn load() -> Result<Self> {
+        let path = journal_path()?;
+        if !path.exists() {
+            return Ok(Journal::default());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = journal_path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The newest pending submission with `execution_id`
+    fn pending_mut(&mut self, execution_id: &str) -> Option<&mut Entry> {
+        self.submissions
+            .iter_mut()
+            .rev()
+            .find(|entry| entry.execution_id == execution_id && entry.status == PENDING)
+    }
+}
+
+impl Entry {
+    fn set_outcome(&mut self, outcome: &CallbackOutcome) {
+        let status = match outcome {
+            CallbackOutcome::Completed { result, .. } => {
+                self.result = Some(*result);
+                "completed"
+            }
+            CallbackOutcome::OutOfBounds => "out_of_bounds",
+            CallbackOutcome::GuestError(reason) => {
+                self.error = Some(reason.clone());
+                "guest_error"
+            }
+            CallbackOutcome::AwaitingFinalize => "awaiting_finalize",
+            CallbackOutcome::Failed(reason) => {
+                self.error = Some(reason.clone());
+                "failed"
+            }
+        };
+        self.status = status.to_string();
+    }
+}
+
+fn print_entry(entry: &Entry) {
+    let outcome = match (entry.result, &entry.error) {
+        (Some(result), _) => format!(" = {}", result),
+        (None, Some(error)) => format!(" ({})", error),
+        (None, None) => String::new(),
+    };
+    say!("{}  {:<16}  {:<18}  {:<17}  {}{}",
+             entry.submitted_at, entry.execution_id, entry.calculation, entry.status, entry.signature, outcome);
+}
+
+fn journal_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(JOURNAL_FILE))
+}
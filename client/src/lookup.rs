@@ -0,0 +1,202 @@
+//! Address lookup tables and the v0 transactions that use them
+//!
+//! With `--lookup-table`, every transaction is compiled as a v0 message that
+//! loads its non-signer accounts from the given tables, which keeps Bonsol's
+//! long execute instructions well under the packet size. `lookup-table`
+//! creates or extends a table holding the accounts those instructions share.
+
+use anyhow::{anyhow, bail, Context, Result};
+use bonsol_interface::util::deployment_address;
+use calculator_common::{codec, treasury_address, CalculatorState};
+use clap::Args;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::address_lookup_table::{
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::AddressLookupTable,
+    AddressLookupTableAccount,
+};
+use solana_program::system_program;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::VersionedTransaction,
+};
+use std::str::FromStr;
+
+use crate::calculators;
+use crate::submit::callback_extra_accounts;
+use crate::{emit, send_instruction, Cli};
+
+// Most addresses one extend instruction can carry within the packet size
+const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+#[derive(Args)]
+pub struct LookupTableArgs {
+    /// Existing table to extend instead of creating a new one
+    #[arg(long)]
+    table: Option<Pubkey>,
+
+    /// Calculator state account whose state and treasury are added too
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Further addresses to add
+    #[arg(long = "address")]
+    addresses: Vec<String>,
+}
+
+/// Fetches and decodes the lookup tables at `addresses`
+pub async fn load(client: &RpcClient, addresses: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>> {
+    let mut tables = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        let account = client
+            .get_account(address)
+            .await
+            .with_context(|| format!("Failed to fetch lookup table {}", address))?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|e| anyhow!("{} is not an address lookup table: {}", address, e))?;
+        tables.push(AddressLookupTableAccount {
+            key: *address,
+            addresses: table.addresses.to_vec(),
+        });
+    }
+    Ok(tables)
+}
+
+/// Compiles `instructions` into a legacy message, or a v0 message loading
+/// accounts from `tables` when any are given
+pub fn compile(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedMessage> {
+    if tables.is_empty() {
+        return Ok(VersionedMessage::Legacy(Message::new_with_blockhash(instructions, Some(payer), &blockhash)));
+    }
+    let message = v0::Message::try_compile(payer, instructions, tables, blockhash)
+        .map_err(|e| anyhow!("Failed to compile v0 message: {}", e))?;
+    Ok(VersionedMessage::V0(message))
+}
+
+/// Signs `instructions` into a transaction, see `compile`
+pub fn sign(
+    payer: &Pubkey,
+    signers: &[&dyn Signer],
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let message = compile(payer, instructions, tables, blockhash)?;
+    let data = message.serialize();
+    let required = message.header().num_required_signatures as usize;
+    // Sign in the message's signer order; `dyn Signer` slices don't implement `Signers`
+    let signatures = message.static_account_keys()[..required]
+        .iter()
+        .map(|key| {
+            let signer = signers
+                .iter()
+                .find(|signer| signer.pubkey() == *key)
+                .ok_or_else(|| anyhow!("Missing signer for {}", key))?;
+            signer
+                .try_sign_message(&data)
+                .with_context(|| format!("Failed to sign transaction with {}", key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(VersionedTransaction { signatures, message })
+}
+
+/// Serialized size of a transaction carrying `instructions`, signatures included
+pub fn transaction_size(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> Result<usize> {
+    let message = compile(payer, instructions, tables, Hash::default())?;
+    let signatures = message.header().num_required_signatures as usize;
+    // Signature count prefix + one signature per required signer + message
+    Ok(1 + 64 * signatures + message.serialize().len())
+}
+
+/// Creates a lookup table owned by the payer, or extends `--table`, with the
+/// accounts Bonsol execute instructions for the calculator share
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &LookupTableArgs) -> Result<()> {
+    let mut addresses = vec![
+        system_program::id(),
+        bonsol_calculator_backend::id(),
+        deployment_address(&cli.deployment.image_id).0,
+    ];
+    if cli.deployment.callback_program != bonsol_calculator_backend::id() {
+        addresses.push(cli.deployment.callback_program);
+    }
+    addresses.extend(callback_extra_accounts()?.iter().map(|meta| meta.pubkey));
+    if let Some(state) = calculators::resolve_state(cli, args.state)? {
+        let state_data = client
+            .get_account_data(&state)
+            .await
+            .with_context(|| format!("Failed to fetch calculator state {}", state))?;
+        let calculator_state: CalculatorState = codec::decode(&state_data)
+            .map_err(|e| anyhow!("{} is not a calculator state account: {}", state, e))?;
+        addresses.push(state);
+        addresses.push(treasury_address(&bonsol_calculator_backend::id(), &state).0);
+        addresses.push(deployment_address(&calculator_state.image_id).0);
+        // Calculator-program submissions pass Bonsol as an account for their CPI
+        addresses.push(bonsol_interface::ID);
+        if calculator_state.config.token_fee.is_some() {
+            addresses.push(spl_token::id());
+        }
+    }
+    for address in &args.addresses {
+        addresses.push(Pubkey::from_str(address).with_context(|| format!("Invalid address {:?}", address))?);
+    }
+    let mut unique = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !unique.contains(&address) {
+            unique.push(address);
+        }
+    }
+    let mut addresses = unique;
+
+    let table = match args.table {
+        Some(table) => {
+            let existing = load(client, &[table]).await?.remove(0);
+            addresses.retain(|address| !existing.addresses.contains(address));
+            say!("📇 Extending lookup table {}", table);
+            table
+        }
+        None => {
+            // The derivation slot has to be in the SlotHashes sysvar, so use a finalized one
+            let recent_slot = client
+                .get_slot_with_commitment(CommitmentConfig::finalized())
+                .await
+                .context("Failed to get a recent slot")?;
+            let (instruction, table) = create_lookup_table(payer.pubkey(), payer.pubkey(), recent_slot);
+            send_instruction(client, cli, payer, &[], instruction).await?;
+            say!("📇 Created lookup table {}", table);
+            table
+        }
+    };
+
+    if addresses.is_empty() && args.table.is_some() {
+        bail!("Lookup table {} already holds every address", table);
+    }
+
+    for chunk in addresses.chunks(MAX_ADDRESSES_PER_EXTEND) {
+        let instruction = extend_lookup_table(table, payer.pubkey(), Some(payer.pubkey()), chunk.to_vec());
+        send_instruction(client, cli, payer, &[], instruction).await?;
+    }
+    say!("✅ Added {} address(es); tables can be used from the slot after they were extended", addresses.len());
+    for address in &addresses {
+        say!("   {}", address);
+    }
+
+    emit(&json!({
+        "table": table.to_string(),
+        "added": addresses.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+    }))
+}
@@ -0,0 +1,210 @@
+//! Unsigned transactions for multisig and offline signing
+//!
+//! `submit --export-unsigned <FILE>` writes the transaction it would send as
+//! base64 wire format with empty signatures instead of sending it. A multisig
+//! tool such as Squads can import its message, or an offline signer can sign
+//! it; `broadcast` then sends the signed transaction. Recent blockhashes
+//! expire within a couple of minutes, so exports meant to be signed later
+//! should use `--nonce` to build on a durable nonce instead.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::nonce_utils::nonblocking::{data_from_account, get_account_with_commitment};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction,
+    transaction::VersionedTransaction,
+};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::ClientError;
+use crate::{emit, explorer, lookup, simulate, Cli};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Write the unsigned transaction to this file as base64 instead of sending it
+    #[arg(long)]
+    export_unsigned: Option<PathBuf>,
+
+    /// Account paying for and requesting the exported transaction, e.g. a multisig vault [default: the payer]
+    #[arg(long, requires = "export_unsigned")]
+    fee_payer: Option<Pubkey>,
+
+    /// Durable nonce account, authorized to the fee payer, to build the exported transaction on
+    #[arg(long, requires = "export_unsigned")]
+    nonce: Option<Pubkey>,
+}
+
+#[derive(Args)]
+pub struct BroadcastArgs {
+    /// File holding a base64 transaction written by --export-unsigned
+    file: PathBuf,
+
+    /// Signature collected offline, as PUBKEY=SIGNATURE; repeatable
+    #[arg(long = "signer")]
+    signers: Vec<String>,
+}
+
+/// `--output json` description of an exported transaction
+#[derive(Serialize)]
+pub struct ExportReport {
+    file: String,
+    fee_payer: String,
+    /// Accounts whose signatures `broadcast` needs, in message order
+    required_signers: Vec<String>,
+    blockhash: String,
+    nonce: Option<String>,
+}
+
+impl ExportArgs {
+    pub fn is_set(&self) -> bool {
+        self.export_unsigned.is_some()
+    }
+
+    /// The account standing in for the local payer, when it won't sign itself
+    pub fn fee_payer(&self) -> Option<Pubkey> {
+        self.fee_payer
+    }
+}
+
+/// Writes `instructions`, paid for by `payer`, to `--export-unsigned` as an unsigned transaction
+pub async fn export(
+    client: &RpcClient,
+    cli: &Cli,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    args: &ExportArgs,
+) -> Result<ExportReport> {
+    let Some(path) = &args.export_unsigned else {
+        bail!("Nothing to export without --export-unsigned");
+    };
+
+    let tables = lookup::load(client, &cli.lookup_tables).await?;
+    let mut budgeted = cli.budget.instructions(client, payer, instructions, &tables).await?;
+    budgeted.extend_from_slice(instructions);
+
+    let blockhash = match &args.nonce {
+        Some(nonce) => {
+            let account = get_account_with_commitment(client, nonce, client.commitment())
+                .await
+                .with_context(|| format!("Failed to fetch nonce account {}", nonce))?;
+            let data = data_from_account(&account)
+                .with_context(|| format!("{} is not an initialized nonce account", nonce))?;
+            if data.authority != *payer {
+                bail!(ClientError::InvalidArgs(format!(
+                    "Nonce account {} is authorized to {}, not the fee payer {}", nonce, data.authority, payer
+                )));
+            }
+            // Advancing the nonce must be the transaction's first instruction
+            budgeted.insert(0, system_instruction::advance_nonce_account(nonce, payer));
+            data.blockhash()
+        }
+        None => client.get_latest_blockhash().await.context("Failed to get latest blockhash")?,
+    };
+
+    let message = lookup::compile(payer, &budgeted, &tables, blockhash)?;
+    let required = message.header().num_required_signatures as usize;
+    let required_signers: Vec<String> =
+        message.static_account_keys()[..required].iter().map(Pubkey::to_string).collect();
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); required],
+        message,
+    };
+    let encoded = BASE64.encode(bincode::serialize(&transaction)?);
+    std::fs::write(path, format!("{}\n", encoded)).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    say!("📦 Wrote the unsigned transaction to {}", path.display());
+    say!("   Needs signatures from: {}", required_signers.join(", "));
+    if args.nonce.is_none() {
+        say!("   ⚠️ Its blockhash expires in about a minute; pass --nonce to sign it later");
+    }
+    Ok(ExportReport {
+        file: path.display().to_string(),
+        fee_payer: payer.to_string(),
+        required_signers,
+        blockhash: blockhash.to_string(),
+        nonce: args.nonce.map(|nonce| nonce.to_string()),
+    })
+}
+
+/// Adds the signatures given with `--signer`, checks every required one, and sends the transaction
+pub async fn broadcast(client: &RpcClient, cli: &Cli, args: &BroadcastArgs) -> Result<()> {
+    let mut transaction = read_transaction(&args.file)?;
+    let data = transaction.message.serialize();
+    let required = transaction.message.header().num_required_signatures as usize;
+    let keys = transaction.message.static_account_keys()[..required].to_vec();
+    if transaction.signatures.len() != required {
+        bail!(ClientError::InvalidArgs(format!(
+            "{} carries {} signatures but its message requires {}",
+            args.file.display(),
+            transaction.signatures.len(),
+            required
+        )));
+    }
+
+    for pair in &args.signers {
+        let (pubkey, signature) = parse_signer(pair)?;
+        let Some(position) = keys.iter().position(|key| *key == pubkey) else {
+            bail!(ClientError::InvalidArgs(format!("{} is not a signer of this transaction", pubkey)));
+        };
+        transaction.signatures[position] = signature;
+    }
+
+    let missing: Vec<String> = keys
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(key, _)| key.to_string())
+        .collect();
+    if !missing.is_empty() {
+        bail!(ClientError::InvalidArgs(format!("Missing signatures from {}", missing.join(", "))));
+    }
+    if let Some((key, _)) = keys
+        .iter()
+        .zip(&transaction.signatures)
+        .find(|(key, signature)| !signature.verify(key.as_ref(), &data))
+    {
+        bail!(ClientError::InvalidArgs(format!("The signature of {} does not verify", key)));
+    }
+
+    if cli.simulate {
+        simulate::preflight(client, &transaction, cli.yes).await?;
+    }
+
+    say!("📡 Broadcasting the transaction from {}...", args.file.display());
+    let signature = client.send_and_confirm_transaction(&transaction).await.map_err(|e| {
+        match e.get_transaction_error() {
+            Some(error) => ClientError::Transaction(format!("Transaction was rejected: {}", error)).into(),
+            None => anyhow::Error::new(e).context("Failed to broadcast the transaction"),
+        }
+    })?;
+    say!("🎉 Transaction sent successfully!");
+    say!("📋 Signature: {}", signature);
+    explorer::print_links(client, cli, &signature).await;
+
+    emit(&json!({ "signature": signature.to_string() }))
+}
+
+fn read_transaction(path: &Path) -> Result<VersionedTransaction> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let bytes = BASE64
+        .decode(contents.trim())
+        .map_err(|e| ClientError::InvalidArgs(format!("{} is not base64: {}", path.display(), e)))?;
+    bincode::deserialize(&bytes)
+        .map_err(|e| ClientError::InvalidArgs(format!("{} does not hold a transaction: {}", path.display(), e)).into())
+}
+
+fn parse_signer(pair: &str) -> Result<(Pubkey, Signature)> {
+    let invalid = || ClientError::InvalidArgs(format!("Invalid --signer {:?}; expected PUBKEY=SIGNATURE", pair));
+    let (pubkey, signature) = pair.split_once('=').ok_or_else(invalid)?;
+    let pubkey = Pubkey::from_str(pubkey).map_err(|_| invalid())?;
+    let signature = Signature::from_str(signature).map_err(|_| invalid())?;
+    Ok((pubkey, signature))
+}
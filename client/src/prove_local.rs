@@ -0,0 +1,202 @@
+//! Running the calculator guest on this machine with the risc0 host SDK
+//!
+//! The guest gets the same input a Bonsol submission would send, so a result
+//! that differs from the native computation points at the guest or the input
+//! encoding rather than the prover network. Guests built with the `trace`
+//! feature write fixed-size trace records to stderr, which are decoded here.
+
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_common::constants::{JOURNAL_SEMANTICS_SATURATED, JOURNAL_SEMANTICS_WRAPPED};
+use calculator_common::{decode_journal, GuestJournal};
+use clap::Args;
+use risc0_zkvm::{compute_image_id, default_executor, default_prover, ExecutorEnv};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::emit;
+use crate::submit::{CalculationArgs, CALCULATOR_IMAGE_ID};
+
+// A trace record: the event byte, then its value as a little-endian i64
+const TRACE_RECORD_LEN: usize = 1 + 8;
+
+#[derive(Args)]
+pub struct ProveLocalArgs {
+    #[command(flatten)]
+    calculation: CalculationArgs,
+
+    /// Guest ELF built by `bonsol build`
+    #[arg(
+        long,
+        default_value = "../zk-program/target/riscv-guest/riscv32im-risc0-zkvm-elf/docker/zk_calculator/zk_calculator"
+    )]
+    elf: PathBuf,
+
+    /// Generate and verify a receipt instead of only executing the guest
+    #[arg(long)]
+    prove: bool,
+
+    /// Where to save the bincode-encoded receipt; implies --prove
+    #[arg(long)]
+    receipt: Option<PathBuf>,
+}
+
+/// `--output json` report of a local run
+#[derive(Serialize)]
+struct ProveLocalReport {
+    calculation: String,
+    image_id: String,
+    /// Whether the ELF's image ID is the one submissions request
+    image_id_matches: bool,
+    input: String,
+    journal: Option<String>,
+    guest_result: Option<i64>,
+    guest_error: Option<String>,
+    /// `None` where the guest is expected to fail
+    native_result: Option<i64>,
+    receipt: Option<String>,
+    /// Trace records of a guest built with the `trace` feature
+    trace: Vec<TraceRecord>,
+}
+
+/// One record of the guest's binary trace
+#[derive(Serialize)]
+struct TraceRecord {
+    event: &'static str,
+    value: i64,
+}
+
+pub fn run(args: &ProveLocalArgs) -> Result<()> {
+    let calculation = args.calculation.to_calculation()?;
+    let input = calculation.input();
+    let prove = args.prove || args.receipt.is_some();
+
+    let elf = std::fs::read(&args.elf)
+        .with_context(|| format!("Failed to read guest ELF {}; build it with `bonsol build`", args.elf.display()))?;
+    let image_id = hex::encode(
+        compute_image_id(&elf)
+            .map_err(|e| anyhow!("Failed to compute the image ID of {}: {}", args.elf.display(), e))?
+            .as_bytes(),
+    );
+
+    say!("🧮 Calculation: {}", calculation.describe());
+    say!("🖼️ Image ID: {}", image_id);
+    if image_id != CALCULATOR_IMAGE_ID {
+        say!("⚠️ Submissions request image {}, this ELF will not match them", CALCULATOR_IMAGE_ID);
+    }
+    say!("📥 Input: {} ({} bytes)", hex::encode(&input), input.len());
+
+    let mut trace = Vec::new();
+    let env = ExecutorEnv::builder()
+        .write_slice(&input)
+        .stderr(&mut trace)
+        .build()
+        .map_err(|e| anyhow!("Failed to build the executor environment: {}", e))?;
+
+    let mut report = ProveLocalReport {
+        calculation: calculation.describe(),
+        image_id_matches: image_id == CALCULATOR_IMAGE_ID,
+        image_id,
+        input: hex::encode(&input),
+        journal: None,
+        guest_result: None,
+        guest_error: None,
+        native_result: calculation.evaluate(),
+        receipt: None,
+        trace: Vec::new(),
+    };
+
+    // The guest panics on overflow, division by zero and malformed input
+    let journal = if prove {
+        say!("\n🔐 Proving locally, this can take a while...");
+        default_prover().prove(env, &elf).map(|info| info.receipt).and_then(|receipt| {
+            receipt.verify(compute_image_id(&elf)?)?;
+            say!("✅ Receipt verified");
+            if let Some(path) = &args.receipt {
+                let encoded = bincode::serialize(&receipt)?;
+                std::fs::write(path, encoded)?;
+                say!("💾 Receipt saved to {}", path.display());
+                report.receipt = Some(path.display().to_string());
+            }
+            Ok(receipt.journal.bytes)
+        })
+    } else {
+        say!("\n▶️ Executing locally...");
+        default_executor().execute(env, &elf).map(|session| session.journal.bytes)
+    };
+
+    match journal {
+        Ok(journal) => {
+            match GuestJournal::parse(&journal) {
+                Ok(GuestJournal { status, result, input_digest, cycles, semantics }) => say!(
+                    "📜 Journal: status {}, result {}, input digest {}, {}, {}",
+                    status,
+                    result,
+                    input_digest.map_or_else(|| "none (legacy journal)".to_string(), hex::encode),
+                    cycles.map_or_else(|| "unmetered".to_string(), |cycles| format!("{} guest cycles", cycles)),
+                    describe_semantics(semantics)
+                ),
+                Err(_) => say!("📜 Journal: {}", hex::encode(&journal)),
+            }
+            report.journal = Some(hex::encode(&journal));
+            match decode_journal(&journal) {
+                Ok(result) => report.guest_result = Some(result),
+                Err(e) => report.guest_error = Some(format!("undecodable journal: {}", e)),
+            }
+        }
+        Err(e) => report.guest_error = Some(format!("{:#}", e)),
+    }
+
+    report.trace = decode_trace(&trace);
+    for record in &report.trace {
+        say!("🔎 Trace: {} {}", record.event, record.value);
+    }
+
+    match (report.guest_result, report.native_result) {
+        (Some(guest), Some(native)) if guest == native => say!("✅ Guest and native results agree: {}", guest),
+        (None, None) => say!("✅ Guest failed as expected: {}", report.guest_error.as_deref().unwrap_or_default()),
+        (guest, native) => {
+            let guest = match guest {
+                Some(result) => result.to_string(),
+                None => format!("failed ({})", report.guest_error.as_deref().unwrap_or_default()),
+            };
+            let native = native.map_or_else(|| "fails".to_string(), |result| result.to_string());
+            emit(&report)?;
+            bail!("Guest and native results disagree: guest {}, native {}", guest, native);
+        }
+    }
+
+    emit(&report)
+}
+
+/// Which overflow semantics a journal's bitmask reports were applied
+fn describe_semantics(semantics: Option<u8>) -> &'static str {
+    let Some(semantics) = semantics else {
+        return "semantics not recorded";
+    };
+    match (semantics & JOURNAL_SEMANTICS_SATURATED != 0, semantics & JOURNAL_SEMANTICS_WRAPPED != 0) {
+        (false, false) => "checked",
+        (true, false) => "saturated",
+        (false, true) => "wrapped",
+        (true, true) => "saturated and wrapped",
+    }
+}
+
+/// Decodes the guest's trace records; a lean guest writes none
+fn decode_trace(trace: &[u8]) -> Vec<TraceRecord> {
+    trace
+        .chunks_exact(TRACE_RECORD_LEN)
+        .map(|record| {
+            let mut value = [0u8; 8];
+            value.copy_from_slice(&record[1..]);
+            let event = match record[0] {
+                0 => "frame_len",
+                1 => "operation",
+                2 => "status",
+                3 => "result",
+                4 => "cycles",
+                _ => "unknown",
+            };
+            TraceRecord { event, value: i64::from_le_bytes(value) }
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+//! Reconciliation of proven results with a native computation
+//!
+//! Every callback that carries a result is checked against the calculation
+//! computed natively on the host. A mismatch means the guest and the client
+//! disagree on how calculations or results are encoded, so it is reported
+//! on stderr even under `--output json`, and `--reconcile-report` writes all
+//! checks of a run to a JSON file.
+
+use anyhow::{Context, Result};
+use calculator_sdk::Calculation;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::wait::CallbackOutcome;
+
+/// One proven result checked against the native computation
+#[derive(Serialize, Clone)]
+pub struct Reconciliation {
+    pub execution_id: String,
+    pub calculation: String,
+    /// Result computed natively, `None` when the host can't compute it either
+    pub expected: Option<i64>,
+    /// Result the callback committed, `None` when it carried none
+    pub proven: Option<i64>,
+    /// The calculation as the callback described it
+    pub proven_description: Option<String>,
+    pub verdict: Verdict,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Match,
+    Mismatch,
+    /// The callback committed no result to check
+    NoResult,
+}
+
+#[derive(Serialize)]
+struct ReconciliationReport<'a> {
+    checked: usize,
+    matched: usize,
+    mismatched: usize,
+    no_result: usize,
+    reconciliations: &'a [Reconciliation],
+}
+
+/// Checks the result of `outcome` against `calculation` computed natively, flagging a mismatch
+pub fn reconcile(execution_id: &str, calculation: &Calculation, outcome: &CallbackOutcome) -> Reconciliation {
+    let expected = calculation.evaluate();
+    let (proven, proven_description) = match outcome {
+        CallbackOutcome::Completed { description, result } => (Some(*result), Some(description.clone())),
+        CallbackOutcome::GuestError(reason) => (None, Some(format!("failed ({})", reason))),
+        _ => (None, None),
+    };
+    let verdict = match (outcome, proven) {
+        // The guest proving the calculation fails agrees with a host that can't compute it
+        (CallbackOutcome::GuestError(_), _) if expected.is_none() => Verdict::Match,
+        (CallbackOutcome::GuestError(_), _) => Verdict::Mismatch,
+        (_, None) => Verdict::NoResult,
+        (_, Some(proven)) if Some(proven) == expected => Verdict::Match,
+        (_, Some(_)) => Verdict::Mismatch,
+    };
+
+    let reconciliation = Reconciliation {
+        execution_id: execution_id.to_string(),
+        calculation: calculation.describe(),
+        expected,
+        proven,
+        proven_description,
+        verdict,
+    };
+    if verdict == Verdict::Mismatch {
+        warn_mismatch(&reconciliation);
+    }
+    reconciliation
+}
+
+/// Writes `reconciliations` with match counts to `path` as JSON
+pub fn write_report(path: &Path, reconciliations: &[Reconciliation]) -> Result<()> {
+    let count = |verdict| reconciliations.iter().filter(|r| r.verdict == verdict).count();
+    let report = ReconciliationReport {
+        checked: reconciliations.len(),
+        matched: count(Verdict::Match),
+        mismatched: count(Verdict::Mismatch),
+        no_result: count(Verdict::NoResult),
+        reconciliations,
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    say!("📝 Wrote the reconciliation report to {}", path.display());
+    Ok(())
+}
+
+// On stderr, so it shows under `--output json` too
+fn warn_mismatch(reconciliation: &Reconciliation) {
+    let expected = reconciliation
+        .expected
+        .map_or_else(|| "no result (the host can't compute it)".to_string(), |expected| expected.to_string());
+    eprintln!("🚨 RESULT MISMATCH for execution {}", reconciliation.execution_id);
+    eprintln!("   Submitted: {}", reconciliation.calculation);
+    eprintln!("   Expected:  {}", expected);
+    eprintln!(
+        "   Proven:    {} = {}",
+        reconciliation.proven_description.as_deref().unwrap_or("-"),
+        reconciliation.proven.map_or_else(|| "-".to_string(), |proven| proven.to_string())
+    );
+    eprintln!("   The guest and the client disagree on how this calculation or its result is encoded");
+}
@@ -0,0 +1,180 @@
+//! Interactive session submitting one execution per expression typed
+//!
+//! A single log subscription stays open for the whole session, so results
+//! are printed as their callbacks land while the next expression is typed.
+//! `ans` stands for the most recent proven result.
+
+use anyhow::{bail, Context, Result};
+use calculator_sdk::{expression, generate_execution_id, Calculation};
+use clap::Args;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Signer;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::error::ClientError;
+use crate::journal;
+use crate::reconcile;
+use crate::submit::execute_instruction;
+use crate::wait::{self, CallbackOutcome};
+use crate::{json_output, send_instruction, Cli};
+
+const PROMPT: &str = "calc> ";
+
+#[derive(Args)]
+pub struct ReplArgs {
+    /// Expiration in slots from current slot for each submission
+    #[arg(long, default_value = "1000")]
+    expiration_slots: u64,
+
+    /// Websocket URL for callback notifications [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+}
+
+/// A submission whose callback hasn't landed yet
+struct Pending {
+    number: usize,
+    calculation: Calculation,
+    submitted: Instant,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &ReplArgs) -> Result<()> {
+    if json_output() {
+        bail!(ClientError::InvalidArgs("repl is interactive and does not support --output json".to_string()));
+    }
+
+    cli.deployment.validate(client).await?;
+    let ws_url = args.ws_url.clone().unwrap_or_else(|| wait::websocket_url(&client.url()));
+    let pubsub = wait::connect(&ws_url).await?;
+    let mut subscription = wait::subscribe(&pubsub, &cli.deployment.callback_program).await?;
+
+    println!("Type an expression such as 17 * (3 + 4) to prove it; `ans` is the last result.");
+    println!("Commands: pending, help, quit");
+    prompt();
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut pending: HashMap<String, Pending> = HashMap::new();
+    let mut ans: Option<i64> = None;
+    let mut submitted = 0;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line.context("Failed to read from stdin")? else {
+                    break;
+                };
+                match line.trim() {
+                    "" => {}
+                    "quit" | "exit" => break,
+                    "help" => {
+                        println!("<expression>  prove an expression; + - * / and parentheses, `ans` for the last result");
+                        println!("pending       list submissions still waiting for their callback");
+                        println!("quit          leave the session");
+                    }
+                    "pending" => {
+                        if pending.is_empty() {
+                            println!("Nothing pending");
+                        }
+                        let mut waiting: Vec<_> = pending.iter().collect();
+                        waiting.sort_by_key(|(_, entry)| entry.number);
+                        for (execution_id, entry) in waiting {
+                            println!("  #{} [{}] {} ({}s)", entry.number, execution_id,
+                                     entry.calculation.describe(), entry.submitted.elapsed().as_secs());
+                        }
+                    }
+                    input => match submit(client, cli, payer, args, input, ans).await {
+                        Ok((execution_id, calculation)) => {
+                            submitted += 1;
+                            println!("⏳ #{} {} submitted as {}", submitted, calculation.describe(), execution_id);
+                            pending.insert(execution_id, Pending {
+                                number: submitted,
+                                calculation,
+                                submitted: Instant::now(),
+                            });
+                        }
+                        Err(e) => println!("❌ {:#}", e),
+                    },
+                }
+                prompt();
+            }
+            callback = subscription.next_callback() => {
+                let (execution_id, outcome) = callback?;
+                // Callbacks of executions submitted elsewhere are not ours to report
+                let Some(entry) = pending.remove(&execution_id) else {
+                    continue;
+                };
+                journal::update(&execution_id, &outcome);
+                reconcile::reconcile(&execution_id, &entry.calculation, &outcome);
+                let description = entry.calculation.describe();
+                let elapsed = entry.submitted.elapsed().as_secs_f64();
+                println!();
+                match outcome {
+                    CallbackOutcome::Completed { result, .. } => {
+                        ans = Some(result);
+                        println!("✅ #{} {} = {} ({:.1}s)", entry.number, description, result, elapsed);
+                    }
+                    CallbackOutcome::OutOfBounds => {
+                        println!("❌ #{} {}: result outside the accepted bounds", entry.number, description);
+                    }
+                    CallbackOutcome::GuestError(reason) => {
+                        println!("❌ #{} {}: the guest proved it fails: {}", entry.number, description, reason);
+                    }
+                    CallbackOutcome::AwaitingFinalize => {
+                        println!("📥 #{} {}: awaiting finalization", entry.number, description);
+                    }
+                    CallbackOutcome::Failed(reason) => {
+                        println!("❌ #{} {}: callback failed: {}", entry.number, description, reason);
+                    }
+                }
+                prompt();
+            }
+        }
+    }
+
+    if !pending.is_empty() {
+        println!("{} submission(s) still pending; check them with `status`", pending.len());
+    }
+    subscription.close().await;
+    Ok(())
+}
+
+/// Submits `input` with `ans` replaced by the previous result, returning the
+/// execution ID and the calculation as submitted
+async fn submit(
+    client: &RpcClient,
+    cli: &Cli,
+    payer: &dyn Signer,
+    args: &ReplArgs,
+    input: &str,
+    ans: Option<i64>,
+) -> Result<(String, Calculation)> {
+    let input = match (input.contains("ans"), ans) {
+        (false, _) => input.to_string(),
+        (true, Some(ans)) => input.replace("ans", &format!("({})", ans)),
+        (true, None) => bail!("`ans` has no value until a result has been proven"),
+    };
+    let calculation = Calculation::from_tokens(expression::parse(&input)?);
+    calculation.validate()?;
+
+    let execution_id = generate_execution_id();
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let instruction = execute_instruction(
+        &cli.deployment,
+        &payer.pubkey(),
+        &execution_id,
+        &calculation,
+        current_slot + args.expiration_slots,
+        false,
+    )?;
+    let signature = send_instruction(client, cli, payer, &[], instruction).await?;
+    journal::record(client, &execution_id, &payer.pubkey(), &signature, "direct-bonsol", &calculation.describe()).await;
+    Ok((execution_id, calculation))
+}
+
+fn prompt() {
+    print!("{}", PROMPT);
+    let _ = std::io::stdout().flush();
+}
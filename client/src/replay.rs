@@ -0,0 +1,381 @@
+//! Replaying a Bonsol execution locally from what is on-chain
+//!
+//! Bonsol shrinks an execution account to its exit code once the execution
+//! settles, so the request is recovered from the transaction that submitted
+//! it, found through the execution account's history when only the
+//! execution ID is known. Its inputs are reassembled into the bytes the
+//! prover fed the guest, the guest is re-run in the local executor, and the
+//! journal is compared with the one the callback delivered. When they differ
+//! the prover committed something the guest doesn't compute from that input;
+//! when they agree a disputed result comes from the input itself.
+
+use anyhow::{anyhow, bail, Context, Result};
+use bonsol_interface::bonsol_schema::InputType;
+use bonsol_interface::util::execution_address;
+use borsh::BorshDeserialize;
+use calculator_common::{decode_outcome, CalculatorInstruction, GuestJournal};
+use calculator_sdk::{pad_execution_id, records, validate_execution_id, Calculation};
+use clap::Args;
+use risc0_zkvm::{compute_image_id, default_executor, ExecutorEnv};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::hash::hash;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::decode::{execution_request_of, fetch_transaction, FetchedTransaction};
+use crate::error::ClientError;
+use crate::{calculators, emit, Cli};
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Signature of the transaction that submitted the execution, or its execution ID
+    target: String,
+
+    /// Account that requested the execution [default: the calculation record's requester]
+    #[arg(long)]
+    requester: Option<Pubkey>,
+
+    /// Calculator state account of the execution [default: found by searching every record for the execution ID]
+    #[arg(long, conflicts_with = "requester")]
+    state: Option<Pubkey>,
+
+    /// Guest ELF built by `bonsol build`
+    #[arg(
+        long,
+        default_value = "../zk-program/target/riscv-guest/riscv32im-risc0-zkvm-elf/docker/zk_calculator/zk_calculator"
+    )]
+    elf: PathBuf,
+}
+
+/// What an execute instruction asked Bonsol to prove
+struct Request {
+    execution_id: String,
+    image_id: Option<String>,
+    input_digest: Option<Vec<u8>>,
+    callback_program: Option<Pubkey>,
+    inputs: Vec<(InputType, Vec<u8>)>,
+}
+
+/// `--output json` report of a replay
+#[derive(Serialize)]
+struct ReplayReport {
+    execution_id: String,
+    submission: String,
+    execution_account: String,
+    image_id: Option<String>,
+    /// Whether the ELF is the image the execution requested
+    image_id_matches: bool,
+    input: String,
+    calculation: Option<String>,
+    /// Whether the reassembled input hashes to the digest the request commits to; `None` without one
+    input_digest_matches: Option<bool>,
+    replayed_journal: Option<String>,
+    replay_error: Option<String>,
+    /// Signature of the transaction that delivered the callback
+    callback: Option<String>,
+    on_chain_journal: Option<String>,
+    /// Fields the replayed and on-chain journals disagree on; empty when they match
+    differences: Vec<String>,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, args: &ReplayArgs) -> Result<()> {
+    let elf = std::fs::read(&args.elf)
+        .with_context(|| format!("Failed to read guest ELF {}; build it with `bonsol build`", args.elf.display()))?;
+    let elf_image_id = hex::encode(
+        compute_image_id(&elf)
+            .map_err(|e| anyhow!("Failed to compute the image ID of {}: {}", args.elf.display(), e))?
+            .as_bytes(),
+    );
+
+    let (submission, request, execution_account) = match Signature::from_str(&args.target) {
+        Ok(signature) => {
+            let transaction = fetch_transaction(client, &signature).await?;
+            let request = find_request(&transaction, None).ok_or_else(|| {
+                ClientError::InvalidArgs(format!("Transaction {} submits no Bonsol execution", signature))
+            })?;
+            // The requester is one of the transaction's accounts, and so is the execution account derived from it
+            let execution_account = transaction
+                .keys
+                .iter()
+                .map(|key| execution_address(key, request.execution_id.as_bytes()).0)
+                .find(|address| transaction.keys.contains(address))
+                .ok_or_else(|| anyhow!("Transaction {} does not include its execution account", signature))?;
+            (signature, request, execution_account)
+        }
+        Err(_) => {
+            validate_execution_id(&args.target)?;
+            let execution_id = pad_execution_id(&args.target);
+            let requester = match args.requester {
+                Some(requester) => requester,
+                None => find_requester(client, cli, args.state, &execution_id).await?,
+            };
+            let (execution_account, _) = execution_address(&requester, execution_id.as_bytes());
+            let (signature, request) = find_submission(client, &execution_account, &execution_id).await?;
+            (signature, request, execution_account)
+        }
+    };
+
+    say!("🎬 Replaying execution {} submitted in {}", request.execution_id, submission);
+    say!("   Execution account: {}", execution_account);
+    let image_id_matches = request.image_id.as_deref() == Some(elf_image_id.as_str());
+    match &request.image_id {
+        Some(image_id) if !image_id_matches => {
+            say!("⚠️ The execution requested image {}, but the ELF is image {}", image_id, elf_image_id)
+        }
+        Some(image_id) => say!("🖼️ Image ID: {}", image_id),
+        None => say!("⚠️ The execution request names no image"),
+    }
+
+    let input = assemble_input(&request.inputs).await?;
+    let calculation = Calculation::from_input(&input).map(|calculation| calculation.describe());
+    say!("📥 Input: {} ({} bytes)", hex::encode(&input), input.len());
+    if let Some(calculation) = &calculation {
+        say!("🧮 Calculation: {}", calculation);
+    }
+    let input_digest_matches = request.input_digest.as_ref().map(|digest| *digest == hash(&input).to_bytes());
+    if input_digest_matches == Some(false) {
+        say!("⚠️ The input does not hash to the digest the request commits to");
+    }
+
+    let mut report = ReplayReport {
+        execution_id: request.execution_id.clone(),
+        submission: submission.to_string(),
+        execution_account: execution_account.to_string(),
+        image_id: request.image_id.clone(),
+        image_id_matches,
+        input: hex::encode(&input),
+        calculation,
+        input_digest_matches,
+        replayed_journal: None,
+        replay_error: None,
+        callback: None,
+        on_chain_journal: None,
+        differences: Vec::new(),
+    };
+
+    say!("\n▶️ Executing locally...");
+    let env = ExecutorEnv::builder()
+        .write_slice(&input)
+        .build()
+        .map_err(|e| anyhow!("Failed to build the executor environment: {}", e))?;
+    // The guest panics on overflow, division by zero and malformed input, so no prover could have proven it
+    let replayed = match default_executor().execute(env, &elf) {
+        Ok(session) => {
+            say!("📜 Replayed journal: {}", describe_journal(&session.journal.bytes));
+            report.replayed_journal = Some(hex::encode(&session.journal.bytes));
+            Some(session.journal.bytes)
+        }
+        Err(e) => {
+            say!("💥 The guest failed: {:#}", e);
+            report.replay_error = Some(format!("{:#}", e));
+            None
+        }
+    };
+
+    let callback_program = request.callback_program.unwrap_or_else(bonsol_calculator_backend::id);
+    let Some((callback, on_chain)) =
+        find_callback(client, &execution_account, &callback_program, &request.execution_id).await?
+    else {
+        say!("⏳ No callback has delivered a journal for this execution yet");
+        return emit(&report);
+    };
+    say!("📜 On-chain journal:  {} (callback {})", describe_journal(&on_chain), callback);
+    report.callback = Some(callback.to_string());
+    report.on_chain_journal = Some(hex::encode(&on_chain));
+
+    report.differences = match &replayed {
+        Some(replayed) => journal_differences(replayed, &on_chain),
+        None => vec!["the guest fails on this input, yet a journal was delivered".to_string()],
+    };
+    if report.differences.is_empty() {
+        say!("✅ The replayed journal matches the one delivered on-chain");
+        return emit(&report);
+    }
+    for difference in &report.differences {
+        say!("❌ {}", difference);
+    }
+    emit(&report)?;
+    bail!("The replayed journal of execution {} differs from the one delivered on-chain", request.execution_id);
+}
+
+/// The requester of `execution_id`, from its calculation record
+async fn find_requester(client: &RpcClient, cli: &Cli, state: Option<Pubkey>, execution_id: &str) -> Result<Pubkey> {
+    let program_id = bonsol_calculator_backend::id();
+    let calc = match calculators::resolve_state(cli, state)? {
+        Some(state) => {
+            let records = records::fetch_records(client, &program_id, &state).await?;
+            records.into_iter().find(|calc| calc.execution_id == execution_id).ok_or_else(|| {
+                anyhow!("No calculation with execution ID {} in {}; pass --requester", execution_id, state)
+            })?
+        }
+        None => {
+            records::find_record(client, &program_id, execution_id)
+                .await
+                .context("Pass --requester for executions submitted straight to Bonsol")?
+                .1
+        }
+    };
+    if calc.is_local {
+        bail!("Calculation #{} was computed on-chain, so there is no execution to replay", calc.index);
+    }
+    Ok(calc.requester)
+}
+
+/// The transaction that submitted `execution_id`: the oldest in the execution account's history requesting it
+async fn find_submission(
+    client: &RpcClient,
+    execution_account: &Pubkey,
+    execution_id: &str,
+) -> Result<(Signature, Request)> {
+    // Newest first; the submission is among the first transactions touching the account
+    let history = client
+        .get_signatures_for_address(execution_account)
+        .await
+        .with_context(|| format!("Failed to fetch the history of execution account {}", execution_account))?;
+    for status in history.iter().rev().filter(|status| status.err.is_none()) {
+        let signature = Signature::from_str(&status.signature)?;
+        let transaction = fetch_transaction(client, &signature).await?;
+        if let Some(request) = find_request(&transaction, Some(execution_id)) {
+            return Ok((signature, request));
+        }
+    }
+    bail!(
+        "No transaction submitting execution {} touches {}; check the requester, or pass the submission's signature",
+        execution_id,
+        execution_account
+    )
+}
+
+/// The first execution request in `transaction`, for `execution_id` when given
+fn find_request(transaction: &FetchedTransaction, execution_id: Option<&str>) -> Option<Request> {
+    transaction.instructions_of(&bonsol_interface::ID).find_map(|instruction| {
+        let request = execution_request_of(&instruction.data)?;
+        let id = request.execution_id()?;
+        if execution_id.is_some_and(|execution_id| execution_id != id) {
+            return None;
+        }
+        let inputs = request
+            .input()
+            .map(|inputs| {
+                inputs
+                    .iter()
+                    .map(|input| {
+                        (input.input_type(), input.data().map(|data| data.bytes().to_vec()).unwrap_or_default())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Request {
+            execution_id: id.to_string(),
+            image_id: request.image_id().map(str::to_string),
+            input_digest: request.input_digest().map(|digest| digest.bytes().to_vec()),
+            callback_program: request
+                .callback_program_id()
+                .and_then(|program_id| Pubkey::try_from(program_id.bytes()).ok()),
+            inputs,
+        })
+    })
+}
+
+/// The bytes the prover fed the guest: every input's data, in order
+async fn assemble_input(inputs: &[(InputType, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut input = Vec::new();
+    for (input_type, data) in inputs {
+        match *input_type {
+            InputType::PublicData => input.extend_from_slice(data),
+            InputType::PublicUrl => {
+                let url = std::str::from_utf8(data).context("URL input is not UTF-8")?;
+                let fetched = reqwest::get(url)
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .with_context(|| format!("Failed to fetch the input at {}", url))?
+                    .bytes()
+                    .await
+                    .with_context(|| format!("Failed to read the input at {}", url))?;
+                input.extend_from_slice(&fetched);
+            }
+            InputType::Private => bail!(
+                "The execution has a private input at {}, which only the claiming prover can fetch",
+                String::from_utf8_lossy(data)
+            ),
+            other => bail!(
+                "Replaying {} inputs is not supported",
+                other.variant_name().map_or_else(|| format!("Unknown({})", other.0), str::to_string)
+            ),
+        }
+    }
+    Ok(input)
+}
+
+/// The journal the callback delivered for `execution_id`, with the transaction that delivered it
+async fn find_callback(
+    client: &RpcClient,
+    execution_account: &Pubkey,
+    callback_program: &Pubkey,
+    execution_id: &str,
+) -> Result<Option<(Signature, Vec<u8>)>> {
+    let history = client
+        .get_signatures_for_address(execution_account)
+        .await
+        .with_context(|| format!("Failed to fetch the history of execution account {}", execution_account))?;
+    for status in history.iter().filter(|status| status.err.is_none()) {
+        let signature = Signature::from_str(&status.signature)?;
+        let transaction = fetch_transaction(client, &signature).await?;
+        let journal = transaction.instructions_of(callback_program).find_map(|instruction| {
+            match CalculatorInstruction::try_from_slice(&instruction.data) {
+                Ok(CalculatorInstruction::Callback { execution_id: id, journal }) if id == execution_id => {
+                    Some(journal)
+                }
+                _ => None,
+            }
+        });
+        if let Some(journal) = journal {
+            return Ok(Some((signature, journal)));
+        }
+    }
+    Ok(None)
+}
+
+fn describe_journal(journal: &[u8]) -> String {
+    match decode_outcome(journal) {
+        Ok(Ok(result)) => format!("{} (result {})", hex::encode(journal), result),
+        Ok(Err(reason)) => format!("{} ({:?})", hex::encode(journal), reason),
+        Err(_) => format!("{} (undecodable)", hex::encode(journal)),
+    }
+}
+
+/// How the on-chain journal differs from the replayed one, field by field where both decode
+fn journal_differences(replayed: &[u8], on_chain: &[u8]) -> Vec<String> {
+    if replayed == on_chain {
+        return Vec::new();
+    }
+    let (Ok(replayed), Ok(on_chain)) = (GuestJournal::parse(replayed), GuestJournal::parse(on_chain)) else {
+        return vec![format!(
+            "journals differ: replayed {}, on-chain {}",
+            hex::encode(replayed),
+            hex::encode(on_chain)
+        )];
+    };
+    let mut differences = Vec::new();
+    let mut compare = |field: &str, replayed: String, on_chain: String| {
+        if replayed != on_chain {
+            differences.push(format!("{} differs: replayed {}, on-chain {}", field, replayed, on_chain));
+        }
+    };
+    compare("status", replayed.status.to_string(), on_chain.status.to_string());
+    compare("result", replayed.result.to_string(), on_chain.result.to_string());
+    compare(
+        "input digest",
+        format!("{:?}", replayed.input_digest.map(hex::encode)),
+        format!("{:?}", on_chain.input_digest.map(hex::encode)),
+    );
+    compare("cycles", format!("{:?}", replayed.cycles), format!("{:?}", on_chain.cycles));
+    compare("semantics", format!("{:?}", replayed.semantics), format!("{:?}", on_chain.semantics));
+    // Same fields, different encoding, e.g. a legacy journal
+    if differences.is_empty() {
+        differences.push("journals encode the same fields differently".to_string());
+    }
+    differences
+}
@@ -0,0 +1,141 @@
+//! Sending transactions through blockhash expiry and transient RPC errors
+//!
+//! Each attempt signs with a fresh blockhash and polls the signature until it
+//! lands or the blockhash's last valid block height passes. Only then is the
+//! transaction re-signed and resent, so an attempt that did land is never
+//! submitted twice. A transaction that landed but failed is not retried.
+
+use anyhow::{bail, Context, Result};
+use solana_client::client_error::ClientError as RpcError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::TransactionError,
+};
+use std::time::{Duration, Instant};
+
+use crate::error::ClientError;
+use crate::lookup;
+
+/// Attempts before giving up on a transaction that never lands
+const MAX_SEND_ATTEMPTS: u32 = 5;
+
+/// Backoff before the second attempt, doubled for each one after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Longest an attempt is polled, in case block heights stop advancing
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How one send attempt ended
+enum Attempt {
+    Landed(Signature),
+    /// Never landed; safe to re-sign and resend
+    NotLanded(String),
+}
+
+/// Signs `instructions` and sends them until they land, retrying with a
+/// fresh blockhash whenever an attempt expires or hits a transient error
+pub async fn send_and_confirm(
+    client: &RpcClient,
+    payer: &Pubkey,
+    signers: &[&dyn Signer],
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> Result<Signature> {
+    let mut backoff = INITIAL_BACKOFF;
+    for attempt in 1..=MAX_SEND_ATTEMPTS {
+        match send_once(client, payer, signers, instructions, tables).await? {
+            Attempt::Landed(signature) => return Ok(signature),
+            Attempt::NotLanded(reason) if attempt < MAX_SEND_ATTEMPTS => {
+                say!("🔁 Attempt {}/{} did not land ({}), retrying in {:?}...",
+                         attempt, MAX_SEND_ATTEMPTS, reason, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Attempt::NotLanded(reason) => {
+                bail!(ClientError::Transaction(format!(
+                    "Transaction did not land after {} attempts: {}",
+                    MAX_SEND_ATTEMPTS, reason
+                )));
+            }
+        }
+    }
+    unreachable!("the last attempt either lands or bails")
+}
+
+async fn send_once(
+    client: &RpcClient,
+    payer: &Pubkey,
+    signers: &[&dyn Signer],
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> Result<Attempt> {
+    let (blockhash, last_valid_block_height) = match client
+        .get_latest_blockhash_with_commitment(client.commitment())
+        .await
+    {
+        Ok(latest) => latest,
+        Err(e) => return Ok(Attempt::NotLanded(format!("failed to get latest blockhash: {}", e))),
+    };
+    let transaction = lookup::sign(payer, signers, instructions, tables, blockhash)?;
+    let signature = transaction.signatures[0];
+
+    if let Err(e) = client.send_transaction(&transaction).await {
+        return match classify_send_error(&e) {
+            Some(reason) => Ok(Attempt::NotLanded(reason)),
+            None => Err(ClientError::Transaction(format!("Transaction was rejected: {}", e)).into()),
+        };
+    }
+
+    let started = Instant::now();
+    loop {
+        match client
+            .get_signature_status_with_commitment(&signature, client.commitment())
+            .await
+        {
+            Ok(Some(Ok(()))) => return Ok(Attempt::Landed(signature)),
+            Ok(Some(Err(e))) => bail!(landed_but_failed(&signature, &e)),
+            Ok(None) => {}
+            // Keep polling through transient RPC failures
+            Err(e) => say!("⚠️ Failed to check signature status: {}", e),
+        }
+
+        let expired = client
+            .get_block_height_with_commitment(client.commitment())
+            .await
+            .is_ok_and(|height| height > last_valid_block_height);
+        if expired || started.elapsed() > CONFIRMATION_TIMEOUT {
+            // It may have landed between the last poll and the expiry
+            let status = client
+                .get_signature_status_with_commitment(&signature, client.commitment())
+                .await
+                .context("Failed to check signature status")?;
+            return match status {
+                Some(Ok(())) => Ok(Attempt::Landed(signature)),
+                Some(Err(e)) => bail!(landed_but_failed(&signature, &e)),
+                None if expired => Ok(Attempt::NotLanded("blockhash expired".to_string())),
+                None => Ok(Attempt::NotLanded(format!("not confirmed within {:?}", CONFIRMATION_TIMEOUT))),
+            };
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn landed_but_failed(signature: &Signature, error: &TransactionError) -> ClientError {
+    ClientError::Transaction(format!("Transaction {} landed but failed: {}", signature, error))
+}
+
+/// Reason to retry a failed send, or `None` when the transaction itself was rejected
+fn classify_send_error(error: &RpcError) -> Option<String> {
+    match error.get_transaction_error() {
+        Some(TransactionError::BlockhashNotFound) => Some("blockhash not found".to_string()),
+        Some(_) => None,
+        // Transport errors and timeouts never reached the runtime
+        None => Some(error.to_string()),
+    }
+}
@@ -0,0 +1,189 @@
+//! RPC transport spread over several endpoints
+//!
+//! `--rpc-url` may be given more than once. Endpoints are health-checked at
+//! startup and healthy ones are tried first; a request that fails in
+//! transport, or that an unhealthy node refuses, moves on to the next
+//! endpoint and stays there. Errors the node answers with, such as a failed
+//! preflight, are returned as is. With `--broadcast-all` transactions are
+//! sent to every endpoint and a signature counts as confirmed as soon as any
+//! endpoint reports it.
+
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde_json::Value;
+use solana_client::client_error::{ClientError as RpcClientError, ClientErrorKind, Result as RpcResult};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::Cli;
+
+/// Timeout of each request to an endpoint, matching `RpcClient::new`
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of the startup health check, short so a dead endpoint doesn't stall the run
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The client for `--rpc-url`, failing over between the endpoints when there are several
+pub async fn connect(cli: &Cli) -> RpcClient {
+    if let [url] = cli.rpc_urls.as_slice() {
+        if !cli.broadcast_all {
+            return RpcClient::new(url.clone());
+        }
+    }
+
+    let mut healthy = Vec::new();
+    let mut unhealthy = Vec::new();
+    let checks = cli.rpc_urls.iter().map(|url| async move {
+        let sender = HttpSender::new_with_timeout(url, HEALTH_CHECK_TIMEOUT);
+        (url, sender.send(RpcRequest::GetHealth, Value::Null).await)
+    });
+    for (url, health) in join_all(checks).await {
+        match health {
+            Ok(_) => healthy.push(url),
+            Err(e) => {
+                say!("⚠️ RPC endpoint {} failed its health check: {}", url, e);
+                unhealthy.push(url);
+            }
+        }
+    }
+    if healthy.is_empty() {
+        say!("⚠️ No RPC endpoint is healthy; trying them in the order given");
+    }
+
+    let endpoints = healthy
+        .into_iter()
+        .chain(unhealthy)
+        .map(|url| HttpSender::new_with_timeout(url, REQUEST_TIMEOUT))
+        .collect();
+    RpcClient::new_sender(
+        FailoverSender { endpoints, current: AtomicUsize::new(0), broadcast_all: cli.broadcast_all },
+        RpcClientConfig::default(),
+    )
+}
+
+/// `RpcSender` trying its endpoints in turn
+struct FailoverSender {
+    endpoints: Vec<HttpSender>,
+    /// Endpoint requests go to first; moved past endpoints that fail
+    current: AtomicUsize,
+    broadcast_all: bool,
+}
+
+#[async_trait]
+impl RpcSender for FailoverSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> RpcResult<Value> {
+        if self.broadcast_all {
+            match request {
+                RpcRequest::SendTransaction => return self.send_to_all(request, params).await,
+                RpcRequest::GetSignatureStatuses => return self.signature_statuses_from_all(params).await,
+                _ => {}
+            }
+        }
+
+        let first = self.current.load(Ordering::Relaxed);
+        let mut last_error = None;
+        for offset in 0..self.endpoints.len() {
+            let index = (first + offset) % self.endpoints.len();
+            let endpoint = &self.endpoints[index];
+            match endpoint.send(request, params.clone()).await {
+                Ok(response) => {
+                    if index != first {
+                        self.current.store(index, Ordering::Relaxed);
+                        say!("🔀 Failed over to RPC endpoint {}", endpoint.url());
+                    }
+                    return Ok(response);
+                }
+                Err(e) if should_fail_over(&e) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("at least one RPC endpoint"))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        let mut total = RpcTransportStats::default();
+        for stats in self.endpoints.iter().map(RpcSender::get_transport_stats) {
+            total.request_count += stats.request_count;
+            total.elapsed_time += stats.elapsed_time;
+            total.rate_limited_time += stats.rate_limited_time;
+        }
+        total
+    }
+
+    fn url(&self) -> String {
+        self.endpoints[self.current.load(Ordering::Relaxed)].url()
+    }
+}
+
+impl FailoverSender {
+    /// Sends to every endpoint at once; succeeds if any of them accepts
+    async fn send_to_all(&self, request: RpcRequest, params: Value) -> RpcResult<Value> {
+        let responses = join_all(self.endpoints.iter().map(|endpoint| endpoint.send(request, params.clone()))).await;
+        let mut errors = Vec::new();
+        for response in responses {
+            match response {
+                Ok(response) => return Ok(response),
+                Err(e) => errors.push(e),
+            }
+        }
+        // Prefer what a node answered, e.g. a failed preflight, over a transport failure
+        let position = errors.iter().position(|e| !should_fail_over(e)).unwrap_or(0);
+        Err(errors.swap_remove(position))
+    }
+
+    /// Asks every endpoint for the statuses and keeps, per signature, the first one any endpoint knows
+    async fn signature_statuses_from_all(&self, params: Value) -> RpcResult<Value> {
+        let responses = join_all(
+            self.endpoints
+                .iter()
+                .map(|endpoint| endpoint.send(RpcRequest::GetSignatureStatuses, params.clone())),
+        )
+        .await;
+        let mut merged: Option<Value> = None;
+        let mut first_error = None;
+        for response in responses {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            let Some(merged) = &mut merged else {
+                merged = Some(response);
+                continue;
+            };
+            if let (Some(Value::Array(known)), Some(Value::Array(statuses))) =
+                (merged.get_mut("value"), response.get("value"))
+            {
+                for (known, status) in known.iter_mut().zip(statuses) {
+                    if known.is_null() {
+                        *known = status.clone();
+                    }
+                }
+            }
+        }
+        match (merged, first_error) {
+            (Some(merged), _) => Ok(merged),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(RpcClientError::from(ClientErrorKind::Custom("No RPC endpoints".to_string()))),
+        }
+    }
+}
+
+/// Whether another endpoint might serve a request that failed with `error`
+fn should_fail_over(error: &RpcClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,154 @@
+//! The payer's signer: a keypair file, a hardware wallet or a remote service
+//!
+//! `--keypair` takes a keypair file path like the Solana CLI, or a
+//! `usb://ledger` locator with an optional `?key=<account>/<change>`
+//! derivation path. `--signer-url` hands signing to an external service
+//! instead, so the secret key never reaches this process.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_remote_wallet::locator::Locator;
+use solana_remote_wallet::remote_keypair::generate_remote_keypair;
+use solana_remote_wallet::remote_wallet::maybe_wallet_manager;
+use solana_sdk::{
+    derivation_path::DerivationPath,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signature, Signer, SignerError},
+};
+use std::path::PathBuf;
+use std::str::FromStr;
+use uriparse::URIReference;
+
+use crate::Cli;
+
+const USB_SCHEME: &str = "usb://";
+
+/// Signer backed by an external signing service
+///
+/// The service answers `GET <url>/pubkey` with `{"pubkey": "<base58>"}` and
+/// `POST <url>/sign` with `{"pubkey", "message": "<hex>"}` with
+/// `{"signature": "<base58>"}`.
+pub struct RemoteSigner {
+    url: String,
+    pubkey: Pubkey,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct PubkeyResponse {
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    pubkey: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+/// Loads the payer's signer from `--signer-url`, `--keypair` or the Solana CLI default keypair
+pub async fn load(cli: &Cli) -> Result<Box<dyn Signer>> {
+    if let Some(url) = &cli.signer_url {
+        return Ok(Box::new(RemoteSigner::connect(url).await?));
+    }
+
+    let path = match &cli.keypair {
+        Some(path) => path.clone(),
+        None => default_keypair_path()?.display().to_string(),
+    };
+    if path.starts_with(USB_SCHEME) {
+        return hardware_wallet(&path);
+    }
+    let keypair = read_keypair_file(&path)
+        .map_err(|e| anyhow!("Failed to read keypair from {}: {}", path, e))?;
+    Ok(Box::new(keypair))
+}
+
+/// Opens the Ledger at `locator`, e.g. `usb://ledger?key=0/0`
+fn hardware_wallet(locator: &str) -> Result<Box<dyn Signer>> {
+    let uri = URIReference::try_from(locator).with_context(|| format!("Invalid wallet locator {}", locator))?;
+    let derivation_path = DerivationPath::from_uri_key_query(&uri)
+        .with_context(|| format!("Invalid derivation path in {}", locator))?
+        .unwrap_or_default();
+    let locator = Locator::new_from_uri(&uri).map_err(|e| anyhow!("Invalid wallet locator {}: {}", locator, e))?;
+
+    let Some(wallet_manager) = maybe_wallet_manager().map_err(|e| anyhow!("Failed to open USB devices: {}", e))?
+    else {
+        bail!("No hardware wallet found; connect and unlock it, then open the Solana app");
+    };
+    let keypair = generate_remote_keypair(locator, derivation_path, &wallet_manager, false, "payer")
+        .map_err(|e| anyhow!("Failed to connect to the hardware wallet: {}", e))?;
+    say!("🔐 Approve each transaction on the hardware wallet");
+    Ok(Box::new(keypair))
+}
+
+/// The Solana CLI's default keypair location, `~/.config/solana/id.json`
+fn default_keypair_path() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .context("HOME is not set; pass --keypair explicitly")?;
+    Ok(PathBuf::from(home).join(".config").join("solana").join("id.json"))
+}
+
+impl RemoteSigner {
+    /// Asks the service at `url` which key it signs with
+    pub async fn connect(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let http = reqwest::Client::new();
+        let response: PubkeyResponse = http
+            .get(format!("{}/pubkey", url))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Failed to reach the signing service at {}", url))?
+            .json()
+            .await
+            .context("The signing service returned an invalid public key response")?;
+        let pubkey = Pubkey::from_str(&response.pubkey)
+            .with_context(|| format!("The signing service returned an invalid public key {:?}", response.pubkey))?;
+        say!("🔐 Signing with {} through {}", pubkey, url);
+        Ok(RemoteSigner { url, pubkey, http })
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Signature> {
+        let response: SignResponse = self
+            .http
+            .post(format!("{}/sign", self.url))
+            .json(&SignRequest {
+                pubkey: self.pubkey.to_string(),
+                message: hex::encode(message),
+            })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("The signing service refused the request")?
+            .json()
+            .await
+            .context("The signing service returned an invalid signature response")?;
+        let signature = Signature::from_str(&response.signature)
+            .with_context(|| format!("The signing service returned an invalid signature {:?}", response.signature))?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            bail!("The signing service returned a signature that does not verify for {}", self.pubkey);
+        }
+        Ok(signature)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        // Signers are synchronous; run the request on the runtime without stalling its other tasks
+        tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(self.sign(message)))
+            .map_err(|e| SignerError::Custom(format!("{:#}", e)))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,102 @@
+//! `--simulate`: dry-running transactions before they are sent
+//!
+//! The signed transaction is simulated against the cluster; its program logs
+//! are printed nested by invocation depth along with the compute units used.
+//! A failed simulation aborts the send, a successful one asks for
+//! confirmation unless `--yes` was given.
+
+use anyhow::{bail, Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::transaction::VersionedTransaction;
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::error::ClientError;
+use crate::json_output;
+
+/// Simulates `transaction` and returns once it is cleared to be sent
+pub async fn preflight(client: &RpcClient, transaction: &VersionedTransaction, assume_yes: bool) -> Result<()> {
+    say!("🧪 Simulating transaction...");
+    let simulation = client
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: true,
+                commitment: Some(client.commitment()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .context("Failed to simulate transaction")?
+        .value;
+
+    let logs = simulation.logs.unwrap_or_default();
+    if !json_output() {
+        print_logs(&logs);
+    }
+    if let Some(units) = simulation.units_consumed {
+        say!("⛽ Compute units consumed: {}", units);
+    }
+
+    if let Some(err) = simulation.err {
+        // Under `--output json` the logs are the only way to see what failed
+        if json_output() {
+            bail!(ClientError::Simulation(format!("Simulation failed: {}\n{}", err, logs.join("\n"))));
+        }
+        bail!(ClientError::Simulation(format!("Simulation failed: {}; transaction not sent", err)));
+    }
+    say!("✅ Simulation succeeded");
+
+    if assume_yes {
+        return Ok(());
+    }
+    if json_output() || !std::io::stdin().is_terminal() {
+        bail!(ClientError::InvalidArgs(
+            "Simulation succeeded; pass --yes to send the transaction non-interactively".to_string()
+        ));
+    }
+    print!("Send the transaction? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => bail!("Aborted after simulation"),
+    }
+}
+
+/// Prints runtime log lines indented by the invocation depth they belong to
+fn print_logs(logs: &[String]) {
+    if logs.is_empty() {
+        return;
+    }
+
+    println!("📜 Program logs:");
+    let mut depth: usize = 0;
+    for log in logs {
+        let indent = "   ".repeat(depth + 1);
+        if let Some(message) = log.strip_prefix("Program log: ") {
+            println!("{}{}", indent, message);
+        } else if let Some(data) = log.strip_prefix("Program data: ") {
+            println!("{}📦 data: {}", indent, data);
+        } else if let Some(rest) = log.strip_prefix("Program return: ") {
+            println!("{}↩️ return: {}", indent, rest);
+        } else if let Some((program, level)) = parse_invoke(log) {
+            depth = level;
+            println!("{}▶ {}", "   ".repeat(depth), program);
+        } else if log.ends_with(" success") || log.contains(" failed: ") {
+            let status = log.strip_prefix("Program ").unwrap_or(log);
+            println!("{}◀ {}", "   ".repeat(depth.max(1)), status);
+            depth = depth.saturating_sub(1);
+        } else {
+            println!("{}{}", indent, log.strip_prefix("Program ").unwrap_or(log));
+        }
+    }
+}
+
+/// Splits `Program <id> invoke [<depth>]` into the program ID and depth
+fn parse_invoke(log: &str) -> Option<(&str, usize)> {
+    let rest = log.strip_prefix("Program ")?;
+    let (program, level) = rest.split_once(" invoke [")?;
+    Some((program, level.strip_suffix(']')?.parse().ok()?))
+}
@@ -0,0 +1,594 @@
+use anyhow::{bail, Context, Result};
+use calculator_common::calculation_address;
+use calculator_common::constants::CALLBACK_INSTRUCTION_PREFIX;
+use calculator_ops::Semantics;
+use calculator_sdk::{
+    expression, generate_execution_id, instruction, pad_execution_id, parse_operation, records, validate_execution_id,
+    Calculation, OPERATION_NAMES,
+};
+use bonsol_interface::instructions::{execute_v1, CallbackConfig, ExecutionConfig};
+use bonsol_interface::util::execution_address;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::instruction::AccountMeta;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::calculators;
+use crate::decode;
+use crate::deployment::DeploymentArgs;
+use crate::error::ClientError;
+use crate::inputs::{self, Input, InputArgs, InputReport};
+use crate::journal;
+use crate::offline::{self, ExportArgs, ExportReport};
+use crate::reconcile::{self, Reconciliation, Verdict};
+use crate::wait::{self, CallbackOutcome};
+use crate::{emit, send_instruction, Cli};
+
+// Calculator ZK program constants (from zk-program folder)
+pub const CALCULATOR_IMAGE_ID: &str = "5881e972d41fe651c2989c65699528da8b1ed68ab7057350a686b8a64a00fc91";
+pub const CALLBACK_PROGRAM_ID: &str = "2zBRw2sEXvjskx7w1w9hqdFEMZWy7KipQ6jKPfwjpnL6";
+
+// Extra accounts from the execution request
+const EA1: &str = "3b6DR2gbTJwrrX27VLEZ2FJcHrDvTSLKEcTLVhdxCoaf";
+const EA2: &str = "g7dD1FHSemkUQrX1Eak37wzvDjscgBW2pFCENwjLdMX";
+const EA3: &str = "FHab8zDcP1DooZqXHWQowikqtXJb1eNHc46FEh1KejmX";
+
+// Lamports tipped to the prover of every execution
+pub use calculator_sdk::bonsol::EXECUTION_TIP;
+
+// Calculator operations
+pub use calculator_ops::{OP_ADD, OP_DIVIDE, OP_MULTIPLY, OP_SUBTRACT};
+
+/// The calculation to run, as an expression or a single operation
+#[derive(Args)]
+pub struct CalculationArgs {
+    /// Arithmetic expression such as "17 * (3 + 4)"; overrides --operation/--operand-a/--operand-b
+    expression: Option<String>,
+
+    /// Read the expression as space-separated RPN tokens, such as "3 dup * 4 swap -"
+    #[arg(long, requires = "expression")]
+    rpn: bool,
+
+    /// Calculator operation (add, subtract, multiply, divide, modulo, power, abs, min, max, gcd, isqrt, log2,
+    /// bps_floor, bps_ceil, bps_round)
+    #[arg(long, default_value = "add")]
+    operation: String,
+
+    /// First operand
+    #[arg(long, default_value = "2")]
+    operand_a: i64,
+
+    /// Second operand
+    #[arg(long, default_value = "12")]
+    operand_b: i64,
+
+    /// What the operation does with a result past the 64-bit range
+    #[arg(long, value_enum, default_value = "checked", conflicts_with = "expression")]
+    semantics: OverflowSemantics,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OverflowSemantics {
+    /// Fail the calculation with an overflow
+    Checked,
+    /// Clamp the result to the nearest bound
+    Saturating,
+    /// Keep the low 64 bits of the result
+    Wrapping,
+}
+
+impl From<OverflowSemantics> for Semantics {
+    fn from(semantics: OverflowSemantics) -> Self {
+        match semantics {
+            OverflowSemantics::Checked => Semantics::Checked,
+            OverflowSemantics::Saturating => Semantics::Saturating,
+            OverflowSemantics::Wrapping => Semantics::Wrapping,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SubmitArgs {
+    #[command(flatten)]
+    calculation: CalculationArgs,
+
+    #[command(flatten)]
+    inputs: InputArgs,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+
+    /// Execution ID of up to 16 letters, digits, `_` or `-`, padded if shorter [default: generated]
+    #[arg(long)]
+    execution_id: Option<String>,
+
+    /// Expiration in slots from current slot
+    #[arg(long, default_value = "1000")]
+    expiration_slots: u64,
+
+    /// Execution method: "calculator-program" or "direct-bonsol"
+    #[arg(long, default_value = "direct-bonsol")]
+    method: String,
+
+    /// Calculator state account, required by the calculator-program method [default: from the address book]
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Have Bonsol check the SHA-256 digest of the input before proving (direct-bonsol only)
+    #[arg(long)]
+    verify_input_hash: bool,
+
+    /// Wait for the calculator callback and print the proven result
+    #[arg(long)]
+    wait: bool,
+
+    /// Websocket URL used by --wait [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Seconds to wait for the callback before giving up
+    #[arg(long, default_value = "600")]
+    wait_timeout: u64,
+
+    /// Write the check of the proven result against the native computation to this JSON file
+    #[arg(long, requires = "wait")]
+    reconcile_report: Option<PathBuf>,
+}
+
+/// `--output json` report of a submission
+#[derive(Serialize)]
+struct SubmitReport {
+    signature: String,
+    execution_id: String,
+    method: String,
+    image_id: String,
+    /// Operation code, `OP_EXPRESSION` for multi-operation expressions
+    operation: i64,
+    operand_a: Option<i64>,
+    operand_b: Option<i64>,
+    /// Infix form of a multi-operation expression
+    expression: Option<String>,
+    /// Hex SHA-256 digest of the input, set with `--verify-input-hash`
+    input_hash: Option<String>,
+    inputs: Vec<InputReport>,
+    requester: String,
+    execution_account: String,
+    accounts: Vec<AccountReport>,
+    /// Set once `--wait` saw the callback land
+    result: Option<ResultReport>,
+    /// The proven result checked against the native computation, set with `--wait`
+    reconciliation: Option<Reconciliation>,
+    /// Set with `--export-unsigned`, which leaves `signature` empty
+    exported: Option<ExportReport>,
+}
+
+#[derive(Serialize)]
+struct AccountReport {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[derive(Serialize)]
+struct ResultReport {
+    /// `completed`, `out_of_bounds`, `awaiting_finalize` or `failed`
+    outcome: &'static str,
+    description: Option<String>,
+    result: Option<i64>,
+    error: Option<String>,
+    elapsed_secs: f64,
+}
+
+impl From<&AccountMeta> for AccountReport {
+    fn from(meta: &AccountMeta) -> Self {
+        AccountReport {
+            pubkey: meta.pubkey.to_string(),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+impl ResultReport {
+    fn new(outcome: &CallbackOutcome, elapsed: Duration) -> Self {
+        let mut report = ResultReport {
+            outcome: "completed",
+            description: None,
+            result: None,
+            error: None,
+            elapsed_secs: elapsed.as_secs_f64(),
+        };
+        match outcome {
+            CallbackOutcome::Completed { description, result } => {
+                report.description = Some(description.clone());
+                report.result = Some(*result);
+            }
+            CallbackOutcome::OutOfBounds => report.outcome = "out_of_bounds",
+            CallbackOutcome::GuestError(reason) => {
+                report.outcome = "guest_error";
+                report.error = Some(reason.clone());
+            }
+            CallbackOutcome::AwaitingFinalize => report.outcome = "awaiting_finalize",
+            CallbackOutcome::Failed(reason) => {
+                report.outcome = "failed";
+                report.error = Some(reason.clone());
+            }
+        }
+        report
+    }
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, payer: &dyn Signer, args: &SubmitArgs) -> Result<()> {
+    say!("📋 Method: {}", args.method);
+
+    let calculation = args.calculation.to_calculation()?;
+    say!("🧮 Calculator operation: {} = ?", calculation.describe());
+    let expected = calculation.validate()?;
+
+    if args.verify_input_hash && args.method != "direct-bonsol" {
+        bail!(ClientError::InvalidArgs(
+            "--verify-input-hash is only supported with the direct-bonsol method; the calculator program builds its own execution config".to_string()
+        ));
+    }
+    if args.inputs.is_remote() && args.method != "direct-bonsol" {
+        bail!(ClientError::InvalidArgs(
+            "URL and private inputs are only supported with the direct-bonsol method; the calculator program sends its inputs inline".to_string()
+        ));
+    }
+    if cli.deployment.is_overridden() && args.method != "direct-bonsol" {
+        bail!(ClientError::InvalidArgs(
+            "--image-id and --callback-program only apply to the direct-bonsol method; the calculator program uses its state's image and calls itself back".to_string()
+        ));
+    }
+    if args.export.is_set() && args.wait {
+        bail!(ClientError::InvalidArgs(
+            "--wait can't be combined with --export-unsigned; wait with `status` once the transaction is broadcast".to_string()
+        ));
+    }
+    if args.method == "direct-bonsol" {
+        cli.deployment.validate(client).await?;
+    }
+    let inputs = inputs::prepare(&args.inputs, &calculation).await?;
+
+    let execution_id = match &args.execution_id {
+        Some(execution_id) => {
+            validate_execution_id(execution_id)?;
+            pad_execution_id(execution_id)
+        }
+        None => {
+            let execution_id = generate_execution_id();
+            say!("🆔 Generated execution ID: {}", execution_id);
+            execution_id
+        }
+    };
+
+    let (instruction, requester) = match args.method.as_str() {
+        "calculator-program" => {
+            let state = calculators::resolve_state(cli, args.state)?;
+            create_execution_via_calculator_program(client, state, &payer.pubkey(), &execution_id, &calculation)
+                .await?
+        }
+        "direct-bonsol" => {
+            create_execution_directly(client, cli, args, payer, &execution_id, &calculation, &inputs).await?
+        }
+        _ => bail!(ClientError::InvalidArgs("Invalid method. Use 'calculator-program' or 'direct-bonsol'".to_string())),
+    };
+    let (execution_account, _) = execution_address(&requester, execution_id.as_bytes());
+    let mut report = SubmitReport {
+        signature: String::new(),
+        execution_id: execution_id.clone(),
+        method: args.method.clone(),
+        image_id: cli.deployment.image_id.clone(),
+        operation: calculation.op_code(),
+        operand_a: None,
+        operand_b: None,
+        expression: None,
+        input_hash: args.verify_input_hash.then(|| hex::encode(calculation.input_hash())),
+        inputs: inputs.iter().map(Input::report).collect(),
+        requester: requester.to_string(),
+        execution_account: execution_account.to_string(),
+        accounts: instruction.accounts.iter().map(AccountReport::from).collect(),
+        result: None,
+        reconciliation: None,
+        exported: None,
+    };
+    match &calculation {
+        Calculation::Single { operand_a, operand_b, .. } => {
+            report.operand_a = Some(*operand_a);
+            report.operand_b = Some(*operand_b);
+        }
+        Calculation::Expression(_) => report.expression = Some(calculation.describe()),
+    }
+
+    if args.export.is_set() {
+        report.exported = Some(offline::export(client, cli, &payer.pubkey(), &[instruction], &args.export).await?);
+        say!("🆔 Execution ID: {}", execution_id);
+        return emit(&report);
+    }
+
+    // Subscribe before sending so a fast prover can't beat us to the callback
+    let pubsub = match args.wait {
+        true => {
+            let ws_url = args.ws_url.clone().unwrap_or_else(|| wait::websocket_url(&client.url()));
+            Some(wait::connect(&ws_url).await?)
+        }
+        false => None,
+    };
+    let subscription = match &pubsub {
+        Some(pubsub) => {
+            Some(wait::subscribe(pubsub, &cli.deployment.callback_program).await?)
+        }
+        None => None,
+    };
+
+    let signature = send_instruction(client, cli, payer, &[], instruction).await?;
+    report.signature = signature.to_string();
+    journal::record(client, &execution_id, &requester, &signature, &args.method, &calculation.describe()).await;
+
+    // Print summary
+    say!("\n📊 Calculator Execution Request Summary:");
+    say!("   Image ID: {}", report.image_id);
+    say!("   Execution ID: {}", execution_id);
+    say!("   Operation: {}", calculation.describe());
+    say!("   Method: {}", args.method);
+
+    let Some(subscription) = subscription else {
+        say!("   Expected result: {} (to be proven by the ZK program)", expected);
+        return emit(&report);
+    };
+
+    say!("\n⏳ Waiting for the callback (up to {}s)...", args.wait_timeout);
+    let (outcome, elapsed) = subscription
+        .wait_for(&execution_id, Duration::from_secs(args.wait_timeout))
+        .await?;
+    report.result = Some(ResultReport::new(&outcome, elapsed));
+    journal::update(&execution_id, &outcome);
+    match &outcome {
+        CallbackOutcome::Completed { description, result } => {
+            say!("✅ Result: {} = {}", description, result);
+        }
+        CallbackOutcome::OutOfBounds => say!("❌ Proven result fell outside the accepted bounds"),
+        CallbackOutcome::GuestError(reason) => say!("❌ The guest proved the calculation fails: {}", reason),
+        CallbackOutcome::AwaitingFinalize => say!("📥 Journal stored, awaiting finalization by an owner"),
+        CallbackOutcome::Failed(reason) => say!("❌ Callback failed: {}", reason),
+    }
+    say!("⏱️ Proving time: {:.1}s", elapsed.as_secs_f64());
+
+    let reconciliation = reconcile::reconcile(&execution_id, &calculation, &outcome);
+    if reconciliation.verdict == Verdict::Match {
+        say!("🤝 Matches the native computation");
+    }
+    if let Some(path) = &args.reconcile_report {
+        reconcile::write_report(path, std::slice::from_ref(&reconciliation))?;
+    }
+    report.reconciliation = Some(reconciliation);
+
+    emit(&report)
+}
+
+impl CalculationArgs {
+    pub fn to_calculation(&self) -> Result<Calculation> {
+        if let Some(expression) = &self.expression {
+            let tokens = if self.rpn { expression::parse_rpn(expression) } else { expression::parse(expression) }
+                .map_err(|e| ClientError::InvalidArgs(format!("{:#}", e)))?;
+            return Ok(Calculation::from_tokens(tokens));
+        }
+
+        let Some(op_code) = parse_operation(&self.operation) else {
+            bail!(ClientError::InvalidArgs(format!("Invalid operation. Use: {}", OPERATION_NAMES)));
+        };
+        let op_code = op_code | Semantics::from(self.semantics).flag();
+        Ok(Calculation::Single { op_code, operand_a: self.operand_a, operand_b: self.operand_b })
+    }
+}
+
+/// Builds `SubmitCalculation` or `SubmitExpression` for `calculation`,
+/// submitted by `payer` for itself
+pub async fn create_execution_via_calculator_program(
+    client: &RpcClient,
+    state: Option<Pubkey>,
+    payer: &Pubkey,
+    execution_id: &str,
+    calculation: &Calculation,
+) -> Result<(Instruction, Pubkey)> {
+    let Some(state) = state else {
+        bail!(ClientError::InvalidArgs("--state is required with the calculator-program method".to_string()));
+    };
+
+    say!("\n🎯 Creating calculator execution request via the calculator program...");
+
+    let program_id = bonsol_calculator_backend::id();
+    let calculator_state = records::fetch_state(client, &state).await?;
+
+    // Submit for ourselves: the payer is also the requester
+    let requester = *payer;
+    say!("🆔 Execution ID: {}", execution_id);
+    say!("📍 Requester: {}", requester);
+
+    let index = calculator_state.calculation_count;
+    let (record, _) = calculation_address(&program_id, &state, index);
+    say!("📝 Calculation record #{}: {}", index, record);
+    if let Some(ref token_fee) = calculator_state.config.token_fee {
+        if token_fee.amount > 0 {
+            say!("🪙 Token fee: {} of mint {}", token_fee.amount, token_fee.mint);
+        }
+    }
+
+    let instruction =
+        instruction::submit(&program_id, &state, &calculator_state, &requester, execution_id, calculation)?;
+    say!("📦 Instruction data length: {} bytes", instruction.data.len());
+
+    Ok((instruction, requester))
+}
+
+async fn create_execution_directly(
+    client: &RpcClient,
+    cli: &Cli,
+    args: &SubmitArgs,
+    payer: &dyn Signer,
+    execution_id: &str,
+    calculation: &Calculation,
+    inputs: &[Input],
+) -> Result<(Instruction, Pubkey)> {
+    say!("\n🎯 Creating calculator execution request directly via Bonsol interface...");
+
+    // For direct execution, we'll use the payer as the requester
+    let requester = payer.pubkey();
+
+    say!("🆔 Execution ID: {}", execution_id);
+    say!("📍 Requester: {}", requester);
+
+    // Get current slot for expiration calculation
+    let current_slot = client.get_slot().await.context("Failed to get current slot")?;
+    let expiration = current_slot + args.expiration_slots;
+    say!("⏰ Expiration slot: {} (current: {})", expiration, current_slot);
+
+    // Create the calculator inputs as the ZK program expects them
+    let combined_input = calculation.input();
+    match calculation {
+        Calculation::Single { op_code, operand_a, operand_b } => {
+            say!("🔢 Calculator inputs (combined into a single frame):");
+            say!("   Operation: {} -> {:?}", op_code, op_code.to_le_bytes());
+            say!("   Operand A: {} -> {:?}", operand_a, operand_a.to_le_bytes());
+            say!("   Operand B: {} -> {:?}", operand_b, operand_b.to_le_bytes());
+            say!("   Combined:  {:?} (length: {})", combined_input, combined_input.len());
+        }
+        Calculation::Expression(tokens) => {
+            say!("🔢 Calculator input (expression frame of {} tokens):", tokens.len());
+            say!("   RPN: {:?}", tokens);
+            say!("   Frame: {} (length: {})", hex::encode(&combined_input), combined_input.len());
+        }
+    }
+
+    if args.verify_input_hash {
+        say!("🔒 Input hash (SHA-256): {}", hex::encode(calculation.input_hash()));
+    }
+
+    let execution_instruction = execute_instruction_with_inputs(
+        &cli.deployment,
+        &requester,
+        execution_id,
+        calculation,
+        inputs,
+        expiration,
+        args.verify_input_hash,
+    )?;
+
+    say!("✅ Created Bonsol calculator execution instruction");
+    say!("📦 Instruction data length: {} bytes", execution_instruction.data.len());
+    say!("👥 Accounts: {} accounts", execution_instruction.accounts.len());
+
+    // Show the request as Bonsol will read it
+    decode::print(&decode::decode_instruction(&execution_instruction.data, None)?);
+
+    // Debug: Print each account
+    say!("\n📋 Accounts in instruction:");
+    for (i, account) in execution_instruction.accounts.iter().enumerate() {
+        say!("   [{}] {} (writable: {}, signer: {})",
+                 i, account.pubkey, account.is_writable, account.is_signer);
+    }
+
+    if let Calculation::Single { op_code, operand_a, operand_b } = *calculation {
+        // Show how the ZK program parses the frame, one field per 8-byte offset
+        // after the version header, if any
+        say!("\n🔄 How the ZK program parses the {}-byte input frame:", combined_input.len());
+        let header = combined_input.len() % 8;
+        if header > 0 {
+            say!("   Byte 0:      {:?} -> schema version", &combined_input[..header]);
+        }
+        for (field, (name, value)) in [("op_code", op_code), ("operand_a", operand_a), ("operand_b", operand_b)]
+            .into_iter()
+            .enumerate()
+        {
+            let start = header + field * 8;
+            say!("   Bytes {:<6} {:?} -> i64::from_le_bytes() = {} ({})",
+                     format!("{}-{}:", start, start + 7), &combined_input[start..start + 8], value, name);
+        }
+    }
+
+    Ok((execution_instruction, requester))
+}
+
+/// Builds the Bonsol execute instruction for `calculation`, requested and paid for by `requester`
+///
+/// With `verify_input_hash`, Bonsol rejects the proof unless the input it
+/// proved hashes to `Calculation::input_hash`.
+pub fn execute_instruction(
+    deployment: &DeploymentArgs,
+    requester: &Pubkey,
+    execution_id: &str,
+    calculation: &Calculation,
+    expiration: u64,
+    verify_input_hash: bool,
+) -> Result<Instruction> {
+    let inputs = [Input::Public(calculation.input())];
+    execute_instruction_with_inputs(
+        deployment,
+        requester,
+        execution_id,
+        calculation,
+        &inputs,
+        expiration,
+        verify_input_hash,
+    )
+}
+
+/// Like `execute_instruction`, with the inputs built by `inputs::prepare`
+pub fn execute_instruction_with_inputs(
+    deployment: &DeploymentArgs,
+    requester: &Pubkey,
+    execution_id: &str,
+    calculation: &Calculation,
+    inputs: &[Input],
+    expiration: u64,
+    verify_input_hash: bool,
+) -> Result<Instruction> {
+    let input_hash = calculation.input_hash();
+
+    let execution_config = ExecutionConfig {
+        verify_input_hash,
+        input_hash: verify_input_hash.then_some(&input_hash[..]),
+        forward_output: true,
+    };
+
+    // Create callback config matching the execution-request.json
+    let callback_config = Some(CallbackConfig {
+        program_id: deployment.callback_program,
+        instruction_prefix: CALLBACK_INSTRUCTION_PREFIX.to_vec(),
+        extra_accounts: callback_extra_accounts()?,
+    });
+
+    // Create the execution instruction
+    execute_v1(
+        requester,
+        requester,
+        &deployment.image_id,
+        execution_id,
+        inputs.iter().map(Input::as_input_ref).collect(),
+        EXECUTION_TIP,
+        expiration,
+        execution_config,
+        callback_config,
+        None, // Use default prover version
+    ).context("Failed to create execution instruction")
+}
+
+/// Extra accounts from execution-request.json, forwarded to the callback
+pub fn callback_extra_accounts() -> Result<Vec<AccountMeta>> {
+    let ea1 = Pubkey::from_str(EA1).context("Failed to parse EA1")?;
+    let ea2 = Pubkey::from_str(EA2).context("Failed to parse EA2")?;
+    let ea3 = Pubkey::from_str(EA3).context("Failed to parse EA3")?;
+    Ok(vec![
+        AccountMeta::new_readonly(ea1, false), // EA1 is readonly
+        AccountMeta::new(ea2, false),          // EA2 is writable
+        AccountMeta::new_readonly(ea3, false), // EA3 is readonly
+    ])
+}
@@ -0,0 +1,171 @@
+//! Checking a RISC Zero receipt independently of Bonsol and the calculator program
+//!
+//! The receipt is verified against the image ID submissions request (or
+//! `--image-id`), so anyone holding it can confirm what the guest proved
+//! without trusting the prover network or the callback. Given an execution
+//! ID, the proven journal is then compared with what the calculation record
+//! stores on-chain: the raw journal, the input digest and the outcome.
+
+use anyhow::{anyhow, bail, Context, Result};
+use calculator_common::{decode_outcome, FailureReason, GuestJournal};
+use calculator_sdk::{pad_execution_id, records, validate_execution_id};
+use clap::Args;
+use risc0_zkvm::{sha::Digest, Receipt};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use std::path::PathBuf;
+
+use crate::calculators;
+use crate::error::ClientError;
+use crate::{emit, Cli};
+
+#[derive(Args)]
+pub struct VerifyReceiptArgs {
+    /// Bincode-encoded receipt, as saved by `prove-local --receipt`
+    receipt: PathBuf,
+
+    /// Execution ID whose on-chain record the journal is cross-checked against
+    #[arg(long)]
+    execution_id: Option<String>,
+
+    /// Calculator state account of the execution [default: found by searching every record for the execution ID]
+    #[arg(long, requires = "execution_id")]
+    state: Option<Pubkey>,
+}
+
+/// `--output json` report of a verification
+#[derive(Serialize)]
+struct VerifyReceiptReport {
+    receipt: String,
+    image_id: String,
+    journal: String,
+    result: Option<i64>,
+    failure: Option<String>,
+    input_digest: Option<String>,
+    cycles: Option<u64>,
+    execution_id: Option<String>,
+    state: Option<String>,
+    on_chain_result: Option<i64>,
+    on_chain_failure: Option<String>,
+    /// Differences between the proven journal and the record; empty when they agree
+    mismatches: Vec<String>,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, args: &VerifyReceiptArgs) -> Result<()> {
+    let image_id = &cli.deployment.image_id;
+    let image_digest: [u8; 32] = hex::decode(image_id).ok().and_then(|id| id.try_into().ok()).ok_or_else(|| {
+        ClientError::InvalidArgs(format!("--image-id {:?} is not an image ID; expected 64 hex characters", image_id))
+    })?;
+
+    let encoded =
+        std::fs::read(&args.receipt).with_context(|| format!("Failed to read receipt {}", args.receipt.display()))?;
+    let receipt: Receipt = bincode::deserialize(&encoded)
+        .with_context(|| format!("{} is not a bincode-encoded receipt", args.receipt.display()))?;
+
+    say!("🖼️ Image ID: {}", image_id);
+    receipt
+        .verify(Digest::from(image_digest))
+        .map_err(|e| anyhow!("Receipt {} does not verify against image {}: {}", args.receipt.display(), image_id, e))?;
+    say!("✅ Receipt verified");
+
+    let journal = receipt.journal.bytes;
+    let parsed = GuestJournal::parse(&journal)
+        .map_err(|e| anyhow!("Journal {} is not a calculator journal: {}", hex::encode(&journal), e))?;
+    let outcome = decode_outcome(&journal)?;
+    match outcome {
+        Ok(result) => say!("📜 Journal proves result {}", result),
+        Err(reason) => say!("📜 Journal proves the calculation fails: {:?}", reason),
+    }
+    if let Some(digest) = parsed.input_digest {
+        say!("   Input digest: {}", hex::encode(digest));
+    }
+
+    let mut report = VerifyReceiptReport {
+        receipt: args.receipt.display().to_string(),
+        image_id: image_id.clone(),
+        journal: hex::encode(&journal),
+        result: outcome.ok(),
+        failure: outcome.err().map(|reason| format!("{:?}", reason)),
+        input_digest: parsed.input_digest.map(hex::encode),
+        cycles: parsed.cycles,
+        execution_id: None,
+        state: None,
+        on_chain_result: None,
+        on_chain_failure: None,
+        mismatches: Vec::new(),
+    };
+
+    let Some(execution_id) = &args.execution_id else {
+        return emit(&report);
+    };
+    let program_id = bonsol_calculator_backend::id();
+    validate_execution_id(execution_id)?;
+    let execution_id = pad_execution_id(execution_id);
+    let (state, calc) = match calculators::resolve_state(cli, args.state)? {
+        Some(state) => {
+            let records = records::fetch_records(client, &program_id, &state).await?;
+            let Some(calc) = records.into_iter().find(|calc| calc.execution_id == execution_id) else {
+                bail!("No calculation with execution ID {} in {}", execution_id, state);
+            };
+            (state, calc)
+        }
+        None => records::find_record(client, &program_id, &execution_id).await?,
+    };
+    if calc.is_local {
+        bail!("Calculation #{} was computed on-chain, so no receipt proves it", calc.index);
+    }
+    say!("\n🔎 Cross-checking calculation #{} ({}) of state {}", calc.index, calc.describe(), state);
+    report.execution_id = Some(execution_id);
+    report.state = Some(state.to_string());
+    report.on_chain_result = calc.result;
+    report.on_chain_failure = calc.failure.map(|reason| format!("{:?}", reason));
+
+    // A manually finalized calculation holds its journal until the owner accepts it
+    let Some(stored) = calc.journal.as_ref().or(calc.pending_journal.as_ref()) else {
+        bail!("Calculation #{} has not received its callback yet", calc.index);
+    };
+    if *stored != journal {
+        report.mismatches.push(format!("the record stores journal {}", hex::encode(stored)));
+    }
+
+    // Records predating the stored digest are checked against their recomputed input
+    let input_hash = calc.input_hash.unwrap_or_else(|| hash(&calc.input()).to_bytes());
+    if parsed.input_digest.is_some_and(|digest| digest != input_hash) {
+        report.mismatches.push(format!("the record's input digest is {}", hex::encode(input_hash)));
+    }
+
+    if calc.journal.is_some() {
+        let agrees = match outcome {
+            // The program rejects results outside the submission's bounds after they are proven
+            Ok(result) => match calc.failure {
+                Some(FailureReason::ResultOutOfBounds) => {
+                    calc.result_bounds.is_some_and(|bounds| !bounds.contains(result))
+                }
+                Some(_) => false,
+                None => calc.result == Some(result),
+            },
+            Err(reason) => calc.failure == Some(reason) && calc.result.is_none(),
+        };
+        if !agrees {
+            report.mismatches.push(match (calc.result, calc.failure) {
+                (_, Some(reason)) => format!("the record reports the calculation failed: {:?}", reason),
+                (Some(result), None) => format!("the record stores result {}", result),
+                (None, None) => "the record stores no result".to_string(),
+            });
+        }
+    } else {
+        say!("⏳ The journal awaits finalization by the state's owner");
+    }
+
+    if report.mismatches.is_empty() {
+        say!("✅ The receipt matches the on-chain record");
+        return emit(&report);
+    }
+    for mismatch in &report.mismatches {
+        say!("❌ The receipt's journal disagrees: {}", mismatch);
+    }
+    emit(&report)?;
+    bail!("Receipt {} does not match the record of calculation #{}", args.receipt.display(), calc.index);
+}
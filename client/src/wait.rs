@@ -0,0 +1,118 @@
+//! Waiting for the calculator program's callback after a submission
+//!
+//! The calculator program logs every callback it receives, so subscribing to
+//! logs mentioning it catches the result as soon as the prover's transaction
+//! lands, without polling.
+
+use anyhow::{bail, Context, Result};
+use futures_util::{future::BoxFuture, stream::BoxStream, StreamExt};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::time::{Duration, Instant};
+
+use crate::error::ClientError;
+
+pub use calculator_sdk::logs::{
+    log_message, parse_cycles, parse_outcome, websocket_url, CallbackOutcome, CALLBACK_RECEIVED,
+};
+
+pub type Unsubscribe = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// Log subscription opened before submitting, so no callback can slip past
+pub struct CallbackSubscription<'a> {
+    logs: BoxStream<'a, Response<RpcLogsResponse>>,
+    unsubscribe: Unsubscribe,
+    started: Instant,
+}
+
+pub async fn connect(ws_url: &str) -> Result<PubsubClient> {
+    PubsubClient::new(ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to websocket {}", ws_url))
+}
+
+/// Subscribes to confirmed transaction logs mentioning `program_id`
+pub async fn subscribe<'a>(pubsub: &'a PubsubClient, program_id: &Pubkey) -> Result<CallbackSubscription<'a>> {
+    let (logs, unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+
+    Ok(CallbackSubscription {
+        logs,
+        unsubscribe,
+        started: Instant::now(),
+    })
+}
+
+impl<'a> CallbackSubscription<'a> {
+    /// Subscription over `logs` from another source, ended by `unsubscribe`
+    #[cfg_attr(not(feature = "geyser"), allow(dead_code))]
+    pub fn new(logs: BoxStream<'a, Response<RpcLogsResponse>>, unsubscribe: Unsubscribe) -> Self {
+        CallbackSubscription { logs, unsubscribe, started: Instant::now() }
+    }
+
+    /// Waits for the callback of `execution_id`, returning how it was handled
+    /// and the time elapsed since subscribing
+    pub async fn wait_for(mut self, execution_id: &str, timeout: Duration) -> Result<(CallbackOutcome, Duration)> {
+        let outcome = tokio::time::timeout(timeout, async {
+            loop {
+                let (callback_id, outcome) = self.next_callback().await?;
+                if callback_id == execution_id {
+                    return Ok::<_, anyhow::Error>(outcome);
+                }
+            }
+        })
+        .await
+        .map_err(|_| {
+            ClientError::CallbackTimeout(format!("No callback for {} within {}s", execution_id, timeout.as_secs()))
+        })??;
+
+        let elapsed = self.started.elapsed();
+        self.close().await;
+        Ok((outcome, elapsed))
+    }
+
+    /// Waits for the next callback of any execution, returning its execution
+    /// ID and how it was handled
+    pub async fn next_callback(&mut self) -> Result<(String, CallbackOutcome)> {
+        let (execution_id, outcome, _) = self.next_metered_callback().await?;
+        Ok((execution_id, outcome))
+    }
+
+    /// Like `next_callback`, also returning the zkVM cycles the guest
+    /// reported when its journal was metered
+    pub async fn next_metered_callback(&mut self) -> Result<(String, CallbackOutcome, Option<u64>)> {
+        loop {
+            let response = self.next_logs().await?;
+            let logs = &response.value;
+            let execution_id = logs
+                .logs
+                .iter()
+                .filter_map(|line| log_message(line))
+                .find_map(|message| message.strip_prefix(CALLBACK_RECEIVED));
+            if let Some(execution_id) = execution_id {
+                return Ok((execution_id.to_string(), parse_outcome(logs), parse_cycles(logs)));
+            }
+        }
+    }
+
+    /// Waits for the logs of the next transaction mentioning the subscribed account
+    pub async fn next_logs(&mut self) -> Result<Response<RpcLogsResponse>> {
+        match self.logs.next().await {
+            Some(response) => Ok(response),
+            None => bail!(ClientError::Rpc("Log subscription closed".to_string())),
+        }
+    }
+
+    pub async fn close(self) {
+        (self.unsubscribe)().await;
+    }
+}
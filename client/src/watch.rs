@@ -0,0 +1,212 @@
+//! Live view of the calculator program's submissions and callbacks
+//!
+//! Events are decoded from the program logs of every confirmed transaction
+//! mentioning the calculator program (or one state account), so executions
+//! submitted by anyone show up, not only those sent from this client. With
+//! `--metrics-listen` they are also exported to Prometheus, with the
+//! wall-clock proving latency of the submissions seen while watching.
+
+use anyhow::Result;
+use calculator_sdk::logs::{decode_events, CallbackOutcome, LogEvent};
+use calculator_sdk::metrics;
+use chrono::Utc;
+use clap::Args;
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use crate::calculators;
+use crate::geyser::{self, GeyserArgs};
+use crate::json_output;
+use crate::wait;
+use crate::Cli;
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Only show activity of this calculator state account
+    #[arg(long)]
+    state: Option<Pubkey>,
+
+    /// Websocket URL to subscribe on [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    #[command(flatten)]
+    geyser: GeyserArgs,
+
+    /// Exit after this many callbacks
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// Serve Prometheus metrics of the activity watched at `http://<addr>/metrics`
+    #[arg(long)]
+    metrics_listen: Option<SocketAddr>,
+}
+
+/// One decoded event; printed as a JSON line under `--output json`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Submitted {
+        execution_id: String,
+        description: String,
+    },
+    ComputedLocally {
+        execution_id: String,
+        description: String,
+    },
+    Retried {
+        execution_id: String,
+        retry_execution_id: String,
+    },
+    Callback {
+        execution_id: String,
+        /// `completed`, `out_of_bounds`, `awaiting_finalize` or `failed`
+        outcome: &'static str,
+        description: Option<String>,
+        result: Option<i64>,
+        error: Option<String>,
+        /// Slots between submission and callback, as logged by the program
+        latency_slots: Option<u64>,
+        /// Seconds since this watch saw the submission, if it did
+        elapsed_secs: Option<f64>,
+    },
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    slot: u64,
+    signature: &'a str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+pub async fn run(client: &RpcClient, cli: &Cli, args: &WatchArgs) -> Result<()> {
+    let account = calculators::resolve_state(cli, args.state)?.unwrap_or_else(bonsol_calculator_backend::id);
+    let ws_url = args.ws_url.clone().unwrap_or_else(|| wait::websocket_url(&client.url()));
+    let pubsub = match args.geyser.is_set() {
+        true => None,
+        false => Some(wait::connect(&ws_url).await?),
+    };
+    let mut subscription = match &pubsub {
+        Some(pubsub) => wait::subscribe(pubsub, &account).await?,
+        None => geyser::subscribe(&args.geyser, &account).await?,
+    };
+    say!("👀 Watching {} on {} (Ctrl-C to stop)", account, args.geyser.url().unwrap_or(&ws_url));
+    if let Some(listen) = args.metrics_listen {
+        metrics::serve(listen)?;
+        say!("📈 Serving metrics on http://{}/metrics", listen);
+    }
+
+    // When each execution was seen being submitted, for wall-clock latency
+    let mut submitted: HashMap<String, Instant> = HashMap::new();
+    let mut callbacks = 0;
+    while args.count.is_none_or(|count| callbacks < count) {
+        let response = subscription.next_logs().await?;
+        let slot = response.context.slot;
+        let events = decode_events(&response.value);
+        events.iter().for_each(metrics::observe);
+        for mut event in events.into_iter().map(Event::from) {
+            match &mut event {
+                Event::Submitted { execution_id, .. } => {
+                    submitted.insert(execution_id.clone(), Instant::now());
+                }
+                Event::Retried { retry_execution_id, .. } => {
+                    submitted.insert(retry_execution_id.clone(), Instant::now());
+                }
+                Event::Callback { execution_id, elapsed_secs, .. } => {
+                    let elapsed = submitted.remove(execution_id).map(|seen| seen.elapsed());
+                    if let Some(elapsed) = elapsed {
+                        metrics::callback_latency(elapsed);
+                    }
+                    *elapsed_secs = elapsed.map(|elapsed| elapsed.as_secs_f64());
+                    callbacks += 1;
+                }
+                Event::ComputedLocally { .. } => {}
+            }
+            print_event(slot, &response.value.signature, &event);
+        }
+    }
+
+    subscription.close().await;
+    Ok(())
+}
+
+impl From<LogEvent> for Event {
+    fn from(event: LogEvent) -> Self {
+        match event {
+            LogEvent::Submitted { execution_id, description } => Event::Submitted { execution_id, description },
+            LogEvent::ComputedLocally { execution_id, description } => {
+                Event::ComputedLocally { execution_id, description }
+            }
+            LogEvent::Retried { execution_id, retry_execution_id } => {
+                Event::Retried { execution_id, retry_execution_id }
+            }
+            LogEvent::Callback { execution_id, outcome, latency_slots, .. } => {
+                let name = outcome.name();
+                let (description, result, error) = match outcome {
+                    CallbackOutcome::Completed { description, result } => (Some(description), Some(result), None),
+                    CallbackOutcome::GuestError(reason) | CallbackOutcome::Failed(reason) => (None, None, Some(reason)),
+                    CallbackOutcome::OutOfBounds | CallbackOutcome::AwaitingFinalize => (None, None, None),
+                };
+                Event::Callback {
+                    execution_id,
+                    outcome: name,
+                    description,
+                    result,
+                    error,
+                    latency_slots,
+                    elapsed_secs: None,
+                }
+            }
+        }
+    }
+}
+
+fn print_event(slot: u64, signature: &str, event: &Event) {
+    if json_output() {
+        let line = EventLine { slot, signature, event };
+        if let Ok(json) = serde_json::to_string(&line) {
+            println!("{}", json);
+        }
+        return;
+    }
+
+    let time = Utc::now().format("%H:%M:%S");
+    match event {
+        Event::Submitted { execution_id, description } => {
+            println!("[{} slot {}] 📤 {} submitted: {} = ?", time, slot, execution_id, description);
+        }
+        Event::ComputedLocally { execution_id, description } => {
+            println!("[{} slot {}] 🖩 {} computed on-chain: {}", time, slot, execution_id, description);
+        }
+        Event::Retried { execution_id, retry_execution_id } => {
+            println!("[{} slot {}] 🔁 {} retried as {}", time, slot, execution_id, retry_execution_id);
+        }
+        Event::Callback { execution_id, outcome, description, result, error, latency_slots, elapsed_secs } => {
+            let mut timing = Vec::new();
+            if let Some(slots) = latency_slots {
+                timing.push(format!("{} slots", slots));
+            }
+            if let Some(elapsed) = elapsed_secs {
+                timing.push(format!("{:.1}s", elapsed));
+            }
+            let timing = match timing.is_empty() {
+                true => String::new(),
+                false => format!(" ({})", timing.join(", ")),
+            };
+            match (description, result, error) {
+                (Some(description), Some(result), _) => {
+                    println!("[{} slot {}] ✅ {} proved: {} = {}{}", time, slot, execution_id, description, result, timing);
+                }
+                (_, _, Some(error)) => {
+                    println!("[{} slot {}] ❌ {} callback failed: {}{}", time, slot, execution_id, error, timing);
+                }
+                _ => println!("[{} slot {}] 📥 {} callback: {}{}", time, slot, execution_id, outcome, timing),
+            }
+        }
+    }
+}
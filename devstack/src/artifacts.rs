@@ -0,0 +1,53 @@
+//! Building what the stack runs
+//!
+//! The calculator program is built for SBF with `cargo build-sbf` and its
+//! `localnet` feature, since no Bonsol program runs to receive its execution
+//! requests, and the mock prover natively, before anything starts, so the
+//! validator never loads a stale program and the prover doesn't compile
+//! while callbacks are awaited. Both builds are incremental, so later runs
+//! only pay for changes.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+pub struct Artifacts {
+    /// The calculator program's shared object, loaded at its declared ID
+    pub program: PathBuf,
+    pub mock_prover: PathBuf,
+}
+
+/// Builds the program and the mock prover in the repository at `root`, or with `skip` only checks they were built
+pub async fn build(root: &Path, skip: bool) -> Result<Artifacts> {
+    let artifacts = Artifacts {
+        program: root.join("solana-program/target/deploy/bonsol_calculator_backend.so"),
+        mock_prover: root.join("mock-prover/target/release/mock-prover"),
+    };
+    if !skip {
+        println!("🔨 Building the calculator program");
+        let program = root.join("solana-program/Cargo.toml");
+        cargo(&["build-sbf", "--features", "localnet", "--manifest-path"], &program).await?;
+        println!("🔨 Building the mock prover");
+        cargo(&["build", "--release", "--manifest-path"], &root.join("mock-prover/Cargo.toml")).await?;
+    }
+    for artifact in [&artifacts.program, &artifacts.mock_prover] {
+        if !artifact.exists() {
+            bail!("{} is missing; run without --skip-build to build it", artifact.display());
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Runs `cargo <args> <manifest>` with its output shown
+async fn cargo(args: &[&str], manifest: &Path) -> Result<()> {
+    let status = Command::new("cargo")
+        .args(args)
+        .arg(manifest)
+        .status()
+        .await
+        .with_context(|| format!("Failed to run cargo {}", args[0]))?;
+    if !status.success() {
+        bail!("cargo {} {} failed with {}", args[0], manifest.display(), status);
+    }
+    Ok(())
+}
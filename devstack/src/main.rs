@@ -0,0 +1,159 @@
+use anyhow::{bail, Context, Result};
+use calculator_sdk::CalculatorClient;
+use clap::Parser;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{read_keypair_file, write_keypair_file, Keypair, Signer};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{ExitCode, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+mod artifacts;
+mod smoke;
+mod validator;
+
+#[derive(Parser)]
+#[command(name = "devstack")]
+#[command(about = "Starts a local validator with the calculator program and a mock prover, and smoke-tests them")]
+struct Cli {
+    /// Directory for the ledger, logs, payer keypair and stack description
+    #[arg(long, default_value = ".devstack")]
+    dir: PathBuf,
+
+    /// Port the validator serves RPC on; its websocket is on the next one
+    #[arg(long, default_value_t = 8899)]
+    rpc_port: u16,
+
+    /// SOL airdropped to the payer
+    #[arg(long, default_value_t = 100)]
+    airdrop: u64,
+
+    /// Use the program and mock prover already built instead of building them
+    #[arg(long)]
+    skip_build: bool,
+
+    /// Tear the stack down after the smoke test instead of keeping it running, e.g. in CI
+    #[arg(long)]
+    smoke_only: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    // The devstack crate sits at the root of the repository
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().context("No repository root")?;
+    let artifacts = artifacts::build(root, cli.skip_build).await?;
+    std::fs::create_dir_all(&cli.dir).with_context(|| format!("Failed to create {}", cli.dir.display()))?;
+    let payer_path = cli.dir.join("payer.json");
+    let payer = payer(&payer_path)?;
+
+    println!("🚀 Starting solana-test-validator on port {}", cli.rpc_port);
+    let mut validator = validator::start(&cli.dir, &artifacts.program, cli.rpc_port).await?;
+    let rpc = Arc::new(RpcClient::new_with_commitment(validator.rpc_url.clone(), CommitmentConfig::confirmed()));
+    airdrop(&rpc, &payer, cli.airdrop).await?;
+
+    let program_id = bonsol_calculator_backend::id();
+    let calculator = CalculatorClient::new(rpc).with_program_id(program_id);
+    let state = Keypair::new();
+    calculator.initialize(&payer, &state).await.context("Failed to initialize a calculator")?;
+    let state = state.pubkey();
+    println!("🧮 Calculator {} initialized", state);
+
+    let mock_log = cli.dir.join("mock-prover.log");
+    let output = File::create(&mock_log).with_context(|| format!("Failed to create {}", mock_log.display()))?;
+    let mut mock_prover = Command::new(&artifacts.mock_prover)
+        .args(["--rpc-url", &validator.rpc_url, "--ws-url", &validator.ws_url])
+        .args(["--program-id", &program_id.to_string(), "--state", &state.to_string()])
+        .arg("--keypair")
+        .arg(&payer_path)
+        .stdout(Stdio::from(output.try_clone()?))
+        .stderr(Stdio::from(output))
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", artifacts.mock_prover.display()))?;
+    println!("🤖 Mock prover answering submissions");
+
+    smoke::run(&calculator, &payer, &state).await?;
+
+    // For scripts and the other tools to find the stack
+    let description = json!({
+        "rpc_url": validator.rpc_url,
+        "ws_url": validator.ws_url,
+        "program_id": program_id.to_string(),
+        "state": state.to_string(),
+        "payer": payer.pubkey().to_string(),
+        "keypair": payer_path,
+    });
+    let description_path = cli.dir.join("devstack.json");
+    std::fs::write(&description_path, serde_json::to_vec_pretty(&description)?)
+        .with_context(|| format!("Failed to write {}", description_path.display()))?;
+
+    if cli.smoke_only {
+        println!("🏁 Smoke test passed; tearing the stack down");
+        return Ok(());
+    }
+    println!();
+    println!("✨ Stack running (Ctrl-C to stop)");
+    println!("   RPC:        {}", validator.rpc_url);
+    println!("   Program:    {}", program_id);
+    println!("   Calculator: {}", state);
+    println!("   Payer:      {} ({})", payer.pubkey(), payer_path.display());
+    println!("   Logs:       {} and {}", validator.log.display(), mock_log.display());
+    println!();
+    println!("   Try: cd client && cargo run -- --rpc-url {} --keypair {} \\", validator.rpc_url, payer_path.display());
+    println!("          submit --method calculator-program --state {} \"6 * 7\" --wait", state);
+    println!();
+
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result.context("Failed to listen for Ctrl-C")?,
+        result = validator.exited() => result?,
+        result = exited(&mut mock_prover, &mock_log) => result?,
+    }
+    println!("👋 Stopping the stack");
+    Ok(())
+}
+
+/// The payer kept in the stack's directory, created on the first run
+fn payer(path: &Path) -> Result<Keypair> {
+    if path.exists() {
+        return read_keypair_file(path).map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", path.display(), e));
+    }
+    let payer = Keypair::new();
+    write_keypair_file(&payer, path).map_err(|e| anyhow::anyhow!("Failed to write keypair {}: {}", path.display(), e))?;
+    Ok(payer)
+}
+
+async fn airdrop(rpc: &RpcClient, payer: &Keypair, sol: u64) -> Result<()> {
+    let signature = rpc
+        .request_airdrop(&payer.pubkey(), sol * LAMPORTS_PER_SOL)
+        .await
+        .context("Failed to request an airdrop")?;
+    for _ in 0..60 {
+        if rpc.confirm_transaction(&signature).await.context("Failed to confirm the airdrop")? {
+            println!("💧 Airdropped {} SOL to {}", sol, payer.pubkey());
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    bail!("Airdrop {} was not confirmed", signature)
+}
+
+/// Resolves when the mock prover exits
+async fn exited(child: &mut Child, log: &Path) -> Result<()> {
+    let status = child.wait().await?;
+    bail!("The mock prover exited with {}; see {}", status, log.display())
+}
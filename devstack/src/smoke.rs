@@ -0,0 +1,30 @@
+//! The smoke submission proving the stack works end to end
+//!
+//! A single addition goes through the calculator program, the mock prover
+//! answers it, and the record must come back complete with the right
+//! result. It fails when any part of the stack is miswired.
+
+use anyhow::{bail, Result};
+use calculator_sdk::{parse_operation, Calculation, CalculationStatus, CalculatorClient, HistoryEntry};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use std::time::{Duration, Instant};
+
+/// How long the mock prover gets to answer
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub async fn run(calculator: &CalculatorClient, payer: &Keypair, state: &Pubkey) -> Result<()> {
+    let op_code = parse_operation("add").expect("add is an operation");
+    let calculation = Calculation::Single { op_code, operand_a: 2, operand_b: 3 };
+    let started = Instant::now();
+    let submission = calculator.submit(payer, state, &calculation, None).await?;
+    println!("🧪 Smoke test: submitted {} as {}", calculation.describe(), submission.execution_id);
+
+    let calc = calculator.wait_for_result(&submission, CALLBACK_TIMEOUT).await?;
+    let status = HistoryEntry::from(&calc).status;
+    if status != CalculationStatus::Complete || calc.result != Some(5) {
+        bail!("Smoke test expected 2 + 3 = 5, got {:?} with result {:?}", status, calc.result);
+    }
+    println!("   ✅ {} = 5, proven in {:.1}s", calculation.describe(), started.elapsed().as_secs_f64());
+    Ok(())
+}
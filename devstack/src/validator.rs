@@ -0,0 +1,103 @@
+//! Running `solana-test-validator` with the calculator program loaded
+//!
+//! The program is loaded at its declared ID from genesis, and the calculator
+//! image's Bonsol deployment account is created next to it, owned by the
+//! Bonsol program, since submissions check it. Nothing runs the Bonsol
+//! program itself: the `localnet` build of the calculator program doesn't
+//! send it execution requests, and the mock prover delivers callbacks
+//! straight to the calculator program. The ledger is reset on every start.
+
+use anyhow::{bail, Context, Result};
+use bonsol_interface::util::deployment_address;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+
+/// How long the validator gets to answer its health check
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Validator {
+    child: Child,
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub log: PathBuf,
+}
+
+/// Starts a validator with its ledger and log in `dir`, serving RPC on `rpc_port` and its websocket on the next port
+pub async fn start(dir: &Path, program: &Path, rpc_port: u16) -> Result<Validator> {
+    let (deployment, _) = deployment_address(bonsol_calculator_backend::CALCULATOR_IMAGE_ID);
+    let deployment_file = dir.join("deployment.json");
+    // Shaped like `solana account --output json`, which --account reads
+    let account = json!({
+        "pubkey": deployment.to_string(),
+        "account": {
+            "lamports": 1_000_000,
+            "data": ["AQ==", "base64"],
+            "owner": bonsol_interface::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 1,
+        },
+    });
+    std::fs::write(&deployment_file, serde_json::to_vec_pretty(&account)?)
+        .with_context(|| format!("Failed to write {}", deployment_file.display()))?;
+
+    let log = dir.join("validator.log");
+    let output = File::create(&log).with_context(|| format!("Failed to create {}", log.display()))?;
+    let child = Command::new("solana-test-validator")
+        .arg("--reset")
+        .arg("--quiet")
+        .arg("--ledger")
+        .arg(dir.join("ledger"))
+        .args(["--rpc-port", &rpc_port.to_string()])
+        .arg("--bpf-program")
+        .arg(bonsol_calculator_backend::id().to_string())
+        .arg(program)
+        .arg("--account")
+        .arg(deployment.to_string())
+        .arg(&deployment_file)
+        .stdout(Stdio::from(output.try_clone()?))
+        .stderr(Stdio::from(output))
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start solana-test-validator; is the Solana CLI installed and on PATH?")?;
+
+    let mut validator = Validator {
+        child,
+        rpc_url: format!("http://127.0.0.1:{}", rpc_port),
+        ws_url: format!("ws://127.0.0.1:{}", rpc_port + 1),
+        log,
+    };
+    validator.wait_until_healthy().await?;
+    Ok(validator)
+}
+
+impl Validator {
+    async fn wait_until_healthy(&mut self) -> Result<()> {
+        let rpc = RpcClient::new(self.rpc_url.clone());
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("solana-test-validator exited with {}; see {}", status, self.log.display());
+            }
+            if rpc.get_health().await.is_ok() {
+                return Ok(());
+            }
+            if Instant::now() > deadline {
+                bail!("Validator not healthy after {}s; see {}", STARTUP_TIMEOUT.as_secs(), self.log.display());
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Resolves when the validator exits
+    pub async fn exited(&mut self) -> Result<()> {
+        let status = self.child.wait().await?;
+        bail!("solana-test-validator exited with {}; see {}", status, self.log.display())
+    }
+}
@@ -0,0 +1,75 @@
+//! The plugin's JSON config file
+//!
+//! The validator reads `libpath` from the same file to find the plugin, so
+//! unknown fields are ignored rather than rejected.
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{GeyserPluginError, Result};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Deserialize)]
+pub struct Config {
+    /// Calculator program to follow [default: the deployed calculator program]
+    #[serde(default)]
+    program_id: Option<String>,
+
+    #[serde(default)]
+    pub sink: SinkConfig,
+
+    /// Events buffered for the sink before new ones are dropped
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// Emit state and record account updates
+    #[serde(default = "enabled")]
+    pub accounts: bool,
+
+    /// Emit the events in the logs of transactions calling the program
+    #[serde(default = "enabled")]
+    pub transactions: bool,
+
+    /// Also emit the accounts loaded from the snapshot while the validator starts
+    #[serde(default)]
+    pub startup: bool,
+}
+
+/// Where events are published, one JSON object each
+#[derive(Deserialize, Default)]
+// Read only by the sinks whose feature is built
+#[cfg_attr(not(all(feature = "kafka", feature = "nats")), allow(dead_code))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    /// One line per event on the validator's stdout
+    #[default]
+    Stdout,
+    /// A Kafka topic, keyed by execution ID or account (`kafka` feature)
+    Kafka { brokers: String, topic: String },
+    /// A NATS subject (`nats` feature)
+    Nats { url: String, subject: String },
+}
+
+fn default_queue_capacity() -> usize {
+    10_000
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| GeyserPluginError::ConfigFileReadError {
+            msg: format!("Invalid calculator plugin config {}: {}", path, e),
+        })
+    }
+
+    pub fn program_id(&self) -> Result<Pubkey> {
+        match &self.program_id {
+            Some(program_id) => program_id.parse().map_err(|_| GeyserPluginError::ConfigFileReadError {
+                msg: format!("Invalid program_id {:?}", program_id),
+            }),
+            None => Ok(bonsol_calculator_backend::id()),
+        }
+    }
+}
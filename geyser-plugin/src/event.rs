@@ -0,0 +1,162 @@
+//! Decoding account updates and transactions into calculator events
+//!
+//! Every account the program owns is offered, but only calculator states
+//! and calculation records become events. A record is recognized by
+//! re-deriving its PDA from the state, index and bump it stores, since the
+//! account data carries no type tag. Transaction events are decoded from the
+//! logs with `calculator-sdk`'s `logs` module, like the indexer's.
+
+use calculator_common::constants::CALCULATION_SEED;
+use calculator_common::{codec, CalculationRecord, CalculationStatus, CalculatorState, HistoryEntry};
+use calculator_sdk::logs::{decode_events, CallbackOutcome, LogEvent};
+use serde::Serialize;
+use solana_client::rpc_response::RpcLogsResponse;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::TransactionStatusMeta;
+
+/// An event as published: the slot it happened in, then the event's fields
+#[derive(Serialize)]
+pub struct Notification {
+    pub slot: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    StateUpdated {
+        pubkey: String,
+        calculation_count: u64,
+        pending_count: u64,
+        paused: bool,
+        image_id: String,
+    },
+    RecordUpdated {
+        pubkey: String,
+        state: String,
+        index: u64,
+        execution_id: String,
+        /// `pending`, `awaiting_finalize`, `complete`, `local` or `failed`
+        status: &'static str,
+        result: Option<i64>,
+        failure: Option<String>,
+        submitted_slot: u64,
+        completed_slot: Option<u64>,
+    },
+    Submitted {
+        signature: String,
+        execution_id: String,
+        description: String,
+    },
+    ComputedLocally {
+        signature: String,
+        execution_id: String,
+        description: String,
+    },
+    Retried {
+        signature: String,
+        execution_id: String,
+        retry_execution_id: String,
+    },
+    Callback {
+        signature: String,
+        execution_id: String,
+        /// `completed`, `out_of_bounds`, `guest_error`, `awaiting_finalize` or `failed`
+        outcome: &'static str,
+        result: Option<i64>,
+        error: Option<String>,
+        latency_slots: Option<u64>,
+        cycles: Option<u64>,
+    },
+}
+
+impl Event {
+    /// What consumers partition on: the execution ID, or the account of a state update
+    pub fn key(&self) -> &str {
+        match self {
+            Event::StateUpdated { pubkey, .. } => pubkey,
+            Event::RecordUpdated { execution_id, .. }
+            | Event::Submitted { execution_id, .. }
+            | Event::ComputedLocally { execution_id, .. }
+            | Event::Retried { execution_id, .. }
+            | Event::Callback { execution_id, .. } => execution_id,
+        }
+    }
+}
+
+/// The event for an update of `pubkey`, an account `program_id` owns, `None`
+/// when it is neither a calculator state nor a calculation record
+pub fn account_event(program_id: &Pubkey, pubkey: &Pubkey, data: &[u8]) -> Option<Event> {
+    if let Ok(calc) = codec::decode::<CalculationRecord>(data) {
+        let seeds: [&[u8]; 4] = [CALCULATION_SEED, calc.state.as_ref(), &calc.index.to_le_bytes(), &[calc.bump]];
+        if Pubkey::create_program_address(&seeds, program_id).ok() == Some(*pubkey) {
+            return Some(record_event(pubkey, &calc));
+        }
+    }
+    let state = codec::decode::<CalculatorState>(data).ok().filter(|state| state.is_initialized)?;
+    Some(Event::StateUpdated {
+        pubkey: pubkey.to_string(),
+        calculation_count: state.calculation_count,
+        pending_count: state.pending_count,
+        paused: state.paused,
+        image_id: state.image_id,
+    })
+}
+
+fn record_event(pubkey: &Pubkey, calc: &CalculationRecord) -> Event {
+    let (status, failure) = match HistoryEntry::from(calc).status {
+        CalculationStatus::Pending => ("pending", None),
+        CalculationStatus::AwaitingFinalize => ("awaiting_finalize", None),
+        CalculationStatus::Complete => ("complete", None),
+        CalculationStatus::Local => ("local", None),
+        CalculationStatus::Failed(reason) => ("failed", Some(format!("{:?}", reason))),
+    };
+    Event::RecordUpdated {
+        pubkey: pubkey.to_string(),
+        state: calc.state.to_string(),
+        index: calc.index,
+        execution_id: calc.execution_id.clone(),
+        status,
+        result: calc.result,
+        failure,
+        submitted_slot: calc.submitted_slot,
+        completed_slot: calc.completed_slot,
+    }
+}
+
+/// The calculator events in the logs of the transaction `signature`
+pub fn transaction_events(signature: &Signature, meta: &TransactionStatusMeta) -> Vec<Event> {
+    let logs = RpcLogsResponse {
+        signature: signature.to_string(),
+        err: meta.status.clone().err(),
+        logs: meta.log_messages.clone().unwrap_or_default(),
+    };
+    decode_events(&logs)
+        .into_iter()
+        .map(|event| {
+            let signature = logs.signature.clone();
+            match event {
+                LogEvent::Submitted { execution_id, description } => {
+                    Event::Submitted { signature, execution_id, description }
+                }
+                LogEvent::ComputedLocally { execution_id, description } => {
+                    Event::ComputedLocally { signature, execution_id, description }
+                }
+                LogEvent::Retried { execution_id, retry_execution_id } => {
+                    Event::Retried { signature, execution_id, retry_execution_id }
+                }
+                LogEvent::Callback { execution_id, outcome, latency_slots, cycles } => {
+                    let name = outcome.name();
+                    let (result, error) = match outcome {
+                        CallbackOutcome::Completed { result, .. } => (Some(result), None),
+                        CallbackOutcome::GuestError(reason) | CallbackOutcome::Failed(reason) => (None, Some(reason)),
+                        CallbackOutcome::OutOfBounds | CallbackOutcome::AwaitingFinalize => (None, None),
+                    };
+                    Event::Callback { signature, execution_id, outcome: name, result, error, latency_slots, cycles }
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! Geyser plugin capturing calculator activity as the validator replays it
+//!
+//! The validator offers the plugin every account update and transaction.
+//! Updates of the calculator program's state accounts and calculation
+//! records, and the events logged by transactions calling the program, are
+//! decoded and published as JSON to the sink the config file names, without
+//! waiting for an RPC node to relay them. Events are emitted at the
+//! processed commitment, so consumers should expect ones from forks that
+//! never confirm.
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, ReplicaTransactionInfoVersions, Result,
+};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+mod config;
+mod event;
+mod sink;
+
+use config::Config;
+use event::Notification;
+use sink::Sink;
+
+#[derive(Debug, Default)]
+pub struct CalculatorPlugin {
+    inner: Option<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    program_id: Pubkey,
+    accounts: bool,
+    transactions: bool,
+    startup: bool,
+    sink: Sink,
+}
+
+impl GeyserPlugin for CalculatorPlugin {
+    fn name(&self) -> &'static str {
+        "calculator-geyser-plugin"
+    }
+
+    fn setup_logger(&self, logger: &'static dyn log::Log, level: log::LevelFilter) -> Result<()> {
+        log::set_max_level(level);
+        log::set_logger(logger).map_err(|e| GeyserPluginError::Custom(Box::new(e)))
+    }
+
+    fn on_load(&mut self, config_file: &str, _is_reload: bool) -> Result<()> {
+        let config = Config::load(config_file)?;
+        let program_id = config.program_id()?;
+        let sink = Sink::start(&config.sink, config.queue_capacity)?;
+        log::info!("Capturing calculator program {} activity", program_id);
+        self.inner = Some(Inner {
+            program_id,
+            accounts: config.accounts,
+            transactions: config.transactions,
+            startup: config.startup,
+            sink,
+        });
+        Ok(())
+    }
+
+    fn on_unload(&mut self) {
+        if let Some(mut inner) = self.inner.take() {
+            inner.sink.stop();
+        }
+    }
+
+    fn update_account(&self, account: ReplicaAccountInfoVersions, slot: Slot, is_startup: bool) -> Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        if is_startup && !inner.startup {
+            return Ok(());
+        }
+        let (pubkey, owner, data) = match account {
+            ReplicaAccountInfoVersions::V0_0_1(info) => (info.pubkey, info.owner, info.data),
+            ReplicaAccountInfoVersions::V0_0_2(info) => (info.pubkey, info.owner, info.data),
+            ReplicaAccountInfoVersions::V0_0_3(info) => (info.pubkey, info.owner, info.data),
+        };
+        if owner != inner.program_id.as_ref() {
+            return Ok(());
+        }
+        let Ok(pubkey) = Pubkey::try_from(pubkey) else {
+            return Ok(());
+        };
+        if let Some(event) = event::account_event(&inner.program_id, &pubkey, data) {
+            inner.sink.send(Notification { slot, event });
+        }
+        Ok(())
+    }
+
+    fn notify_transaction(&self, transaction: ReplicaTransactionInfoVersions, slot: Slot) -> Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        let (signature, is_vote, sanitized, meta) = match transaction {
+            ReplicaTransactionInfoVersions::V0_0_1(info) => {
+                (info.signature, info.is_vote, info.transaction, info.transaction_status_meta)
+            }
+            ReplicaTransactionInfoVersions::V0_0_2(info) => {
+                (info.signature, info.is_vote, info.transaction, info.transaction_status_meta)
+            }
+        };
+        if is_vote || !sanitized.message().account_keys().iter().any(|key| *key == inner.program_id) {
+            return Ok(());
+        }
+        for event in event::transaction_events(signature, meta) {
+            inner.sink.send(Notification { slot, event });
+        }
+        Ok(())
+    }
+
+    fn account_data_notifications_enabled(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.accounts)
+    }
+
+    fn transaction_notifications_enabled(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.transactions)
+    }
+}
+
+/// Entry point the validator loads the plugin through
+///
+/// # Safety
+///
+/// The validator takes ownership of the returned plugin and must be built
+/// with the same Agave version and compiler as this library.
+#[no_mangle]
+#[allow(improper_ctypes_definitions)]
+pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
+    let plugin: Box<dyn GeyserPlugin> = Box::<CalculatorPlugin>::default();
+    Box::into_raw(plugin)
+}
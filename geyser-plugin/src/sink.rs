@@ -0,0 +1,145 @@
+//! Publishing events off the validator's replay threads
+//!
+//! Notifications are queued on a bounded channel and published by a writer
+//! thread, so a slow broker never stalls the validator. When the queue is
+//! full new events are dropped and counted instead. The sink connects when
+//! the plugin loads, so a bad broker address fails the load rather than
+//! every event.
+
+use agave_geyser_plugin_interface::geyser_plugin_interface::{GeyserPluginError, Result};
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use crate::config::SinkConfig;
+use crate::event::Notification;
+
+#[derive(Debug)]
+pub struct Sink {
+    sender: Option<SyncSender<Notification>>,
+    writer: Option<JoinHandle<()>>,
+    dropped: AtomicU64,
+}
+
+impl Sink {
+    pub fn start(config: &SinkConfig, queue_capacity: usize) -> Result<Self> {
+        let mut publisher = Publisher::connect(config).map_err(|msg| GeyserPluginError::ConfigFileReadError { msg })?;
+        let (sender, receiver) = mpsc::sync_channel::<Notification>(queue_capacity);
+        let writer = thread::Builder::new()
+            .name("calculatorSink".to_string())
+            .spawn(move || {
+                for notification in receiver {
+                    let payload = match serde_json::to_vec(&notification) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            log::error!("Failed to encode a calculator event: {}", e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = publisher.publish(notification.event.key(), &payload) {
+                        log::error!("Failed to publish a calculator event: {}", e);
+                    }
+                }
+                publisher.flush();
+            })
+            .map_err(|e| GeyserPluginError::Custom(Box::new(e)))?;
+        Ok(Sink { sender: Some(sender), writer: Some(writer), dropped: AtomicU64::new(0) })
+    }
+
+    /// Queues `notification`, dropping it if the writer has fallen behind
+    pub fn send(&self, notification: Notification) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        match sender.try_send(notification) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped.is_power_of_two() {
+                    log::warn!("Calculator event queue is full; {} event(s) dropped so far", dropped);
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => log::error!("Calculator event writer has stopped"),
+        }
+    }
+
+    /// Publishes what is still queued and stops the writer
+    pub fn stop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+enum Publisher {
+    Stdout(std::io::Stdout),
+    #[cfg(feature = "kafka")]
+    Kafka { producer: rdkafka::producer::BaseProducer, topic: String },
+    #[cfg(feature = "nats")]
+    Nats { connection: nats::Connection, subject: String },
+}
+
+impl Publisher {
+    fn connect(config: &SinkConfig) -> std::result::Result<Self, String> {
+        match config {
+            SinkConfig::Stdout => Ok(Publisher::Stdout(std::io::stdout())),
+            #[cfg(feature = "kafka")]
+            SinkConfig::Kafka { brokers, topic } => {
+                let producer: rdkafka::producer::BaseProducer = rdkafka::ClientConfig::new()
+                    .set("bootstrap.servers", brokers)
+                    .create()
+                    .map_err(|e| format!("Failed to create a Kafka producer for {}: {}", brokers, e))?;
+                Ok(Publisher::Kafka { producer, topic: topic.clone() })
+            }
+            #[cfg(feature = "nats")]
+            SinkConfig::Nats { url, subject } => {
+                let connection =
+                    nats::connect(url).map_err(|e| format!("Failed to connect to NATS at {}: {}", url, e))?;
+                Ok(Publisher::Nats { connection, subject: subject.clone() })
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err("This sink needs the plugin built with its feature: cargo build --release --features kafka|nats"
+                .to_string()),
+        }
+    }
+
+    // Only Kafka keys its messages
+    #[cfg_attr(not(feature = "kafka"), allow(unused_variables))]
+    fn publish(&mut self, key: &str, payload: &[u8]) -> std::result::Result<(), String> {
+        match self {
+            Publisher::Stdout(stdout) => {
+                let mut stdout = stdout.lock();
+                stdout.write_all(payload).and_then(|()| stdout.write_all(b"\n")).map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "kafka")]
+            Publisher::Kafka { producer, topic } => {
+                let record = rdkafka::producer::BaseRecord::to(topic).key(key).payload(payload);
+                let sent = producer.send(record).map_err(|(e, _)| e.to_string());
+                // Serves delivery callbacks, so the producer's queue drains
+                producer.poll(std::time::Duration::ZERO);
+                sent
+            }
+            #[cfg(feature = "nats")]
+            Publisher::Nats { connection, subject } => connection.publish(subject, payload).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn flush(&mut self) {
+        match self {
+            Publisher::Stdout(stdout) => {
+                let _ = stdout.flush();
+            }
+            #[cfg(feature = "kafka")]
+            Publisher::Kafka { producer, .. } => {
+                use rdkafka::producer::Producer;
+                let _ = producer.flush(std::time::Duration::from_secs(5));
+            }
+            #[cfg(feature = "nats")]
+            Publisher::Nats { connection, .. } => {
+                let _ = connection.flush();
+            }
+        }
+    }
+}
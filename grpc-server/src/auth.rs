@@ -0,0 +1,22 @@
+//! API-key authentication
+//!
+//! Every call needs one of the keys the server was started with, sent in
+//! the `x-api-key` metadata or as `authorization: Bearer <key>`.
+
+use std::collections::HashSet;
+use tonic::{Request, Status};
+
+pub fn require_api_key(api_keys: &HashSet<String>, request: Request<()>) -> Result<Request<()>, Status> {
+    let metadata = request.metadata();
+    let key = metadata.get("x-api-key").and_then(|value| value.to_str().ok()).or_else(|| {
+        metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+    });
+    match key {
+        Some(key) if api_keys.contains(key) => Ok(request),
+        Some(_) => Err(Status::unauthenticated("Invalid API key")),
+        None => Err(Status::unauthenticated("Missing API key; send it in x-api-key")),
+    }
+}
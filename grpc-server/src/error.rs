@@ -0,0 +1,18 @@
+//! Errors as gRPC statuses
+//!
+//! Calls fail with `anyhow` chains like the SDK; a `ClientError` anywhere in
+//! the chain picks the status code, the way it picks the REST API's.
+
+use calculator_sdk::ClientError;
+use tonic::{Code, Status};
+
+pub fn status(error: anyhow::Error) -> Status {
+    let code = match error.chain().find_map(|cause| cause.downcast_ref::<ClientError>()) {
+        Some(ClientError::InvalidArgs(_)) => Code::InvalidArgument,
+        Some(ClientError::Simulation(_)) => Code::FailedPrecondition,
+        Some(ClientError::Rpc(_)) | Some(ClientError::Transaction(_)) => Code::Unavailable,
+        Some(ClientError::CallbackTimeout(_)) => Code::DeadlineExceeded,
+        None => Code::Internal,
+    };
+    Status::new(code, format!("{:#}", error))
+}
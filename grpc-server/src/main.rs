@@ -0,0 +1,125 @@
+// Every call fails with tonic's `Status`, which is large but what the generated service returns
+#![allow(clippy::result_large_err)]
+
+use anyhow::{Context, Result};
+use calculator_sdk::logs::websocket_url;
+use calculator_sdk::CalculatorClient;
+use clap::Parser;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tonic::transport::Server;
+
+mod auth;
+mod error;
+mod results;
+mod service;
+
+pub mod proto {
+    tonic::include_proto!("calculator.v1");
+}
+
+use proto::calculator_server::CalculatorServer;
+use results::Notice;
+
+/// Callbacks buffered for each watcher before a slow one is cut off
+const RESULT_BUFFER: usize = 1024;
+
+#[derive(Parser)]
+#[command(name = "grpc-server")]
+#[command(about = "Serves calculator submissions, statuses and results over gRPC")]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+
+    /// RPC URL for the Solana cluster
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// Websocket URL results are followed on [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Calculator program to submit to [default: the deployed calculator program]
+    #[arg(long)]
+    program_id: Option<Pubkey>,
+
+    /// Keypair paying for sponsored submissions [default: ~/.config/solana/id.json]
+    #[arg(long)]
+    keypair: Option<PathBuf>,
+
+    /// Calculator state account sponsored submissions are recorded in
+    #[arg(long)]
+    state: Pubkey,
+
+    /// API key clients must send in `x-api-key` or as a bearer token; repeat it or give a comma-separated list
+    #[arg(long = "api-key", env = "API_KEYS", value_delimiter = ',', required = true)]
+    api_keys: Vec<String>,
+}
+
+/// What every call shares
+pub struct AppState {
+    pub calculator: CalculatorClient,
+    /// Pays for and requests every submission
+    pub payer: Keypair,
+    pub state: Pubkey,
+    /// Held while submitting, so concurrent submissions don't race for the
+    /// state's next record index
+    pub submitting: Mutex<()>,
+    /// The calculator events of every confirmed transaction, for `WatchResults`
+    pub notices: broadcast::Sender<Notice>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let keypair_path = match cli.keypair {
+        Some(path) => path,
+        None => {
+            let home = std::env::var_os("HOME").context("HOME is not set; pass --keypair explicitly")?;
+            PathBuf::from(home).join(".config").join("solana").join("id.json")
+        }
+    };
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", keypair_path.display(), e))?;
+
+    let ws_url = cli.ws_url.unwrap_or_else(|| websocket_url(&cli.rpc_url));
+    let rpc = Arc::new(RpcClient::new_with_commitment(cli.rpc_url, CommitmentConfig::confirmed()));
+    let calculator =
+        CalculatorClient::new(rpc).with_program_id(cli.program_id.unwrap_or_else(bonsol_calculator_backend::id));
+    let (notices, _) = broadcast::channel(RESULT_BUFFER);
+    tokio::spawn(results::follow(ws_url.clone(), *calculator.program_id(), notices.clone()));
+
+    let state = Arc::new(AppState { calculator, payer, state: cli.state, submitting: Mutex::new(()), notices });
+    let api_keys: HashSet<String> = cli.api_keys.into_iter().filter(|key| !key.is_empty()).collect();
+    let service = CalculatorServer::with_interceptor(service::CalculatorService::new(state.clone()), move |request| {
+        auth::require_api_key(&api_keys, request)
+    });
+
+    println!("🚀 Serving gRPC on {}", cli.listen);
+    println!("   Sponsoring submissions from {} into {}", state.payer.pubkey(), state.state);
+    println!("   Following results on {}", ws_url);
+    Server::builder()
+        .add_service(service)
+        .serve(cli.listen)
+        .await
+        .with_context(|| format!("Failed to serve on {}", cli.listen))?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! Following callbacks for `WatchResults`
+//!
+//! One websocket log subscription is shared by every watcher: the events of
+//! each confirmed transaction mentioning the program are broadcast, and each
+//! watch picks out the callbacks it asked for. The subscription is
+//! re-established when it drops, so a node restart costs watchers the
+//! callbacks confirmed in between rather than the stream.
+
+use anyhow::{bail, Context, Result};
+use calculator_common::FailureReason;
+use calculator_sdk::logs::{decode_events, CallbackOutcome, LogEvent};
+use calculator_sdk::{CalculationRecord, CalculationStatus, HistoryEntry};
+use futures_util::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::proto::{CalculationResult, Outcome};
+
+/// Wait before resubscribing after the subscription fails or closes
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// A calculator event and the transaction it was logged by
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub slot: u64,
+    pub signature: String,
+    pub event: LogEvent,
+}
+
+/// Broadcasts the calculator events of every confirmed transaction
+/// mentioning `program_id`, for as long as the server runs
+pub async fn follow(ws_url: String, program_id: Pubkey, notices: broadcast::Sender<Notice>) {
+    loop {
+        if let Err(e) = subscribe(&ws_url, &program_id, &notices).await {
+            eprintln!("⚠️ {:#}; resubscribing in {}s", e, RESUBSCRIBE_DELAY.as_secs());
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn subscribe(ws_url: &str, program_id: &Pubkey, notices: &broadcast::Sender<Notice>) -> Result<()> {
+    let pubsub = PubsubClient::new(ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to websocket {}", ws_url))?;
+    let (mut logs, unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+    while let Some(response) = logs.next().await {
+        for event in decode_events(&response.value) {
+            // Nobody watching is not an error
+            let _ = notices.send(Notice {
+                slot: response.context.slot,
+                signature: response.value.signature.clone(),
+                event,
+            });
+        }
+    }
+    unsubscribe().await;
+    bail!("Log subscription closed")
+}
+
+/// The result a callback logged, `None` for other events
+pub fn from_notice(notice: &Notice) -> Option<CalculationResult> {
+    let LogEvent::Callback { execution_id, outcome, latency_slots, cycles } = &notice.event else {
+        return None;
+    };
+    let (result, error) = match outcome {
+        CallbackOutcome::Completed { result, .. } => (Some(*result), None),
+        CallbackOutcome::GuestError(reason) | CallbackOutcome::Failed(reason) => (None, Some(reason.clone())),
+        CallbackOutcome::OutOfBounds | CallbackOutcome::AwaitingFinalize => (None, None),
+    };
+    let outcome = match outcome {
+        CallbackOutcome::Completed { .. } => Outcome::Completed,
+        CallbackOutcome::OutOfBounds => Outcome::OutOfBounds,
+        CallbackOutcome::GuestError(_) => Outcome::GuestError,
+        CallbackOutcome::AwaitingFinalize => Outcome::AwaitingFinalize,
+        CallbackOutcome::Failed(_) => Outcome::Failed,
+    };
+    Some(CalculationResult {
+        execution_id: execution_id.clone(),
+        outcome: outcome.into(),
+        result,
+        error,
+        signature: notice.signature.clone(),
+        slot: notice.slot,
+        latency_slots: *latency_slots,
+        cycles: *cycles,
+    })
+}
+
+/// The result already in a calculation record, `None` while it waits for its callback
+///
+/// What only the callback transaction tells (its signature, the cycles) is
+/// left out.
+pub fn from_record(calc: &CalculationRecord) -> Option<CalculationResult> {
+    let (outcome, error) = match HistoryEntry::from(calc).status {
+        CalculationStatus::Pending => return None,
+        CalculationStatus::AwaitingFinalize => (Outcome::AwaitingFinalize, None),
+        CalculationStatus::Complete | CalculationStatus::Local => (Outcome::Completed, None),
+        CalculationStatus::Failed(reason) => {
+            let outcome = match reason {
+                FailureReason::ResultOutOfBounds => Outcome::OutOfBounds,
+                _ => Outcome::GuestError,
+            };
+            (outcome, Some(format!("{:?}", reason)))
+        }
+    };
+    Some(CalculationResult {
+        execution_id: calc.execution_id.clone(),
+        outcome: outcome.into(),
+        result: calc.result,
+        error,
+        signature: String::new(),
+        slot: calc.completed_slot.unwrap_or_default(),
+        latency_slots: calc.completed_slot.map(|slot| slot.saturating_sub(calc.submitted_slot)),
+        cycles: None,
+    })
+}
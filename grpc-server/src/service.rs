@@ -0,0 +1,198 @@
+//! The `Calculator` service
+//!
+//! Submissions go through the SDK, paid for and requested by the server's
+//! keypair, and statuses are read from calculation records, so callers need
+//! neither a Solana keypair nor Borsh. `WatchResults` picks callbacks out of
+//! the shared log subscription (see `results`).
+
+use calculator_common::calculation_address;
+use calculator_sdk::logs::LogEvent;
+use calculator_sdk::{
+    expression, pad_execution_id, parse_operation, records, validate_execution_id, Calculation, CalculationRecord,
+    CalculationStatus as RecordStatus, HistoryEntry, OPERATION_NAMES,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::error;
+use crate::proto::calculator_server::Calculator;
+use crate::proto::submit_calculation_request::Calculation as RequestedCalculation;
+use crate::proto::{
+    CalculationResult, CalculationStatus, GetStatusRequest, Operation, SubmitCalculationRequest,
+    SubmitCalculationResponse, Status as ProtoStatus, WatchResultsRequest,
+};
+use crate::results;
+use crate::AppState;
+
+/// Results queued for a watcher that reads slower than callbacks land
+const WATCH_BUFFER: usize = 64;
+
+pub struct CalculatorService {
+    state: Arc<AppState>,
+}
+
+impl CalculatorService {
+    pub fn new(state: Arc<AppState>) -> Self {
+        CalculatorService { state }
+    }
+
+    /// The record of `execution_id`, as the caller gave it
+    async fn find_record(&self, execution_id: &str) -> Result<(String, CalculationRecord), Status> {
+        validate_execution_id(execution_id).map_err(error::status)?;
+        let execution_id = pad_execution_id(execution_id);
+        let calculator = &self.state.calculator;
+        match records::find_record(calculator.rpc(), calculator.program_id(), &execution_id).await {
+            Ok((_, calc)) => Ok((execution_id, calc)),
+            Err(_) => Err(Status::not_found(format!("No calculation with execution ID {}", execution_id))),
+        }
+    }
+}
+
+fn calculation(request: &SubmitCalculationRequest) -> Result<Calculation, Status> {
+    let tokens = match &request.calculation {
+        Some(RequestedCalculation::Expression(expression)) => expression::parse(expression),
+        Some(RequestedCalculation::Rpn(rpn)) => expression::parse_rpn(rpn),
+        Some(RequestedCalculation::Operation(Operation { name, operand_a, operand_b })) => {
+            let op_code = parse_operation(name).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown operation {:?}; use {}", name, OPERATION_NAMES))
+            })?;
+            return Ok(Calculation::Single { op_code, operand_a: *operand_a, operand_b: operand_b.unwrap_or(0) });
+        }
+        None => return Err(Status::invalid_argument("Give an expression, RPN tokens or an operation")),
+    };
+    let tokens = tokens.map_err(|e| Status::invalid_argument(format!("Invalid expression: {:#}", e)))?;
+    Ok(Calculation::from_tokens(tokens))
+}
+
+#[tonic::async_trait]
+impl Calculator for CalculatorService {
+    async fn submit_calculation(
+        &self,
+        request: Request<SubmitCalculationRequest>,
+    ) -> Result<Response<SubmitCalculationResponse>, Status> {
+        let request = request.into_inner();
+        let calculation = calculation(&request)?;
+        let state = &self.state;
+        let submission = {
+            let _submitting = state.submitting.lock().await;
+            state
+                .calculator
+                .submit(&state.payer, &state.state, &calculation, request.execution_id.as_deref())
+                .await
+                .map_err(error::status)?
+        };
+        Ok(Response::new(SubmitCalculationResponse {
+            execution_id: submission.execution_id,
+            description: calculation.describe(),
+            signature: submission.signature.to_string(),
+            state: submission.state.to_string(),
+            index: submission.index,
+            record: submission.record.to_string(),
+        }))
+    }
+
+    async fn get_status(&self, request: Request<GetStatusRequest>) -> Result<Response<CalculationStatus>, Status> {
+        let (_, calc) = self.find_record(&request.get_ref().execution_id).await?;
+        let (status, failure) = match HistoryEntry::from(&calc).status {
+            _ if calc.retried => (ProtoStatus::Retried, None),
+            RecordStatus::Pending => (ProtoStatus::Pending, None),
+            RecordStatus::AwaitingFinalize => (ProtoStatus::AwaitingFinalize, None),
+            RecordStatus::Complete => (ProtoStatus::Complete, None),
+            RecordStatus::Local => (ProtoStatus::Local, None),
+            RecordStatus::Failed(reason) => (ProtoStatus::Failed, Some(format!("{:?}", reason))),
+        };
+        let (record, _) = calculation_address(self.state.calculator.program_id(), &calc.state, calc.index);
+        Ok(Response::new(CalculationStatus {
+            execution_id: calc.execution_id.clone(),
+            description: calc.describe(),
+            state: calc.state.to_string(),
+            index: calc.index,
+            record: record.to_string(),
+            status: status.into(),
+            result: calc.result,
+            failure,
+            submitted_slot: calc.submitted_slot,
+            completed_slot: calc.completed_slot,
+            payer: calc.payer.to_string(),
+        }))
+    }
+
+    type WatchResultsStream = ReceiverStream<Result<CalculationResult, Status>>;
+
+    /// Streams every callback, or with execution IDs, the result of each and
+    /// then ends
+    ///
+    /// Results already in are sent first, read from their records. A watched
+    /// calculation that is retried is followed to its retry, whose result is
+    /// sent under the retry's execution ID.
+    async fn watch_results(
+        &self,
+        request: Request<WatchResultsRequest>,
+    ) -> Result<Response<Self::WatchResultsStream>, Status> {
+        // Subscribed before the records are read, so a callback landing in between isn't missed
+        let mut notices = self.state.notices.subscribe();
+        let follow_all = request.get_ref().execution_ids.is_empty();
+        let mut watched = HashSet::new();
+        let mut ready = Vec::new();
+        for execution_id in &request.get_ref().execution_ids {
+            let (execution_id, calc) = self.find_record(execution_id).await?;
+            if calc.retried {
+                return Err(Status::failed_precondition(format!(
+                    "Calculation {} was superseded by a retry; watch the retry instead",
+                    execution_id
+                )));
+            }
+            match results::from_record(&calc) {
+                Some(result) => ready.push(result),
+                None => {
+                    watched.insert(execution_id);
+                }
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        tokio::spawn(async move {
+            for result in ready {
+                if sender.send(Ok(result)).await.is_err() {
+                    return;
+                }
+            }
+            while follow_all || !watched.is_empty() {
+                let notice = tokio::select! {
+                    notice = notices.recv() => notice,
+                    // Stops following as soon as the caller hangs up
+                    () = sender.closed() => return,
+                };
+                let notice = match notice {
+                    Ok(notice) => notice,
+                    Err(RecvError::Lagged(missed)) => {
+                        let message = format!("Watch fell {} events behind and was dropped; watch again", missed);
+                        let _ = sender.send(Err(Status::data_loss(message))).await;
+                        return;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                if let LogEvent::Retried { execution_id, retry_execution_id } = &notice.event {
+                    if watched.remove(execution_id) {
+                        watched.insert(retry_execution_id.clone());
+                    }
+                    continue;
+                }
+                let Some(result) = results::from_notice(&notice) else {
+                    continue;
+                };
+                if !follow_all && !watched.remove(&result.execution_id) {
+                    continue;
+                }
+                if sender.send(Ok(result)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
@@ -0,0 +1,73 @@
+//! Indexing past transactions from the calculator program's signature history
+//!
+//! `getSignaturesForAddress` pages backwards from the newest transaction, so
+//! the signatures are collected first and indexed oldest first. Only as much
+//! history as the RPC node keeps can be backfilled. Rows are upserted, so
+//! overlapping an earlier backfill or the live indexer is harmless.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+
+use crate::index::Indexer;
+
+// Largest page getSignaturesForAddress returns
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Args)]
+pub struct BackfillArgs {
+    /// Start from the transaction before this one [default: the newest]
+    #[arg(long)]
+    before: Option<Signature>,
+
+    /// Stop at this transaction, such as the last one an earlier backfill indexed
+    #[arg(long)]
+    until: Option<Signature>,
+
+    /// Index at most this many transactions
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+pub async fn run(indexer: &Indexer, args: &BackfillArgs) -> Result<()> {
+    let mut signatures = Vec::new();
+    let mut before = args.before;
+    loop {
+        let remaining = args.limit.map_or(PAGE_SIZE, |limit| limit - signatures.len());
+        let page = indexer
+            .rpc
+            .get_signatures_for_address_with_config(
+                &indexer.program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: args.until,
+                    limit: Some(remaining.min(PAGE_SIZE)),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .context("Failed to fetch the program's signature history")?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.signature.parse().context("RPC returned an invalid signature")?);
+        let full = page.len() == remaining.min(PAGE_SIZE);
+        signatures.extend(page.into_iter().map(|status| (status.signature, status.slot)));
+        if !full || args.limit.is_some_and(|limit| signatures.len() >= limit) {
+            break;
+        }
+    }
+    println!("📚 Backfilling {} transaction(s) of {}", signatures.len(), indexer.program_id);
+
+    let mut events = 0;
+    for (signature, slot) in signatures.iter().rev() {
+        let (info, logs) = indexer.fetch_transaction(signature).await?;
+        events += indexer.index(*slot, &logs, Some(info)).await?;
+    }
+    println!("✅ Indexed {} event(s) from {} transaction(s)", events, signatures.len());
+    if let Some((newest, _)) = signatures.first() {
+        println!("   Resume later with --until {}", newest);
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Turning one confirmed transaction into rows
+//!
+//! The events come from the transaction's logs. A submission's operands are
+//! read from its calculation record, since variables may have replaced the
+//! literal operands of the instruction; a record pruned before it was
+//! indexed leaves them empty. The payer is the transaction's fee payer.
+
+use anyhow::{anyhow, Context, Result};
+use calculator_indexer::store::{CallbackRow, Store, SubmissionRow};
+use calculator_sdk::logs::{decode_events, CallbackOutcome, LogEvent};
+use calculator_sdk::{metrics, records};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_response::RpcLogsResponse;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+/// What the logs don't say about a transaction
+pub struct TransactionInfo {
+    pub fee_payer: Pubkey,
+    /// Unix timestamp of the block
+    pub block_time: Option<i64>,
+}
+
+pub struct Indexer {
+    pub rpc: RpcClient,
+    pub store: Store,
+    pub program_id: Pubkey,
+}
+
+impl Indexer {
+    /// Indexes the calculator events in the logs of the transaction confirmed
+    /// at `slot`, returning how many there were
+    ///
+    /// `info` is fetched when the logs hold a submission and it isn't given.
+    /// Each event is also recorded in the metrics, which `live` exports with
+    /// `--metrics-listen`.
+    pub async fn index(&self, slot: u64, logs: &RpcLogsResponse, info: Option<TransactionInfo>) -> Result<usize> {
+        let events = decode_events(logs);
+        let mut info = info;
+        for event in &events {
+            metrics::observe(event);
+            match event {
+                LogEvent::Submitted { execution_id, description }
+                | LogEvent::ComputedLocally { execution_id, description } => {
+                    if info.is_none() {
+                        info = self.fetch_transaction(&logs.signature).await.map(|(info, _)| info).ok();
+                    }
+                    let row = self.submission(slot, logs, info.as_ref(), event, execution_id, description).await;
+                    self.store.record_submission(&row).await?;
+                    println!("[slot {}] 📤 {} submitted: {}", slot, execution_id, description);
+                }
+                LogEvent::Retried { execution_id, retry_execution_id } => {
+                    self.store.record_retry(execution_id, retry_execution_id).await?;
+                    println!("[slot {}] 🔁 {} retried as {}", slot, execution_id, retry_execution_id);
+                }
+                LogEvent::Callback { execution_id, outcome, latency_slots, cycles } => {
+                    let (result, error) = match outcome {
+                        CallbackOutcome::Completed { result, .. } => (Some(*result), None),
+                        CallbackOutcome::GuestError(reason) | CallbackOutcome::Failed(reason) => {
+                            (None, Some(reason.clone()))
+                        }
+                        CallbackOutcome::OutOfBounds | CallbackOutcome::AwaitingFinalize => (None, None),
+                    };
+                    let row = CallbackRow {
+                        execution_id: execution_id.clone(),
+                        outcome: outcome.name(),
+                        result,
+                        error,
+                        cycles: cycles.map(|cycles| cycles as i64),
+                        signature: logs.signature.clone(),
+                        slot: slot as i64,
+                        latency_slots: latency_slots.map(|slots| slots as i64),
+                    };
+                    self.store.record_callback(&row).await?;
+                    println!("[slot {}] 📥 {} callback: {}", slot, execution_id, outcome.name());
+                }
+            }
+        }
+        Ok(events.len())
+    }
+
+    async fn submission(
+        &self,
+        slot: u64,
+        logs: &RpcLogsResponse,
+        info: Option<&TransactionInfo>,
+        event: &LogEvent,
+        execution_id: &str,
+        description: &str,
+    ) -> SubmissionRow {
+        let mut row = SubmissionRow {
+            execution_id: execution_id.to_string(),
+            state: None,
+            record_index: None,
+            payer: info.map(|info| info.fee_payer.to_string()),
+            requester: None,
+            operation: None,
+            operand_a: None,
+            operand_b: None,
+            description: description.to_string(),
+            computed_locally: matches!(event, LogEvent::ComputedLocally { .. }),
+            signature: logs.signature.clone(),
+            slot: slot as i64,
+            block_time: info.and_then(|info| info.block_time),
+        };
+        match records::find_record(&self.rpc, &self.program_id, execution_id).await {
+            Ok((state, calc)) => {
+                row.state = Some(state.to_string());
+                row.record_index = Some(calc.index as i64);
+                row.requester = Some(calc.requester.to_string());
+                row.operation = Some(calc.operation);
+                // Expressions keep their operands in the description
+                if calc.expression.is_empty() {
+                    row.operand_a = Some(calc.operand_a);
+                    row.operand_b = Some(calc.operand_b);
+                }
+            }
+            Err(e) => {
+                eprintln!("⚠️ No calculation record for {}, indexing it without operands: {:#}", execution_id, e)
+            }
+        }
+        row
+    }
+
+    /// Fetches a confirmed transaction, returning what the logs don't say and the logs themselves
+    pub async fn fetch_transaction(&self, signature: &str) -> Result<(TransactionInfo, RpcLogsResponse)> {
+        let parsed: Signature = signature.parse().with_context(|| format!("Invalid signature {}", signature))?;
+        let transaction = self
+            .rpc
+            .get_transaction_with_config(
+                &parsed,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .with_context(|| format!("Failed to fetch transaction {}", signature))?;
+
+        let fee_payer = transaction
+            .transaction
+            .transaction
+            .decode()
+            .and_then(|decoded| decoded.message.static_account_keys().first().copied())
+            .ok_or_else(|| anyhow!("Transaction {} could not be decoded", signature))?;
+        let meta = transaction
+            .transaction
+            .meta
+            .ok_or_else(|| anyhow!("Transaction {} has no status metadata", signature))?;
+        let logs = match meta.log_messages {
+            OptionSerializer::Some(logs) => logs,
+            _ => Vec::new(),
+        };
+
+        let info = TransactionInfo { fee_payer, block_time: transaction.block_time };
+        Ok((info, RpcLogsResponse { signature: signature.to_string(), err: meta.err, logs }))
+    }
+}
@@ -0,0 +1,4 @@
+//! The indexer's database, shared with services that read what it indexed
+//! such as the API server
+
+pub mod store;
@@ -0,0 +1,78 @@
+//! Indexing transactions as they are confirmed
+//!
+//! Transactions mentioning the calculator program arrive from an RPC
+//! websocket log subscription, or with `--geyser-url` from a Yellowstone
+//! gRPC stream, which doesn't drop notifications under load. Anything missed
+//! while the indexer was down can be filled in with `backfill`. With
+//! `--metrics-listen`, the events indexed are exported to Prometheus, so
+//! operators can alert on callbacks falling behind submissions.
+
+use anyhow::{bail, Context, Result};
+use calculator_sdk::logs::websocket_url;
+use calculator_sdk::metrics;
+use clap::Args;
+use futures_util::{stream::BoxStream, StreamExt};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::net::SocketAddr;
+
+use crate::geyser;
+use crate::index::Indexer;
+
+#[derive(Args)]
+pub struct LiveArgs {
+    /// Websocket URL to subscribe on [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Yellowstone gRPC endpoint to stream transactions from instead of the websocket (`geyser` feature)
+    #[arg(long)]
+    pub geyser_url: Option<String>,
+
+    /// Access token for --geyser-url, sent as `x-token`
+    #[arg(long, requires = "geyser_url")]
+    pub geyser_token: Option<String>,
+
+    /// Serve Prometheus metrics of the transactions indexed at `http://<addr>/metrics`
+    #[arg(long)]
+    metrics_listen: Option<SocketAddr>,
+}
+
+pub async fn run(indexer: &Indexer, args: &LiveArgs) -> Result<()> {
+    if let Some(listen) = args.metrics_listen {
+        metrics::serve(listen)?;
+        println!("📈 Serving metrics on http://{}/metrics", listen);
+    }
+    if args.geyser_url.is_some() {
+        let transactions = geyser::subscribe(args, &indexer.program_id).await?;
+        return index_stream(indexer, transactions).await;
+    }
+
+    let ws_url = args.ws_url.clone().unwrap_or_else(|| websocket_url(&indexer.rpc.url()));
+    let pubsub = PubsubClient::new(&ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to websocket {}", ws_url))?;
+    let (logs, unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![indexer.program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+    println!("👀 Indexing {} from {} (Ctrl-C to stop)", indexer.program_id, ws_url);
+
+    let result = index_stream(indexer, logs).await;
+    unsubscribe().await;
+    result
+}
+
+async fn index_stream(indexer: &Indexer, mut transactions: BoxStream<'_, Response<RpcLogsResponse>>) -> Result<()> {
+    while let Some(response) = transactions.next().await {
+        indexer.index(response.context.slot, &response.value, None).await?;
+    }
+    bail!("Transaction stream closed")
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use calculator_indexer::store::Store;
+use clap::{Parser, Subcommand};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::process::ExitCode;
+
+mod backfill;
+mod geyser;
+mod index;
+mod live;
+
+use index::Indexer;
+
+#[derive(Parser)]
+#[command(name = "indexer")]
+#[command(about = "Indexes the calculator program's submissions and callbacks into SQLite or Postgres")]
+struct Cli {
+    /// RPC URL for the Solana cluster
+    #[arg(long, default_value = "http://127.0.0.1:8899", global = true)]
+    rpc_url: String,
+
+    /// Database to index into, `sqlite://<file>?mode=rwc` or `postgres://<user>@<host>/<database>`
+    #[arg(long, env = "DATABASE_URL", default_value = "sqlite://calculator-index.db?mode=rwc", global = true)]
+    database_url: String,
+
+    /// Calculator program to index [default: the deployed calculator program]
+    #[arg(long, global = true)]
+    program_id: Option<Pubkey>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index transactions as they are confirmed
+    Live(live::LiveArgs),
+    /// Index past transactions from the program's signature history
+    Backfill(backfill::BackfillArgs),
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let indexer = Indexer {
+        rpc: RpcClient::new_with_commitment(cli.rpc_url, CommitmentConfig::confirmed()),
+        store: Store::connect(&cli.database_url).await?,
+        program_id: cli.program_id.unwrap_or_else(bonsol_calculator_backend::id),
+    };
+    match &cli.command {
+        Command::Live(args) => live::run(&indexer, args).await,
+        Command::Backfill(args) => backfill::run(&indexer, args).await,
+    }
+}
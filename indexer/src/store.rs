@@ -0,0 +1,302 @@
+//! The database calculations are indexed into
+//!
+//! One row per execution ID holds the submission and, once it lands, the
+//! callback. Both are upserts that only touch their own columns, so a
+//! transaction can be indexed twice (a backfill overlapping the live
+//! indexer, say) and a callback seen before its submission still ends up in
+//! the same row. The statements stick to SQL that SQLite and Postgres both
+//! accept, so either can be picked with the database URL alone. The API
+//! server reads the rows back through the same `Store`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::any::{install_default_drivers, AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Row};
+
+const SCHEMA: [&str; 4] = [
+    "CREATE TABLE IF NOT EXISTS calculations (
+        execution_id TEXT PRIMARY KEY,
+        state TEXT,
+        record_index BIGINT,
+        payer TEXT,
+        requester TEXT,
+        operation BIGINT,
+        operand_a BIGINT,
+        operand_b BIGINT,
+        description TEXT,
+        computed_locally BOOLEAN,
+        submit_signature TEXT,
+        submitted_slot BIGINT,
+        submitted_at BIGINT,
+        retried_as TEXT,
+        outcome TEXT,
+        result BIGINT,
+        error TEXT,
+        cycles BIGINT,
+        callback_signature TEXT,
+        completed_slot BIGINT,
+        latency_slots BIGINT
+    )",
+    "CREATE INDEX IF NOT EXISTS calculations_payer ON calculations (payer)",
+    "CREATE INDEX IF NOT EXISTS calculations_state ON calculations (state, record_index)",
+    "CREATE INDEX IF NOT EXISTS calculations_submitted_slot ON calculations (submitted_slot)",
+];
+
+/// A submission, with the calculation record's fields when it could still be read
+pub struct SubmissionRow {
+    pub execution_id: String,
+    pub state: Option<String>,
+    pub record_index: Option<i64>,
+    /// Fee payer of the submitting transaction
+    pub payer: Option<String>,
+    pub requester: Option<String>,
+    pub operation: Option<i64>,
+    pub operand_a: Option<i64>,
+    pub operand_b: Option<i64>,
+    pub description: String,
+    pub computed_locally: bool,
+    pub signature: String,
+    pub slot: i64,
+    /// Unix timestamp of the submitting block
+    pub block_time: Option<i64>,
+}
+
+/// A callback the calculator program handled
+pub struct CallbackRow {
+    pub execution_id: String,
+    /// `completed`, `out_of_bounds`, `guest_error`, `awaiting_finalize` or `failed`
+    pub outcome: &'static str,
+    pub result: Option<i64>,
+    pub error: Option<String>,
+    pub cycles: Option<i64>,
+    pub signature: String,
+    pub slot: i64,
+    pub latency_slots: Option<i64>,
+}
+
+/// Every column of `calculations`, in `CalculationRow` order
+const COLUMNS: &str = "execution_id, state, record_index, payer, requester, operation, operand_a, operand_b,
+    description, computed_locally, submit_signature, submitted_slot, submitted_at, retried_as, outcome, result, error,
+    cycles, callback_signature, completed_slot, latency_slots";
+
+/// One calculation as far as it has been indexed
+///
+/// A row seen only through its callback has no submission columns, and one
+/// still waiting for its callback has no outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct CalculationRow {
+    pub execution_id: String,
+    pub state: Option<String>,
+    pub record_index: Option<i64>,
+    pub payer: Option<String>,
+    pub requester: Option<String>,
+    pub operation: Option<i64>,
+    pub operand_a: Option<i64>,
+    pub operand_b: Option<i64>,
+    pub description: Option<String>,
+    pub computed_locally: Option<bool>,
+    pub submit_signature: Option<String>,
+    pub submitted_slot: Option<i64>,
+    pub submitted_at: Option<i64>,
+    pub retried_as: Option<String>,
+    pub outcome: Option<String>,
+    pub result: Option<i64>,
+    pub error: Option<String>,
+    pub cycles: Option<i64>,
+    pub callback_signature: Option<String>,
+    pub completed_slot: Option<i64>,
+    pub latency_slots: Option<i64>,
+}
+
+impl CalculationRow {
+    fn from_row(row: &AnyRow) -> Result<Self, sqlx::Error> {
+        Ok(CalculationRow {
+            execution_id: row.try_get("execution_id")?,
+            state: row.try_get("state")?,
+            record_index: row.try_get("record_index")?,
+            payer: row.try_get("payer")?,
+            requester: row.try_get("requester")?,
+            operation: row.try_get("operation")?,
+            operand_a: row.try_get("operand_a")?,
+            operand_b: row.try_get("operand_b")?,
+            description: row.try_get("description")?,
+            computed_locally: row.try_get("computed_locally")?,
+            submit_signature: row.try_get("submit_signature")?,
+            submitted_slot: row.try_get("submitted_slot")?,
+            submitted_at: row.try_get("submitted_at")?,
+            retried_as: row.try_get("retried_as")?,
+            outcome: row.try_get("outcome")?,
+            result: row.try_get("result")?,
+            error: row.try_get("error")?,
+            cycles: row.try_get("cycles")?,
+            callback_signature: row.try_get("callback_signature")?,
+            completed_slot: row.try_get("completed_slot")?,
+            latency_slots: row.try_get("latency_slots")?,
+        })
+    }
+}
+
+/// Which submissions `Store::history` returns, newest first
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub payer: Option<String>,
+    pub state: Option<String>,
+    /// Only submissions before this slot, for paging
+    pub before_slot: Option<i64>,
+    pub limit: i64,
+}
+
+/// Totals over everything indexed
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub submitted: i64,
+    pub completed: i64,
+    /// Guest errors, out-of-bounds results and failed callbacks
+    pub failed: i64,
+    /// Submitted and neither called back nor retried yet
+    pub pending: i64,
+    pub retried: i64,
+    pub payers: i64,
+    pub average_latency_slots: Option<f64>,
+}
+
+pub struct Store {
+    pool: AnyPool,
+}
+
+impl Store {
+    /// Connects to `url`, `sqlite://...` or `postgres://...`, and creates the schema if needed
+    pub async fn connect(url: &str) -> Result<Self> {
+        install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(4)
+            .connect(url)
+            .await
+            .with_context(|| format!("Failed to connect to database {}", url))?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await.context("Failed to create the schema")?;
+        }
+        Ok(Store { pool })
+    }
+
+    pub async fn record_submission(&self, row: &SubmissionRow) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO calculations (execution_id, state, record_index, payer, requester, operation, operand_a,
+                operand_b, description, computed_locally, submit_signature, submitted_slot, submitted_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (execution_id) DO UPDATE SET state = excluded.state, record_index = excluded.record_index,
+                payer = excluded.payer, requester = excluded.requester, operation = excluded.operation,
+                operand_a = excluded.operand_a, operand_b = excluded.operand_b, description = excluded.description,
+                computed_locally = excluded.computed_locally, submit_signature = excluded.submit_signature,
+                submitted_slot = excluded.submitted_slot, submitted_at = excluded.submitted_at",
+        )
+        .bind(&row.execution_id)
+        .bind(&row.state)
+        .bind(row.record_index)
+        .bind(&row.payer)
+        .bind(&row.requester)
+        .bind(row.operation)
+        .bind(row.operand_a)
+        .bind(row.operand_b)
+        .bind(&row.description)
+        .bind(row.computed_locally)
+        .bind(&row.signature)
+        .bind(row.slot)
+        .bind(row.block_time)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Failed to store the submission of {}", row.execution_id))?;
+        Ok(())
+    }
+
+    pub async fn record_callback(&self, row: &CallbackRow) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO calculations (execution_id, outcome, result, error, cycles, callback_signature,
+                completed_slot, latency_slots)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (execution_id) DO UPDATE SET outcome = excluded.outcome, result = excluded.result,
+                error = excluded.error, cycles = excluded.cycles, callback_signature = excluded.callback_signature,
+                completed_slot = excluded.completed_slot, latency_slots = excluded.latency_slots",
+        )
+        .bind(&row.execution_id)
+        .bind(row.outcome)
+        .bind(row.result)
+        .bind(&row.error)
+        .bind(row.cycles)
+        .bind(&row.signature)
+        .bind(row.slot)
+        .bind(row.latency_slots)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Failed to store the callback of {}", row.execution_id))?;
+        Ok(())
+    }
+
+    /// Links an expired execution to the one that retried it
+    pub async fn record_retry(&self, execution_id: &str, retry_execution_id: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO calculations (execution_id, retried_as) VALUES ($1, $2)
+            ON CONFLICT (execution_id) DO UPDATE SET retried_as = excluded.retried_as",
+        )
+        .bind(execution_id)
+        .bind(retry_execution_id)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Failed to store the retry of {}", execution_id))?;
+        Ok(())
+    }
+
+    pub async fn calculation(&self, execution_id: &str) -> Result<Option<CalculationRow>> {
+        let row = sqlx::query(&format!("SELECT {} FROM calculations WHERE execution_id = $1", COLUMNS))
+            .bind(execution_id)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to read calculation {}", execution_id))?;
+        Ok(row.as_ref().map(CalculationRow::from_row).transpose()?)
+    }
+
+    pub async fn history(&self, filter: &HistoryFilter) -> Result<Vec<CalculationRow>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM calculations
+            WHERE submitted_slot IS NOT NULL AND ($1 IS NULL OR payer = $1) AND ($2 IS NULL OR state = $2)
+                AND ($3 IS NULL OR submitted_slot < $3)
+            ORDER BY submitted_slot DESC, execution_id
+            LIMIT $4",
+            COLUMNS
+        ))
+        .bind(&filter.payer)
+        .bind(&filter.state)
+        .bind(filter.before_slot)
+        .bind(filter.limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to read the calculation history")?;
+        Ok(rows.iter().map(CalculationRow::from_row).collect::<Result<_, _>>()?)
+    }
+
+    pub async fn stats(&self) -> Result<Stats> {
+        let row = sqlx::query(
+            "SELECT COUNT(submit_signature) AS submitted,
+                COUNT(CASE WHEN outcome = 'completed' THEN 1 END) AS completed,
+                COUNT(CASE WHEN outcome IN ('guest_error', 'out_of_bounds', 'failed') THEN 1 END) AS failed,
+                COUNT(CASE WHEN submit_signature IS NOT NULL AND outcome IS NULL AND retried_as IS NULL THEN 1 END)
+                    AS pending,
+                COUNT(retried_as) AS retried,
+                COUNT(DISTINCT payer) AS payers,
+                CAST(AVG(latency_slots) AS DOUBLE PRECISION) AS average_latency_slots
+            FROM calculations",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to read the indexer's statistics")?;
+        Ok(Stats {
+            submitted: row.try_get("submitted")?,
+            completed: row.try_get("completed")?,
+            failed: row.try_get("failed")?,
+            pending: row.try_get("pending")?,
+            retried: row.try_get("retried")?,
+            payers: row.try_get("payers")?,
+            average_latency_slots: row.try_get("average_latency_slots")?,
+        })
+    }
+}
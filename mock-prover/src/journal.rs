@@ -0,0 +1,150 @@
+//! The journal a calculation's callback delivers
+//!
+//! In `fast` mode it is computed natively, with the same checked arithmetic,
+//! statuses and input digest as the guest, and committed unmetered since no
+//! zkVM cycles were counted. In `guest` mode the guest ELF is executed with
+//! the risc0 executor, without proving, and its journal is delivered as is.
+
+use anyhow::Result;
+use calculator_common::constants::{
+    JOURNAL_STATUS_DIV_BY_ZERO, JOURNAL_STATUS_INVALID_OPERAND, JOURNAL_STATUS_MALFORMED_INPUT, JOURNAL_STATUS_OK,
+    JOURNAL_STATUS_OVERFLOW, JOURNAL_STATUS_UNKNOWN_OP, MAX_STACK_DEPTH,
+};
+use calculator_common::{CalculationRecord, ExprToken, GuestJournal};
+use calculator_ops::{apply, arity, OpError};
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Compute the result natively
+    Fast,
+    /// Execute the guest ELF (`guest` feature)
+    Guest,
+}
+
+pub enum Prover {
+    Fast,
+    #[cfg(feature = "guest")]
+    Guest { elf: Vec<u8> },
+}
+
+impl Prover {
+    /// Loads the guest ELF at `elf` in `guest` mode
+    pub fn new(mode: Mode, elf: &Path) -> Result<Self> {
+        match mode {
+            Mode::Fast => Ok(Prover::Fast),
+            Mode::Guest => guest::load(elf),
+        }
+    }
+
+    /// The journal the guest commits for the input `calc` submitted
+    pub fn journal(&self, calc: &CalculationRecord) -> Result<Vec<u8>> {
+        match self {
+            Prover::Fast => Ok(native_journal(calc)),
+            #[cfg(feature = "guest")]
+            Prover::Guest { elf } => guest::execute(elf, &calc.input()),
+        }
+    }
+}
+
+fn native_journal(calc: &CalculationRecord) -> Vec<u8> {
+    let (status, result) = match evaluate(calc) {
+        Ok(result) => (JOURNAL_STATUS_OK, result),
+        Err(status) => (status, 0),
+    };
+    GuestJournal {
+        status,
+        result,
+        input_digest: Some(Sha256::digest(calc.input()).into()),
+        cycles: None,
+        semantics: None,
+    }
+    .encode()
+}
+
+// The result, or the status the guest commits instead
+fn evaluate(calc: &CalculationRecord) -> Result<i64, u8> {
+    if calc.expression.is_empty() {
+        return apply(calc.operation, calc.operand_a, calc.operand_b).map_err(journal_status);
+    }
+
+    let mut stack: Vec<i64> = Vec::with_capacity(MAX_STACK_DEPTH);
+    for token in &calc.expression {
+        match *token {
+            ExprToken::Push(literal) => stack.push(literal),
+            ExprToken::Op(op_code) => {
+                // Unary operations take `a` alone
+                let b = if arity(op_code) == 2 { stack.pop() } else { Some(0) };
+                let (Some(b), Some(a)) = (b, stack.pop()) else {
+                    return Err(JOURNAL_STATUS_MALFORMED_INPUT);
+                };
+                stack.push(apply(op_code, a, b).map_err(journal_status)?);
+            }
+            ExprToken::Dup => match stack.last() {
+                Some(&top) => stack.push(top),
+                None => return Err(JOURNAL_STATUS_MALFORMED_INPUT),
+            },
+            ExprToken::Swap => match stack.len() {
+                0 | 1 => return Err(JOURNAL_STATUS_MALFORMED_INPUT),
+                len => stack.swap(len - 1, len - 2),
+            },
+        }
+        if stack.len() > MAX_STACK_DEPTH {
+            return Err(JOURNAL_STATUS_MALFORMED_INPUT);
+        }
+    }
+    match stack[..] {
+        [result] => Ok(result),
+        _ => Err(JOURNAL_STATUS_MALFORMED_INPUT),
+    }
+}
+
+fn journal_status(error: OpError) -> u8 {
+    match error {
+        OpError::DivisionByZero => JOURNAL_STATUS_DIV_BY_ZERO,
+        OpError::Overflow => JOURNAL_STATUS_OVERFLOW,
+        OpError::UnknownOperation => JOURNAL_STATUS_UNKNOWN_OP,
+        OpError::InvalidOperand => JOURNAL_STATUS_INVALID_OPERAND,
+    }
+}
+
+#[cfg(feature = "guest")]
+mod guest {
+    use anyhow::{anyhow, Context, Result};
+    use risc0_zkvm::{compute_image_id, default_executor, ExecutorEnv};
+    use std::path::Path;
+
+    use super::Prover;
+
+    pub fn load(path: &Path) -> Result<Prover> {
+        let elf = std::fs::read(path)
+            .with_context(|| format!("Failed to read guest ELF {}; build it with `bonsol build`", path.display()))?;
+        let image_id = compute_image_id(&elf)
+            .map_err(|e| anyhow!("Failed to compute the image ID of {}: {}", path.display(), e))?;
+        println!("🖼️ Executing guest image {}", hex::encode(image_id.as_bytes()));
+        Ok(Prover::Guest { elf })
+    }
+
+    pub fn execute(elf: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        let env = ExecutorEnv::builder()
+            .write_slice(input)
+            .build()
+            .map_err(|e| anyhow!("Failed to build the executor environment: {}", e))?;
+        let session = default_executor().execute(env, elf).map_err(|e| anyhow!("Guest execution failed: {}", e))?;
+        Ok(session.journal.bytes)
+    }
+}
+
+#[cfg(not(feature = "guest"))]
+mod guest {
+    use anyhow::{bail, Result};
+    use std::path::Path;
+
+    use super::Prover;
+
+    pub fn load(_path: &Path) -> Result<Prover> {
+        bail!("--mode guest needs the mock prover built with the `guest` feature: cargo run --features guest -- ...")
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use calculator_common::decode_outcome;
+use calculator_sdk::logs::{decode_events, websocket_url, LogEvent};
+use calculator_sdk::{instruction, records, CalculationRecord, CalculationStatus, HistoryEntry};
+use clap::Parser;
+use futures_util::StreamExt;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod journal;
+
+use journal::{Mode, Prover};
+
+#[derive(Parser)]
+#[command(name = "mock-prover")]
+#[command(about = "Answers calculator submissions on a localnet with the callback Bonsol would send")]
+struct Cli {
+    /// RPC URL of the local validator
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// Websocket URL to subscribe on [default: derived from --rpc-url]
+    #[arg(long)]
+    ws_url: Option<String>,
+
+    /// Calculator program to answer [default: the deployed calculator program]
+    #[arg(long)]
+    program_id: Option<Pubkey>,
+
+    /// Keypair paying for the callback transactions [default: ~/.config/solana/id.json]
+    #[arg(long)]
+    keypair: Option<PathBuf>,
+
+    /// How results are computed
+    #[arg(long, value_enum, default_value = "fast")]
+    mode: Mode,
+
+    /// Guest ELF built by `bonsol build`, for --mode guest
+    #[arg(
+        long,
+        default_value = "../zk-program/target/riscv-guest/riscv32im-risc0-zkvm-elf/docker/zk_calculator/zk_calculator"
+    )]
+    elf: PathBuf,
+
+    /// Calculator state whose pending calculations are answered at startup; repeat for several
+    #[arg(long)]
+    state: Vec<Pubkey>,
+}
+
+struct MockProver {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    payer: Keypair,
+    prover: Prover,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let keypair_path = match cli.keypair {
+        Some(path) => path,
+        None => {
+            let home = std::env::var_os("HOME").context("HOME is not set; pass --keypair explicitly")?;
+            PathBuf::from(home).join(".config").join("solana").join("id.json")
+        }
+    };
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read keypair {}: {}", keypair_path.display(), e))?;
+
+    let mock = MockProver {
+        rpc: RpcClient::new_with_commitment(cli.rpc_url, CommitmentConfig::confirmed()),
+        program_id: cli.program_id.unwrap_or_else(bonsol_calculator_backend::id),
+        payer,
+        prover: Prover::new(cli.mode, &cli.elf)?,
+    };
+
+    // Subscribe first, so nothing submitted while catching up is missed
+    let ws_url = cli.ws_url.unwrap_or_else(|| websocket_url(&mock.rpc.url()));
+    let pubsub = PubsubClient::new(&ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to websocket {}", ws_url))?;
+    let (mut logs, unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![mock.program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+
+    for state in &cli.state {
+        let pending: Vec<CalculationRecord> = records::fetch_records(&mock.rpc, &mock.program_id, state)
+            .await?
+            .into_iter()
+            .filter(is_pending)
+            .collect();
+        println!("⏪ {} pending calculation(s) in {}", pending.len(), state);
+        for calc in pending {
+            mock.answer(&calc).await;
+        }
+    }
+
+    println!("👀 Answering submissions to {} from {} (Ctrl-C to stop)", mock.program_id, ws_url);
+    println!("   Callbacks are paid for by {}", mock.payer.pubkey());
+    while let Some(response) = logs.next().await {
+        for event in decode_events(&response.value) {
+            let execution_id = match event {
+                LogEvent::Submitted { execution_id, .. } => execution_id,
+                LogEvent::Retried { retry_execution_id, .. } => retry_execution_id,
+                _ => continue,
+            };
+            match records::find_record(&mock.rpc, &mock.program_id, &execution_id).await {
+                Ok((_, calc)) if is_pending(&calc) => mock.answer(&calc).await,
+                Ok(_) => {}
+                Err(e) => eprintln!("⚠️ Skipping {}: {:#}", execution_id, e),
+            }
+        }
+    }
+    unsubscribe().await;
+    anyhow::bail!("Log subscription closed")
+}
+
+/// Whether `calc` is still waiting for Bonsol's callback
+fn is_pending(calc: &CalculationRecord) -> bool {
+    !calc.retried && HistoryEntry::from(calc).status == CalculationStatus::Pending
+}
+
+impl MockProver {
+    /// Computes the journal of `calc` and delivers it, reporting rather than
+    /// returning failures so one bad calculation doesn't stop the others
+    async fn answer(&self, calc: &CalculationRecord) {
+        println!("🧮 {} #{}: {}", calc.execution_id, calc.index, calc.describe());
+        match self.deliver(calc).await {
+            Ok(signature) => println!("   📨 Callback sent: {}", signature),
+            Err(e) => eprintln!("   ⚠️ Callback failed: {:#}", e),
+        }
+    }
+
+    async fn deliver(&self, calc: &CalculationRecord) -> Result<String> {
+        let journal = self.prover.journal(calc)?;
+        match decode_outcome(&journal) {
+            Ok(Ok(result)) => println!("   📜 Result: {}", result),
+            Ok(Err(reason)) => println!("   📜 Proven failure: {:?}", reason),
+            Err(_) => println!("   📜 Journal: {} bytes", journal.len()),
+        }
+        let instruction = instruction::callback(&self.program_id, &self.payer.pubkey(), calc, journal)?;
+        let blockhash = self.rpc.get_latest_blockhash().await.context("Failed to fetch a blockhash")?;
+        let transaction =
+            Transaction::new_signed_with_payer(&[instruction], Some(&self.payer.pubkey()), &[&self.payer], blockhash);
+        let signature = self
+            .rpc
+            .send_and_confirm_transaction(&transaction)
+            .await
+            .context("Failed to send the callback transaction")?;
+        Ok(signature.to_string())
+    }
+}
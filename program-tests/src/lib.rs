@@ -0,0 +1,186 @@
+//! Running the calculator program end to end in `solana-program-test`
+//!
+//! The calculator program runs natively in a `BanksClient` bank next to a
+//! stub of the Bonsol program, so neither a validator nor a prover is needed.
+//! The stub owns the image's deployment account, which submissions check,
+//! logs the execution requests submissions send it, and stands in for the
+//! prover by invoking the calculator's callback with a journal the test
+//! supplies, its data prefixed the way Bonsol prefixes callback data.
+
+use bonsol_calculator_backend::{derive_retry_execution_id, CALCULATOR_IMAGE_ID};
+use bonsol_interface::util::deployment_address;
+use borsh::BorshSerialize;
+use calculator_common::{calculation_address, codec, CalculationRecord, CalculatorState};
+use calculator_sdk::{instruction, Calculation, Submission};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+pub mod stub_bonsol;
+
+pub use bonsol_calculator_backend::id as calculator_id;
+
+/// A calculator initialized by the payer of a fresh bank
+pub struct TestCalculator {
+    pub context: ProgramTestContext,
+    pub state: Pubkey,
+    /// Log messages of the last transaction sent
+    pub logs: Vec<String>,
+}
+
+/// The calculator and stub Bonsol programs, both running natively
+pub fn program_test() -> ProgramTest {
+    let mut test = ProgramTest::new(
+        "bonsol_calculator_backend",
+        calculator_id(),
+        processor!(bonsol_calculator_backend::process_instruction),
+    );
+    test.add_program("stub_bonsol", bonsol_interface::ID, processor!(stub_bonsol::process_instruction));
+    test.prefer_bpf(false);
+    test
+}
+
+impl TestCalculator {
+    /// Starts a bank, initializes a calculator in it and, with `deployed`,
+    /// deploys the calculator image to the stub Bonsol program
+    pub async fn start(deployed: bool) -> Self {
+        let mut test = program_test();
+        // In genesis rather than set later, so warping past it keeps the bank's capitalization consistent
+        if deployed {
+            let (deployment, _) = deployment_address(CALCULATOR_IMAGE_ID);
+            let account = Account {
+                lamports: 1_000_000,
+                data: vec![1],
+                owner: bonsol_interface::ID,
+                executable: false,
+                rent_epoch: 0,
+            };
+            test.add_account(deployment, account);
+        }
+        let context = test.start_with_context().await;
+        let state = Keypair::new();
+        let mut calculator = TestCalculator { context, state: state.pubkey(), logs: Vec::new() };
+
+        let initialize = instruction::initialize(&calculator_id(), &calculator.payer().pubkey(), &state.pubkey())
+            .expect("Initialize instruction");
+        calculator.send(&[initialize], &[&state]).await.expect("Initialize");
+        calculator
+    }
+
+    pub fn payer(&self) -> &Keypair {
+        &self.context.payer
+    }
+
+    /// Sends `instructions` in one transaction paid for by the bank's payer
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let payer = self.context.payer.pubkey();
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&payer), &all_signers, blockhash);
+        let signature = transaction.signatures[0];
+        let processed = self.context.banks_client.process_transaction_with_metadata(transaction).await?;
+        self.logs = processed.metadata.map(|metadata| metadata.log_messages).unwrap_or_default();
+        processed.result?;
+        Ok(signature)
+    }
+
+    /// Execution request accounts the stub Bonsol program was invoked with
+    /// during the last transaction
+    pub fn execution_requests(&self) -> Vec<Pubkey> {
+        stub_bonsol::execution_requests(&self.logs)
+    }
+
+    pub async fn calculator_state(&mut self) -> CalculatorState {
+        let account = self.context.banks_client.get_account(self.state).await.unwrap().expect("State account");
+        codec::decode(&account.data).expect("Calculator state")
+    }
+
+    /// The calculation record at `record`, `None` once it has been closed
+    pub async fn record(&mut self, record: &Pubkey) -> Option<CalculationRecord> {
+        let account = self.context.banks_client.get_account(*record).await.unwrap()?;
+        Some(codec::decode(&account.data).expect("Calculation record"))
+    }
+
+    /// Submits `calculation` under `execution_id`, requested by the bank's payer
+    pub async fn submit(
+        &mut self,
+        execution_id: &str,
+        calculation: &Calculation,
+    ) -> Result<Submission, BanksClientError> {
+        let requester = self.payer().pubkey();
+        self.submit_for(&requester, execution_id, calculation).await
+    }
+
+    /// Submits `calculation` under `execution_id` for `requester`, paid for
+    /// by the bank's payer
+    pub async fn submit_for(
+        &mut self,
+        requester: &Pubkey,
+        execution_id: &str,
+        calculation: &Calculation,
+    ) -> Result<Submission, BanksClientError> {
+        let calculator_state = self.calculator_state().await;
+        let payer = self.payer().pubkey();
+        let submit = instruction::submit_sponsored(
+            &calculator_id(),
+            &self.state,
+            &calculator_state,
+            &payer,
+            requester,
+            execution_id,
+            calculation,
+        )
+        .expect("Submit instruction");
+        let index = calculator_state.calculation_count;
+        let (record, _) = calculation_address(&calculator_id(), &self.state, index);
+        let signature = self.send(&[submit], &[]).await?;
+        let execution_id = execution_id.to_string();
+        Ok(Submission { signature, execution_id, state: self.state, index, record, requester: *requester })
+    }
+
+    /// Retries `submission` once its execution has expired, returning the
+    /// retry's execution ID
+    pub async fn retry(&mut self, submission: &Submission) -> Result<String, BanksClientError> {
+        let original = self.record(&submission.record).await.expect("Record");
+        let slot = self.context.banks_client.get_root_slot().await?;
+        if slot <= original.expiration_slot {
+            self.context.warp_to_slot(original.expiration_slot + 1).expect("Warp past the expiration slot");
+        }
+        let calculator_state = self.calculator_state().await;
+        let payer = self.payer().pubkey();
+        let retry = instruction::retry(&calculator_id(), &self.state, &calculator_state, &payer, &original)
+            .expect("Retry instruction");
+        self.send(&[retry], &[]).await?;
+        Ok(derive_retry_execution_id(&original.execution_id, original.retry_count + 1))
+    }
+
+    /// Delivers `journal` for `submission` through the stub Bonsol program,
+    /// as the prover's callback transaction would
+    pub async fn callback(
+        &mut self,
+        submission: &Submission,
+        journal: Vec<u8>,
+    ) -> Result<Signature, BanksClientError> {
+        let callback = stub_bonsol::callback(
+            &calculator_id(),
+            &submission.requester,
+            vec![AccountMeta::new(submission.state, false), AccountMeta::new(submission.record, false)],
+            &submission.execution_id,
+            journal,
+        );
+        self.send(&[callback], &[]).await
+    }
+}
+
+/// Borsh bytes of `value`, for instruction data
+pub fn to_vec<T: BorshSerialize>(value: &T) -> Vec<u8> {
+    value.try_to_vec().expect("Borsh serialization")
+}
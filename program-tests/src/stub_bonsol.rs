@@ -0,0 +1,107 @@
+//! A stand-in for the Bonsol program
+//!
+//! It only does what the calculator program relies on during a test. It owns
+//! deployment accounts, accepts execution requests, logging the execution
+//! request account each one names so tests can tell it was sent, and on its
+//! own `DELIVER_CALLBACK` instruction invokes a callback program the way
+//! Bonsol does once a proof is verified: signed by the execution request PDA,
+//! with the program's instruction prefix ahead of the Borsh
+//! `(execution_id, journal)` data.
+
+use bonsol_interface::util::{execution_address, execution_address_seeds};
+use borsh::BorshDeserialize;
+use calculator_common::constants::CALLBACK_INSTRUCTION_PREFIX;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::msg;
+use solana_sdk::program::invoke_signed;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::to_vec;
+
+/// Leading byte of the stub's callback instruction; any other data is taken
+/// for an execution request
+pub const DELIVER_CALLBACK: u8 = 0xff;
+
+/// Logged with the execution request account of every execution request
+pub const EXECUTION_REQUEST_LOG: &str = "Stub Bonsol received execution request ";
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.split_first() {
+        Some((&DELIVER_CALLBACK, callback_data)) => deliver_callback(program_id, accounts, callback_data),
+        _ => execution_request(accounts),
+    }
+}
+
+/// Accounts as `execute_v1` lists them: requester, payer, execution request,
+/// deployment, callback program, system program
+fn execution_request(accounts: &[AccountInfo]) -> ProgramResult {
+    let execution = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    msg!("{}{}", EXECUTION_REQUEST_LOG, execution.key);
+    Ok(())
+}
+
+/// Execution request accounts the stub logged in `logs`, in request order
+pub fn execution_requests(logs: &[String]) -> Vec<Pubkey> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program log: ")?.strip_prefix(EXECUTION_REQUEST_LOG)?.parse().ok())
+        .collect()
+}
+
+/// Accounts: the callback program, the execution request PDA, then the
+/// accounts forwarded to it. Data: the requester, then the callback data.
+fn deliver_callback(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (callback_program, forwarded) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if data.len() < 32 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (requester, data) = data.split_at(32);
+    let requester = Pubkey::try_from(requester).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let execution_id = String::deserialize(&mut &data[..])?;
+
+    let mut callback_data = CALLBACK_INSTRUCTION_PREFIX.to_vec();
+    callback_data.extend_from_slice(data);
+    let mut metas: Vec<AccountMeta> = forwarded
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+    // Bonsol signs the callback with the execution request PDA
+    let mut seeds = execution_address_seeds(&requester, execution_id.as_bytes());
+    let (execution, bump) = Pubkey::find_program_address(&seeds, program_id);
+    let execution_meta = metas.first_mut().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if execution_meta.pubkey != execution {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    execution_meta.is_signer = true;
+    let bump = [bump];
+    seeds.push(&bump);
+
+    let callback = Instruction { program_id: *callback_program.key, accounts: metas, data: callback_data };
+    invoke_signed(&callback, accounts, &[&seeds])
+}
+
+/// The stub's instruction delivering `journal` for the execution `execution_id`
+/// of `requester` to `callback_program`, with `accounts` following the
+/// execution request PDA
+pub fn callback(
+    callback_program: &Pubkey,
+    requester: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    execution_id: &str,
+    journal: Vec<u8>,
+) -> Instruction {
+    let mut data = vec![DELIVER_CALLBACK];
+    data.extend_from_slice(requester.as_ref());
+    data.extend(to_vec(&(execution_id.to_string(), journal)));
+    let (execution, _) = execution_address(requester, execution_id.as_bytes());
+    let mut metas =
+        vec![AccountMeta::new_readonly(*callback_program, false), AccountMeta::new_readonly(execution, false)];
+    metas.extend(accounts);
+    Instruction::new_with_bytes(bonsol_interface::ID, &data, metas)
+}
@@ -0,0 +1,210 @@
+//! Initialize, submit and callback through the calculator program, with the
+//! stub Bonsol program standing in for the prover
+//!
+//! Each test starts its own bank, so they run independently and in parallel.
+
+use bonsol_calculator_backend::error::CalculatorError;
+use bonsol_interface::util::execution_address;
+use calculator_common::constants::{JOURNAL_BATCH_VERSION_1, JOURNAL_STATUS_DIV_BY_ZERO, JOURNAL_STATUS_OK};
+use calculator_common::{CalculationStatus, FailureReason, GuestJournal, HistoryEntry};
+use calculator_ops::{OP_ADD, OP_DIVIDE, OP_MULTIPLY};
+use calculator_program_tests::{calculator_id, TestCalculator};
+use calculator_sdk::{instruction, pad_execution_id, Calculation, Submission};
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::{AccountMeta, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+
+/// The journal the guest commits for `calculation`: `result` with `status`,
+/// over the calculation's own input
+fn journal(calculation: &Calculation, status: u8, result: i64) -> Vec<u8> {
+    GuestJournal {
+        status,
+        result,
+        input_digest: Some(calculation.input_hash()),
+        cycles: Some(1_024),
+        semantics: Some(0),
+    }
+    .encode()
+}
+
+fn custom_error(error: BanksClientError) -> Option<u32> {
+    match error.unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+        _ => None,
+    }
+}
+
+async fn submit(calculator: &mut TestCalculator, execution_id: &str, calculation: &Calculation) -> Submission {
+    calculator.submit(&pad_execution_id(execution_id), calculation).await.expect("Submit")
+}
+
+#[tokio::test]
+async fn callback_completes_the_calculation() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_MULTIPLY, operand_a: 6, operand_b: 7 };
+    let submission = submit(&mut calculator, "multiply", &calculation).await;
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(record.execution_id, submission.execution_id);
+    assert_eq!((record.operation, record.operand_a, record.operand_b), (OP_MULTIPLY, 6, 7));
+    assert_eq!(record.input_hash, Some(calculation.input_hash()));
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Pending);
+    let state = calculator.calculator_state().await;
+    assert_eq!((state.calculation_count, state.pending_count), (1, 1));
+
+    calculator.callback(&submission, journal(&calculation, JOURNAL_STATUS_OK, 42)).await.expect("Callback");
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Complete);
+    assert_eq!(record.result, Some(42));
+    assert!(record.completed_slot.is_some());
+    assert!(record.journal.is_some());
+    let state = calculator.calculator_state().await;
+    assert_eq!((state.calculation_count, state.pending_count), (1, 0));
+}
+
+#[tokio::test]
+async fn submission_sends_the_execution_request_to_bonsol() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 3, operand_b: 4 };
+    let submission = submit(&mut calculator, "request", &calculation).await;
+
+    let (execution, _) = execution_address(&submission.requester, submission.execution_id.as_bytes());
+    assert_eq!(calculator.execution_requests(), vec![execution]);
+}
+
+#[tokio::test]
+async fn sponsored_submission_is_requested_for_the_requester() {
+    let mut calculator = TestCalculator::start(true).await;
+    let requester = Pubkey::new_unique();
+    let calculation = Calculation::Single { op_code: OP_MULTIPLY, operand_a: 3, operand_b: 5 };
+    let submission =
+        calculator.submit_for(&requester, &pad_execution_id("sponsored"), &calculation).await.expect("Submit");
+
+    // Bonsol's execution request belongs to the requester, not the payer
+    let (execution, _) = execution_address(&requester, submission.execution_id.as_bytes());
+    assert_eq!(calculator.execution_requests(), vec![execution]);
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!((record.requester, record.payer), (requester, calculator.payer().pubkey()));
+
+    calculator.callback(&submission, journal(&calculation, JOURNAL_STATUS_OK, 15)).await.expect("Callback");
+    assert_eq!(calculator.record(&submission.record).await.expect("Record").result, Some(15));
+}
+
+#[tokio::test]
+async fn retry_sends_a_new_execution_request_to_bonsol() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 8, operand_b: 9 };
+    let submission = submit(&mut calculator, "expire", &calculation).await;
+
+    let retry_execution_id = calculator.retry(&submission).await.expect("Retry");
+
+    let (execution, _) = execution_address(&submission.requester, retry_execution_id.as_bytes());
+    assert_eq!(calculator.execution_requests(), vec![execution]);
+    let state = calculator.calculator_state().await;
+    assert_eq!((state.calculation_count, state.pending_count), (2, 1));
+    let original = calculator.record(&submission.record).await.expect("Record");
+    assert!(original.retried);
+}
+
+#[tokio::test]
+async fn proven_failure_marks_the_calculation_failed() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_DIVIDE, operand_a: 1, operand_b: 0 };
+    let submission = submit(&mut calculator, "divide", &calculation).await;
+
+    calculator.callback(&submission, journal(&calculation, JOURNAL_STATUS_DIV_BY_ZERO, 0)).await.expect("Callback");
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(record.failure, Some(FailureReason::DivisionByZero));
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Failed(FailureReason::DivisionByZero));
+    assert_eq!(record.result, None);
+    assert_eq!(calculator.calculator_state().await.pending_count, 0);
+}
+
+#[tokio::test]
+async fn journal_over_another_input_is_rejected() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 1, operand_b: 2 };
+    let submission = submit(&mut calculator, "add", &calculation).await;
+
+    let other = Calculation::Single { op_code: OP_ADD, operand_a: 1, operand_b: 3 };
+    let error = calculator.callback(&submission, journal(&other, JOURNAL_STATUS_OK, 4)).await.unwrap_err();
+    assert_eq!(custom_error(error), Some(CalculatorError::InputDigestMismatch as u32));
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Pending);
+}
+
+#[tokio::test]
+async fn batch_journal_is_rejected() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 2, operand_b: 2 };
+    let submission = submit(&mut calculator, "batch", &calculation).await;
+
+    // A one-operation batch journal, short enough to pass as a single-result one
+    let mut batch = vec![JOURNAL_BATCH_VERSION_1, 1];
+    batch.extend_from_slice(&calculation.input_hash());
+    batch.push(JOURNAL_STATUS_OK);
+    batch.extend_from_slice(&4i64.to_le_bytes());
+    let error = calculator.callback(&submission, batch).await.unwrap_err();
+    assert!(matches!(
+        error.unwrap(),
+        TransactionError::InstructionError(_, InstructionError::InvalidInstructionData)
+    ));
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Pending);
+}
+
+#[tokio::test]
+async fn callback_not_signed_by_bonsol_is_rejected() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 5, operand_b: 5 };
+    let submission = submit(&mut calculator, "forged", &calculation).await;
+    let record = calculator.record(&submission.record).await.expect("Record");
+
+    // Signed by someone other than Bonsol's execution request PDA
+    let payer = calculator.payer().pubkey();
+    let mut forged =
+        instruction::callback(&calculator_id(), &payer, &record, journal(&calculation, JOURNAL_STATUS_OK, 10))
+            .expect("Callback instruction");
+    let error = calculator.send(&[forged.clone()], &[]).await.unwrap_err();
+    assert_eq!(custom_error(error), Some(CalculatorError::UnauthorizedCallback as u32));
+
+    // Naming the execution request PDA without its signature
+    let (execution, _) = execution_address(&submission.requester, submission.execution_id.as_bytes());
+    forged.accounts[0] = AccountMeta::new_readonly(execution, false);
+    let error = calculator.send(&[forged], &[]).await.unwrap_err();
+    assert_eq!(custom_error(error), Some(CalculatorError::UnauthorizedCallback as u32));
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(HistoryEntry::from(&record).status, CalculationStatus::Pending);
+}
+
+#[tokio::test]
+async fn repeated_callback_is_ignored() {
+    let mut calculator = TestCalculator::start(true).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 2, operand_b: 2 };
+    let submission = submit(&mut calculator, "repeat", &calculation).await;
+
+    calculator.callback(&submission, journal(&calculation, JOURNAL_STATUS_OK, 4)).await.expect("Callback");
+    // A different journal so the transaction isn't a duplicate of the first
+    calculator.callback(&submission, journal(&calculation, JOURNAL_STATUS_OK, 5)).await.expect("Repeated callback");
+
+    let record = calculator.record(&submission.record).await.expect("Record");
+    assert_eq!(record.result, Some(4));
+    assert_eq!(calculator.calculator_state().await.pending_count, 0);
+}
+
+#[tokio::test]
+async fn submission_needs_a_deployed_image() {
+    let mut calculator = TestCalculator::start(false).await;
+    let calculation = Calculation::Single { op_code: OP_ADD, operand_a: 1, operand_b: 1 };
+
+    let error = calculator.submit(&pad_execution_id("undeployed"), &calculation).await.unwrap_err();
+    assert_eq!(custom_error(error), Some(CalculatorError::ImageNotDeployed as u32));
+    assert_eq!(calculator.calculator_state().await.calculation_count, 0);
+}
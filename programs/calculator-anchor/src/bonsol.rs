@@ -0,0 +1,136 @@
+//! Bonsol execution request construction shared by submission and retry.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::Discriminator;
+use bonsol_interface::instructions::{execute_v1, CallbackConfig, ExecutionConfig, InputRef};
+use bonsol_interface::util::execution_address;
+use calculator_common::journal::{self, GuestJournal};
+
+use crate::error::CalculatorError;
+use crate::state::*;
+
+/// Sends the Bonsol execution request for a calculation record
+///
+/// The callback is configured with the state and record accounts followed by
+/// the record's forwarded accounts, result hook, referral accounts and memo
+/// program, and is prefixed with the `callback` instruction discriminator.
+/// `accounts` are the ones `execute_v1` references: requester, payer,
+/// execution request PDA, deployment, calculator program and system program,
+/// plus the Bonsol program itself.
+#[allow(clippy::too_many_arguments)]
+pub fn request_execution<'info>(
+    payer: &Pubkey,
+    state: &Pubkey,
+    record: &Pubkey,
+    treasury: &Pubkey,
+    image_id: &str,
+    calc: &CalculationRecord,
+    accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    msg!("Creating Bonsol execution request for {}", calc.describe());
+
+    let combined_input = calc.input();
+    let inputs = vec![InputRef::public(&combined_input)];
+
+    let mut extra_accounts = vec![AccountMeta::new(*state, false), AccountMeta::new(*record, false)];
+    extra_accounts.extend(calc.callback_accounts.iter().map(|account| {
+        if account.is_writable {
+            AccountMeta::new(account.pubkey, false)
+        } else {
+            AccountMeta::new_readonly(account.pubkey, false)
+        }
+    }));
+    if let Some(hook_program_id) = calc.result_hook {
+        extra_accounts.push(AccountMeta::new_readonly(hook_program_id, false));
+    }
+    if let Some(referrer) = calc.referrer {
+        extra_accounts.push(AccountMeta::new(*treasury, false));
+        extra_accounts.push(AccountMeta::new(referrer, false));
+    }
+    if calc.emit_memo {
+        extra_accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+
+    let callback_config = Some(CallbackConfig {
+        program_id: crate::ID,
+        instruction_prefix: crate::instruction::Callback::DISCRIMINATOR.to_vec(),
+        extra_accounts,
+    });
+
+    let execution_config = ExecutionConfig {
+        verify_input_hash: false,
+        input_hash: None,
+        forward_output: true,
+    };
+
+    let bonsol_instruction = execute_v1(
+        &calc.requester,
+        payer,
+        image_id,
+        &calc.execution_id,
+        inputs,
+        1000, // tip in lamports
+        calc.expiration_slot,
+        execution_config,
+        callback_config,
+        calc.prover_version.map(ProverVersion::to_bonsol),
+    )
+    .map_err(|_| error!(CalculatorError::ExecutionRequestFailed))?;
+
+    msg!("Created Bonsol instruction with {} accounts", bonsol_instruction.accounts.len());
+    if let Some(prover_version) = calc.prover_version {
+        msg!("Pinned prover version: {:?}", prover_version);
+    }
+
+    // A local validator has no Bonsol program; the mock prover answers instead
+    if cfg!(feature = "localnet") {
+        msg!("Localnet build: execution request left to the mock prover");
+        return Ok(());
+    }
+    invoke(&bonsol_instruction, accounts)?;
+    Ok(())
+}
+
+/// Whether `execution` is the Bonsol execution request PDA of `calc`'s
+/// requester and execution ID, which Bonsol signs callbacks with
+///
+/// No other program or keypair can sign for it. A `localnet` build accepts
+/// any signer, for the mock prover.
+pub fn is_execution_request(calc: &CalculationRecord, execution: &Pubkey) -> bool {
+    cfg!(feature = "localnet") || *execution == execution_address(&calc.requester, calc.execution_id.as_bytes()).0
+}
+
+/// Parses a single-result guest journal, in any of the layouts
+/// `calculator_common::journal::GuestJournal` decodes
+pub fn parse_journal(journal: &[u8]) -> Result<GuestJournal> {
+    GuestJournal::parse(journal).map_err(|_| error!(CalculatorError::InvalidJournal))
+}
+
+/// Decodes what a guest journal proves: the result, or why the guest could
+/// not compute one (see `calculator_common::journal::decode_outcome`)
+pub fn decode_outcome(journal: &[u8]) -> Result<std::result::Result<i64, FailureReason>> {
+    let outcome = journal::decode_outcome(journal).map_err(|_| error!(CalculatorError::InvalidJournal))?;
+    Ok(outcome.map_err(FailureReason::from))
+}
+
+/// Rejects a journal proven over an input other than the one the record submitted
+///
+/// Binds the proof to the submission even when Bonsol doesn't verify the
+/// input hash. Legacy journals carry no digest to compare.
+pub fn verify_input_digest(calc: &CalculationRecord, journal: &GuestJournal) -> Result<()> {
+    let (Some(expected), Some(input_digest)) = (calc.input_hash, journal.input_digest) else {
+        return Ok(());
+    };
+    if input_digest != expected {
+        msg!(
+            "Journal was proven over input {} instead of the submitted {}",
+            Hash::new_from_array(input_digest),
+            Hash::new_from_array(expected)
+        );
+        return err!(CalculatorError::InputDigestMismatch);
+    }
+    Ok(())
+}
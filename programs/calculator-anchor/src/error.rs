@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum CalculatorError {
+    #[msg("Unknown calculator operation")]
+    InvalidOperation,
+    #[msg("Execution ID exceeds the maximum length")]
+    ExecutionIdTooLong,
+    #[msg("Label exceeds the maximum length")]
+    LabelTooLong,
+    #[msg("Too many callback accounts")]
+    TooManyCallbackAccounts,
+    #[msg("Callback account is duplicated or reserved")]
+    DuplicateCallbackAccount,
+    #[msg("Result hook cannot target the calculator program itself")]
+    InvalidResultHook,
+    #[msg("Result bounds minimum exceeds maximum")]
+    InvalidResultBounds,
+    #[msg("Referral share exceeds 100%")]
+    InvalidReferralShare,
+    #[msg("Signer is not allowed to perform this action")]
+    Unauthorized,
+    #[msg("Journal exceeds the maximum length")]
+    JournalTooLong,
+    #[msg("Journal does not contain an i64 result")]
+    InvalidJournal,
+    #[msg("Execution ID does not match the calculation record")]
+    ExecutionIdMismatch,
+    #[msg("No pending journal to finalize")]
+    NoPendingJournal,
+    #[msg("Forwarded callback account does not match the submission")]
+    CallbackAccountMismatch,
+    #[msg("Result hook program does not match the submission")]
+    ResultHookMismatch,
+    #[msg("Referrer account does not match the submission")]
+    ReferrerMismatch,
+    #[msg("Calculation record does not belong to this calculator")]
+    InvalidRecord,
+    #[msg("Token fee accounts are missing or invalid")]
+    InvalidTokenFeeAccounts,
+    #[msg("Calculation already received its callback or was retried")]
+    NotRetryable,
+    #[msg("Execution has not expired yet")]
+    NotExpired,
+    #[msg("Retry count overflow")]
+    RetryLimitReached,
+    #[msg("Failed to build the Bonsol execution request")]
+    ExecutionRequestFailed,
+    #[msg("Local calculations are disabled")]
+    LocalCalculationsDisabled,
+    #[msg("Operand exceeds the local calculation limit")]
+    OperandAboveLocalLimit,
+    #[msg("Local calculation overflowed or divided by zero")]
+    LocalCalculationFailed,
+    #[msg("Calculator is paused")]
+    Paused,
+    #[msg("Image ID must be 64 hex characters")]
+    InvalidImageId,
+    #[msg("Owner multisig must have 1 to 10 distinct keys")]
+    InvalidOwners,
+    #[msg("Threshold must be between 1 and the number of owners")]
+    InvalidThreshold,
+    #[msg("Requester must sign its submissions")]
+    RequesterSignatureRequired,
+    #[msg("Deployment account is not the Bonsol deployment PDA of the image")]
+    InvalidDeploymentAccount,
+    #[msg("Image is not deployed to Bonsol on this cluster")]
+    ImageNotDeployed,
+    #[msg("Expression is empty, too long or malformed")]
+    InvalidExpression,
+    #[msg("Variable name must be 1 to 32 bytes")]
+    InvalidVariableName,
+    #[msg("Variable account is missing or does not match the operand's variable")]
+    VariableMismatch,
+    #[msg("Journal was proven over an input other than the submitted one")]
+    InputDigestMismatch,
+    #[msg("Callback is not signed by the Bonsol execution request account of the calculation")]
+    UnauthorizedCallback,
+    #[msg("Requester account does not match the calculation record")]
+    RequesterMismatch,
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+
+use crate::state::FailureReason;
+
+#[event]
+pub struct CalculationSubmitted {
+    pub state: Pubkey,
+    pub record: Pubkey,
+    pub index: u64,
+    pub execution_id: String,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub submitted_slot: u64,
+    pub label: Option<String>,
+}
+
+#[event]
+pub struct JournalStored {
+    pub state: Pubkey,
+    pub record: Pubkey,
+    pub execution_id: String,
+}
+
+#[event]
+pub struct CalculationCompleted {
+    pub state: Pubkey,
+    pub record: Pubkey,
+    pub execution_id: String,
+    pub result: i64,
+    pub latency_slots: Option<u64>,
+    pub label: Option<String>,
+}
+
+#[event]
+pub struct CalculationFailed {
+    pub state: Pubkey,
+    pub record: Pubkey,
+    pub execution_id: String,
+    pub reason: FailureReason,
+    pub label: Option<String>,
+}
+
+#[event]
+pub struct CalculationRetried {
+    pub state: Pubkey,
+    pub record: Pubkey,
+    pub parent_execution_id: String,
+    pub execution_id: String,
+    pub retry_count: u8,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub state: Pubkey,
+    pub fee_lamports: u64,
+    pub open_submissions: bool,
+    pub referral_share_bps: u16,
+}
+
+#[event]
+pub struct ImageIdUpdated {
+    pub state: Pubkey,
+    pub image_id: String,
+}
+
+#[event]
+pub struct PausedChanged {
+    pub state: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct OwnersUpdated {
+    pub state: Pubkey,
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub state: Pubkey,
+    pub destination: Pubkey,
+    pub lamports: u64,
+    pub token_amount: u64,
+}
+
+#[event]
+pub struct VariableSet {
+    pub state: Pubkey,
+    pub name: String,
+    pub value: i64,
+    pub updated_slot: u64,
+}
@@ -0,0 +1,321 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+
+use crate::bonsol::{decode_outcome, is_execution_request, parse_journal, verify_input_digest};
+use crate::error::CalculatorError;
+use crate::events::{CalculationCompleted, CalculationFailed, JournalStored};
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct Callback<'info> {
+    /// Bonsol's execution request PDA of the record's requester and execution
+    /// ID, which Bonsol signs the callback with
+    #[account(constraint = is_execution_request(&record, &execution.key()) @ CalculatorError::UnauthorizedCallback)]
+    pub execution: Signer<'info>,
+
+    #[account(mut)]
+    pub state: Account<'info, CalculatorState>,
+
+    // The record PDA was registered in the callback config, so no lookup is needed
+    #[account(
+        mut,
+        has_one = state @ CalculatorError::InvalidRecord,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &record.index.to_le_bytes()],
+        bump = record.bump,
+    )]
+    pub record: Account<'info, CalculationRecord>,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Callback<'info>>,
+    execution_id: String,
+    journal: Vec<u8>,
+) -> Result<()> {
+    msg!("Callback received for execution ID: {}", execution_id);
+    // Records prove a single operation or expression; batch proofs, and the
+    // Merkle roots they commit, are for direct Bonsol executions
+    if matches!(journal.first(), Some(&(JOURNAL_BATCH_VERSION_1 | JOURNAL_BATCH_VERSION_2))) {
+        msg!("Batch journals are not accepted; the callback takes single-result journals");
+        return err!(CalculatorError::InvalidJournal);
+    }
+    require!(journal.len() <= MAX_JOURNAL_LEN, CalculatorError::JournalTooLong);
+
+    let record = &mut ctx.accounts.record;
+    if record.execution_id != execution_id {
+        msg!("Warning: Execution ID mismatch in callback");
+        return Ok(());
+    }
+    if record.is_complete || record.pending_journal.is_some() {
+        msg!("Warning: Calculation #{} already received its callback", record.index);
+        return Ok(());
+    }
+    if record.retried {
+        msg!("Warning: Calculation #{} was superseded by a retry", record.index);
+        return Ok(());
+    }
+    let guest_journal = parse_journal(&journal)?;
+    verify_input_digest(record, &guest_journal)?;
+    if let Some(cycles) = guest_journal.cycles {
+        msg!("Guest cycles: {}", cycles);
+    }
+    if let Some(applied @ 1..) = guest_journal.semantics {
+        msg!("Overflow semantics applied: {:#04b}", applied);
+    }
+
+    // The prover's work ends when the callback lands, even if finalization comes later
+    record.completed_slot = Some(Clock::get()?.slot);
+    if let Some(latency) = record.proving_latency_slots() {
+        msg!("Proving latency: {} slots (submitted at {})", latency, record.submitted_slot);
+    }
+
+    if record.manual_finalize {
+        // Park the raw journal until the owner finalizes it
+        record.pending_journal = Some(journal);
+        emit!(JournalStored {
+            state: ctx.accounts.state.key(),
+            record: record.key(),
+            execution_id,
+        });
+        msg!("Journal stored, awaiting finalize_calculation by owner");
+        return Ok(());
+    }
+
+    let outcome = decode_outcome(&journal)?;
+    ctx.accounts.record.journal = Some(journal);
+    let result = match outcome {
+        Ok(result) => result,
+        Err(reason) => {
+            msg!("❌ ZK computation failed: {:?}", reason);
+            return fail_calculation(&mut ctx.accounts.state, &mut ctx.accounts.record, reason);
+        }
+    };
+    msg!("ZK computation result: {}", result);
+
+    complete_calculation(
+        &mut ctx.accounts.state,
+        &mut ctx.accounts.record,
+        result,
+        ctx.remaining_accounts,
+    )
+}
+
+/// Commits `result` to a calculation record, updates state, pays the referral
+/// share and runs the result hook
+///
+/// `remaining_accounts` are the forwarded callback accounts, followed by the
+/// hook program, the treasury + referrer and the memo program when the record
+/// has them.
+pub(crate) fn complete_calculation<'info>(
+    state: &mut Account<'info, CalculatorState>,
+    record: &mut Account<'info, CalculationRecord>,
+    result: i64,
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    if let Some(bounds) = record.result_bounds {
+        if !bounds.contains(result) {
+            msg!("❌ Result {} outside accepted bounds [{}, {}], marking calculation failed",
+                 result, bounds.min, bounds.max);
+            return fail_calculation(state, record, FailureReason::ResultOutOfBounds);
+        }
+    }
+
+    let remaining = &mut remaining_accounts.iter();
+    let forwarded_accounts = verify_forwarded_accounts(&record.callback_accounts, remaining)?;
+    let hook_program = match record.result_hook {
+        Some(_) => Some(remaining.next().ok_or(ErrorCode::AccountNotEnoughKeys)?),
+        None => None,
+    };
+
+    record.result = Some(result);
+    record.is_complete = true;
+    msg!("✅ ZK computation completed: {} = {}", record.describe(), result);
+    if let Some(ref label) = record.label {
+        msg!("Label: {}", label);
+    }
+
+    state.pending_count = state.pending_count.saturating_sub(1);
+
+    if let Some(referrer) = record.referrer {
+        let treasury = remaining.next().ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        let referrer_account = remaining.next().ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        let expected_treasury = Pubkey::create_program_address(
+            &[TREASURY_SEED, state.key().as_ref(), &[state.treasury_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(CalculatorError::InvalidRecord))?;
+        require_keys_eq!(treasury.key(), expected_treasury, ErrorCode::ConstraintSeeds);
+        require_keys_eq!(referrer_account.key(), referrer, CalculatorError::ReferrerMismatch);
+        record.referral_lamports = pay_referral(record, treasury, referrer_account)?;
+    }
+
+    let memo_program = match record.emit_memo {
+        true => Some(remaining.next().ok_or(ErrorCode::AccountNotEnoughKeys)?),
+        false => None,
+    };
+
+    emit!(CalculationCompleted {
+        state: state.key(),
+        record: record.key(),
+        execution_id: record.execution_id.clone(),
+        result,
+        latency_slots: record.proving_latency_slots(),
+        label: record.label.clone(),
+    });
+
+    // Persist updated record and state before handing control to the hook program
+    record.exit(&crate::ID)?;
+    state.exit(&crate::ID)?;
+
+    if let Some(memo_program) = memo_program {
+        let memo = format!("calc: {} = {} (exec {})", record.describe(), result, record.execution_id);
+        emit_memo(memo_program, &memo)?;
+    }
+
+    if let (Some(hook_program_id), Some(hook_program)) = (record.result_hook, hook_program) {
+        let hook_data = ResultHookData {
+            execution_id: record.execution_id.clone(),
+            operation: record.operation,
+            operand_a: record.operand_a,
+            operand_b: record.operand_b,
+            result,
+        };
+        invoke_result_hook(
+            &hook_program_id,
+            hook_program,
+            &state.to_account_info(),
+            &forwarded_accounts,
+            &hook_data,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Completes a calculation without a result, recording why
+///
+/// Neither the result hook nor the referral payout runs for failed calculations.
+pub(crate) fn fail_calculation(
+    state: &mut Account<CalculatorState>,
+    record: &mut Account<CalculationRecord>,
+    reason: FailureReason,
+) -> Result<()> {
+    record.result = None;
+    record.is_complete = true;
+    record.failure = Some(reason);
+    state.pending_count = state.pending_count.saturating_sub(1);
+
+    emit!(CalculationFailed {
+        state: state.key(),
+        record: record.key(),
+        execution_id: record.execution_id.clone(),
+        reason,
+        label: record.label.clone(),
+    });
+
+    msg!("Calculation #{} failed: {:?}", record.index, reason);
+    Ok(())
+}
+
+/// Moves the referrer's share of a calculation's lamport fee out of the treasury
+///
+/// The payout never dips into the treasury's rent-exempt minimum.
+fn pay_referral<'info>(
+    record: &CalculationRecord,
+    treasury: &AccountInfo<'info>,
+    referrer_account: &AccountInfo<'info>,
+) -> Result<u64> {
+    let share = (u128::from(record.fee_lamports) * u128::from(record.referral_share_bps)
+        / u128::from(BPS_DENOMINATOR)) as u64;
+    let available = treasury
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(treasury.data_len()));
+    let amount = share.min(available);
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    treasury.sub_lamports(amount)?;
+    referrer_account.add_lamports(amount)?;
+
+    msg!("Paid referral share: {} lamports to {}", amount, referrer_account.key);
+    Ok(amount)
+}
+
+/// Logs `memo` through the SPL Memo program so wallets and explorers show it
+fn emit_memo<'info>(memo_program: &AccountInfo<'info>, memo: &str) -> Result<()> {
+    require_keys_eq!(memo_program.key(), MEMO_PROGRAM_ID, ErrorCode::InvalidProgramId);
+
+    invoke(
+        &Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: vec![],
+            data: memo.as_bytes().to_vec(),
+        },
+        std::slice::from_ref(memo_program),
+    )?;
+    Ok(())
+}
+
+/// Checks that the leading remaining accounts match the ones persisted at submission
+fn verify_forwarded_accounts<'info>(
+    expected: &[CallbackAccount],
+    remaining: &mut std::slice::Iter<'info, AccountInfo<'info>>,
+) -> Result<Vec<&'info AccountInfo<'info>>> {
+    let mut forwarded_accounts = Vec::with_capacity(expected.len());
+    for account in expected {
+        let forwarded = remaining.next().ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        require_keys_eq!(forwarded.key(), account.pubkey, CalculatorError::CallbackAccountMismatch);
+        require!(
+            !account.is_writable || forwarded.is_writable,
+            CalculatorError::CallbackAccountMismatch
+        );
+        forwarded_accounts.push(forwarded);
+    }
+
+    Ok(forwarded_accounts)
+}
+
+/// Forwards a completed result to the program registered at submission
+///
+/// The hook receives the state account (read-only) followed by the forwarded
+/// callback accounts, and `RESULT_HOOK_DISCRIMINATOR` + Borsh `ResultHookData`
+/// as instruction data.
+fn invoke_result_hook<'info>(
+    hook_program_id: &Pubkey,
+    hook_program: &AccountInfo<'info>,
+    state: &AccountInfo<'info>,
+    forwarded_accounts: &[&AccountInfo<'info>],
+    hook_data: &ResultHookData,
+) -> Result<()> {
+    require!(
+        hook_program.key == hook_program_id && hook_program.executable,
+        CalculatorError::ResultHookMismatch
+    );
+
+    let mut account_metas = vec![AccountMeta::new_readonly(state.key(), false)];
+    let mut account_infos = vec![state.clone()];
+    for account in forwarded_accounts {
+        account_metas.push(if account.is_writable {
+            AccountMeta::new(account.key(), false)
+        } else {
+            AccountMeta::new_readonly(account.key(), false)
+        });
+        account_infos.push((*account).clone());
+    }
+    account_infos.push(hook_program.clone());
+
+    let mut data = RESULT_HOOK_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&hook_data.try_to_vec()?);
+
+    msg!("Forwarding result to hook program: {}", hook_program_id);
+    invoke(
+        &Instruction {
+            program_id: *hook_program_id,
+            accounts: account_metas,
+            data,
+        },
+        &account_infos,
+    )?;
+    Ok(())
+}
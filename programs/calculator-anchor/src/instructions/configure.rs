@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::error::CalculatorError;
+use crate::events::ConfigUpdated;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct Configure<'info> {
+    #[account(mut)]
+    pub state: Account<'info, CalculatorState>,
+}
+
+pub fn handler(ctx: Context<Configure>, config: CalculatorConfig) -> Result<()> {
+    ctx.accounts.state.verify_owner_signers(ctx.remaining_accounts)?;
+    require!(
+        u64::from(config.referral_share_bps) <= BPS_DENOMINATOR,
+        CalculatorError::InvalidReferralShare
+    );
+
+    msg!(
+        "Updating config: fee {} lamports, open submissions: {}",
+        config.fee_lamports,
+        config.open_submissions
+    );
+    if let Some(ref token_fee) = config.token_fee {
+        msg!("Token fee: {} of mint {}", token_fee.amount, token_fee.mint);
+    }
+
+    emit!(ConfigUpdated {
+        state: ctx.accounts.state.key(),
+        fee_lamports: config.fee_lamports,
+        open_submissions: config.open_submissions,
+        referral_share_bps: config.referral_share_bps,
+    });
+
+    ctx.accounts.state.config = config;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use super::callback::{complete_calculation, fail_calculation};
+use crate::bonsol::decode_outcome;
+use crate::error::CalculatorError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct FinalizeCalculation<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut, constraint = state.is_owner(&owner.key()) @ CalculatorError::Unauthorized)]
+    pub state: Account<'info, CalculatorState>,
+
+    #[account(
+        mut,
+        has_one = state @ CalculatorError::InvalidRecord,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &record.index.to_le_bytes()],
+        bump = record.bump,
+    )]
+    pub record: Account<'info, CalculationRecord>,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FinalizeCalculation<'info>>,
+    execution_id: String,
+) -> Result<()> {
+    let record = &mut ctx.accounts.record;
+    require!(record.execution_id == execution_id, CalculatorError::ExecutionIdMismatch);
+
+    let journal = record
+        .pending_journal
+        .take()
+        .ok_or(error!(CalculatorError::NoPendingJournal))?;
+
+    let outcome = decode_outcome(&journal)?;
+    record.journal = Some(journal);
+    let result = match outcome {
+        Ok(result) => result,
+        Err(reason) => {
+            msg!("❌ ZK computation failed: {:?}", reason);
+            return fail_calculation(&mut ctx.accounts.state, &mut ctx.accounts.record, reason);
+        }
+    };
+    msg!("Finalizing ZK computation result: {}", result);
+
+    complete_calculation(
+        &mut ctx.accounts.state,
+        &mut ctx.accounts.record,
+        result,
+        ctx.remaining_accounts,
+    )
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use calculator_ops::Operation;
+
+use crate::error::CalculatorError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct GetHistory<'info> {
+    pub state: Account<'info, CalculatorState>,
+}
+
+/// Remaining accounts: the record PDAs of the window in index order; pruned
+/// records are passed at their (closed) PDA addresses and skipped
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, GetHistory<'info>>,
+    offset: u32,
+    limit: u8,
+) -> Result<HistorySnapshot> {
+    let state = &ctx.accounts.state;
+
+    let start = u64::from(offset).min(state.calculation_count);
+    let end = (start + u64::from(limit.min(MAX_HISTORY_LIMIT))).min(state.calculation_count);
+
+    msg!("Calculator History:");
+    msg!("Total calculations: {}", state.calculation_count);
+    msg!("Pending calculations: {}", state.pending_count);
+    msg!("Showing calculations #{}..#{}", start, end);
+
+    let mut snapshot = HistorySnapshot {
+        calculation_count: state.calculation_count,
+        pending_count: state.pending_count,
+        offset,
+        entries: Vec::new(),
+    };
+
+    let records = &mut ctx.remaining_accounts.iter();
+    for index in start..end {
+        let record_info = records.next().ok_or(ErrorCode::AccountNotEnoughKeys)?;
+        if record_info.owner != ctx.program_id {
+            let (record_address, _) = Pubkey::find_program_address(
+                &[CALCULATION_SEED, state.key().as_ref(), &index.to_le_bytes()],
+                ctx.program_id,
+            );
+            require_keys_eq!(record_address, record_info.key(), CalculatorError::InvalidRecord);
+            msg!("Calculation #{}: (pruned)", index);
+            continue;
+        }
+
+        let calculation = Account::<CalculationRecord>::try_from(record_info)?;
+        require!(calculation.index == index, CalculatorError::InvalidRecord);
+        let expected = Pubkey::create_program_address(
+            &[
+                CALCULATION_SEED,
+                state.key().as_ref(),
+                &calculation.index.to_le_bytes(),
+                &[calculation.bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| error!(CalculatorError::InvalidRecord))?;
+        require_keys_eq!(calculation.state, state.key(), CalculatorError::InvalidRecord);
+        require_keys_eq!(expected, record_info.key(), CalculatorError::InvalidRecord);
+        snapshot.entries.push(HistoryEntry::from(&*calculation));
+
+        let operation = Operation { op: calculation.operation, a: calculation.operand_a, b: calculation.operand_b };
+        if let Some(reason) = calculation.failure {
+            msg!("Calculation #{}: {} = (failed: {:?})", calculation.index, operation, reason);
+        } else if calculation.is_local {
+            msg!("Calculation #{}: {} = {} (local)", calculation.index, operation,
+                 calculation.result.unwrap_or(0));
+        } else if calculation.is_complete {
+            msg!("Calculation #{}: {} = {}", calculation.index, operation,
+                 calculation.result.unwrap_or(0));
+        } else if calculation.pending_journal.is_some() {
+            msg!("Calculation #{}: {} = (awaiting finalize...)", calculation.index, operation);
+        } else {
+            msg!("Calculation #{}: {} = (pending...)", calculation.index, operation);
+        }
+        if !calculation.expression.is_empty() {
+            msg!("  Expression: {}", format_expression(&calculation.expression));
+        }
+        if let Some(ref label) = calculation.label {
+            msg!("  Label: {}", label);
+        }
+    }
+
+    Ok(snapshot)
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(init, payer = payer, space = 8 + CalculatorState::INIT_SPACE)]
+    pub state: Account<'info, CalculatorState>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [TREASURY_SEED, state.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+pub fn handler(ctx: Context<Initialize>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    state.owners = vec![ctx.accounts.payer.key()];
+    state.threshold = 1;
+    state.calculation_count = 0;
+    state.pending_count = 0;
+    state.treasury_bump = ctx.bumps.treasury;
+    state.config = CalculatorConfig::default();
+    state.image_id = CALCULATOR_IMAGE_ID.to_string();
+    state.paused = false;
+
+    msg!("Calculator backend initialized for owner: {}", ctx.accounts.payer.key());
+    Ok(())
+}
@@ -0,0 +1,34 @@
+// Every instruction module exposes a `handler`; only the account contexts are
+// meant to be reached through the glob re-exports.
+#![allow(ambiguous_glob_reexports)]
+
+pub mod callback;
+pub mod configure;
+pub mod finalize_calculation;
+pub mod get_history;
+pub mod initialize;
+pub mod pause;
+pub mod prune_history;
+pub mod retry_calculation;
+pub mod set_image_id;
+pub mod set_owners;
+pub mod set_variable;
+pub mod submit_calculation;
+pub mod submit_expression;
+pub mod submit_local_calculation;
+pub mod withdraw_fees;
+
+pub use callback::*;
+pub use configure::*;
+pub use finalize_calculation::*;
+pub use get_history::*;
+pub use initialize::*;
+pub use pause::*;
+pub use prune_history::*;
+pub use retry_calculation::*;
+pub use set_image_id::*;
+pub use set_owners::*;
+pub use set_variable::*;
+pub use submit_calculation::*;
+pub use submit_local_calculation::*;
+pub use withdraw_fees::*;
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+
+use crate::events::PausedChanged;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    #[account(mut)]
+    pub state: Account<'info, CalculatorState>,
+}
+
+/// Callbacks for executions already in flight are still processed while paused
+pub fn handler(ctx: Context<Pause>, paused: bool) -> Result<()> {
+    ctx.accounts.state.verify_owner_signers(ctx.remaining_accounts)?;
+
+    let state = &mut ctx.accounts.state;
+    state.paused = paused;
+
+    emit!(PausedChanged { state: state.key(), paused });
+    msg!("Calculator {}", if paused { "paused" } else { "resumed" });
+    Ok(())
+}
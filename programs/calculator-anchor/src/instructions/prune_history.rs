@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::error::CalculatorError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct PruneHistory<'info> {
+    pub state: Account<'info, CalculatorState>,
+
+    /// Owner signer that receives the rent of closed records
+    #[account(mut, constraint = state.is_owner(&owner.key()) @ CalculatorError::Unauthorized)]
+    pub owner: Signer<'info>,
+}
+
+/// Remaining accounts: co-signing owners, then the record PDAs to prune
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PruneHistory<'info>>,
+    before_timestamp: i64,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let mut signers = vec![ctx.accounts.owner.to_account_info()];
+    signers.extend(ctx.remaining_accounts.iter().filter(|account| account.is_signer).cloned());
+    state.verify_owner_signers(&signers)?;
+
+    let mut pruned = 0u64;
+    let mut refunded = 0u64;
+    for record_info in ctx.remaining_accounts.iter().filter(|account| !account.is_signer) {
+        let calculation = Account::<CalculationRecord>::try_from(record_info)?;
+        require_keys_eq!(calculation.state, state.key(), CalculatorError::InvalidRecord);
+        if !calculation.is_complete || calculation.timestamp >= before_timestamp {
+            msg!("Skipping calculation #{}: pending or too recent", calculation.index);
+            continue;
+        }
+
+        refunded += record_info.lamports();
+        calculation.close(ctx.accounts.owner.to_account_info())?;
+        pruned += 1;
+    }
+
+    msg!("Pruned {} calculation records, refunded {} lamports to {}",
+         pruned, refunded, ctx.accounts.owner.key());
+    Ok(())
+}
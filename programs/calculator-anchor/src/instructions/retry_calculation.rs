@@ -0,0 +1,171 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+use bonsol_interface::util::deployment_address;
+use calculator_common::derive_retry_execution_id;
+
+use crate::bonsol::request_execution;
+use crate::error::CalculatorError;
+use crate::events::CalculationRetried;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(execution_id: String)]
+pub struct RetryCalculation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !state.paused @ CalculatorError::Paused,
+        constraint = state.config.open_submissions || state.is_owner(&payer.key()) @ CalculatorError::Unauthorized,
+    )]
+    pub state: Account<'info, CalculatorState>,
+
+    #[account(
+        mut,
+        has_one = state @ CalculatorError::InvalidRecord,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &original_record.index.to_le_bytes()],
+        bump = original_record.bump,
+        constraint = original_record.execution_id == execution_id @ CalculatorError::ExecutionIdMismatch,
+    )]
+    pub original_record: Account<'info, CalculationRecord>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CalculationRecord::INIT_SPACE,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &state.calculation_count.to_le_bytes()],
+        bump,
+    )]
+    pub record: Account<'info, CalculationRecord>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The original calculation's requester, which the retry stays attributed to
+    #[account(mut, address = original_record.requester @ CalculatorError::RequesterMismatch)]
+    pub requester: UncheckedAccount<'info>,
+
+    /// CHECK: Bonsol deployment account of the configured image
+    #[account(
+        address = deployment_address(&state.image_id).0 @ CalculatorError::InvalidDeploymentAccount,
+    )]
+    pub deployment: UncheckedAccount<'info>,
+
+    /// CHECK: Bonsol execution request PDA of the requester and retry execution ID, created by Bonsol
+    #[account(mut)]
+    pub execution: UncheckedAccount<'info>,
+
+    /// The calculator program, which Bonsol calls back
+    pub calculator_program: Program<'info, crate::program::CalculatorAnchor>,
+
+    /// CHECK: The Bonsol program the execution request is sent to
+    #[account(address = bonsol_interface::ID)]
+    pub bonsol_program: UncheckedAccount<'info>,
+}
+
+pub fn handler(ctx: Context<RetryCalculation>, execution_id: String) -> Result<()> {
+    let original = &ctx.accounts.original_record;
+    require!(
+        !original.is_complete && original.pending_journal.is_none() && !original.retried,
+        CalculatorError::NotRetryable
+    );
+
+    let clock = Clock::get()?;
+    if clock.slot <= original.expiration_slot {
+        msg!("Execution {} has not expired yet (expires at slot {})", execution_id, original.expiration_slot);
+        return err!(CalculatorError::NotExpired);
+    }
+
+    let state_key = ctx.accounts.state.key();
+    let record_key = ctx.accounts.record.key();
+    let index = ctx.accounts.state.calculation_count;
+    let retry_count = original
+        .retry_count
+        .checked_add(1)
+        .ok_or(error!(CalculatorError::RetryLimitReached))?;
+    let retry_execution_id = derive_retry_execution_id(&original.execution_id, retry_count);
+    let treasury = Pubkey::create_program_address(
+        &[TREASURY_SEED, state_key.as_ref(), &[ctx.accounts.state.treasury_bump]],
+        &crate::ID,
+    )
+    .map_err(|_| error!(ErrorCode::ConstraintSeeds))?;
+
+    let retry = CalculationRecord {
+        state: state_key,
+        index,
+        bump: ctx.bumps.record,
+        execution_id: retry_execution_id.clone(),
+        operation: original.operation,
+        operand_a: original.operand_a,
+        operand_b: original.operand_b,
+        result: None,
+        timestamp: clock.unix_timestamp,
+        is_complete: false,
+        callback_accounts: original.callback_accounts.clone(),
+        result_hook: original.result_hook,
+        manual_finalize: original.manual_finalize,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: None,
+        fee_lamports: original.fee_lamports,
+        token_fee_amount: original.token_fee_amount,
+        referrer: original.referrer,
+        referral_share_bps: original.referral_share_bps,
+        referral_lamports: 0,
+        expiration_slot: clock.slot + EXPIRATION_SLOTS,
+        parent_execution_id: Some(original.execution_id.clone()),
+        retry_count,
+        retried: false,
+        result_bounds: original.result_bounds,
+        failure: None,
+        is_local: false,
+        emit_memo: original.emit_memo,
+        requester: original.requester,
+        payer: ctx.accounts.payer.key(),
+        prover_version: original.prover_version,
+        label: original.label.clone(),
+        journal: None,
+        expression: original.expression.clone(),
+        operand_a_variable: original.operand_a_variable.clone(),
+        operand_b_variable: original.operand_b_variable.clone(),
+        input_hash: original.input_hash,
+    };
+
+    request_execution(
+        &ctx.accounts.payer.key(),
+        &state_key,
+        &record_key,
+        &treasury,
+        &ctx.accounts.state.image_id,
+        &retry,
+        &[
+            ctx.accounts.requester.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.execution.to_account_info(),
+            ctx.accounts.deployment.to_account_info(),
+            ctx.accounts.calculator_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.bonsol_program.to_account_info(),
+        ],
+    )?;
+
+    ctx.accounts.original_record.retried = true;
+    ctx.accounts.record.set_inner(retry);
+
+    // The original stays counted as pending through its retry
+    ctx.accounts.state.calculation_count += 1;
+
+    emit!(CalculationRetried {
+        state: state_key,
+        record: record_key,
+        parent_execution_id: execution_id.clone(),
+        execution_id: retry_execution_id.clone(),
+        retry_count,
+    });
+
+    set_return_data(retry_execution_id.as_bytes());
+    msg!("Retrying execution {} as {} (retry #{})", execution_id, retry_execution_id, retry_count);
+    msg!("Record: {} (calculation #{})", record_key, index);
+
+    Ok(())
+}
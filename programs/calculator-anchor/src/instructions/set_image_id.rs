@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::error::CalculatorError;
+use crate::events::ImageIdUpdated;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SetImageId<'info> {
+    #[account(mut)]
+    pub state: Account<'info, CalculatorState>,
+}
+
+pub fn handler(ctx: Context<SetImageId>, image_id: String) -> Result<()> {
+    ctx.accounts.state.verify_owner_signers(ctx.remaining_accounts)?;
+    require!(
+        image_id.len() == IMAGE_ID_LEN && image_id.bytes().all(|byte| byte.is_ascii_hexdigit()),
+        CalculatorError::InvalidImageId
+    );
+
+    let state = &mut ctx.accounts.state;
+    msg!("Updating image ID: {} -> {}", state.image_id, image_id);
+    state.image_id = image_id.clone();
+
+    emit!(ImageIdUpdated { state: state.key(), image_id });
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::CalculatorError;
+use crate::events::OwnersUpdated;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SetOwners<'info> {
+    #[account(mut)]
+    pub state: Account<'info, CalculatorState>,
+}
+
+pub fn handler(ctx: Context<SetOwners>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+    ctx.accounts.state.verify_owner_signers(ctx.remaining_accounts)?;
+    require!(
+        !owners.is_empty()
+            && owners.len() <= MAX_OWNERS
+            && !owners.iter().enumerate().any(|(i, owner)| owners[..i].contains(owner)),
+        CalculatorError::InvalidOwners
+    );
+    require!(
+        threshold > 0 && usize::from(threshold) <= owners.len(),
+        CalculatorError::InvalidThreshold
+    );
+
+    let state = &mut ctx.accounts.state;
+    msg!("Updating owners: {}-of-{}", threshold, owners.len());
+    state.owners = owners.clone();
+    state.threshold = threshold;
+
+    emit!(OwnersUpdated { state: state.key(), owners, threshold });
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::error::CalculatorError;
+use crate::events::VariableSet;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetVariable<'info> {
+    pub state: Account<'info, CalculatorState>,
+
+    /// Owner signer that pays rent for a new variable
+    #[account(mut, constraint = state.is_owner(&owner.key()) @ CalculatorError::Unauthorized)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Variable::INIT_SPACE,
+        seeds = [VARIABLE_SEED, state.key().as_ref(), name.as_bytes()],
+        bump,
+    )]
+    pub variable: Account<'info, Variable>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Remaining accounts: co-signing owners
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SetVariable<'info>>,
+    name: String,
+    value: i64,
+) -> Result<()> {
+    let mut signers = vec![ctx.accounts.owner.to_account_info()];
+    signers.extend(ctx.remaining_accounts.iter().cloned());
+    ctx.accounts.state.verify_owner_signers(&signers)?;
+    require!(
+        !name.is_empty() && name.len() <= MAX_VARIABLE_NAME_LEN,
+        CalculatorError::InvalidVariableName
+    );
+
+    let slot = Clock::get()?.slot;
+    let variable = &mut ctx.accounts.variable;
+    variable.state = ctx.accounts.state.key();
+    variable.name = name.clone();
+    variable.bump = ctx.bumps.variable;
+    variable.value = value;
+    variable.updated_slot = slot;
+
+    msg!("Set variable {:?} = {}", name, value);
+    emit!(VariableSet {
+        state: ctx.accounts.state.key(),
+        name,
+        value,
+        updated_slot: slot,
+    });
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, Token, TokenAccount};
+use bonsol_interface::util::deployment_address;
+use calculator_common::derive_execution_id;
+
+use crate::bonsol::request_execution;
+use crate::error::CalculatorError;
+use crate::events::CalculationSubmitted;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SubmitCalculation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Account the calculation is attributed to; must sign only when
+    /// `config.requester_must_sign` is set
+    #[account(
+        mut,
+        constraint = !state.config.requester_must_sign || requester.is_signer
+            @ CalculatorError::RequesterSignatureRequired,
+    )]
+    pub requester: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = !state.paused @ CalculatorError::Paused,
+        constraint = state.config.open_submissions || state.is_owner(&payer.key()) @ CalculatorError::Unauthorized,
+    )]
+    pub state: Account<'info, CalculatorState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CalculationRecord::INIT_SPACE,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &state.calculation_count.to_le_bytes()],
+        bump,
+    )]
+    pub record: Account<'info, CalculationRecord>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(mut, seeds = [TREASURY_SEED, state.key().as_ref()], bump = state.treasury_bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RequesterStats::INIT_SPACE,
+        seeds = [REQUESTER_SEED, state.key().as_ref(), requester.key().as_ref()],
+        bump,
+    )]
+    pub requester_stats: Account<'info, RequesterStats>,
+
+    /// CHECK: Bonsol deployment account of the configured image, verified in
+    /// the handler so a missing deployment surfaces as `ImageNotDeployed`
+    #[account(
+        address = deployment_address(&state.image_id).0 @ CalculatorError::InvalidDeploymentAccount,
+    )]
+    pub deployment: UncheckedAccount<'info>,
+
+    /// CHECK: Bonsol execution request PDA of the requester and execution ID,
+    /// created by Bonsol; for a derived execution ID, the one for the slot the
+    /// transaction lands in
+    #[account(mut)]
+    pub execution: UncheckedAccount<'info>,
+
+    /// The calculator program, which Bonsol calls back
+    pub calculator_program: Program<'info, crate::program::CalculatorAnchor>,
+
+    /// CHECK: The Bonsol program the execution request is sent to
+    #[account(address = bonsol_interface::ID)]
+    pub bonsol_program: UncheckedAccount<'info>,
+
+    /// Variable named by `operand_a_variable`
+    pub operand_a_variable: Option<Account<'info, Variable>>,
+
+    /// Variable named by `operand_b_variable`
+    pub operand_b_variable: Option<Account<'info, Variable>>,
+
+    /// Source of the token fee, required when `config.token_fee` is set
+    #[account(mut)]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// The treasury's associated token account for the fee mint
+    #[account(mut)]
+    pub fee_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handler(
+    ctx: Context<SubmitCalculation>,
+    execution_id: Option<String>,
+    operation: i64,
+    operand_a: i64,
+    operand_b: i64,
+    callback_accounts: Vec<CallbackAccount>,
+    result_hook: Option<Pubkey>,
+    manual_finalize: bool,
+    referrer: Option<Pubkey>,
+    result_bounds: Option<ResultBounds>,
+    prover_version: Option<ProverVersion>,
+    label: Option<String>,
+    expression: Vec<ExprToken>,
+    operand_a_variable: Option<String>,
+    operand_b_variable: Option<String>,
+) -> Result<()> {
+    if expression.is_empty() {
+        require!(calculator_ops::is_operation(operation), CalculatorError::InvalidOperation);
+    } else {
+        validate_expression(&expression)?;
+    }
+    if let Some(ref execution_id) = execution_id {
+        require!(execution_id.len() <= MAX_EXECUTION_ID_LEN, CalculatorError::ExecutionIdTooLong);
+    }
+    if let Some(ref label) = label {
+        require!(label.len() <= MAX_LABEL_LEN, CalculatorError::LabelTooLong);
+    }
+    let state_key = ctx.accounts.state.key();
+    let operand_a = match (&operand_a_variable, &ctx.accounts.operand_a_variable) {
+        (Some(name), Some(variable)) => variable.resolve(&state_key, name)?,
+        (None, _) => operand_a,
+        (Some(_), None) => return err!(CalculatorError::VariableMismatch),
+    };
+    let operand_b = match (&operand_b_variable, &ctx.accounts.operand_b_variable) {
+        (Some(name), Some(variable)) => variable.resolve(&state_key, name)?,
+        (None, _) => operand_b,
+        (Some(_), None) => return err!(CalculatorError::VariableMismatch),
+    };
+    let deployment = &ctx.accounts.deployment;
+    require!(
+        *deployment.owner == bonsol_interface::ID && !deployment.data_is_empty(),
+        CalculatorError::ImageNotDeployed
+    );
+    validate_callback_accounts(
+        &[ctx.accounts.state.key(), ctx.accounts.record.key()],
+        &callback_accounts,
+    )?;
+    require!(result_hook != Some(crate::ID), CalculatorError::InvalidResultHook);
+    if let Some(bounds) = result_bounds {
+        require!(bounds.min <= bounds.max, CalculatorError::InvalidResultBounds);
+    }
+
+    let record_key = ctx.accounts.record.key();
+    let index = ctx.accounts.state.calculation_count;
+    let clock = Clock::get()?;
+
+    let execution_id = match execution_id {
+        Some(execution_id) => execution_id,
+        None => {
+            let execution_id = derive_execution_id(&state_key, index, clock.slot);
+            msg!("Derived execution ID: {}", execution_id);
+            set_return_data(execution_id.as_bytes());
+            execution_id
+        }
+    };
+
+    let config = ctx.accounts.state.config.clone();
+    if config.fee_lamports > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            config.fee_lamports,
+        )?;
+        msg!("Charged protocol fee: {} lamports", config.fee_lamports);
+    }
+
+    let token_fee_amount = match config.token_fee {
+        Some(ref token_fee) if token_fee.amount > 0 => {
+            let (Some(payer_token_account), Some(fee_token_account), Some(token_program)) = (
+                &ctx.accounts.payer_token_account,
+                &ctx.accounts.fee_token_account,
+                &ctx.accounts.token_program,
+            ) else {
+                return err!(CalculatorError::InvalidTokenFeeAccounts);
+            };
+            require_keys_eq!(
+                fee_token_account.key(),
+                get_associated_token_address(&ctx.accounts.treasury.key(), &token_fee.mint),
+                CalculatorError::InvalidTokenFeeAccounts
+            );
+
+            token::transfer(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    token::Transfer {
+                        from: payer_token_account.to_account_info(),
+                        to: fee_token_account.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                token_fee.amount,
+            )?;
+            msg!("Charged token fee: {} of mint {}", token_fee.amount, token_fee.mint);
+            token_fee.amount
+        }
+        _ => 0,
+    };
+
+    let mut calculation = CalculationRecord {
+        state: state_key,
+        index,
+        bump: ctx.bumps.record,
+        execution_id: execution_id.clone(),
+        operation,
+        operand_a,
+        operand_b,
+        result: None,
+        timestamp: clock.unix_timestamp,
+        is_complete: false,
+        callback_accounts,
+        result_hook,
+        manual_finalize,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: None,
+        fee_lamports: config.fee_lamports,
+        token_fee_amount,
+        referrer,
+        referral_share_bps: if referrer.is_some() { config.referral_share_bps } else { 0 },
+        referral_lamports: 0,
+        expiration_slot: clock.slot + EXPIRATION_SLOTS,
+        parent_execution_id: None,
+        retry_count: 0,
+        retried: false,
+        result_bounds,
+        failure: None,
+        is_local: false,
+        emit_memo: config.emit_memo,
+        requester: ctx.accounts.requester.key(),
+        payer: ctx.accounts.payer.key(),
+        prover_version: prover_version.or(config.prover_version),
+        label: label.clone(),
+        journal: None,
+        expression,
+        operand_a_variable,
+        operand_b_variable,
+        input_hash: None,
+    };
+    calculation.input_hash = Some(hash(&calculation.input()).to_bytes());
+
+    request_execution(
+        &ctx.accounts.payer.key(),
+        &state_key,
+        &record_key,
+        &ctx.accounts.treasury.key(),
+        &ctx.accounts.state.image_id,
+        &calculation,
+        &[
+            ctx.accounts.requester.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.execution.to_account_info(),
+            ctx.accounts.deployment.to_account_info(),
+            ctx.accounts.calculator_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.bonsol_program.to_account_info(),
+        ],
+    )?;
+
+    ctx.accounts.record.set_inner(calculation);
+    ctx.accounts.requester_stats.record_submission(
+        state_key,
+        ctx.accounts.requester.key(),
+        ctx.bumps.requester_stats,
+        false,
+        clock.slot,
+    );
+
+    let state = &mut ctx.accounts.state;
+    state.calculation_count += 1;
+    state.pending_count += 1;
+
+    emit!(CalculationSubmitted {
+        state: state_key,
+        record: record_key,
+        index,
+        execution_id: execution_id.clone(),
+        operation,
+        operand_a,
+        operand_b,
+        submitted_slot: clock.slot,
+        label: label.clone(),
+    });
+
+    msg!("Submitted ZK execution request: {}", ctx.accounts.record.describe());
+    msg!("Execution ID: {}", execution_id);
+    msg!("Record: {} (calculation #{})", record_key, index);
+    if let Some(ref label) = label {
+        msg!("Label: {}", label);
+    }
+    if ctx.accounts.requester.key() != ctx.accounts.payer.key() {
+        msg!("Requested by {}, sponsored by {}", ctx.accounts.requester.key(), ctx.accounts.payer.key());
+    }
+    msg!("Awaiting ZK proof computation...");
+
+    Ok(())
+}
+
+/// Rejects forwarded accounts that are duplicated or collide with the accounts
+/// the callback already receives
+fn validate_callback_accounts(reserved_keys: &[Pubkey], callback_accounts: &[CallbackAccount]) -> Result<()> {
+    require!(
+        callback_accounts.len() <= MAX_CALLBACK_EXTRA_ACCOUNTS,
+        CalculatorError::TooManyCallbackAccounts
+    );
+    for (i, account) in callback_accounts.iter().enumerate() {
+        require!(
+            !reserved_keys.contains(&account.pubkey)
+                && !callback_accounts[..i].iter().any(|other| other.pubkey == account.pubkey),
+            CalculatorError::DuplicateCallbackAccount
+        );
+    }
+    Ok(())
+}
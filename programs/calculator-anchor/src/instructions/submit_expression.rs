@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::submit_calculation::{self, SubmitCalculation};
+use crate::state::*;
+
+/// Submits an RPN program with the `submit_calculation` defaults for every
+/// other option
+pub fn handler(ctx: Context<SubmitCalculation>, execution_id: Option<String>, tokens: Vec<ExprToken>) -> Result<()> {
+    submit_calculation::handler(
+        ctx,
+        execution_id,
+        OP_EXPRESSION,
+        0,
+        0,
+        Vec::new(),
+        None,
+        false,
+        None,
+        None,
+        None,
+        None,
+        tokens,
+        None,
+        None,
+    )
+}
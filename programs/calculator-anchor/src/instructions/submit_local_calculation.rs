@@ -0,0 +1,130 @@
+use anchor_lang::prelude::*;
+use calculator_common::derive_execution_id;
+use calculator_ops::Operation;
+
+use crate::error::CalculatorError;
+use crate::events::CalculationCompleted;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SubmitLocalCalculation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = !state.paused @ CalculatorError::Paused,
+        constraint = state.config.open_submissions || state.is_owner(&payer.key()) @ CalculatorError::Unauthorized,
+    )]
+    pub state: Account<'info, CalculatorState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CalculationRecord::INIT_SPACE,
+        seeds = [CALCULATION_SEED, state.key().as_ref(), &state.calculation_count.to_le_bytes()],
+        bump,
+    )]
+    pub record: Account<'info, CalculationRecord>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RequesterStats::INIT_SPACE,
+        seeds = [REQUESTER_SEED, state.key().as_ref(), payer.key().as_ref()],
+        bump,
+    )]
+    pub requester_stats: Account<'info, RequesterStats>,
+}
+
+pub fn handler(
+    ctx: Context<SubmitLocalCalculation>,
+    operation: i64,
+    operand_a: i64,
+    operand_b: i64,
+) -> Result<()> {
+    let limit = ctx.accounts.state.config.local_operand_limit;
+    require!(limit > 0, CalculatorError::LocalCalculationsDisabled);
+    require!(
+        operand_a.unsigned_abs() <= limit && operand_b.unsigned_abs() <= limit,
+        CalculatorError::OperandAboveLocalLimit
+    );
+    require!(calculator_ops::is_operation(operation), CalculatorError::InvalidOperation);
+    let result = compute_locally(operation, operand_a, operand_b)
+        .ok_or(error!(CalculatorError::LocalCalculationFailed))?;
+
+    let state_key = ctx.accounts.state.key();
+    let record_key = ctx.accounts.record.key();
+    let index = ctx.accounts.state.calculation_count;
+    let clock = Clock::get()?;
+    let execution_id = derive_execution_id(&state_key, index, clock.slot);
+
+    ctx.accounts.record.set_inner(CalculationRecord {
+        state: state_key,
+        index,
+        bump: ctx.bumps.record,
+        execution_id: execution_id.clone(),
+        operation,
+        operand_a,
+        operand_b,
+        result: Some(result),
+        timestamp: clock.unix_timestamp,
+        is_complete: true,
+        callback_accounts: Vec::new(),
+        result_hook: None,
+        manual_finalize: false,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: Some(clock.slot),
+        fee_lamports: 0,
+        token_fee_amount: 0,
+        referrer: None,
+        referral_share_bps: 0,
+        referral_lamports: 0,
+        expiration_slot: clock.slot,
+        parent_execution_id: None,
+        retry_count: 0,
+        retried: false,
+        result_bounds: None,
+        failure: None,
+        is_local: true,
+        emit_memo: false,
+        requester: ctx.accounts.payer.key(),
+        payer: ctx.accounts.payer.key(),
+        prover_version: None,
+        label: None,
+        journal: None,
+        expression: Vec::new(),
+        operand_a_variable: None,
+        operand_b_variable: None,
+        input_hash: None,
+    });
+
+    ctx.accounts.requester_stats.record_submission(
+        state_key,
+        ctx.accounts.payer.key(),
+        ctx.bumps.requester_stats,
+        true,
+        clock.slot,
+    );
+
+    // Never pending, so only the total moves
+    ctx.accounts.state.calculation_count += 1;
+
+    emit!(CalculationCompleted {
+        state: state_key,
+        record: record_key,
+        execution_id: execution_id.clone(),
+        result,
+        latency_slots: Some(0),
+        label: None,
+    });
+
+    msg!("Computed locally: {} = {}", Operation { op: operation, a: operand_a, b: operand_b }, result);
+    msg!("Execution ID: {}", execution_id);
+    msg!("Record: {} (calculation #{})", record_key, index);
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount};
+
+use crate::error::CalculatorError;
+use crate::events::FeesWithdrawn;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    pub state: Account<'info, CalculatorState>,
+
+    #[account(mut, seeds = [TREASURY_SEED, state.key().as_ref()], bump = state.treasury_bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    /// CHECK: any account may receive the withdrawn lamports
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    /// Token fees to sweep, owned by the treasury
+    #[account(mut, token::authority = treasury)]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+pub fn handler(ctx: Context<WithdrawFees>) -> Result<()> {
+    ctx.accounts.state.verify_owner_signers(ctx.remaining_accounts)?;
+
+    let treasury = &ctx.accounts.treasury;
+
+    // Keep the treasury rent exempt so it survives the sweep
+    let treasury_info = treasury.to_account_info();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(treasury_info.data_len());
+    let lamports = treasury_info.lamports().saturating_sub(rent_exempt_minimum);
+    if lamports > 0 {
+        treasury.sub_lamports(lamports)?;
+        ctx.accounts.destination.add_lamports(lamports)?;
+        msg!("Withdrew {} lamports of fees to {}", lamports, ctx.accounts.destination.key());
+    } else {
+        msg!("No lamport fees to withdraw");
+    }
+
+    let mut token_amount = 0;
+    if let Some(treasury_token_account) = &ctx.accounts.treasury_token_account {
+        let (Some(destination_token_account), Some(token_program)) =
+            (&ctx.accounts.destination_token_account, &ctx.accounts.token_program)
+        else {
+            return err!(CalculatorError::InvalidTokenFeeAccounts);
+        };
+
+        token_amount = treasury_token_account.amount;
+        if token_amount > 0 {
+            let state_key = ctx.accounts.state.key();
+            let signer_seeds: &[&[&[u8]]] =
+                &[&[TREASURY_SEED, state_key.as_ref(), &[ctx.accounts.state.treasury_bump]]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    token::Transfer {
+                        from: treasury_token_account.to_account_info(),
+                        to: destination_token_account.to_account_info(),
+                        authority: treasury.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                token_amount,
+            )?;
+            msg!("Withdrew {} tokens of mint {} to {}",
+                 token_amount, treasury_token_account.mint, destination_token_account.key());
+        } else {
+            msg!("No token fees to withdraw");
+        }
+    }
+
+    emit!(FeesWithdrawn {
+        state: ctx.accounts.state.key(),
+        destination: ctx.accounts.destination.key(),
+        lamports,
+        token_amount,
+    });
+
+    Ok(())
+}
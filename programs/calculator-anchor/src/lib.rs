@@ -0,0 +1,188 @@
+//! Anchor implementation of the Bonsol calculator program.
+//!
+//! Mirrors the raw `solana-program` backend in `solana-program/`: same
+//! instructions, PDA seeds and callback flow, with typed account contexts,
+//! errors and events so integrators can consume the generated IDL.
+
+use anchor_lang::prelude::*;
+
+pub mod bonsol;
+pub mod error;
+pub mod events;
+pub mod instructions;
+pub mod state;
+
+pub use instructions::*;
+pub use state::*;
+
+declare_id!("2zBRw2sEXvjskx7w1w9hqdFEMZWy7KipQ6jKPfwjpnL6");
+
+#[program]
+pub mod calculator_anchor {
+    use super::*;
+
+    /// Initialize calculator state and its fee treasury
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        instructions::initialize::handler(ctx)
+    }
+
+    /// Submit a calculation request to Bonsol ZK network
+    ///
+    /// Remaining accounts are unused; token fee accounts are passed through the
+    /// optional accounts of the context. `prover_version` pins the Bonsol
+    /// prover, falling back to `config.prover_version` when `None`.
+    /// The payer covers fees and rent while the calculation is attributed to
+    /// `requester`, which may be the payer itself. `label` is a UTF-8
+    /// description of at most `MAX_LABEL_LEN` bytes kept with the record.
+    /// `operand_a_variable` / `operand_b_variable` name variables whose
+    /// current values, read from the matching optional accounts, replace the
+    /// literal operands.
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit_calculation(
+        ctx: Context<SubmitCalculation>,
+        execution_id: Option<String>,
+        operation: i64,
+        operand_a: i64,
+        operand_b: i64,
+        callback_accounts: Vec<CallbackAccount>,
+        result_hook: Option<Pubkey>,
+        manual_finalize: bool,
+        referrer: Option<Pubkey>,
+        result_bounds: Option<ResultBounds>,
+        prover_version: Option<ProverVersion>,
+        label: Option<String>,
+        operand_a_variable: Option<String>,
+        operand_b_variable: Option<String>,
+    ) -> Result<()> {
+        instructions::submit_calculation::handler(
+            ctx,
+            execution_id,
+            operation,
+            operand_a,
+            operand_b,
+            callback_accounts,
+            result_hook,
+            manual_finalize,
+            referrer,
+            result_bounds,
+            prover_version,
+            label,
+            Vec::new(),
+            operand_a_variable,
+            operand_b_variable,
+        )
+    }
+
+    /// Log a window of calculation history, indices `offset..offset + limit`
+    ///
+    /// The window is clamped to the calculation count and `MAX_HISTORY_LIMIT`.
+    /// The returned `HistorySnapshot` is set as transaction return data.
+    pub fn get_history<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetHistory<'info>>,
+        offset: u32,
+        limit: u8,
+    ) -> Result<HistorySnapshot> {
+        instructions::get_history::handler(ctx, offset, limit)
+    }
+
+    /// Callback instruction from Bonsol when ZK computation completes
+    ///
+    /// Bonsol signs with the execution request PDA of the record's requester
+    /// and execution ID, so no one else can complete a calculation; fails
+    /// with `CalculatorError::UnauthorizedCallback` otherwise.
+    /// Remaining accounts: the callback accounts, result hook program,
+    /// treasury + referrer and memo program registered at submission.
+    pub fn callback<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Callback<'info>>,
+        execution_id: String,
+        journal: Vec<u8>,
+    ) -> Result<()> {
+        instructions::callback::handler(ctx, execution_id, journal)
+    }
+
+    /// Owner accepts a journal stored by a manual-finalize callback
+    ///
+    /// Remaining accounts follow the same layout as `callback`.
+    pub fn finalize_calculation<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeCalculation<'info>>,
+        execution_id: String,
+    ) -> Result<()> {
+        instructions::finalize_calculation::handler(ctx, execution_id)
+    }
+
+    /// Replace the calculator configuration
+    ///
+    /// Remaining accounts: owner signers meeting the threshold. The same
+    /// applies to every owner-only instruction except `finalize_calculation`.
+    pub fn configure(ctx: Context<Configure>, config: CalculatorConfig) -> Result<()> {
+        instructions::configure::handler(ctx, config)
+    }
+
+    /// Resubmit a calculation whose execution expired without a callback
+    pub fn retry_calculation(ctx: Context<RetryCalculation>, execution_id: String) -> Result<()> {
+        instructions::retry_calculation::handler(ctx, execution_id)
+    }
+
+    /// Sweep collected fees above the treasury's rent-exempt minimum
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        instructions::withdraw_fees::handler(ctx)
+    }
+
+    /// Compute a small calculation directly on-chain, without Bonsol
+    pub fn submit_local_calculation(
+        ctx: Context<SubmitLocalCalculation>,
+        operation: i64,
+        operand_a: i64,
+        operand_b: i64,
+    ) -> Result<()> {
+        instructions::submit_local_calculation::handler(ctx, operation, operand_a, operand_b)
+    }
+
+    /// Replace the guest image requested for new executions
+    pub fn set_image_id(ctx: Context<SetImageId>, image_id: String) -> Result<()> {
+        instructions::set_image_id::handler(ctx, image_id)
+    }
+
+    /// Stop or resume accepting submissions and retries
+    pub fn pause(ctx: Context<Pause>, paused: bool) -> Result<()> {
+        instructions::pause::handler(ctx, paused)
+    }
+
+    /// Replace the owner multisig
+    pub fn set_owners(ctx: Context<SetOwners>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        instructions::set_owners::handler(ctx, owners, threshold)
+    }
+
+    /// Close completed records older than `before_timestamp`, refunding rent
+    /// to the owner while keeping the state's counters
+    pub fn prune_history<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PruneHistory<'info>>,
+        before_timestamp: i64,
+    ) -> Result<()> {
+        instructions::prune_history::handler(ctx, before_timestamp)
+    }
+
+    /// Submit an arithmetic expression as an RPN program to Bonsol
+    ///
+    /// The guest evaluates `tokens` with checked math and proves the single
+    /// value left on the stack. Takes the `submit_calculation` accounts.
+    pub fn submit_expression(
+        ctx: Context<SubmitCalculation>,
+        execution_id: Option<String>,
+        tokens: Vec<ExprToken>,
+    ) -> Result<()> {
+        instructions::submit_expression::handler(ctx, execution_id, tokens)
+    }
+
+    /// Create or update a named variable usable as an operand
+    ///
+    /// Remaining accounts are co-signing owners; the owner account pays rent
+    /// for a new variable.
+    pub fn set_variable<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetVariable<'info>>,
+        name: String,
+        value: i64,
+    ) -> Result<()> {
+        instructions::set_variable::handler(ctx, name, value)
+    }
+}
@@ -0,0 +1,508 @@
+use anchor_lang::prelude::*;
+use calculator_ops::{arity, is_infix, is_operation, Operation};
+
+use crate::error::CalculatorError;
+
+// Calculator ZK program image ID, requested until `set_image_id` replaces it
+pub const CALCULATOR_IMAGE_ID: &str = "5881e972d41fe651c2989c65699528da8b1ed68ab7057350a686b8a64a00fc91";
+
+// Calculator operations, defined once in `calculator-ops` so the guest and
+// both programs number them the same way
+pub use calculator_ops::{
+    OPERATIONS, OP_ABS, OP_ADD, OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_DIVIDE, OP_GCD, OP_ISQRT, OP_LOG2, OP_MAX,
+    OP_MIN, OP_MODULO, OP_MULTIPLY, OP_POWER, OP_SUBTRACT,
+};
+
+// Operation code of records submitted through `submit_expression`; the guest
+// reads it as the first word of an expression input frame
+pub use calculator_ops::OP_EXPRESSION;
+
+// Frame and journal layouts, account limits and PDA seeds, defined once in
+// `calculator-common` so the guest, both programs and the client agree on them
+pub use calculator_common::constants::{
+    CALCULATION_SEED, IMAGE_ID_LEN, JOURNAL_BATCH_VERSION_1, JOURNAL_BATCH_VERSION_2, JOURNAL_METERED_V1_LEN,
+    JOURNAL_METERED_V2_LEN, JOURNAL_METERED_VERSION_1, JOURNAL_METERED_VERSION_2, JOURNAL_STATUS_DIV_BY_ZERO,
+    JOURNAL_STATUS_INVALID_OPERAND, JOURNAL_STATUS_MALFORMED_INPUT, JOURNAL_STATUS_OK, JOURNAL_STATUS_OVERFLOW,
+    JOURNAL_STATUS_UNKNOWN_OP, JOURNAL_V1_LEN, JOURNAL_VERSION_1, MAX_CALLBACK_EXTRA_ACCOUNTS, MAX_EXECUTION_ID_LEN,
+    MAX_EXPRESSION_TOKENS, MAX_JOURNAL_LEN, MAX_LABEL_LEN, MAX_OWNERS, MAX_STACK_DEPTH, MAX_VARIABLE_NAME_LEN,
+    REQUESTER_SEED, TREASURY_SEED, VARIABLE_SEED,
+};
+
+// Slots a Bonsol execution request stays claimable before it expires
+pub const EXPIRATION_SLOTS: u64 = 100;
+
+// Denominator for basis-point shares
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Instruction data prefix for result hook invocations
+pub const RESULT_HOOK_DISCRIMINATOR: [u8; 8] = *b"calchook";
+
+// SPL Memo program (v2), used for result summaries
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct CalculatorState {
+    /// Owner multisig; `threshold` of these keys must sign admin instructions
+    #[max_len(MAX_OWNERS)]
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub calculation_count: u64,
+    pub pending_count: u64,
+    pub treasury_bump: u8,
+    pub config: CalculatorConfig,
+    /// Guest image requested for new executions, replaced by `set_image_id`
+    #[max_len(IMAGE_ID_LEN)]
+    pub image_id: String,
+    /// While set, new submissions and retries are rejected
+    pub paused: bool,
+}
+
+impl CalculatorState {
+    pub fn is_owner(&self, key: &Pubkey) -> bool {
+        self.owners.contains(key)
+    }
+
+    /// Checks that the signing owners among `signers` meet the threshold
+    ///
+    /// Admin instructions take their owner signers as remaining accounts.
+    pub fn verify_owner_signers(&self, signers: &[AccountInfo]) -> Result<()> {
+        let mut signed: Vec<&Pubkey> = Vec::with_capacity(signers.len());
+        for signer in signers {
+            if signer.is_signer && self.is_owner(signer.key) && !signed.contains(&signer.key) {
+                signed.push(signer.key);
+            }
+        }
+        if signed.is_empty() || signed.len() < usize::from(self.threshold) {
+            msg!("{} of {} required owner signatures", signed.len(), self.threshold);
+            return err!(CalculatorError::Unauthorized);
+        }
+        Ok(())
+    }
+}
+
+/// Owner-controlled settings, replaced as a whole by `configure`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, Default)]
+pub struct CalculatorConfig {
+    /// Protocol fee transferred from the payer to the treasury on every submission
+    pub fee_lamports: u64,
+    /// When false only owners may submit calculations
+    pub open_submissions: bool,
+    /// SPL token fee transferred to the treasury's associated token account,
+    /// charged in addition to `fee_lamports`
+    pub token_fee: Option<TokenFee>,
+    /// Share of `fee_lamports`, in basis points, paid to a submission's referrer on completion
+    pub referral_share_bps: u16,
+    /// Largest operand magnitude `submit_local_calculation` computes on-chain; 0 disables it
+    pub local_operand_limit: u64,
+    /// Issue an SPL Memo summarizing each completed calculation
+    pub emit_memo: bool,
+    /// Require the requester to sign `submit_calculation`; otherwise a relayer
+    /// may pay for and submit calculations attributed to any requester
+    pub requester_must_sign: bool,
+    /// Prover version requested for submissions that don't pin one; `None`
+    /// leaves the choice to Bonsol
+    pub prover_version: Option<ProverVersion>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, PartialEq)]
+pub struct TokenFee {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// Data-less fee treasury, a PDA of the state account
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Treasury {}
+
+/// Per-calculation account, a PDA derived from the state account and the
+/// calculation's index
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct CalculationRecord {
+    pub state: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    #[max_len(MAX_EXECUTION_ID_LEN)]
+    pub execution_id: String,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: Option<i64>,
+    pub timestamp: i64,
+    pub is_complete: bool,
+    #[max_len(MAX_CALLBACK_EXTRA_ACCOUNTS)]
+    pub callback_accounts: Vec<CallbackAccount>,
+    pub result_hook: Option<Pubkey>,
+    pub manual_finalize: bool,
+    #[max_len(MAX_JOURNAL_LEN)]
+    pub pending_journal: Option<Vec<u8>>,
+    pub submitted_slot: u64,
+    pub completed_slot: Option<u64>,
+    pub fee_lamports: u64,
+    pub token_fee_amount: u64,
+    pub referrer: Option<Pubkey>,
+    pub referral_share_bps: u16,
+    pub referral_lamports: u64,
+    pub expiration_slot: u64,
+    /// Execution this calculation retries, if any
+    #[max_len(MAX_EXECUTION_ID_LEN)]
+    pub parent_execution_id: Option<String>,
+    pub retry_count: u8,
+    /// Set once a `retry_calculation` has superseded this execution
+    pub retried: bool,
+    /// Inclusive range the result must fall in to be accepted
+    pub result_bounds: Option<ResultBounds>,
+    /// Why the calculation was completed without a result
+    pub failure: Option<FailureReason>,
+    /// Computed on-chain by `submit_local_calculation` instead of proven through Bonsol
+    pub is_local: bool,
+    /// Memo program is forwarded to the callback and receives a result summary
+    pub emit_memo: bool,
+    /// Account the calculation is attributed to
+    pub requester: Pubkey,
+    /// Account that paid for the submission (or the retry)
+    pub payer: Pubkey,
+    /// Prover version the execution was requested with; retries keep it
+    pub prover_version: Option<ProverVersion>,
+    /// Submitter-provided description, at most `MAX_LABEL_LEN` bytes
+    #[max_len(MAX_LABEL_LEN)]
+    pub label: Option<String>,
+    /// Raw guest journal `result` was decoded from (see `decode_outcome`),
+    /// kept so the decoding can be re-verified from on-chain data
+    #[max_len(MAX_JOURNAL_LEN)]
+    pub journal: Option<Vec<u8>>,
+    /// RPN program of a `submit_expression` calculation, whose `operation` is
+    /// `OP_EXPRESSION`; empty for single-operation calculations
+    #[max_len(MAX_EXPRESSION_TOKENS)]
+    pub expression: Vec<ExprToken>,
+    /// Variable `operand_a` was resolved from at submission, if any
+    #[max_len(MAX_VARIABLE_NAME_LEN)]
+    pub operand_a_variable: Option<String>,
+    /// Variable `operand_b` was resolved from at submission, if any
+    #[max_len(MAX_VARIABLE_NAME_LEN)]
+    pub operand_b_variable: Option<String>,
+    /// SHA-256 digest of the input frame sent to Bonsol, which the callback
+    /// compares with the digest the guest commits; `None` for local calculations
+    pub input_hash: Option<[u8; 32]>,
+}
+
+impl CalculationRecord {
+    /// Human-readable form of the calculation, `a op b` or the infix expression
+    pub fn describe(&self) -> String {
+        if self.expression.is_empty() {
+            Operation { op: self.operation, a: self.operand_a, b: self.operand_b }.to_string()
+        } else {
+            format_expression(&self.expression)
+        }
+    }
+
+    /// The guest input frame of the operation or expression, encoded as the
+    /// native program encodes it
+    pub fn input(&self) -> Vec<u8> {
+        if !self.expression.is_empty() {
+            let tokens: Vec<calculator_common::ExprToken> = self.expression.iter().map(|&token| token.into()).collect();
+            return calculator_common::encode_expression(&tokens);
+        }
+        calculator_common::encode_single(self.operation, self.operand_a, self.operand_b)
+    }
+
+    /// Slots between submission and the prover's callback, once it has landed
+    pub fn proving_latency_slots(&self) -> Option<u64> {
+        self.completed_slot
+            .map(|completed_slot| completed_slot.saturating_sub(self.submitted_slot))
+    }
+}
+
+/// Per-requester submission counters, a PDA derived from the state account and
+/// the requester
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RequesterStats {
+    pub state: Pubkey,
+    pub requester: Pubkey,
+    pub bump: u8,
+    /// Calculations submitted through Bonsol, not counting retries
+    pub calculation_count: u64,
+    /// Calculations computed on-chain by `submit_local_calculation`
+    pub local_count: u64,
+    pub last_submitted_slot: u64,
+}
+
+impl RequesterStats {
+    /// Counts a submission, filling in the identity fields on first use
+    pub fn record_submission(&mut self, state: Pubkey, requester: Pubkey, bump: u8, local: bool, slot: u64) {
+        self.state = state;
+        self.requester = requester;
+        self.bump = bump;
+        if local {
+            self.local_count += 1;
+        } else {
+            self.calculation_count += 1;
+        }
+        self.last_submitted_slot = slot;
+    }
+}
+
+/// Token of an RPN program submitted through `submit_expression`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, Copy, PartialEq)]
+pub enum ExprToken {
+    /// Push a literal onto the stack
+    Push(i64),
+    /// Pop `b`, then `a`, and push `a op b` for one of the `OP_*` codes
+    Op(i64),
+    /// Push a copy of the top value
+    Dup,
+    /// Swap the top two values
+    Swap,
+}
+
+impl From<ExprToken> for calculator_common::ExprToken {
+    fn from(token: ExprToken) -> Self {
+        match token {
+            ExprToken::Push(literal) => Self::Push(literal),
+            ExprToken::Op(operation) => Self::Op(operation),
+            ExprToken::Dup => Self::Dup,
+            ExprToken::Swap => Self::Swap,
+        }
+    }
+}
+
+/// Bonsol prover version an execution can be pinned to
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, Copy, PartialEq)]
+pub enum ProverVersion {
+    V1_0_1,
+}
+
+impl ProverVersion {
+    pub fn to_bonsol(self) -> bonsol_interface::prover_version::ProverVersion {
+        match self {
+            ProverVersion::V1_0_1 => bonsol_interface::prover_version::VERSION_V1_0_1,
+        }
+    }
+}
+
+/// Named value curated by the owners, a PDA derived from the state account and
+/// the name
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Variable {
+    pub state: Pubkey,
+    #[max_len(MAX_VARIABLE_NAME_LEN)]
+    pub name: String,
+    pub bump: u8,
+    pub value: i64,
+    pub updated_slot: u64,
+}
+
+impl Variable {
+    /// Current value, checking this is the `state`'s variable called `name`
+    pub fn resolve(&self, state: &Pubkey, name: &str) -> Result<i64> {
+        require!(self.state == *state && self.name == name, CalculatorError::VariableMismatch);
+        msg!("Resolved variable {:?} = {}", name, self.value);
+        Ok(self.value)
+    }
+}
+
+/// Additional account forwarded to the callback alongside the state account
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, PartialEq)]
+pub struct CallbackAccount {
+    pub pubkey: Pubkey,
+    pub is_writable: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, Copy, PartialEq)]
+pub struct ResultBounds {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl ResultBounds {
+    pub fn contains(&self, value: i64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Debug, Clone, Copy, PartialEq)]
+pub enum FailureReason {
+    /// The proven result fell outside the submission's `result_bounds`
+    ResultOutOfBounds,
+    /// The guest proved the calculation divides by zero
+    DivisionByZero,
+    /// The guest proved the calculation overflows an `i64`
+    Overflow,
+    /// The guest proved the calculation uses an unknown operation code
+    UnknownOperation,
+    /// The guest proved an operand is outside the operation's domain, e.g. a negative exponent
+    InvalidOperand,
+    /// The guest proved it read an input frame it couldn't evaluate
+    MalformedInput,
+}
+
+impl From<calculator_common::FailureReason> for FailureReason {
+    fn from(reason: calculator_common::FailureReason) -> Self {
+        match reason {
+            calculator_common::FailureReason::ResultOutOfBounds => Self::ResultOutOfBounds,
+            calculator_common::FailureReason::DivisionByZero => Self::DivisionByZero,
+            calculator_common::FailureReason::Overflow => Self::Overflow,
+            calculator_common::FailureReason::UnknownOperation => Self::UnknownOperation,
+            calculator_common::FailureReason::InvalidOperand => Self::InvalidOperand,
+            calculator_common::FailureReason::MalformedInput => Self::MalformedInput,
+        }
+    }
+}
+
+/// Return data of `get_history`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct HistorySnapshot {
+    pub calculation_count: u64,
+    pub pending_count: u64,
+    /// Index of the first calculation in the window
+    pub offset: u32,
+    /// Entries of the window in index order; pruned records are left out
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub index: u64,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: Option<i64>,
+    pub status: CalculationStatus,
+    pub label: Option<String>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CalculationStatus {
+    Pending,
+    AwaitingFinalize,
+    Complete,
+    Local,
+    Failed(FailureReason),
+}
+
+impl HistorySnapshot {
+    // u64 + u64 + u32 + vec length
+    pub const HEADER_LEN: usize = 8 + 8 + 4 + 4;
+}
+
+impl HistoryEntry {
+    // Upper bound: index + operation/operands + optional result + status with
+    // failure reason + optional label
+    pub const LEN: usize = 8 + 8 * 3 + 9 + 2 + 1 + 4 + MAX_LABEL_LEN;
+}
+
+// Largest `get_history` window whose snapshot fits in return data
+pub const MAX_HISTORY_LIMIT: u8 = ((anchor_lang::solana_program::program::MAX_RETURN_DATA
+    - HistorySnapshot::HEADER_LEN)
+    / HistoryEntry::LEN) as u8;
+
+impl From<&CalculationRecord> for HistoryEntry {
+    fn from(calc: &CalculationRecord) -> Self {
+        let status = if let Some(reason) = calc.failure {
+            CalculationStatus::Failed(reason)
+        } else if calc.is_local {
+            CalculationStatus::Local
+        } else if calc.is_complete {
+            CalculationStatus::Complete
+        } else if calc.pending_journal.is_some() {
+            CalculationStatus::AwaitingFinalize
+        } else {
+            CalculationStatus::Pending
+        };
+
+        HistoryEntry {
+            index: calc.index,
+            operation: calc.operation,
+            operand_a: calc.operand_a,
+            operand_b: calc.operand_b,
+            result: calc.result,
+            status,
+            label: calc.label.clone(),
+        }
+    }
+}
+
+/// Payload passed to a result hook program when a calculation completes
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct ResultHookData {
+    pub execution_id: String,
+    pub operation: i64,
+    pub operand_a: i64,
+    pub operand_b: i64,
+    pub result: i64,
+}
+
+/// Evaluates a calculation the way the guest does, returning `None` for
+/// unknown operations and whatever the guest would commit as a failure
+pub fn compute_locally(operation: i64, operand_a: i64, operand_b: i64) -> Option<i64> {
+    calculator_ops::apply(operation, operand_a, operand_b).ok()
+}
+
+/// Checks that an RPN program is within the token and stack limits, uses
+/// known operations with enough operands and leaves exactly one value on the
+/// stack
+pub fn validate_expression(tokens: &[ExprToken]) -> Result<()> {
+    require!(
+        !tokens.is_empty() && tokens.len() <= MAX_EXPRESSION_TOKENS,
+        CalculatorError::InvalidExpression
+    );
+
+    let mut depth = 0usize;
+    for token in tokens {
+        match *token {
+            ExprToken::Push(_) => depth += 1,
+            ExprToken::Op(operation) => {
+                require!(is_operation(operation), CalculatorError::InvalidOperation);
+                let operands = arity(operation);
+                require!(depth >= operands, CalculatorError::InvalidExpression);
+                depth -= operands - 1;
+            }
+            ExprToken::Dup => {
+                require!(depth >= 1, CalculatorError::InvalidExpression);
+                depth += 1;
+            }
+            ExprToken::Swap => require!(depth >= 2, CalculatorError::InvalidExpression),
+        }
+        require!(depth <= MAX_STACK_DEPTH, CalculatorError::InvalidExpression);
+    }
+
+    require!(depth == 1, CalculatorError::InvalidExpression);
+    Ok(())
+}
+
+/// Renders a validated RPN program in infix form, e.g. `(3 + 4) * 2` or `max(3, 4) * 2`
+pub fn format_expression(tokens: &[ExprToken]) -> String {
+    let mut stack: Vec<String> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        match *token {
+            ExprToken::Push(literal) => stack.push(literal.to_string()),
+            ExprToken::Op(operation) => {
+                let b = if arity(operation) == 2 { stack.pop().unwrap_or_default() } else { String::new() };
+                let a = stack.pop().unwrap_or_default();
+                let rendered = Operation { op: operation, a, b };
+                stack.push(if is_infix(operation) { format!("({})", rendered) } else { rendered.to_string() });
+            }
+            // Stack tokens only move values, so the infix form repeats or reorders them
+            ExprToken::Dup => {
+                if let Some(top) = stack.last().cloned() {
+                    stack.push(top);
+                }
+            }
+            ExprToken::Swap => {
+                if let [.., a, b] = stack.as_mut_slice() {
+                    std::mem::swap(a, b);
+                }
+            }
+        }
+    }
+
+    let rendered = stack.join(" ");
+    match rendered.strip_prefix('(').and_then(|inner| inner.strip_suffix(')')) {
+        Some(inner) if stack.len() == 1 && tokens.len() > 1 => inner.to_string(),
+        _ => rendered,
+    }
+}
@@ -0,0 +1,19 @@
+//! Program-specific errors, surfaced to clients as `ProgramError::Custom`
+
+use solana_program::program_error::ProgramError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalculatorError {
+    /// The requested image has no Bonsol deployment account on this cluster
+    ImageNotDeployed = 0,
+    /// The journal commits the digest of an input other than the one submitted
+    InputDigestMismatch = 1,
+    /// The callback is not signed by the Bonsol execution request account of the calculation
+    UnauthorizedCallback = 2,
+}
+
+impl From<CalculatorError> for ProgramError {
+    fn from(error: CalculatorError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
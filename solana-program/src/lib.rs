@@ -1,85 +1,86 @@
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
     msg,
     pubkey::Pubkey,
     program_error::ProgramError,
-    program::invoke,
+    program::{invoke, invoke_signed, set_return_data},
+    hash::{hash, Hash},
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
     system_instruction,
     sysvar::{rent::Rent, Sysvar},
     clock::Clock,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use bonsol_interface::instructions::{execute_v1, CallbackConfig, ExecutionConfig, InputRef};
+use bonsol_interface::util::{deployment_address, execution_address};
+use spl_associated_token_account_client::address::get_associated_token_address;
+use spl_token::state::Account as TokenAccount;
+#[cfg(feature = "cu-audit")]
+use solana_program::compute_units;
+
+pub mod error;
+
+// Instructions, accounts, frames and journals live in `calculator-common`,
+// shared with the client and the guest; re-exported so this crate's paths
+// keep working for programs that already depend on it
+pub use calculator_common::*;
+
+use calculator_ops::{arity, is_operation, Operation};
+use constants::{
+    CALCULATION_SEED, CALLBACK_INSTRUCTION_PREFIX, IMAGE_ID_LEN, JOURNAL_BATCH_VERSION_1, JOURNAL_BATCH_VERSION_2,
+    MAX_CALLBACK_EXTRA_ACCOUNTS, MAX_EXECUTION_ID_LEN, MAX_EXPRESSION_TOKENS, MAX_JOURNAL_LEN, MAX_LABEL_LEN,
+    MAX_OWNERS, MAX_STACK_DEPTH, MAX_VARIABLE_NAME_LEN, OP_EXPRESSION, REQUESTER_SEED, TREASURY_SEED, VARIABLE_SEED,
+};
+use error::CalculatorError;
 
 // Program ID - you'll need to deploy this and update the ID
 solana_program::declare_id!("2zBRw2sEXvjskx7w1w9hqdFEMZWy7KipQ6jKPfwjpnL6");
 
-// Calculator ZK program image ID
-const CALCULATOR_IMAGE_ID: &str = "5881e972d41fe651c2989c65699528da8b1ed68ab7057350a686b8a64a00fc91";
-
-// Calculator operations
-const OP_ADD: i64 = 0;
-const OP_SUBTRACT: i64 = 1;
-const OP_MULTIPLY: i64 = 2;
-const OP_DIVIDE: i64 = 3;
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-pub struct CalculatorState {
-    pub is_initialized: bool,
-    pub owner: Pubkey,
-    pub calculation_count: u64,
-    pub last_calculation: Option<CalculationRecord>,
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-pub struct CalculationRecord {
-    pub execution_id: String,
-    pub operation: i64,
-    pub operand_a: i64,
-    pub operand_b: i64,
-    pub result: Option<i64>,
-    pub timestamp: i64,
-    pub is_complete: bool,
-}
-
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub enum CalculatorInstruction {
-    /// Initialize calculator state
-    Initialize,
-    
-    /// Submit a calculation request to Bonsol ZK network
-    SubmitCalculation {
-        execution_id: String,
-        operation: i64,
-        operand_a: i64,
-        operand_b: i64,
-    },
-    
-    /// Get calculation history (read-only)
-    GetHistory,
-    
-    /// Callback instruction from Bonsol when ZK computation completes
-    Callback {
-        execution_id: String,
-        result: i64,
-    },
-}
+/// Calculator ZK program image ID, requested until `SetImageId` replaces it
+pub const CALCULATOR_IMAGE_ID: &str = "5881e972d41fe651c2989c65699528da8b1ed68ab7057350a686b8a64a00fc91";
 
-impl CalculatorState {
-    pub const LEN: usize = 1 + 32 + 8 + 200; // bool + pubkey + u64 + optional record
+// Slots a Bonsol execution request stays claimable before it expires
+const EXPIRATION_SLOTS: u64 = 100;
+
+// Denominator for basis-point shares
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// The Bonsol prover version a record's `ProverVersion` pins
+fn bonsol_prover_version(version: ProverVersion) -> bonsol_interface::prover_version::ProverVersion {
+    match version {
+        ProverVersion::V1_0_1 => bonsol_interface::prover_version::VERSION_V1_0_1,
+    }
 }
 
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
-fn process_instruction(
+pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
     let instruction = CalculatorInstruction::try_from_slice(instruction_data)?;
-    
+
+    #[cfg(feature = "cu-audit")]
+    let (name, start) = (instruction.name(), compute_units::sol_remaining_compute_units());
+
+    let result = dispatch(program_id, accounts, instruction);
+
+    // Reports per-handler cost so compute regressions show up in test logs
+    #[cfg(feature = "cu-audit")]
+    msg!(
+        "CU audit: {} consumed {} compute units",
+        name,
+        start.saturating_sub(compute_units::sol_remaining_compute_units())
+    );
+
+    result
+}
+
+fn dispatch(program_id: &Pubkey, accounts: &[AccountInfo], instruction: CalculatorInstruction) -> ProgramResult {
     match instruction {
         CalculatorInstruction::Initialize => initialize(program_id, accounts),
         CalculatorInstruction::SubmitCalculation {
@@ -87,6 +88,15 @@ fn process_instruction(
             operation,
             operand_a,
             operand_b,
+            callback_accounts,
+            result_hook,
+            manual_finalize,
+            referrer,
+            result_bounds,
+            prover_version,
+            label,
+            operand_a_variable,
+            operand_b_variable,
         } => submit_calculation(
             program_id,
             accounts,
@@ -94,9 +104,57 @@ fn process_instruction(
             operation,
             operand_a,
             operand_b,
+            callback_accounts,
+            result_hook,
+            manual_finalize,
+            referrer,
+            result_bounds,
+            prover_version,
+            label,
+            Vec::new(),
+            operand_a_variable,
+            operand_b_variable,
+        ),
+        CalculatorInstruction::GetHistory { offset, limit } => get_history(program_id, accounts, offset, limit),
+        CalculatorInstruction::Callback { execution_id, journal } => {
+            callback(program_id, accounts, execution_id, journal)
+        }
+        CalculatorInstruction::FinalizeCalculation { execution_id } => {
+            finalize_calculation(program_id, accounts, execution_id)
+        }
+        CalculatorInstruction::Configure { config } => configure(accounts, config),
+        CalculatorInstruction::RetryCalculation { execution_id } => {
+            retry_calculation(program_id, accounts, execution_id)
+        }
+        CalculatorInstruction::WithdrawFees => withdraw_fees(program_id, accounts),
+        CalculatorInstruction::SubmitLocalCalculation { operation, operand_a, operand_b } => {
+            submit_local_calculation(program_id, accounts, operation, operand_a, operand_b)
+        }
+        CalculatorInstruction::SetImageId { image_id } => set_image_id(accounts, image_id),
+        CalculatorInstruction::Pause { paused } => pause(accounts, paused),
+        CalculatorInstruction::SetOwners { owners, threshold } => set_owners(accounts, owners, threshold),
+        CalculatorInstruction::PruneHistory { before_timestamp } => {
+            prune_history(program_id, accounts, before_timestamp)
+        }
+        CalculatorInstruction::SubmitExpression { execution_id, tokens } => submit_calculation(
+            program_id,
+            accounts,
+            execution_id,
+            OP_EXPRESSION,
+            0,
+            0,
+            Vec::new(),
+            None,
+            false,
+            None,
+            None,
+            None,
+            None,
+            tokens,
+            None,
+            None,
         ),
-        CalculatorInstruction::GetHistory => get_history(accounts),
-        CalculatorInstruction::Callback { execution_id, result } => callback(accounts, execution_id, result),
+        CalculatorInstruction::SetVariable { name, value } => set_variable(program_id, accounts, name, value),
     }
 }
 
@@ -105,14 +163,21 @@ fn initialize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let payer = next_account_info(account_info_iter)?;
     let calculator_state_account = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    let treasury_account = next_account_info(account_info_iter)?;
 
     if !payer.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    let (treasury, treasury_bump) = treasury_address(program_id, calculator_state_account.key);
+    if treasury != *treasury_account.key {
+        msg!("Treasury account must be the treasury PDA for this state");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
     // Create the calculator state account
     let rent = Rent::get()?;
-    let space = CalculatorState::LEN;
+    let space = codec::account_size(CalculatorState::LEN);
     let lamports = rent.minimum_balance(space);
 
     invoke(
@@ -126,219 +191,1651 @@ fn initialize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         &[payer.clone(), calculator_state_account.clone(), system_program.clone()],
     )?;
 
+    // Create the data-less treasury, rent exempt so it can hold any fee amount
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            treasury_account.key,
+            rent.minimum_balance(0),
+            0,
+            program_id,
+        ),
+        &[payer.clone(), treasury_account.clone(), system_program.clone()],
+        &[&[TREASURY_SEED, calculator_state_account.key.as_ref(), &[treasury_bump]]],
+    )?;
+
     // Initialize the state
     let calculator_state = CalculatorState {
         is_initialized: true,
-        owner: *payer.key,
+        owners: vec![*payer.key],
+        threshold: 1,
         calculation_count: 0,
-        last_calculation: None,
+        pending_count: 0,
+        treasury_bump,
+        config: CalculatorConfig::default(),
+        image_id: CALCULATOR_IMAGE_ID.to_string(),
+        paused: false,
     };
 
-    let mut data = calculator_state_account.try_borrow_mut_data()?;
-    let serialized = calculator_state.try_to_vec()?;
-    data[..serialized.len()].copy_from_slice(&serialized);
+    save_state(calculator_state_account, &calculator_state)?;
 
     msg!("Calculator backend initialized for owner: {}", payer.key);
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn submit_calculation(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
-    execution_id: String,
+    execution_id: Option<String>,
     operation: i64,
     operand_a: i64,
     operand_b: i64,
+    callback_accounts: Vec<CallbackAccount>,
+    result_hook: Option<Pubkey>,
+    manual_finalize: bool,
+    referrer: Option<Pubkey>,
+    result_bounds: Option<ResultBounds>,
+    prover_version: Option<ProverVersion>,
+    label: Option<String>,
+    expression: Vec<ExprToken>,
+    operand_a_variable: Option<String>,
+    operand_b_variable: Option<String>,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let payer = next_account_info(account_info_iter)?;
+    let requester = next_account_info(account_info_iter)?;
     let calculator_state_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let treasury_account = next_account_info(account_info_iter)?;
+    let requester_stats_account = next_account_info(account_info_iter)?;
+    let deployment_account = next_account_info(account_info_iter)?;
+    let execution_account = next_account_info(account_info_iter)?;
+    let calculator_program = next_account_info(account_info_iter)?;
+    let bonsol_program = next_account_info(account_info_iter)?;
 
     if !payer.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
     // Validate operation
-    if ![OP_ADD, OP_SUBTRACT, OP_MULTIPLY, OP_DIVIDE].contains(&operation) {
-        return Err(ProgramError::InvalidInstructionData);
+    if expression.is_empty() {
+        if !is_operation(operation) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    } else {
+        validate_expression(&expression)?;
+    }
+
+    if let Some(ref execution_id) = execution_id {
+        if execution_id.len() > MAX_EXECUTION_ID_LEN {
+            msg!("Execution ID too long: {} bytes (max {})", execution_id.len(), MAX_EXECUTION_ID_LEN);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    for name in operand_a_variable.iter().chain(operand_b_variable.iter()) {
+        if name.is_empty() || name.len() > MAX_VARIABLE_NAME_LEN {
+            msg!("Variable name must be 1 to {} bytes, got {}", MAX_VARIABLE_NAME_LEN, name.len());
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    if let Some(ref label) = label {
+        if label.len() > MAX_LABEL_LEN {
+            msg!("Label too long: {} bytes (max {})", label.len(), MAX_LABEL_LEN);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    validate_callback_accounts(
+        &[*calculator_state_account.key, *record_account.key],
+        &callback_accounts,
+    )?;
+
+    if result_hook == Some(*program_id) {
+        msg!("Result hook cannot target the calculator program itself");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if let Some(bounds) = result_bounds {
+        if bounds.min > bounds.max {
+            msg!("Invalid result bounds: min {} > max {}", bounds.min, bounds.max);
+            return Err(ProgramError::InvalidArgument);
+        }
     }
 
     // Load calculator state
-    let data = calculator_state_account.try_borrow_data()?;
-    let mut calculator_state = CalculatorState::try_from_slice(&data)?;
-    drop(data);
+    let mut calculator_state = load_state(calculator_state_account)?;
     
-    if calculator_state.owner != *payer.key {
-        return Err(ProgramError::IncorrectProgramId);
+    if calculator_state.paused {
+        msg!("Calculator is paused");
+        return Err(ProgramError::InvalidAccountData);
     }
 
-    // Create Bonsol execution request instead of calculating immediately
-    msg!("Creating Bonsol execution request for {} {} {}", operand_a, match operation {
-        OP_ADD => "+",
-        OP_SUBTRACT => "-", 
-        OP_MULTIPLY => "*",
-        OP_DIVIDE => "/",
-        _ => "?",
-    }, operand_b);
+    if !calculator_state.config.open_submissions && !calculator_state.is_owner(payer.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    // Prepare inputs for ZK program (matching the format from client)
-    let operation_bytes = operation.to_le_bytes();
-    let operand_a_bytes = operand_a.to_le_bytes();
-    let operand_b_bytes = operand_b.to_le_bytes();
+    if calculator_state.config.requester_must_sign && !requester.is_signer {
+        msg!("Requester {} must sign its submissions", requester.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    // Combine all three 8-byte values into a single 24-byte input
-    let mut combined_input = Vec::with_capacity(24);
-    combined_input.extend_from_slice(&operation_bytes);
-    combined_input.extend_from_slice(&operand_a_bytes);
-    combined_input.extend_from_slice(&operand_b_bytes);
+    verify_deployment(&calculator_state.image_id, deployment_account)?;
 
-    let inputs = vec![InputRef::public(&combined_input)];
+    // Variable accounts follow the deployment account in operand order
+    let operand_a = match operand_a_variable {
+        Some(ref name) => {
+            load_variable(program_id, calculator_state_account.key, name, next_account_info(account_info_iter)?)?
+        }
+        None => operand_a,
+    };
+    let operand_b = match operand_b_variable {
+        Some(ref name) => {
+            load_variable(program_id, calculator_state_account.key, name, next_account_info(account_info_iter)?)?
+        }
+        None => operand_b,
+    };
 
-    // Get current slot for expiration
-    let current_slot = Clock::get()?.slot;
-    let expiration = current_slot + 100; // 100 slots expiration
+    let index = calculator_state.calculation_count;
+    let (record_address, bump) = calculation_address(program_id, calculator_state_account.key, index);
+    if record_address != *record_account.key {
+        msg!("Record account must be the PDA for calculation #{}", index);
+        return Err(ProgramError::InvalidSeeds);
+    }
 
-    // Create callback config to receive results
-    let callback_config = Some(CallbackConfig {
-        program_id: *_program_id,
-        instruction_prefix: vec![2], // Callback instruction variant
-        extra_accounts: vec![
-            solana_program::instruction::AccountMeta::new(*calculator_state_account.key, false),
-        ],
-    });
+    // Read each sysvar once; they are reused for the record, stats and expiration
+    let clock = Clock::get()?;
+    let rent = Rent::get()?;
 
-    // Create the Bonsol execution instruction
-    let execution_config = ExecutionConfig {
-        verify_input_hash: false,
-        input_hash: None,
-        forward_output: true,
+    let execution_id = match execution_id {
+        Some(execution_id) => execution_id,
+        None => {
+            let execution_id = derive_execution_id(
+                calculator_state_account.key,
+                calculator_state.calculation_count,
+                clock.slot,
+            );
+            msg!("Derived execution ID: {}", execution_id);
+            set_return_data(execution_id.as_bytes());
+            execution_id
+        }
     };
 
-    let bonsol_instruction = execute_v1(
-        payer.key,
-        payer.key,
-        CALCULATOR_IMAGE_ID,
-        &execution_id,
-        inputs,
-        1000, // tip in lamports
-        expiration,
-        execution_config,
-        callback_config,
-        None, // default prover version
-    ).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let treasury = treasury_key(program_id, calculator_state_account.key, calculator_state.treasury_bump)?;
+    let fee_lamports = calculator_state.config.fee_lamports;
+    // The treasury also owns the token fee account, so validate it up front
+    if fee_lamports > 0 || calculator_state.config.token_fee.is_some() {
+        verify_treasury(program_id, &treasury, treasury_account)?;
+    }
+    if fee_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, treasury_account.key, fee_lamports),
+            &[payer.clone(), treasury_account.clone(), system_program.clone()],
+        )?;
+        msg!("Charged protocol fee: {} lamports", fee_lamports);
+    }
 
-    msg!("Created Bonsol instruction with {} accounts", bonsol_instruction.accounts.len());
-    msg!("Bonsol instruction program ID: {}", bonsol_instruction.program_id);
+    let token_fee_amount = match calculator_state.config.token_fee {
+        Some(ref token_fee) if token_fee.amount > 0 => {
+            let payer_token_account = next_account_info(account_info_iter)?;
+            let fee_token_account = next_account_info(account_info_iter)?;
+            let token_program = next_account_info(account_info_iter)?;
 
-    // TODO: Invoke the Bonsol instruction - temporarily disabled for testing
-    // invoke(&bonsol_instruction, accounts)?;
-    msg!("Bonsol execution request created (invoke temporarily disabled for testing)");
+            if *token_program.key != spl_token::id() {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let expected_fee_account = get_associated_token_address(treasury_account.key, &token_fee.mint);
+            if expected_fee_account != *fee_token_account.key {
+                msg!("Fee token account must be the treasury's associated token account for {}", token_fee.mint);
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program.key,
+                    payer_token_account.key,
+                    fee_token_account.key,
+                    payer.key,
+                    &[],
+                    token_fee.amount,
+                )?,
+                &[
+                    payer_token_account.clone(),
+                    fee_token_account.clone(),
+                    payer.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            msg!("Charged token fee: {} of mint {}", token_fee.amount, token_fee.mint);
+            token_fee.amount
+        }
+        _ => 0,
+    };
 
     // Create calculation record (marked as pending)
-    let calculation = CalculationRecord {
+    create_record_account(
+        program_id,
+        &rent,
+        payer,
+        record_account,
+        system_program,
+        calculator_state_account.key,
+        index,
+        bump,
+    )?;
+
+    let mut calculation = CalculationRecord {
+        state: *calculator_state_account.key,
+        index,
+        bump,
         execution_id: execution_id.clone(),
         operation,
         operand_a,
         operand_b,
         result: None, // No result yet - waiting for ZK computation
-        timestamp: Clock::get()?.unix_timestamp,
+        timestamp: clock.unix_timestamp,
         is_complete: false, // Still pending ZK proof
+        callback_accounts,
+        result_hook,
+        manual_finalize,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: None,
+        fee_lamports,
+        token_fee_amount,
+        referrer,
+        referral_share_bps: if referrer.is_some() { calculator_state.config.referral_share_bps } else { 0 },
+        referral_lamports: 0,
+        expiration_slot: clock.slot + EXPIRATION_SLOTS,
+        parent_execution_id: None,
+        retry_count: 0,
+        retried: false,
+        result_bounds,
+        failure: None,
+        is_local: false,
+        emit_memo: calculator_state.config.emit_memo,
+        requester: *requester.key,
+        payer: *payer.key,
+        prover_version: prover_version.or(calculator_state.config.prover_version),
+        label,
+        journal: None,
+        expression,
+        operand_a_variable,
+        operand_b_variable,
+        input_hash: None,
     };
+    calculation.input_hash = Some(hash(&calculation.input()).to_bytes());
+
+    request_execution(
+        program_id,
+        payer,
+        calculator_state_account.key,
+        &treasury,
+        &calculator_state.image_id,
+        &calculation,
+        &[
+            requester.clone(),
+            payer.clone(),
+            execution_account.clone(),
+            deployment_account.clone(),
+            calculator_program.clone(),
+            system_program.clone(),
+            bonsol_program.clone(),
+        ],
+    )?;
+
+    save_record(record_account, &calculation)?;
+    record_submission(
+        program_id,
+        &rent,
+        payer,
+        requester.key,
+        requester_stats_account,
+        system_program,
+        calculator_state_account.key,
+        false,
+        clock.slot,
+    )?;
 
-    // Update state
+    // Only the counters changed, so skip re-serializing the rest of the state
     calculator_state.calculation_count += 1;
-    calculator_state.last_calculation = Some(calculation);
-
-    let mut data = calculator_state_account.try_borrow_mut_data()?;
-    let serialized = calculator_state.try_to_vec()?;
-    data[..serialized.len()].copy_from_slice(&serialized);
-
-    let op_symbol = match operation {
-        OP_ADD => "+",
-        OP_SUBTRACT => "-",
-        OP_MULTIPLY => "*",
-        OP_DIVIDE => "/",
-        _ => "?",
-    };
+    calculator_state.pending_count += 1;
+
+    save_counters(calculator_state_account, &calculator_state.counters())?;
 
-    msg!("Submitted ZK execution request: {} {} {}", operand_a, op_symbol, operand_b);
+    msg!("Submitted ZK execution request: {}", calculation.describe());
     msg!("Execution ID: {}", execution_id);
+    msg!("Record: {} (calculation #{})", record_address, index);
+    if let Some(ref label) = calculation.label {
+        msg!("Label: {}", label);
+    }
+    if requester.key != payer.key {
+        msg!("Requested by {}, sponsored by {}", requester.key, payer.key);
+    }
     msg!("Awaiting ZK proof computation...");
 
     Ok(())
 }
 
-fn get_history(accounts: &[AccountInfo]) -> ProgramResult {
-    let calculator_state_account = &accounts[0];
-    let data = calculator_state_account.try_borrow_data()?;
-    let calculator_state = CalculatorState::try_from_slice(&data)?;
+fn retry_calculation(program_id: &Pubkey, accounts: &[AccountInfo], execution_id: String) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer = next_account_info(account_info_iter)?;
+    let calculator_state_account = next_account_info(account_info_iter)?;
+    let original_record_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let requester = next_account_info(account_info_iter)?;
+    let deployment_account = next_account_info(account_info_iter)?;
+    let execution_account = next_account_info(account_info_iter)?;
+    let calculator_program = next_account_info(account_info_iter)?;
+    let bonsol_program = next_account_info(account_info_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    msg!("Calculator History:");
-    msg!("Total calculations: {}", calculator_state.calculation_count);
-    
-    if let Some(ref calculation) = calculator_state.last_calculation {
-        let op_symbol = match calculation.operation {
-            OP_ADD => "+",
-            OP_SUBTRACT => "-", 
-            OP_MULTIPLY => "*",
-            OP_DIVIDE => "/",
-            _ => "?",
-        };
+    let mut calculator_state = load_state(calculator_state_account)?;
 
-        if calculation.is_complete {
-            msg!("Last calculation: {} {} {} = {}", 
-                 calculation.operand_a, op_symbol, calculation.operand_b, 
-                 calculation.result.unwrap_or(0));
-        } else {
-            msg!("Last calculation: {} {} {} = (pending...)", 
-                 calculation.operand_a, op_symbol, calculation.operand_b);
-        }
+    if calculator_state.paused {
+        msg!("Calculator is paused");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !calculator_state.config.open_submissions && !calculator_state.is_owner(payer.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut original = load_record(program_id, calculator_state_account.key, original_record_account)?;
+    if original.execution_id != execution_id {
+        msg!("No calculation found for execution ID: {}", execution_id);
+        return Err(ProgramError::InvalidArgument);
     }
+    if original.is_complete || original.pending_journal.is_some() || original.retried {
+        msg!("Calculation #{} already received its callback or was retried", original.index);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if original.requester != *requester.key {
+        msg!("Requester account must be {}, the requester of calculation #{}", original.requester, original.index);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let clock = Clock::get()?;
+    let rent = Rent::get()?;
+    if clock.slot <= original.expiration_slot {
+        msg!("Execution {} has not expired yet (expires at slot {})", execution_id, original.expiration_slot);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let index = calculator_state.calculation_count;
+    let (record_address, bump) = calculation_address(program_id, calculator_state_account.key, index);
+    if record_address != *record_account.key {
+        msg!("Record account must be the PDA for calculation #{}", index);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let retry_count = original.retry_count.checked_add(1).ok_or(ProgramError::InvalidArgument)?;
+    let retry_execution_id = derive_retry_execution_id(&original.execution_id, retry_count);
+
+    create_record_account(
+        program_id,
+        &rent,
+        payer,
+        record_account,
+        system_program,
+        calculator_state_account.key,
+        index,
+        bump,
+    )?;
+
+    let retry = CalculationRecord {
+        state: *calculator_state_account.key,
+        index,
+        bump,
+        execution_id: retry_execution_id.clone(),
+        operation: original.operation,
+        operand_a: original.operand_a,
+        operand_b: original.operand_b,
+        result: None,
+        timestamp: clock.unix_timestamp,
+        is_complete: false,
+        callback_accounts: original.callback_accounts.clone(),
+        result_hook: original.result_hook,
+        manual_finalize: original.manual_finalize,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: None,
+        fee_lamports: original.fee_lamports,
+        token_fee_amount: original.token_fee_amount,
+        referrer: original.referrer,
+        referral_share_bps: original.referral_share_bps,
+        referral_lamports: 0,
+        expiration_slot: clock.slot + EXPIRATION_SLOTS,
+        parent_execution_id: Some(original.execution_id.clone()),
+        retry_count,
+        retried: false,
+        result_bounds: original.result_bounds,
+        failure: None,
+        is_local: false,
+        emit_memo: original.emit_memo,
+        requester: original.requester,
+        payer: *payer.key,
+        prover_version: original.prover_version,
+        label: original.label.clone(),
+        journal: None,
+        expression: original.expression.clone(),
+        operand_a_variable: original.operand_a_variable.clone(),
+        operand_b_variable: original.operand_b_variable.clone(),
+        input_hash: original.input_hash,
+    };
+
+    request_execution(
+        program_id,
+        payer,
+        calculator_state_account.key,
+        &treasury_key(program_id, calculator_state_account.key, calculator_state.treasury_bump)?,
+        &calculator_state.image_id,
+        &retry,
+        &[
+            requester.clone(),
+            payer.clone(),
+            execution_account.clone(),
+            deployment_account.clone(),
+            calculator_program.clone(),
+            system_program.clone(),
+            bonsol_program.clone(),
+        ],
+    )?;
+
+    original.retried = true;
+    save_record(original_record_account, &original)?;
+    save_record(record_account, &retry)?;
+
+    // The original stays counted as pending through its retry
+    calculator_state.calculation_count += 1;
+
+    save_counters(calculator_state_account, &calculator_state.counters())?;
+
+    set_return_data(retry_execution_id.as_bytes());
+    msg!("Retrying execution {} as {} (retry #{})", execution_id, retry_execution_id, retry_count);
+    msg!("Record: {} (calculation #{})", record_address, index);
 
     Ok(())
 }
 
-fn callback(accounts: &[AccountInfo], execution_id: String, result: i64) -> ProgramResult {
-    msg!("Callback received for execution ID: {}", execution_id);
-    msg!("ZK computation result: {}", result);
-    
+fn submit_local_calculation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    operation: i64,
+    operand_a: i64,
+    operand_b: i64,
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
+    let payer = next_account_info(account_info_iter)?;
     let calculator_state_account = next_account_info(account_info_iter)?;
-    
-    // Load calculator state
-    let data = calculator_state_account.try_borrow_data()?;
-    let mut calculator_state = CalculatorState::try_from_slice(&data)?;
-    drop(data);
-    
-    // Update the last calculation with the result
-    if let Some(ref mut calc) = calculator_state.last_calculation {
-        if calc.execution_id == execution_id {
-            calc.result = Some(result);
-            calc.is_complete = true;
-            
-            let op_symbol = match calc.operation {
-                OP_ADD => "+",
-                OP_SUBTRACT => "-",
-                OP_MULTIPLY => "*", 
-                OP_DIVIDE => "/",
-                _ => "?",
-            };
-            
-            msg!("✅ ZK computation completed: {} {} {} = {}", 
-                 calc.operand_a, op_symbol, calc.operand_b, result);
-                 
-            // Save updated state
-            let mut data = calculator_state_account.try_borrow_mut_data()?;
-            let serialized = calculator_state.try_to_vec()?;
-            data[..serialized.len()].copy_from_slice(&serialized);
-        } else {
-            msg!("Warning: Execution ID mismatch in callback");
+    let record_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let requester_stats_account = next_account_info(account_info_iter)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut calculator_state = load_state(calculator_state_account)?;
+
+    if calculator_state.paused {
+        msg!("Calculator is paused");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !calculator_state.config.open_submissions && !calculator_state.is_owner(payer.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let limit = calculator_state.config.local_operand_limit;
+    if limit == 0 {
+        msg!("Local calculations are disabled");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if operand_a.unsigned_abs() > limit || operand_b.unsigned_abs() > limit {
+        msg!("Operands exceed the local calculation limit of {}", limit);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let result = match calculator_ops::apply(operation, operand_a, operand_b) {
+        Ok(result) => result,
+        Err(error) => {
+            msg!("Local calculation failed: {}", error);
+            return Err(ProgramError::InvalidInstructionData);
         }
-    } else {
-        msg!("Warning: No pending calculation found for callback");
+    };
+
+    let index = calculator_state.calculation_count;
+    let (record_address, bump) = calculation_address(program_id, calculator_state_account.key, index);
+    if record_address != *record_account.key {
+        msg!("Record account must be the PDA for calculation #{}", index);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let clock = Clock::get()?;
+    let rent = Rent::get()?;
+    let execution_id = derive_execution_id(calculator_state_account.key, index, clock.slot);
+
+    create_record_account(
+        program_id,
+        &rent,
+        payer,
+        record_account,
+        system_program,
+        calculator_state_account.key,
+        index,
+        bump,
+    )?;
+
+    let calculation = CalculationRecord {
+        state: *calculator_state_account.key,
+        index,
+        bump,
+        execution_id: execution_id.clone(),
+        operation,
+        operand_a,
+        operand_b,
+        result: Some(result),
+        timestamp: clock.unix_timestamp,
+        is_complete: true,
+        callback_accounts: Vec::new(),
+        result_hook: None,
+        manual_finalize: false,
+        pending_journal: None,
+        submitted_slot: clock.slot,
+        completed_slot: Some(clock.slot),
+        fee_lamports: 0,
+        token_fee_amount: 0,
+        referrer: None,
+        referral_share_bps: 0,
+        referral_lamports: 0,
+        expiration_slot: clock.slot,
+        parent_execution_id: None,
+        retry_count: 0,
+        retried: false,
+        result_bounds: None,
+        failure: None,
+        is_local: true,
+        emit_memo: false,
+        requester: *payer.key,
+        payer: *payer.key,
+        prover_version: None,
+        label: None,
+        journal: None,
+        expression: Vec::new(),
+        operand_a_variable: None,
+        operand_b_variable: None,
+        input_hash: None,
+    };
+    save_record(record_account, &calculation)?;
+    record_submission(
+        program_id,
+        &rent,
+        payer,
+        payer.key,
+        requester_stats_account,
+        system_program,
+        calculator_state_account.key,
+        true,
+        clock.slot,
+    )?;
+
+    // Never pending, so only the total moves
+    calculator_state.calculation_count += 1;
+    save_counters(calculator_state_account, &calculator_state.counters())?;
+
+    msg!("Computed locally: {} = {}", Operation { op: operation, a: operand_a, b: operand_b }, result);
+    msg!("Execution ID: {}", execution_id);
+    msg!("Record: {} (calculation #{})", record_address, index);
+
+    Ok(())
+}
+
+/// Checks that an RPN program is within the token and stack limits, uses
+/// known operations with enough operands and leaves exactly one value on the
+/// stack
+fn validate_expression(tokens: &[ExprToken]) -> ProgramResult {
+    if tokens.is_empty() || tokens.len() > MAX_EXPRESSION_TOKENS {
+        msg!("Expression must have 1 to {} tokens, got {}", MAX_EXPRESSION_TOKENS, tokens.len());
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut depth = 0usize;
+    for token in tokens {
+        match *token {
+            ExprToken::Push(_) => depth += 1,
+            ExprToken::Op(operation) => {
+                if !is_operation(operation) {
+                    msg!("Unknown operation {} in expression", operation);
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                let operands = arity(operation);
+                if depth < operands {
+                    msg!("Expression operation {} needs {} operands", operation, operands);
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                depth -= operands - 1;
+            }
+            ExprToken::Dup if depth == 0 => {
+                msg!("Expression duplicates a value of an empty stack");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            ExprToken::Dup => depth += 1,
+            ExprToken::Swap if depth < 2 => {
+                msg!("Expression swaps with fewer than 2 values on the stack");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            ExprToken::Swap => {}
+        }
+        if depth > MAX_STACK_DEPTH {
+            msg!("Expression holds more than {} values on the stack", MAX_STACK_DEPTH);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    if depth != 1 {
+        msg!("Expression leaves {} values on the stack, expected 1", depth);
+        return Err(ProgramError::InvalidInstructionData);
     }
-    
     Ok(())
 }
 
-// TODO: Implement callback instruction parsing and handling logic. 
+/// Sends the Bonsol execution request for a calculation record
+///
+/// The callback is configured with the state and record accounts followed by
+/// the record's forwarded accounts, result hook, referral accounts and memo
+/// program. `accounts` are the ones `execute_v1` references: requester,
+/// payer, execution request PDA, deployment, calculator program and system
+/// program, plus the Bonsol program itself.
+fn request_execution(
+    program_id: &Pubkey,
+    payer: &AccountInfo,
+    state_key: &Pubkey,
+    treasury: &Pubkey,
+    image_id: &str,
+    calc: &CalculationRecord,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    // Create Bonsol execution request instead of calculating immediately
+    msg!("Creating Bonsol execution request for {}", calc.describe());
+
+    // Prepare inputs for ZK program (matching the format from client)
+    let combined_input = calc.input();
+
+    let inputs = vec![InputRef::public(&combined_input)];
+
+    let (record_address, _) = calculation_address(program_id, state_key, calc.index);
+
+    // Create callback config to receive results
+    let mut extra_accounts = vec![
+        AccountMeta::new(*state_key, false),
+        AccountMeta::new(record_address, false),
+    ];
+    extra_accounts.extend(calc.callback_accounts.iter().map(|account| {
+        if account.is_writable {
+            AccountMeta::new(account.pubkey, false)
+        } else {
+            AccountMeta::new_readonly(account.pubkey, false)
+        }
+    }));
+    // The hook program has to be present in the callback transaction to be invoked
+    if let Some(hook_program_id) = calc.result_hook {
+        extra_accounts.push(AccountMeta::new_readonly(hook_program_id, false));
+    }
+    // The referral share is paid out of the treasury during the callback
+    if let Some(referrer) = calc.referrer {
+        extra_accounts.push(AccountMeta::new(*treasury, false));
+        extra_accounts.push(AccountMeta::new(referrer, false));
+    }
+    if calc.emit_memo {
+        extra_accounts.push(AccountMeta::new_readonly(MEMO_PROGRAM_ID, false));
+    }
+
+    let callback_config = Some(CallbackConfig {
+        program_id: *program_id,
+        instruction_prefix: CALLBACK_INSTRUCTION_PREFIX.to_vec(),
+        extra_accounts,
+    });
+
+    // Create the Bonsol execution instruction
+    let execution_config = ExecutionConfig {
+        verify_input_hash: false,
+        input_hash: None,
+        forward_output: true,
+    };
+
+    let bonsol_instruction = execute_v1(
+        &calc.requester,
+        payer.key,
+        image_id,
+        &calc.execution_id,
+        inputs,
+        1000, // tip in lamports
+        calc.expiration_slot,
+        execution_config,
+        callback_config,
+        calc.prover_version.map(bonsol_prover_version),
+    ).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    msg!("Created Bonsol instruction with {} accounts", bonsol_instruction.accounts.len());
+    if let Some(prover_version) = calc.prover_version {
+        msg!("Pinned prover version: {:?}", prover_version);
+    }
+
+    // A local validator has no Bonsol program; the mock prover answers instead
+    if cfg!(feature = "localnet") {
+        msg!("Localnet build: execution request left to the mock prover");
+        return Ok(());
+    }
+    invoke(&bonsol_instruction, accounts)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_record_account<'a>(
+    program_id: &Pubkey,
+    rent: &Rent,
+    payer: &AccountInfo<'a>,
+    record_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    state_key: &Pubkey,
+    index: u64,
+    bump: u8,
+) -> ProgramResult {
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            record_account.key,
+            rent.minimum_balance(codec::account_size(CalculationRecord::LEN)),
+            codec::account_size(CalculationRecord::LEN) as u64,
+            program_id,
+        ),
+        &[payer.clone(), record_account.clone(), system_program.clone()],
+        &[&[CALCULATION_SEED, state_key.as_ref(), &index.to_le_bytes(), &[bump]]],
+    )
+}
+
+/// Counts a submission against the requester's stats PDA, creating it on first use
+#[allow(clippy::too_many_arguments)]
+fn record_submission<'a>(
+    program_id: &Pubkey,
+    rent: &Rent,
+    payer: &AccountInfo<'a>,
+    requester: &Pubkey,
+    stats_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    state_key: &Pubkey,
+    local: bool,
+    slot: u64,
+) -> ProgramResult {
+    let (stats_address, bump) = requester_stats_address(program_id, state_key, requester);
+    if stats_address != *stats_account.key {
+        msg!("Requester stats account must be the PDA for {}", requester);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let mut stats = if stats_account.owner == program_id {
+        codec::decode::<RequesterStats>(&stats_account.try_borrow_data()?)?
+    } else {
+        let space = codec::account_size(RequesterStats::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                stats_account.key,
+                rent.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), stats_account.clone(), system_program.clone()],
+            &[&[REQUESTER_SEED, state_key.as_ref(), requester.as_ref(), &[bump]]],
+        )?;
+        RequesterStats {
+            state: *state_key,
+            requester: *requester,
+            bump,
+            calculation_count: 0,
+            local_count: 0,
+            last_submitted_slot: 0,
+        }
+    };
+
+    if local {
+        stats.local_count += 1;
+    } else {
+        stats.calculation_count += 1;
+    }
+    stats.last_submitted_slot = slot;
+
+    codec::encode(&stats, &mut stats_account.try_borrow_mut_data()?)
+}
+
+fn validate_callback_accounts(reserved_keys: &[Pubkey], callback_accounts: &[CallbackAccount]) -> ProgramResult {
+    if callback_accounts.len() > MAX_CALLBACK_EXTRA_ACCOUNTS {
+        msg!("Too many callback accounts: {} (max {})", callback_accounts.len(), MAX_CALLBACK_EXTRA_ACCOUNTS);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    for (i, account) in callback_accounts.iter().enumerate() {
+        // The state and record accounts are always forwarded first, so they must not be repeated
+        if reserved_keys.contains(&account.pubkey) {
+            msg!("Callback account {} duplicates a calculator account", account.pubkey);
+            return Err(ProgramError::InvalidArgument);
+        }
+        if callback_accounts[..i].iter().any(|other| other.pubkey == account.pubkey) {
+            msg!("Duplicate callback account: {}", account.pubkey);
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+
+    Ok(())
+}
+
+fn get_history(program_id: &Pubkey, accounts: &[AccountInfo], offset: u32, limit: u8) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let calculator_state_account = next_account_info(account_info_iter)?;
+    let calculator_state = load_state(calculator_state_account)?;
+
+    let start = u64::from(offset).min(calculator_state.calculation_count);
+    let end = (start + u64::from(limit.min(MAX_HISTORY_LIMIT))).min(calculator_state.calculation_count);
+
+    msg!("Calculator History:");
+    msg!("Total calculations: {}", calculator_state.calculation_count);
+    msg!("Pending calculations: {}", calculator_state.pending_count);
+    msg!("Showing calculations #{}..#{}", start, end);
+
+    let mut snapshot = HistorySnapshot {
+        calculation_count: calculator_state.calculation_count,
+        pending_count: calculator_state.pending_count,
+        offset,
+        entries: Vec::new(),
+    };
+
+    for index in start..end {
+        let record_account = next_account_info(account_info_iter)?;
+        if record_account.owner != program_id {
+            let (record_address, _) = calculation_address(program_id, calculator_state_account.key, index);
+            if record_address != *record_account.key {
+                msg!("Record account must be the PDA for calculation #{}", index);
+                return Err(ProgramError::InvalidSeeds);
+            }
+            msg!("Calculation #{}: (pruned)", index);
+            continue;
+        }
+
+        let calculation = load_record(program_id, calculator_state_account.key, record_account)?;
+        if calculation.index != index {
+            msg!("Expected record for calculation #{}, got #{}", index, calculation.index);
+            return Err(ProgramError::InvalidArgument);
+        }
+        snapshot.entries.push(HistoryEntry::from(&calculation));
+
+        let operation = Operation { op: calculation.operation, a: calculation.operand_a, b: calculation.operand_b };
+
+        if let Some(reason) = calculation.failure {
+            msg!("Calculation #{}: {} = (failed: {:?})", calculation.index, operation, reason);
+        } else if calculation.is_local {
+            msg!("Calculation #{}: {} = {} (local)", calculation.index, operation,
+                 calculation.result.unwrap_or(0));
+        } else if calculation.is_complete {
+            msg!("Calculation #{}: {} = {}", calculation.index, operation,
+                 calculation.result.unwrap_or(0));
+        } else if calculation.pending_journal.is_some() {
+            msg!("Calculation #{}: {} = (awaiting finalize...)", calculation.index, operation);
+        } else {
+            msg!("Calculation #{}: {} = (pending...)", calculation.index, operation);
+        }
+        if !calculation.expression.is_empty() {
+            msg!("  Expression: {}", format_expression(&calculation.expression));
+        }
+        if let Some(ref label) = calculation.label {
+            msg!("  Label: {}", label);
+        }
+    }
+
+    set_return_data(&snapshot.try_to_vec()?);
+    Ok(())
+}
+
+fn prune_history(program_id: &Pubkey, accounts: &[AccountInfo], before_timestamp: i64) -> ProgramResult {
+    let calculator_state_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let calculator_state = load_state(calculator_state_account)?;
+    let record_accounts = verify_owner_signers(&calculator_state, &accounts[1..])?;
+    // The run of owner signers is never empty once verified
+    let rent_destination = &accounts[1];
+
+    let mut pruned = 0u64;
+    let mut refunded = 0u64;
+    for record_account in record_accounts {
+        let calculation = load_record(program_id, calculator_state_account.key, record_account)?;
+        if !calculation.is_complete || calculation.timestamp >= before_timestamp {
+            msg!("Skipping calculation #{}: pending or too recent", calculation.index);
+            continue;
+        }
+
+        refunded += close_record(record_account, rent_destination)?;
+        pruned += 1;
+    }
+
+    msg!("Pruned {} calculation records, refunded {} lamports to {}", pruned, refunded, rent_destination.key);
+    Ok(())
+}
+
+fn set_variable(program_id: &Pubkey, accounts: &[AccountInfo], name: String, value: i64) -> ProgramResult {
+    let calculator_state_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let calculator_state = load_state(calculator_state_account)?;
+    let account_info_iter = &mut verify_owner_signers(&calculator_state, &accounts[1..])?.iter();
+    let variable_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    // The run of owner signers is never empty once verified
+    let payer = &accounts[1];
+
+    if name.is_empty() || name.len() > MAX_VARIABLE_NAME_LEN {
+        msg!("Variable name must be 1 to {} bytes, got {}", MAX_VARIABLE_NAME_LEN, name.len());
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (variable_address, bump) = variable_address(program_id, calculator_state_account.key, &name);
+    if variable_address != *variable_account.key {
+        msg!("Variable account must be the PDA for variable {:?}", name);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if variable_account.owner != program_id {
+        let space = codec::account_size(Variable::LEN);
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                variable_account.key,
+                Rent::get()?.minimum_balance(space),
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), variable_account.clone(), system_program.clone()],
+            &[&[VARIABLE_SEED, calculator_state_account.key.as_ref(), name.as_bytes(), &[bump]]],
+        )?;
+        msg!("Created variable {:?}", name);
+    }
+
+    let variable = Variable {
+        state: *calculator_state_account.key,
+        name,
+        bump,
+        value,
+        updated_slot: Clock::get()?.slot,
+    };
+    codec::encode(&variable, &mut variable_account.try_borrow_mut_data()?)?;
+
+    msg!("Set variable {:?} = {}", variable.name, value);
+    Ok(())
+}
+
+/// Reads the current value of the variable `name` from its PDA
+fn load_variable(program_id: &Pubkey, state_key: &Pubkey, name: &str, variable_account: &AccountInfo) -> Result<i64, ProgramError> {
+    let (variable_address, _) = variable_address(program_id, state_key, name);
+    if variable_address != *variable_account.key || variable_account.owner != program_id {
+        msg!("Variable account must be the existing PDA for variable {:?}", name);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let variable = codec::decode::<Variable>(&variable_account.try_borrow_data()?)?;
+    msg!("Resolved variable {:?} = {}", name, variable.value);
+    Ok(variable.value)
+}
+
+/// Closes a record account, moving its lamports to `destination`
+fn close_record(record_account: &AccountInfo, destination: &AccountInfo) -> Result<u64, ProgramError> {
+    let lamports = record_account.lamports();
+    **record_account.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? += lamports;
+
+    record_account.realloc(0, false)?;
+    record_account.assign(&solana_program::system_program::id());
+    Ok(lamports)
+}
+
+/// Loads a calculation record, checking it is a record PDA of `state_key`
+fn load_record(
+    program_id: &Pubkey,
+    state_key: &Pubkey,
+    record_account: &AccountInfo,
+) -> Result<CalculationRecord, ProgramError> {
+    if record_account.owner != program_id {
+        msg!("Record account {} is not owned by the calculator program", record_account.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let record: CalculationRecord = codec::decode(&record_account.try_borrow_data()?)?;
+
+    let expected_address = Pubkey::create_program_address(
+        &[CALCULATION_SEED, state_key.as_ref(), &record.index.to_le_bytes(), &[record.bump]],
+        program_id,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    if record.state != *state_key || expected_address != *record_account.key {
+        msg!("Record account {} does not belong to state {}", record_account.key, state_key);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(record)
+}
+
+fn save_record(record_account: &AccountInfo, record: &CalculationRecord) -> ProgramResult {
+    codec::encode(record, &mut record_account.try_borrow_mut_data()?)
+}
+
+fn load_state(calculator_state_account: &AccountInfo) -> Result<CalculatorState, ProgramError> {
+    codec::decode(&calculator_state_account.try_borrow_data()?)
+}
+
+fn save_state(calculator_state_account: &AccountInfo, calculator_state: &CalculatorState) -> ProgramResult {
+    codec::encode(calculator_state, &mut calculator_state_account.try_borrow_mut_data()?)
+}
+
+fn load_counters(calculator_state_account: &AccountInfo) -> Result<StateCounters, ProgramError> {
+    let counters: StateCounters = codec::decode_head(&calculator_state_account.try_borrow_data()?)?;
+    if !counters.is_initialized {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(counters)
+}
+
+fn save_counters(calculator_state_account: &AccountInfo, counters: &StateCounters) -> ProgramResult {
+    codec::encode_head(counters, &mut calculator_state_account.try_borrow_mut_data()?)
+}
+
+fn callback(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    execution_id: String,
+    journal: Vec<u8>,
+) -> ProgramResult {
+    msg!("Callback received for execution ID: {}", execution_id);
+
+    // Records prove a single operation or expression; batch proofs, and the
+    // Merkle roots they commit, are for direct Bonsol executions
+    if matches!(journal.first(), Some(&(JOURNAL_BATCH_VERSION_1 | JOURNAL_BATCH_VERSION_2))) {
+        msg!("Batch journals are not accepted; the callback takes single-result journals");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if journal.len() > MAX_JOURNAL_LEN {
+        msg!("Journal too long: {} bytes (max {})", journal.len(), MAX_JOURNAL_LEN);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    
+    let account_info_iter = &mut accounts.iter();
+    let execution_account = next_account_info(account_info_iter)?;
+    let calculator_state_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+    
+    // The record PDA was registered in the callback config, so no lookup is needed
+    let mut calc = load_record(program_id, calculator_state_account.key, record_account)?;
+    verify_bonsol_caller(&calc, execution_account)?;
+
+    if calc.execution_id != execution_id {
+        msg!("Warning: Execution ID mismatch in callback");
+        return Ok(());
+    }
+
+    if calc.is_complete || calc.pending_journal.is_some() {
+        msg!("Warning: Calculation #{} already received its callback", calc.index);
+        return Ok(());
+    }
+
+    if calc.retried {
+        msg!("Warning: Calculation #{} was superseded by a retry", calc.index);
+        return Ok(());
+    }
+
+    verify_input_digest(&calc, &journal)?;
+    if let Ok(GuestJournal { cycles: Some(cycles), semantics, .. }) = GuestJournal::parse(&journal) {
+        msg!("Guest cycles: {}", cycles);
+        match semantics.unwrap_or_default() {
+            0 => {}
+            applied => msg!("Overflow semantics applied: {:#04b}", applied),
+        }
+    }
+
+    // The prover's work ends when the callback lands, even if finalization comes later
+    calc.completed_slot = Some(Clock::get()?.slot);
+    if let Some(latency) = calc.proving_latency_slots() {
+        msg!("Proving latency: {} slots (submitted at {})", latency, calc.submitted_slot);
+    }
+
+    if calc.manual_finalize {
+        // Park the raw journal until the owner finalizes it
+        calc.pending_journal = Some(journal);
+        save_record(record_account, &calc)?;
+
+        msg!("Journal stored, awaiting FinalizeCalculation by owner");
+        return Ok(());
+    }
+
+    let outcome = decode_outcome(&journal)?;
+    calc.journal = Some(journal);
+    let result = match outcome {
+        Ok(result) => result,
+        Err(reason) => {
+            msg!("❌ ZK computation failed: {:?}", reason);
+            return fail_calculation(calculator_state_account, record_account, calc, reason);
+        }
+    };
+    msg!("ZK computation result: {}", result);
+
+    complete_calculation(program_id, calculator_state_account, record_account, calc, result, account_info_iter)
+}
+
+/// Checks that a callback was sent by Bonsol for `calc`'s execution request
+///
+/// Bonsol signs callbacks with the execution request PDA it derives from the
+/// requester and execution ID, which no other program or keypair can sign
+/// for. A `localnet` build accepts any signer, for the mock prover.
+fn verify_bonsol_caller(calc: &CalculationRecord, execution_account: &AccountInfo) -> ProgramResult {
+    if !execution_account.is_signer {
+        msg!("Callback must be signed by the Bonsol execution request account");
+        return Err(CalculatorError::UnauthorizedCallback.into());
+    }
+    if cfg!(feature = "localnet") {
+        return Ok(());
+    }
+    let (execution, _) = execution_address(&calc.requester, calc.execution_id.as_bytes());
+    if execution != *execution_account.key {
+        msg!("Callback signer {} is not the Bonsol execution request {}", execution_account.key, execution);
+        return Err(CalculatorError::UnauthorizedCallback.into());
+    }
+    Ok(())
+}
+
+/// Rejects a journal proven over an input other than the one the record submitted
+///
+/// Binds the proof to the submission even when Bonsol doesn't verify the
+/// input hash. Legacy journals carry no digest to compare.
+fn verify_input_digest(calc: &CalculationRecord, journal: &[u8]) -> ProgramResult {
+    let input_digest = GuestJournal::parse(journal)?.input_digest;
+    match (calc.input_hash, input_digest) {
+        (Some(expected), Some(input_digest)) if expected != input_digest => {
+            msg!(
+                "Journal was proven over input {} instead of the submitted {}",
+                Hash::new_from_array(input_digest),
+                Hash::new_from_array(expected)
+            );
+            Err(CalculatorError::InputDigestMismatch.into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn finalize_calculation(program_id: &Pubkey, accounts: &[AccountInfo], execution_id: String) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner = next_account_info(account_info_iter)?;
+    let calculator_state_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let calculator_state = load_state(calculator_state_account)?;
+
+    if !calculator_state.is_owner(owner.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut calc = load_record(program_id, calculator_state_account.key, record_account)?;
+    if calc.execution_id != execution_id {
+        msg!("No calculation found for execution ID: {}", execution_id);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let journal = match calc.pending_journal.take() {
+        Some(journal) => journal,
+        None => {
+            msg!("No pending journal to finalize for execution ID: {}", execution_id);
+            return Err(ProgramError::InvalidAccountData);
+        }
+    };
+
+    let outcome = decode_outcome(&journal)?;
+    calc.journal = Some(journal);
+    let result = match outcome {
+        Ok(result) => result,
+        Err(reason) => {
+            msg!("❌ ZK computation failed: {:?}", reason);
+            return fail_calculation(calculator_state_account, record_account, calc, reason);
+        }
+    };
+    msg!("Finalizing ZK computation result: {}", result);
+
+    complete_calculation(program_id, calculator_state_account, record_account, calc, result, account_info_iter)
+}
+
+fn configure(accounts: &[AccountInfo], config: CalculatorConfig) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let calculator_state_account = next_account_info(account_info_iter)?;
+
+    let mut calculator_state = load_state(calculator_state_account)?;
+    verify_owner_signers(&calculator_state, &accounts[1..])?;
+
+    if u64::from(config.referral_share_bps) > BPS_DENOMINATOR {
+        msg!("Referral share {} bps exceeds 100%", config.referral_share_bps);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("Updating config: fee {} lamports, open submissions: {}",
+         config.fee_lamports, config.open_submissions);
+    if let Some(ref token_fee) = config.token_fee {
+        msg!("Token fee: {} of mint {}", token_fee.amount, token_fee.mint);
+    }
+    calculator_state.config = config;
+
+    save_state(calculator_state_account, &calculator_state)?;
+
+    Ok(())
+}
+
+fn withdraw_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let calculator_state_account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let calculator_state = load_state(calculator_state_account)?;
+    let account_info_iter = &mut verify_owner_signers(&calculator_state, &accounts[1..])?.iter();
+    let treasury_account = next_account_info(account_info_iter)?;
+    let destination = next_account_info(account_info_iter)?;
+
+    let treasury = treasury_key(program_id, calculator_state_account.key, calculator_state.treasury_bump)?;
+    verify_treasury(program_id, &treasury, treasury_account)?;
+
+    // Keep the treasury rent exempt so it survives the sweep
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(treasury_account.data_len());
+    let amount = treasury_account.lamports().saturating_sub(rent_exempt_minimum);
+    if amount > 0 {
+        **treasury_account.try_borrow_mut_lamports()? -= amount;
+        **destination.try_borrow_mut_lamports()? += amount;
+        msg!("Withdrew {} lamports of fees to {}", amount, destination.key);
+    } else {
+        msg!("No lamport fees to withdraw");
+    }
+
+    // Optional token sweep
+    let treasury_token_account = match next_account_info(account_info_iter) {
+        Ok(account) => account,
+        Err(_) => return Ok(()),
+    };
+    let destination_token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if *token_program.key != spl_token::id() || treasury_token_account.owner != token_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let token_account = TokenAccount::unpack(&treasury_token_account.try_borrow_data()?)?;
+    if token_account.owner != *treasury_account.key {
+        msg!("Token account {} is not owned by the treasury", treasury_token_account.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if token_account.amount == 0 {
+        msg!("No token fees to withdraw");
+        return Ok(());
+    }
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            treasury_token_account.key,
+            destination_token_account.key,
+            treasury_account.key,
+            &[],
+            token_account.amount,
+        )?,
+        &[
+            treasury_token_account.clone(),
+            destination_token_account.clone(),
+            treasury_account.clone(),
+            token_program.clone(),
+        ],
+        &[&[TREASURY_SEED, calculator_state_account.key.as_ref(), &[calculator_state.treasury_bump]]],
+    )?;
+
+    msg!("Withdrew {} tokens of mint {} to {}", token_account.amount, token_account.mint, destination_token_account.key);
+    Ok(())
+}
+
+fn set_image_id(accounts: &[AccountInfo], image_id: String) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let calculator_state_account = next_account_info(account_info_iter)?;
+
+    let mut calculator_state = load_state(calculator_state_account)?;
+    verify_owner_signers(&calculator_state, &accounts[1..])?;
+
+    if image_id.len() != IMAGE_ID_LEN || !image_id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        msg!("Image ID must be {} hex characters", IMAGE_ID_LEN);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("Updating image ID: {} -> {}", calculator_state.image_id, image_id);
+    calculator_state.image_id = image_id;
+    save_state(calculator_state_account, &calculator_state)
+}
+
+fn pause(accounts: &[AccountInfo], paused: bool) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let calculator_state_account = next_account_info(account_info_iter)?;
+
+    let mut calculator_state = load_state(calculator_state_account)?;
+    verify_owner_signers(&calculator_state, &accounts[1..])?;
+
+    calculator_state.paused = paused;
+    save_state(calculator_state_account, &calculator_state)?;
+
+    msg!("Calculator {}", if paused { "paused" } else { "resumed" });
+    Ok(())
+}
+
+fn set_owners(accounts: &[AccountInfo], owners: Vec<Pubkey>, threshold: u8) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let calculator_state_account = next_account_info(account_info_iter)?;
+
+    let mut calculator_state = load_state(calculator_state_account)?;
+    verify_owner_signers(&calculator_state, &accounts[1..])?;
+
+    if owners.is_empty() || owners.len() > MAX_OWNERS {
+        msg!("Owner multisig must have between 1 and {} keys", MAX_OWNERS);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if owners.iter().enumerate().any(|(i, owner)| owners[..i].contains(owner)) {
+        msg!("Owner multisig contains duplicate keys");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if threshold == 0 || usize::from(threshold) > owners.len() {
+        msg!("Threshold {} must be between 1 and {}", threshold, owners.len());
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("Updating owners: {}-of-{}", threshold, owners.len());
+    calculator_state.owners = owners;
+    calculator_state.threshold = threshold;
+    save_state(calculator_state_account, &calculator_state)
+}
+
+/// Checks the leading run of owner signers in `accounts` meets the threshold
+///
+/// Admin instructions take the owner signers right after the state account;
+/// the run ends at the first account that is not a signing owner, and the
+/// accounts after it are returned.
+fn verify_owner_signers<'a, 'b>(
+    calculator_state: &CalculatorState,
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<&'a [AccountInfo<'b>], ProgramError> {
+    let signer_count = accounts
+        .iter()
+        .take_while(|account| account.is_signer && calculator_state.is_owner(account.key))
+        .count();
+    let signers = &accounts[..signer_count];
+
+    let distinct = signers
+        .iter()
+        .enumerate()
+        .filter(|(i, signer)| !signers[..*i].iter().any(|other| other.key == signer.key))
+        .count();
+    if distinct == 0 {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if distinct < usize::from(calculator_state.threshold) {
+        msg!("{} of {} required owner signatures", distinct, calculator_state.threshold);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    Ok(&accounts[signer_count..])
+}
+
+fn treasury_key(program_id: &Pubkey, state_key: &Pubkey, treasury_bump: u8) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_program_address(&[TREASURY_SEED, state_key.as_ref(), &[treasury_bump]], program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+fn verify_treasury(program_id: &Pubkey, treasury: &Pubkey, treasury_account: &AccountInfo) -> ProgramResult {
+    if treasury != treasury_account.key || treasury_account.owner != program_id {
+        msg!("Treasury account must be the treasury PDA for this state");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Checks that `deployment_account` is the live Bonsol deployment of `image_id`
+fn verify_deployment(image_id: &str, deployment_account: &AccountInfo) -> ProgramResult {
+    let (deployment, _) = deployment_address(image_id);
+    if deployment != *deployment_account.key {
+        msg!("Deployment account must be the Bonsol deployment PDA for image {}", image_id);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if *deployment_account.owner != bonsol_interface::ID || deployment_account.data_is_empty() {
+        msg!("Image {} is not deployed to Bonsol on this cluster", image_id);
+        return Err(CalculatorError::ImageNotDeployed.into());
+    }
+    Ok(())
+}
+
+/// Commits `result` to a calculation record, updates state, pays the referral
+/// share and runs the result hook
+///
+/// `account_info_iter` must be positioned at the forwarded callback accounts.
+fn complete_calculation<'a, 'b: 'a, I>(
+    program_id: &Pubkey,
+    calculator_state_account: &'a AccountInfo<'b>,
+    record_account: &'a AccountInfo<'b>,
+    mut calc: CalculationRecord,
+    result: i64,
+    account_info_iter: &mut I,
+) -> ProgramResult
+where
+    I: Iterator<Item = &'a AccountInfo<'b>>,
+{
+    if let Some(bounds) = calc.result_bounds {
+        if !bounds.contains(result) {
+            msg!("❌ Result {} outside accepted bounds [{}, {}], marking calculation failed",
+                 result, bounds.min, bounds.max);
+            return fail_calculation(calculator_state_account, record_account, calc, FailureReason::ResultOutOfBounds);
+        }
+    }
+
+    let forwarded_accounts = verify_forwarded_accounts(&calc.callback_accounts, account_info_iter)?;
+    let hook_program = match calc.result_hook {
+        Some(_) => Some(next_account_info(account_info_iter)?),
+        None => None,
+    };
+
+    calc.result = Some(result);
+    calc.is_complete = true;
+    
+    let description = calc.describe();
+    msg!("✅ ZK computation completed: {} = {}", description, result);
+    if let Some(ref label) = calc.label {
+        msg!("Label: {}", label);
+    }
+
+    let mut counters = load_counters(calculator_state_account)?;
+    counters.pending_count = counters.pending_count.saturating_sub(1);
+
+    if let Some(referrer) = calc.referrer {
+        let treasury_account = next_account_info(account_info_iter)?;
+        let referrer_account = next_account_info(account_info_iter)?;
+        let treasury = treasury_key(program_id, calculator_state_account.key, counters.treasury_bump)?;
+        verify_treasury(program_id, &treasury, treasury_account)?;
+        if *referrer_account.key != referrer {
+            msg!("Referrer account mismatch: expected {}, got {}", referrer, referrer_account.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        calc.referral_lamports = pay_referral(&calc, treasury_account, referrer_account)?;
+    }
+
+    let memo_program = match calc.emit_memo {
+        true => Some(next_account_info(account_info_iter)?),
+        false => None,
+    };
+
+    // Save updated record and state before handing control to the hook program
+    save_record(record_account, &calc)?;
+
+    save_counters(calculator_state_account, &counters)?;
+
+    if let Some(memo_program) = memo_program {
+        let memo = format!("calc: {} = {} (exec {})", description, result, calc.execution_id);
+        emit_memo(memo_program, &memo)?;
+    }
+
+    if let (Some(hook_program_id), Some(hook_program)) = (calc.result_hook, hook_program) {
+        let hook_data = ResultHookData {
+            execution_id: calc.execution_id,
+            operation: calc.operation,
+            operand_a: calc.operand_a,
+            operand_b: calc.operand_b,
+            result,
+        };
+        invoke_result_hook(
+            &hook_program_id,
+            hook_program,
+            calculator_state_account,
+            &forwarded_accounts,
+            &hook_data,
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Completes a calculation without a result, recording why
+///
+/// Neither the result hook nor the referral payout runs for failed calculations.
+fn fail_calculation(
+    calculator_state_account: &AccountInfo,
+    record_account: &AccountInfo,
+    mut calc: CalculationRecord,
+    reason: FailureReason,
+) -> ProgramResult {
+    calc.result = None;
+    calc.is_complete = true;
+    calc.failure = Some(reason);
+    save_record(record_account, &calc)?;
+
+    let mut counters = load_counters(calculator_state_account)?;
+    counters.pending_count = counters.pending_count.saturating_sub(1);
+
+    save_counters(calculator_state_account, &counters)?;
+
+    msg!("Calculation #{} failed: {:?}", calc.index, reason);
+    Ok(())
+}
+
+/// Moves the referrer's share of a calculation's lamport fee out of the treasury
+///
+/// The treasury is program-owned, so lamports are moved directly rather than
+/// through the system program. The payout never dips into the treasury's
+/// rent-exempt minimum.
+fn pay_referral(
+    calc: &CalculationRecord,
+    treasury_account: &AccountInfo,
+    referrer_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    let share = (u128::from(calc.fee_lamports) * u128::from(calc.referral_share_bps)
+        / u128::from(BPS_DENOMINATOR)) as u64;
+    let available = treasury_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(treasury_account.data_len()));
+    let amount = share.min(available);
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    **treasury_account.try_borrow_mut_lamports()? -= amount;
+    **referrer_account.try_borrow_mut_lamports()? += amount;
+
+    msg!("Paid referral share: {} lamports to {}", amount, referrer_account.key);
+    Ok(amount)
+}
+
+/// Logs `memo` through the SPL Memo program so wallets and explorers show it
+fn emit_memo(memo_program: &AccountInfo, memo: &str) -> ProgramResult {
+    if *memo_program.key != MEMO_PROGRAM_ID {
+        msg!("Memo program mismatch: expected {}, got {}", MEMO_PROGRAM_ID, memo_program.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    invoke(
+        &Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: vec![],
+            data: memo.as_bytes().to_vec(),
+        },
+        std::slice::from_ref(memo_program),
+    )
+}
+
+/// Checks that the accounts following the record account match the ones persisted at submission
+fn verify_forwarded_accounts<'a, 'b: 'a, I>(
+    expected: &[CallbackAccount],
+    account_info_iter: &mut I,
+) -> Result<Vec<&'a AccountInfo<'b>>, ProgramError>
+where
+    I: Iterator<Item = &'a AccountInfo<'b>>,
+{
+    let mut forwarded_accounts = Vec::with_capacity(expected.len());
+    for account in expected {
+        let forwarded = next_account_info(account_info_iter)?;
+        if forwarded.key != &account.pubkey {
+            msg!("Callback account mismatch: expected {}, got {}", account.pubkey, forwarded.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if account.is_writable && !forwarded.is_writable {
+            msg!("Callback account {} must be writable", account.pubkey);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        forwarded_accounts.push(forwarded);
+    }
+
+    Ok(forwarded_accounts)
+}
+
+/// Forwards a completed result to the program registered at submission
+///
+/// The hook receives the state account (read-only) followed by the forwarded
+/// callback accounts, and `RESULT_HOOK_DISCRIMINATOR` + Borsh `ResultHookData`
+/// as instruction data.
+fn invoke_result_hook<'a>(
+    hook_program_id: &Pubkey,
+    hook_program: &AccountInfo<'a>,
+    calculator_state_account: &AccountInfo<'a>,
+    forwarded_accounts: &[&AccountInfo<'a>],
+    hook_data: &ResultHookData,
+) -> ProgramResult {
+    if hook_program.key != hook_program_id || !hook_program.executable {
+        msg!("Result hook program mismatch: expected {}, got {}", hook_program_id, hook_program.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut account_metas = vec![AccountMeta::new_readonly(*calculator_state_account.key, false)];
+    let mut account_infos = vec![calculator_state_account.clone()];
+    for account in forwarded_accounts {
+        account_metas.push(if account.is_writable {
+            AccountMeta::new(*account.key, false)
+        } else {
+            AccountMeta::new_readonly(*account.key, false)
+        });
+        account_infos.push((*account).clone());
+    }
+    account_infos.push(hook_program.clone());
+
+    let mut data = RESULT_HOOK_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&hook_data.try_to_vec()?);
+
+    msg!("Forwarding result to hook program: {}", hook_program_id);
+    invoke(
+        &Instruction {
+            program_id: *hook_program_id,
+            accounts: account_metas,
+            data,
+        },
+        &account_infos,
+    )
+}
@@ -0,0 +1,34 @@
+#!/bin/sh
+# Builds both images of the calculator guest and records their image IDs in
+# image-ids.json:
+#   release  lean image without debug logging, deployed by `bonsol build` to
+#            manifest.json; this is the image submissions request
+#   debug    image built with the `debug-logs` feature, for `prove-local
+#            --elf` runs that need the guest's log lines
+# Both builds run in risc0's docker image, so the IDs are reproducible.
+set -eu
+cd "$(dirname "$0")"
+
+ELF_DIR=target/riscv-guest/riscv32im-risc0-zkvm-elf/docker/zk_calculator
+
+# Both builds write the same ELF path, so the debug image is built first and
+# moved aside before the release build takes its place
+debug_id=$(cargo risczero build --manifest-path Cargo.toml --features debug-logs |
+    sed -n 's/^ImageID: \([0-9a-f]\{64\}\).*/\1/p' | tail -n 1)
+if [ -z "$debug_id" ]; then
+    echo "cargo risczero build did not report the debug image ID" >&2
+    exit 1
+fi
+mv "$ELF_DIR/zk_calculator" "$ELF_DIR/zk_calculator-debug"
+
+bonsol build --zk-program-path .
+release_id=$(sed -n 's/.*"imageId": "\([0-9a-f]\{64\}\)".*/\1/p' manifest.json)
+
+cat > image-ids.json <<EOF
+{
+  "release": "$release_id",
+  "debug": "$debug_id"
+}
+EOF
+echo "release image: $release_id"
+echo "debug image:   $debug_id ($ELF_DIR/zk_calculator-debug)"
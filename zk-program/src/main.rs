@@ -1,93 +1,588 @@
+use calculator_common::constants::{
+    BATCH_OUTCOME_LEN, EXPR_TOKEN_DUP, EXPR_TOKEN_OP, EXPR_TOKEN_PUSH, EXPR_TOKEN_SWAP, FRAME_VERSION_1,
+    FRAME_VERSION_1_OPERATIONS, FRAME_VERSION_2, JOURNAL_BATCH_VERSION_2, JOURNAL_CHAINED_V1_LEN,
+    JOURNAL_CHAINED_VERSION_1, JOURNAL_DECIMAL_V1_LEN, JOURNAL_DECIMAL_VERSION_1, JOURNAL_METERED_V1_LEN,
+    JOURNAL_METERED_V2_LEN, JOURNAL_METERED_VERSION_1, JOURNAL_METERED_VERSION_2, JOURNAL_RANGE_V1_LEN,
+    JOURNAL_RANGE_VERSION_1, JOURNAL_SEMANTICS_SATURATED, JOURNAL_SEMANTICS_WRAPPED, JOURNAL_STATUS_DIV_BY_ZERO,
+    JOURNAL_STATUS_INVALID_OPERAND, JOURNAL_STATUS_MALFORMED_INPUT, JOURNAL_STATUS_OK, JOURNAL_STATUS_OVERFLOW,
+    JOURNAL_STATUS_UNKNOWN_OP, JOURNAL_V1_LEN, JOURNAL_VERSION_1, JOURNAL_WIDE_VERSION_1, MAX_BATCH_OPERATIONS,
+    MAX_EXPRESSION_TOKENS, MAX_STACK_DEPTH, MAX_STATISTICS_VALUES, MERKLE_LEAF_PREFIX, MERKLE_NODE_PREFIX,
+};
+use calculator_ops::{
+    apply_decimal, apply_wide, apply_with_semantics, arity, Decimal, OpError, Operation, Semantics, WideInt, I256,
+    OP_BATCH, OP_CHAINED, OP_DECIMAL, OP_EXPRESSION, OP_PRIVATE_RANGE, OP_STATISTICS, OP_WIDE,
+};
 use risc0_zkvm::guest::env;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Read, Write};
 
-const OP_ADD: u8 = 0;
-const OP_SUBTRACT: u8 = 1;
-const OP_MULTIPLY: u8 = 2;
-const OP_DIVIDE: u8 = 3;
+// Frame versions, journal layouts and statuses come from `calculator-common`,
+// which the programs and the client decode them with. Frames without a
+// version header are told apart from versioned ones by length: their length
+// is a multiple of FIELD_LEN and a versioned frame's is one byte longer. A
+// frame the guest can't evaluate at all is committed with the malformed-input
+// status in a metered journal, whatever kind of frame it is
 
-fn read_i64_input(field_name: &str) -> i64 {
-    let mut input_bytes = [0u8; 8]; // Assume host sends each decimal string as an 8-byte i64
-    env::read_slice(&mut input_bytes);
-    let number = i64::from_le_bytes(input_bytes);
-    env::log(&format!("[ZK_GUEST_DEBUG] Read {}: {} (from bytes: {:?})", field_name, number, input_bytes));
+// Every field of a frame is a little-endian i64
+const FIELD_LEN: usize = 8;
+// Operation code, operand A, operand B
+const SINGLE_FRAME_FIELDS: usize = 3;
+// Operation code, result scale, then each operand's value and scale
+const DECIMAL_FRAME_FIELDS: usize = 6;
+// Operation code, operand B, lower and upper bound; operand A follows as the
+// private input
+const RANGE_FRAME_FIELDS: usize = 4;
+// Operation code and operand B; the previous journal follows, zero-padded to
+// whole fields
+const CHAINED_FRAME_FIELDS: usize = 2;
+
+// Trace events, each recorded with a little-endian i64 value: the input frame's
+// length, its operation code, then the status, result and cycle count of a
+// single-result calculation
+const TRACE_FRAME_LEN: u8 = 0;
+const TRACE_OPERATION: u8 = 1;
+const TRACE_STATUS: u8 = 2;
+const TRACE_RESULT: u8 = 3;
+const TRACE_CYCLES: u8 = 4;
+const TRACE_RECORD_LEN: usize = 1 + 8;
+
+// Logs a `[ZK_GUEST_DEBUG]` line, only in images built with the `debug-logs`
+// feature: formatting them costs more cycles than most operations. The
+// arguments are still type-checked in lean builds, which compile the call away
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "debug-logs") {
+            env::log(&format!("[ZK_GUEST_DEBUG] {}", format_args!($($arg)*)));
+        }
+    };
+}
+
+// Writes a fixed-size trace record, the event then its value, to the guest's
+// stderr in images built with the `trace` feature: a few cycles each, against
+// the hundreds a formatted log line takes. `prove-local` decodes them
+fn trace(event: u8, value: i64) {
+    if cfg!(feature = "trace") {
+        let mut record = [0u8; TRACE_RECORD_LEN];
+        record[0] = event;
+        record[1..].copy_from_slice(&value.to_le_bytes());
+        // Tracing is a diagnostic; a lost record must not fail the proof
+        let _ = env::stderr().write_all(&record);
+    }
+}
+
+// Reads the whole input, whether the host sent it as one combined frame or
+// as one public input per field, which arrive concatenated
+fn read_frame() -> Vec<u8> {
+    let mut frame = Vec::new();
+    if let Err(e) = env::stdin().read_to_end(&mut frame) {
+        env::log(&format!("[ZK_GUEST_ERROR] Failed to read the input: {}", e));
+        panic!("Failed to read the input");
+    }
+    debug_log!("Read a {}-byte input frame", frame.len());
+    trace(TRACE_FRAME_LEN, frame.len() as i64);
+    frame
+}
+
+// The schema version of `frame` and its fields, after its version header if it has one
+fn frame_fields(frame: &[u8]) -> Result<(u8, &[u8]), u8> {
+    match frame.len() % FIELD_LEN {
+        0 => {
+            debug_log!("Legacy frame without a version header");
+            Ok((FRAME_VERSION_1, frame))
+        }
+        1 if frame[0] == FRAME_VERSION_1 || frame[0] == FRAME_VERSION_2 => {
+            debug_log!("Frame version {}", frame[0]);
+            Ok((frame[0], &frame[1..]))
+        }
+        1 => Err(malformed(format_args!("Unsupported frame version: {}", frame[0]))),
+        _ => Err(malformed(format_args!("Input frame of {} bytes is not a whole number of fields!", frame.len()))),
+    }
+}
+
+// The field at `index`; the caller has checked the frame holds it
+fn read_field(fields: &[u8], index: usize, field_name: &str) -> i64 {
+    let offset = index * FIELD_LEN;
+    let mut field_bytes = [0u8; FIELD_LEN];
+    field_bytes.copy_from_slice(&fields[offset..offset + FIELD_LEN]);
+    let number = i64::from_le_bytes(field_bytes);
+    debug_log!("Read {} at offset {}: {} (from bytes: {:?})", field_name, offset, number, field_bytes);
     number
 }
 
-fn main() {
-    env::log("[ZK_GUEST_DEBUG] Generic Calculator App Started - Decimal String Inputs Mode");
-
-    // Read operation code
-    // Host is assumed to convert "0", "1", "2", "3" from inputs.json into an i64.
-    // We then take the i64 value and cast to u8.
-    let op_i64 = read_i64_input("operation_as_i64");
-    if op_i64 < 0 || op_i64 > u8::MAX as i64 {
-        env::log(&format!("[ZK_GUEST_ERROR] Operation code {} out of u8 range!", op_i64));
-        panic!("Operation code out of u8 range");
-    }
-    let operation = op_i64 as u8; // Cast to u8
-    env::log(&format!("[ZK_GUEST_DEBUG] Parsed operation code: {}", operation));
-
-    // Read operands
-    let a = read_i64_input("operand_a");
-    let b = read_i64_input("operand_b");
-
-    let op_symbol = match operation {
-        OP_ADD => "+",
-        OP_SUBTRACT => "-",
-        OP_MULTIPLY => "*",
-        OP_DIVIDE => "/",
-        _ => "?" // Should not happen if previous checks are in place
+// Fails as malformed unless `fields` holds exactly `expected` fields
+fn expect_field_count(fields: &[u8], expected: usize, frame_kind: &str) -> Result<(), u8> {
+    let count = fields.len() / FIELD_LEN;
+    if count != expected {
+        return Err(malformed(format_args!("{} frame has {} fields, expected {}!", frame_kind, count, expected)));
+    }
+    Ok(())
+}
+
+// Logs why the frame can't be evaluated and returns the status to commit for
+// it. Malformed frames fail here rather than panicking, so the prover still
+// gets a journal binding the frame to the failure
+fn malformed(reason: fmt::Arguments) -> u8 {
+    env::log(&format!("[ZK_GUEST_ERROR] {}", reason));
+    JOURNAL_STATUS_MALFORMED_INPUT
+}
+
+// The metered journal for `result`, bound to the exact input `frame`
+fn encode_journal(status: u8, result: i64, cycles: u64, semantics: u8, frame: &[u8]) -> [u8; JOURNAL_METERED_V2_LEN] {
+    let mut journal = [0u8; JOURNAL_METERED_V2_LEN];
+    journal[0] = JOURNAL_METERED_VERSION_2;
+    journal[1] = status;
+    journal[2..10].copy_from_slice(&result.to_le_bytes());
+    journal[10..18].copy_from_slice(&cycles.to_le_bytes());
+    journal[18] = semantics;
+    journal[19..].copy_from_slice(&Sha256::digest(frame));
+    journal
+}
+
+// `a op b`, or the journal status of why it can't be computed. Sets the bit of
+// the semantics a saturating or wrapping operation fell back on in `applied`
+fn apply(operation: i64, a: i64, b: i64, applied: &mut u8) -> Result<i64, u8> {
+    let (result, semantics) = apply_with_semantics(operation, a, b).map_err(|error| {
+        env::log(&format!("[ZK_GUEST_ERROR] Cannot compute {}: {}!", Operation { op: operation, a, b }, error));
+        journal_status(error)
+    })?;
+    match semantics {
+        Semantics::Checked => {}
+        Semantics::Saturating => *applied |= JOURNAL_SEMANTICS_SATURATED,
+        Semantics::Wrapping => *applied |= JOURNAL_SEMANTICS_WRAPPED,
+    }
+    Ok(result)
+}
+
+// Fails as an unknown operation unless schema `version` has `operation`:
+// version 1 frames, legacy ones included, only know the original four
+fn expect_operation(version: u8, operation: i64) -> Result<(), u8> {
+    if version == FRAME_VERSION_1 && !FRAME_VERSION_1_OPERATIONS.contains(&operation) {
+        env::log(&format!("[ZK_GUEST_ERROR] Operation {} needs a version {} frame!", operation, FRAME_VERSION_2));
+        return Err(JOURNAL_STATUS_UNKNOWN_OP);
+    }
+    Ok(())
+}
+
+fn journal_status(error: OpError) -> u8 {
+    match error {
+        OpError::DivisionByZero => JOURNAL_STATUS_DIV_BY_ZERO,
+        OpError::Overflow => JOURNAL_STATUS_OVERFLOW,
+        OpError::UnknownOperation => JOURNAL_STATUS_UNKNOWN_OP,
+        OpError::InvalidOperand => JOURNAL_STATUS_INVALID_OPERAND,
+    }
+}
+
+// Evaluates a wide frame into its journal: after the operation code come the
+// operand width in bytes, 16 for i128 or 32 for 256-bit integers, the code of
+// the operation to apply, then both operands as little-endian two's
+// complement, each spanning `width / 8` fields
+fn evaluate_wide(fields: &[u8], frame: &[u8]) -> Result<Vec<u8>, u8> {
+    if fields.len() < 3 * FIELD_LEN {
+        return Err(malformed(format_args!("Wide frame has no operand width or operation!")));
+    }
+    let width = read_field(fields, 1, "operand_width");
+    let operation = read_field(fields, 2, "operation");
+    match width {
+        16 => wide_journal::<i128>(operation, fields, frame),
+        32 => wide_journal::<I256>(operation, fields, frame),
+        _ => Err(malformed(format_args!("Unsupported operand width: {} bytes", width))),
+    }
+}
+
+fn wide_journal<T: WideInt>(operation: i64, fields: &[u8], frame: &[u8]) -> Result<Vec<u8>, u8> {
+    expect_field_count(fields, 3 + 2 * T::BYTES / FIELD_LEN, "Wide")?;
+    let operands = &fields[3 * FIELD_LEN..];
+    let a = T::read_le(&operands[..T::BYTES]);
+    let b = T::read_le(&operands[T::BYTES..]);
+    debug_log!("Performing {}-bit operation: {}", T::BYTES * 8, Operation { op: operation, a, b });
+
+    let (status, result) = match apply_wide(operation, a, b) {
+        Ok(value) => (JOURNAL_STATUS_OK, value),
+        Err(error) => {
+            env::log(&format!("[ZK_GUEST_ERROR] Cannot compute {}: {}!", Operation { op: operation, a, b }, error));
+            (journal_status(error), T::ZERO)
+        }
     };
+    debug_log!("Wide result: {} (status {})", result, status);
 
-    env::log(&format!("[ZK_GUEST_DEBUG] Performing operation: {} {} {}", a, op_symbol, b));
+    let mut journal = vec![0u8; 1 + 1 + 1 + T::BYTES];
+    journal[0] = JOURNAL_WIDE_VERSION_1;
+    journal[1] = status;
+    journal[2] = T::BYTES as u8;
+    result.write_le(&mut journal[3..]);
+    journal.extend_from_slice(&Sha256::digest(frame));
+    Ok(journal)
+}
 
-    let result = match operation {
-        OP_ADD => a.checked_add(b),
-        OP_SUBTRACT => a.checked_sub(b),
-        OP_MULTIPLY => a.checked_mul(b),
-        OP_DIVIDE => {
-            if b == 0 {
-                env::log("[ZK_GUEST_ERROR] Division by zero!");
-                panic!("Division by zero");
-            }
-            a.checked_div(b)
+// Evaluates a batch frame into its journal: after the operation code come
+// the operation count, then an (op, a, b) triple per operation. Every
+// operation gets its own status, so one failure doesn't fail the others
+fn evaluate_batch(fields: &[u8], frame: &[u8]) -> Result<Vec<u8>, u8> {
+    if fields.len() < 2 * FIELD_LEN {
+        return Err(malformed(format_args!("Batch frame has no operation count!")));
+    }
+    let count = read_field(fields, 1, "operation_count");
+    if !(1..=MAX_BATCH_OPERATIONS as i64).contains(&count) {
+        return Err(malformed(format_args!("Operation count {} out of range!", count)));
+    }
+    let count = count as usize;
+    expect_field_count(fields, 2 + SINGLE_FRAME_FIELDS * count, "Batch")?;
+
+    let mut outcomes = Vec::with_capacity(count * BATCH_OUTCOME_LEN);
+    for index in 0..count {
+        let offset = 2 + SINGLE_FRAME_FIELDS * index;
+        let operation = read_field(fields, offset, "operation");
+        let a = read_field(fields, offset + 1, "operand_a");
+        let b = read_field(fields, offset + 2, "operand_b");
+        // Batch journals have no room for the semantics applied
+        let (status, result) = match apply(operation, a, b, &mut 0) {
+            Ok(value) => (JOURNAL_STATUS_OK, value),
+            Err(status) => (status, 0),
+        };
+        debug_log!("Batch operation {}: {} = {} (status {})", index, Operation { op: operation, a, b }, result, status);
+        outcomes.push(status);
+        outcomes.extend_from_slice(&result.to_le_bytes());
+    }
+
+    let mut journal = Vec::with_capacity(1 + 1 + 32 + 32 + outcomes.len());
+    journal.push(JOURNAL_BATCH_VERSION_2);
+    journal.push(count as u8);
+    journal.extend_from_slice(&Sha256::digest(frame));
+    journal.extend_from_slice(&merkle_root(&outcomes));
+    journal.extend_from_slice(&outcomes);
+    Ok(journal)
+}
+
+// The root of the binary Merkle tree over the batch's outcomes, mirrored by
+// the backend crate's `merkle` module: a level with an odd number of nodes
+// carries its last node up unhashed
+fn merkle_root(outcomes: &[u8]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = outcomes
+        .chunks_exact(BATCH_OUTCOME_LEN)
+        .map(|outcome| Sha256::new().chain_update([MERKLE_LEAF_PREFIX]).chain_update(outcome).finalize().into())
+        .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Sha256::new()
+                    .chain_update([MERKLE_NODE_PREFIX])
+                    .chain_update(left)
+                    .chain_update(right)
+                    .finalize()
+                    .into(),
+                _ => pair[0],
+            })
+            .collect();
+    }
+    debug_log!("Merkle root over {} outcomes: {:02x?}", outcomes.len() / BATCH_OUTCOME_LEN, level[0]);
+    level[0]
+}
+
+// Evaluates a decimal frame into its journal: after the marker come the
+// operation code, the scale of the result, then the value and scale of each
+// operand. Scales outside 0..=18 are committed as invalid operands
+fn evaluate_decimal(fields: &[u8], frame: &[u8]) -> Result<[u8; JOURNAL_DECIMAL_V1_LEN], u8> {
+    expect_field_count(fields, 1 + DECIMAL_FRAME_FIELDS, "Decimal")?;
+    let scale = |index, field_name| u8::try_from(read_field(fields, index, field_name)).unwrap_or(u8::MAX);
+    let operation = read_field(fields, 1, "operation");
+    let result_scale = scale(2, "result_scale");
+    let a = Decimal { value: read_field(fields, 3, "operand_a"), scale: scale(4, "operand_a_scale") };
+    let b = Decimal { value: read_field(fields, 5, "operand_b"), scale: scale(6, "operand_b_scale") };
+    debug_log!("Performing decimal operation: {} at scale {}", Operation { op: operation, a, b }, result_scale);
+
+    let (status, result) = match apply_decimal(operation, a, b, result_scale) {
+        Ok(result) => (JOURNAL_STATUS_OK, result.value),
+        Err(error) => {
+            env::log(&format!("[ZK_GUEST_ERROR] Cannot compute {}: {}!", Operation { op: operation, a, b }, error));
+            (journal_status(error), 0)
         }
-        _ => {
-            env::log(&format!("[ZK_GUEST_ERROR] Unknown operation code: {}", operation));
-            panic!("Unknown operation");
+    };
+    debug_log!("Decimal result: {} (status {})", Decimal { value: result, scale: result_scale }, status);
+
+    let mut journal = [0u8; JOURNAL_DECIMAL_V1_LEN];
+    journal[0] = JOURNAL_DECIMAL_VERSION_1;
+    journal[1] = status;
+    journal[2..10].copy_from_slice(&result.to_le_bytes());
+    journal[10] = result_scale;
+    journal[11..].copy_from_slice(&Sha256::digest(frame));
+    Ok(journal)
+}
+
+// Evaluates a private-operand range frame into its journal: after the marker
+// come the operation code, operand B and the inclusive bounds, then operand A
+// as the last field, sent as a private input. Only the public fields are
+// bound by the digest, and operand A is never logged
+fn evaluate_range(fields: &[u8], frame: &[u8]) -> Result<[u8; JOURNAL_RANGE_V1_LEN], u8> {
+    expect_field_count(fields, 1 + RANGE_FRAME_FIELDS + 1, "Range")?;
+    let operation = read_field(fields, 1, "operation");
+    let b = read_field(fields, 2, "operand_b");
+    let min = read_field(fields, 3, "min");
+    let max = read_field(fields, 4, "max");
+    let mut private_bytes = [0u8; FIELD_LEN];
+    private_bytes.copy_from_slice(&fields[5 * FIELD_LEN..]);
+    let a = i64::from_le_bytes(private_bytes);
+    debug_log!("Checking a {} b against [{}, {}] with private a", calculator_ops::symbol(operation), min, max);
+
+    // Failures are committed without a result, so they never reveal the operand either
+    let (status, within) = match calculator_ops::apply(operation, a, b) {
+        Ok(value) => (JOURNAL_STATUS_OK, (min..=max).contains(&value)),
+        Err(error) => {
+            env::log(&format!("[ZK_GUEST_ERROR] Cannot compute the private operation: {}!", error));
+            (journal_status(error), false)
+        }
+    };
+    debug_log!("Result within bounds: {} (status {})", within, status);
+
+    let mut journal = [0u8; JOURNAL_RANGE_V1_LEN];
+    journal[0] = JOURNAL_RANGE_VERSION_1;
+    journal[1] = status;
+    journal[2] = within as u8;
+    journal[3..].copy_from_slice(&Sha256::digest(&frame[..frame.len() - FIELD_LEN]));
+    Ok(journal)
+}
+
+// Evaluates a chained frame into its journal: after the marker come the
+// operation code and operand B, then a single-result or chained journal
+// whose result becomes operand A. The guest can't check the previous journal
+// was proven, so it commits the journal's digest for the consumer to match
+// against the journal it verified. A previous failure has no result to start
+// from and is committed as an invalid operand
+fn evaluate_chained(fields: &[u8], frame: &[u8]) -> Result<[u8; JOURNAL_CHAINED_V1_LEN], u8> {
+    let header_len = (1 + CHAINED_FRAME_FIELDS) * FIELD_LEN;
+    if fields.len() <= header_len {
+        return Err(malformed(format_args!("Chained frame has no previous journal!")));
+    }
+    let operation = read_field(fields, 1, "operation");
+    let b = read_field(fields, 2, "operand_b");
+    let padded = &fields[header_len..];
+    let journal_len = match padded[0] {
+        JOURNAL_VERSION_1 => JOURNAL_V1_LEN,
+        JOURNAL_METERED_VERSION_1 => JOURNAL_METERED_V1_LEN,
+        JOURNAL_METERED_VERSION_2 => JOURNAL_METERED_V2_LEN,
+        JOURNAL_CHAINED_VERSION_1 => JOURNAL_CHAINED_V1_LEN,
+        version => return Err(malformed(format_args!("Cannot chain from a version {} journal!", version))),
+    };
+    if padded.len() != journal_len.div_ceil(FIELD_LEN) * FIELD_LEN
+        || padded[journal_len..].iter().any(|&byte| byte != 0)
+    {
+        return Err(malformed(format_args!("Previous journal padded to {} bytes is malformed!", padded.len())));
+    }
+    let previous_journal = &padded[..journal_len];
+    let mut previous_bytes = [0u8; FIELD_LEN];
+    previous_bytes.copy_from_slice(&previous_journal[2..10]);
+    let previous_result = i64::from_le_bytes(previous_bytes);
+    debug_log!("Chaining {} from a version {} journal", Operation { op: operation, a: previous_result, b }, padded[0]);
+
+    let (status, result) = if previous_journal[1] != JOURNAL_STATUS_OK {
+        env::log(&format!("[ZK_GUEST_ERROR] Previous journal reports status {}!", previous_journal[1]));
+        (JOURNAL_STATUS_INVALID_OPERAND, 0)
+    } else {
+        match apply(operation, previous_result, b, &mut 0) {
+            Ok(value) => (JOURNAL_STATUS_OK, value),
+            Err(status) => (status, 0),
         }
     };
+    debug_log!("Chained result: {} (status {})", result, status);
+
+    let mut journal = [0u8; JOURNAL_CHAINED_V1_LEN];
+    journal[0] = JOURNAL_CHAINED_VERSION_1;
+    journal[1] = status;
+    journal[2..10].copy_from_slice(&result.to_le_bytes());
+    journal[10..18].copy_from_slice(&previous_result.to_le_bytes());
+    journal[18..50].copy_from_slice(&Sha256::digest(previous_journal));
+    journal[50..].copy_from_slice(&Sha256::digest(frame));
+    Ok(journal)
+}
 
-    match result {
-        Some(value) => {
-            env::log(&format!("[ZK_GUEST_DEBUG] Calculation result: {}", value));
-            // Commit the string representation of the result
-            let result_string = value.to_string();
-            // Pad the string to 32 bytes
-            let mut padded_result_bytes = [0u8; 32];
-            let result_bytes = result_string.as_bytes();
-            let len = result_bytes.len();
-
-            if len > 32 {
-                // If the string is somehow longer than 32 (e.g. very large negative number)
-                // we'll truncate, though this case should be rare with i64.
-                // Or, one could panic here if truncation is not desired.
-                env::log(&format!("[ZK_GUEST_WARNING] Result string ({} bytes) too long, truncating to 32 bytes.", len));
-                padded_result_bytes.copy_from_slice(&result_bytes[..32]);
-            } else {
-                // Copy the result bytes and fill the rest with spaces (or another padding char)
-                padded_result_bytes[..len].copy_from_slice(result_bytes);
-                for i in len..32 {
-                    padded_result_bytes[i] = b' '; // Pad with spaces
+// Evaluates a statistics frame: after the marker come the statistic code,
+// the value count, then the values. Unknown statistics are committed as a
+// failure like unknown operations
+fn evaluate_statistics(fields: &[u8]) -> Result<i64, u8> {
+    if fields.len() < 3 * FIELD_LEN {
+        return Err(malformed(format_args!("Statistics frame has no statistic or value count!")));
+    }
+    let stat = read_field(fields, 1, "statistic");
+    let count = read_field(fields, 2, "value_count");
+    if !(1..=MAX_STATISTICS_VALUES as i64).contains(&count) {
+        return Err(malformed(format_args!("Value count {} out of range!", count)));
+    }
+    expect_field_count(fields, 3 + count as usize, "Statistics")?;
+
+    let mut values: Vec<i64> = (0..count as usize).map(|index| read_field(fields, 3 + index, "value")).collect();
+    calculator_ops::statistic(stat, &mut values).map_err(|error| {
+        env::log(&format!("[ZK_GUEST_ERROR] Cannot compute statistic {} over {} values: {}!", stat, count, error));
+        journal_status(error)
+    })
+}
+
+// Evaluates an expression frame on a stack: after the operation code come
+// the token count, then a (kind, value) pair per token. Malformed frames,
+// including ones that underflow or overflow the stack, fail with the
+// malformed-input status. Operations the frame's schema
+// version lacks fail as unknown
+fn evaluate_expression(version: u8, fields: &[u8], applied: &mut u8) -> Result<i64, u8> {
+    if fields.len() < 2 * FIELD_LEN {
+        return Err(malformed(format_args!("Expression frame has no token count!")));
+    }
+    let token_count = read_field(fields, 1, "token_count");
+    if !(1..=MAX_EXPRESSION_TOKENS as i64).contains(&token_count) {
+        return Err(malformed(format_args!("Token count {} out of range!", token_count)));
+    }
+    expect_field_count(fields, 2 + 2 * token_count as usize, "Expression")?;
+
+    let mut stack: Vec<i64> = Vec::with_capacity(MAX_STACK_DEPTH);
+    for token in 0..token_count as usize {
+        let kind = read_field(fields, 2 + 2 * token, "token_kind");
+        let value = read_field(fields, 3 + 2 * token, "token_value");
+        match kind {
+            EXPR_TOKEN_PUSH => stack.push(value),
+            EXPR_TOKEN_OP => {
+                expect_operation(version, value)?;
+                // Unary operations take `a` alone and ignore `b`
+                let b = if arity(value) == 2 { stack.pop() } else { Some(0) };
+                let (Some(b), Some(a)) = (b, stack.pop()) else {
+                    return Err(stack_underflow());
+                };
+                stack.push(apply(value, a, b, applied)?);
+            }
+            EXPR_TOKEN_DUP => {
+                let Some(&top) = stack.last() else {
+                    return Err(stack_underflow());
+                };
+                stack.push(top);
+            }
+            EXPR_TOKEN_SWAP => {
+                if stack.len() < 2 {
+                    return Err(stack_underflow());
                 }
+                let top = stack.len() - 1;
+                stack.swap(top, top - 1);
             }
+            _ => return Err(malformed(format_args!("Unknown token kind: {}", kind))),
+        }
+        if stack.len() > MAX_STACK_DEPTH {
+            return Err(malformed(format_args!("Expression stack deeper than {} values!", MAX_STACK_DEPTH)));
+        }
+    }
 
-            env::commit_slice(&padded_result_bytes);
-            env::log(&format!("[ZK_GUEST_DEBUG] Result committed as 32-byte padded string: \"{}\"", String::from_utf8_lossy(&padded_result_bytes)));
+    if stack.len() != 1 {
+        return Err(malformed(format_args!("Expression left {} values on the stack!", stack.len())));
+    }
+    Ok(stack[0])
+}
+
+fn stack_underflow() -> u8 {
+    malformed(format_args!("Expression stack underflow!"))
+}
+
+fn main() {
+    debug_log!("Calculator guest started - reading a versioned binary input frame");
+
+    let frame = read_frame();
+    let mut metering = Metering::default();
+    // Every failure without a journal layout of its own, malformed frames
+    // included, is committed here in the metered layout
+    let journal = evaluate(&frame, &mut metering).unwrap_or_else(|status| {
+        debug_log!("Calculation failed with status {}", status);
+        trace(TRACE_STATUS, status as i64);
+        encode_journal(status, 0, metering.cycles, metering.semantics, &frame).to_vec()
+    });
+    env::commit_slice(&journal);
+    debug_log!("Committed a {}-byte journal", journal.len());
+}
+
+// What a metered journal reports besides the outcome: the cycles the
+// arithmetic took and the bitmask of the semantics it fell back on
+#[derive(Default)]
+struct Metering {
+    cycles: u64,
+    semantics: u8,
+}
+
+// Evaluates `frame` into the journal to commit, or the status of why it
+// can't be computed
+fn evaluate(frame: &[u8], metering: &mut Metering) -> Result<Vec<u8>, u8> {
+    let (version, fields) = frame_fields(frame)?;
+    if fields.len() < FIELD_LEN {
+        return Err(malformed(format_args!("Input frame has no operation code!")));
+    }
+
+    // Read operation code; unknown ones are committed as a failure by `apply`
+    let operation = read_field(fields, 0, "operation");
+    trace(TRACE_OPERATION, operation);
+    // A version 1 frame with one of these markers is read as a single
+    // operation, with an unknown operation code
+    if version == FRAME_VERSION_2 {
+        if let Some(journal) = evaluate_extended(operation, fields, frame)? {
+            return Ok(journal);
         }
-        None => {
-            env::log("[ZK_GUEST_ERROR] Arithmetic overflow/underflow during calculation!");
-            panic!("Arithmetic overflow/underflow");
+    }
+    // Meter the arithmetic alone, so the count tracks the operation's proving
+    // cost rather than the frame's size
+    let start_cycles = env::cycle_count();
+    let result = calculate(version, operation, fields, &mut metering.semantics);
+    metering.cycles = env::cycle_count().saturating_sub(start_cycles);
+    debug_log!("Calculation took {} cycles", metering.cycles);
+    trace(TRACE_CYCLES, metering.cycles as i64);
+
+    let value = result?;
+    debug_log!("Calculation result: {}", value);
+    trace(TRACE_STATUS, JOURNAL_STATUS_OK as i64);
+    trace(TRACE_RESULT, value);
+    debug_log!("Semantics applied: {:#04b}", metering.semantics);
+    Ok(encode_journal(JOURNAL_STATUS_OK, value, metering.cycles, metering.semantics, frame).to_vec())
+}
+
+// Evaluates a version 2 frame that commits a journal of its own, `None` for
+// the frames committing a metered journal
+fn evaluate_extended(operation: i64, fields: &[u8], frame: &[u8]) -> Result<Option<Vec<u8>>, u8> {
+    let journal = match operation {
+        OP_BATCH => {
+            debug_log!("Evaluating a batch of operations");
+            evaluate_batch(fields, frame)?
+        }
+        OP_WIDE => {
+            debug_log!("Evaluating a wide-arithmetic operation");
+            evaluate_wide(fields, frame)?
+        }
+        OP_DECIMAL => {
+            debug_log!("Evaluating a fixed-point decimal operation");
+            evaluate_decimal(fields, frame)?.to_vec()
+        }
+        OP_PRIVATE_RANGE => {
+            debug_log!("Evaluating a private-operand range check");
+            evaluate_range(fields, frame)?.to_vec()
+        }
+        OP_CHAINED => {
+            debug_log!("Evaluating an operation chained from a previous journal");
+            evaluate_chained(fields, frame)?.to_vec()
         }
+        _ => return Ok(None),
+    };
+    Ok(Some(journal))
+}
+
+// Computes the calculation `fields` describes for `operation` in a frame of
+// schema `version`, recording the semantics it fell back on in `applied`
+fn calculate(version: u8, operation: i64, fields: &[u8], applied: &mut u8) -> Result<i64, u8> {
+    if operation == OP_EXPRESSION {
+        debug_log!("Evaluating RPN expression");
+        evaluate_expression(version, fields, applied)
+    } else if operation == OP_STATISTICS && version == FRAME_VERSION_2 {
+        debug_log!("Evaluating a statistic over a vector");
+        evaluate_statistics(fields)
+    } else {
+        expect_field_count(fields, SINGLE_FRAME_FIELDS, "Single operation")?;
+
+        // Read operands
+        let a = read_field(fields, 1, "operand_a");
+        let b = read_field(fields, 2, "operand_b");
+
+        debug_log!("Performing operation: {}", Operation { op: operation, a, b });
+
+        expect_operation(version, operation)?;
+        apply(operation, a, b, applied)
     }
 }
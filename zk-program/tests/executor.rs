@@ -0,0 +1,449 @@
+//! Runs the calculator guest in the risc0 executor and checks its journals
+//! against native checked arithmetic from `calculator-ops`
+//!
+//! The guest ELF has to be built first, with `bonsol build` or
+//! `./build-images.sh`; set `ZK_CALCULATOR_ELF` to test another build of it.
+//! Executing without proving keeps each run to a fraction of a second.
+
+use calculator_common::constants::{
+    FRAME_VERSION_1, FRAME_VERSION_1_OPERATIONS, FRAME_VERSION_2, JOURNAL_BATCH_VERSION_2, JOURNAL_CHAINED_V1_LEN,
+    JOURNAL_CHAINED_VERSION_1, JOURNAL_DECIMAL_V1_LEN, JOURNAL_DECIMAL_VERSION_1, JOURNAL_METERED_V2_LEN,
+    JOURNAL_METERED_VERSION_2, JOURNAL_RANGE_V1_LEN, JOURNAL_RANGE_VERSION_1, JOURNAL_SEMANTICS_SATURATED,
+    JOURNAL_SEMANTICS_WRAPPED, JOURNAL_STATUS_DIV_BY_ZERO, JOURNAL_STATUS_INVALID_OPERAND,
+    JOURNAL_STATUS_MALFORMED_INPUT, JOURNAL_STATUS_OK, JOURNAL_STATUS_OVERFLOW, JOURNAL_STATUS_UNKNOWN_OP,
+    JOURNAL_WIDE_VERSION_1, MAX_STATISTICS_VALUES,
+};
+use calculator_ops::{
+    apply_wide, OpError, WideInt, FLAG_SATURATING, FLAG_WRAPPING, I256, OPERATIONS, OP_ABS, OP_ADD, OP_BATCH,
+    OP_BPS_CEIL, OP_BPS_FLOOR, OP_BPS_ROUND, OP_CHAINED, OP_DECIMAL, OP_DIVIDE, OP_EXPRESSION, OP_ISQRT, OP_LOG2,
+    OP_MAX, OP_MODULO, OP_MULTIPLY, OP_PRIVATE_RANGE, OP_STATISTICS, OP_SUBTRACT, OP_WIDE, STATISTICS, STAT_SUM,
+    STAT_VARIANCE,
+};
+use proptest::prelude::*;
+use risc0_zkvm::{default_executor, ExecutorEnv};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+const DEFAULT_ELF: &str = "target/riscv-guest/riscv32im-risc0-zkvm-elf/docker/zk_calculator/zk_calculator";
+
+/// A decoded metered journal
+#[derive(Debug)]
+struct Journal {
+    status: u8,
+    result: i64,
+    semantics: u8,
+    input_digest: [u8; 32],
+}
+
+fn elf() -> &'static [u8] {
+    static ELF: OnceLock<Vec<u8>> = OnceLock::new();
+    ELF.get_or_init(|| {
+        let path = std::env::var("ZK_CALCULATOR_ELF")
+            .unwrap_or_else(|_| format!("{}/{}", env!("CARGO_MANIFEST_DIR"), DEFAULT_ELF));
+        std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read the guest ELF {}: {}; build it first", path, e))
+    })
+}
+
+/// Executes the guest on `input`, returning its journal bytes, or the error of a guest panic
+fn execute(input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let env = ExecutorEnv::builder().write_slice(input).build()?;
+    Ok(default_executor().execute(env, elf())?.journal.bytes)
+}
+
+fn parse(journal: &[u8]) -> Journal {
+    assert_eq!(journal.len(), JOURNAL_METERED_V2_LEN, "journal {:02x?}", journal);
+    assert_eq!(journal[0], JOURNAL_METERED_VERSION_2);
+    let mut input_digest = [0u8; 32];
+    input_digest.copy_from_slice(&journal[19..]);
+    Journal {
+        status: journal[1],
+        result: i64::from_le_bytes(journal[2..10].try_into().unwrap()),
+        semantics: journal[18],
+        input_digest,
+    }
+}
+
+fn frame(words: &[i64]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// `op, a, b` as a host frames them, behind a version 2 header unless `op`
+/// is one of the operations version 1 frames take
+fn single_frame(op: i64, a: i64, b: i64) -> Vec<u8> {
+    let header = (!FRAME_VERSION_1_OPERATIONS.contains(&op)).then_some(FRAME_VERSION_2);
+    header.into_iter().chain(frame(&[op, a, b])).collect()
+}
+
+/// The status and result the guest should commit for `a op b`
+fn expected(op: i64, a: i64, b: i64) -> (u8, i64) {
+    match calculator_ops::apply(op, a, b) {
+        Ok(result) => (0, result),
+        Err(OpError::DivisionByZero) => (1, 0),
+        Err(OpError::Overflow) => (2, 0),
+        Err(OpError::UnknownOperation) => (3, 0),
+        Err(OpError::InvalidOperand) => (4, 0),
+    }
+}
+
+/// Executes `a op b`, in the frame a host sends for it, and asserts the
+/// journal matches the native computation
+fn check_single(op: i64, a: i64, b: i64) {
+    let input = single_frame(op, a, b);
+    let journal = parse(&execute(&input).expect("the guest panicked"));
+    assert_eq!((journal.status, journal.result), expected(op, a, b), "{} {} {}", op, a, b);
+    assert_eq!(journal.input_digest[..], Sha256::digest(&input)[..]);
+}
+
+fn operand() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        4 => any::<i64>(),
+        2 => -1_000i64..=1_000,
+        1 => prop::sample::select(vec![i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX]),
+    ]
+}
+
+proptest! {
+    // Every case executes the guest, so keep the count modest
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn single_operations_match_native(op in prop::sample::select(OPERATIONS.to_vec()), a in operand(), b in operand()) {
+        check_single(op, a, b);
+    }
+
+    #[test]
+    fn flagged_operations_match_native(
+        op in prop::sample::select(OPERATIONS.to_vec()),
+        flag in prop::sample::select(vec![FLAG_SATURATING, FLAG_WRAPPING]),
+        a in operand(),
+        b in operand(),
+    ) {
+        check_single(op | flag, a, b);
+    }
+
+    #[test]
+    fn expressions_match_native(a in operand(), b in operand(), c in operand()) {
+        // (a + b) * c, evaluated natively step by step
+        let input = frame(&[OP_EXPRESSION, 5, 0, a, 0, b, 1, OP_ADD, 0, c, 1, OP_MULTIPLY]);
+        let native = match calculator_ops::apply(OP_ADD, a, b) {
+            Ok(sum) => expected(OP_MULTIPLY, sum, c),
+            Err(_) => expected(OP_ADD, a, b),
+        };
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        prop_assert_eq!((journal.status, journal.result), native);
+    }
+}
+
+#[test]
+fn min_divided_by_minus_one_overflows() {
+    check_single(OP_DIVIDE, i64::MIN, -1);
+    assert_eq!(expected(OP_DIVIDE, i64::MIN, -1), (2, 0));
+}
+
+#[test]
+fn arithmetic_at_the_bounds_overflows() {
+    check_single(OP_ADD, i64::MAX, 1);
+    check_single(OP_SUBTRACT, i64::MIN, 1);
+    check_single(OP_MULTIPLY, i64::MIN, -1);
+    check_single(OP_ABS, i64::MIN, 0);
+}
+
+#[test]
+fn flagged_operations_record_the_semantics_applied() {
+    let cases = [
+        (OP_ADD | FLAG_SATURATING, i64::MAX, 1, i64::MAX, JOURNAL_SEMANTICS_SATURATED),
+        (OP_ADD | FLAG_WRAPPING, i64::MAX, 1, i64::MIN, JOURNAL_SEMANTICS_WRAPPED),
+        (OP_ABS | FLAG_SATURATING, i64::MIN, 0, i64::MAX, JOURNAL_SEMANTICS_SATURATED),
+        // No overflow, so nothing was applied
+        (OP_MULTIPLY | FLAG_WRAPPING, 6, 7, 42, 0),
+    ];
+    for (op, a, b, result, semantics) in cases {
+        let journal = parse(&execute(&single_frame(op, a, b)).expect("the guest panicked"));
+        assert_eq!((journal.status, journal.result, journal.semantics), (0, result, semantics), "{} {} {}", op, a, b);
+    }
+    // Both flags at once are an unknown operation
+    check_single(OP_ADD | FLAG_SATURATING | FLAG_WRAPPING, 1, 2);
+}
+
+#[test]
+fn roots_and_logarithms_at_their_edges() {
+    // The largest perfect square in an i64 and its neighbours
+    let root = 3_037_000_499;
+    let roots = [(0, 0), (1, 1), (3, 1), (4, 2), (root * root - 1, root - 1), (root * root, root), (i64::MAX, root)];
+    for (a, result) in roots {
+        check_single(OP_ISQRT, a, 0);
+        assert_eq!(expected(OP_ISQRT, a, 0), (0, result));
+    }
+    for (a, result) in [(1, 0), (2, 1), (3, 1), (1 << 62, 62), ((1 << 62) - 1, 61), (i64::MAX, 62)] {
+        check_single(OP_LOG2, a, 0);
+        assert_eq!(expected(OP_LOG2, a, 0), (0, result));
+    }
+    // Outside the domain: square roots of negatives, logarithms of zero and below
+    for a in [-1, i64::MIN] {
+        check_single(OP_ISQRT, a, 0);
+        assert_eq!(expected(OP_ISQRT, a, 0), (4, 0));
+    }
+    for a in [0, -1, i64::MIN] {
+        check_single(OP_LOG2, a, 0);
+        assert_eq!(expected(OP_LOG2, a, 0), (4, 0));
+    }
+}
+
+#[test]
+fn basis_points_round_in_each_mode() {
+    // 1999 * 30 / 10_000 is 5.997; -1999 * 30 / 10_000 is -5.997; 250 * 10 / 10_000 is 0.25
+    let cases = [
+        (OP_BPS_FLOOR, 1999, 30, 5),
+        (OP_BPS_CEIL, 1999, 30, 6),
+        (OP_BPS_ROUND, 1999, 30, 6),
+        (OP_BPS_FLOOR, -1999, 30, -6),
+        (OP_BPS_CEIL, -1999, 30, -5),
+        (OP_BPS_ROUND, -1999, 30, -6),
+        (OP_BPS_FLOOR, 250, 10, 0),
+        (OP_BPS_CEIL, 250, 10, 1),
+        (OP_BPS_ROUND, 250, 10, 0),
+        // Ties go to the even neighbour: 0.5 to 0, 1.5 to 2, -2.5 to -2
+        (OP_BPS_ROUND, 5_000, 1, 0),
+        (OP_BPS_ROUND, 15_000, 1, 2),
+        (OP_BPS_ROUND, -25_000, 1, -2),
+        // The product passes i64 but the share fits
+        (OP_BPS_FLOOR, i64::MAX, 10_000, i64::MAX),
+        (OP_BPS_CEIL, i64::MIN, 10_000, i64::MIN),
+    ];
+    for (op, a, b, result) in cases {
+        check_single(op, a, b);
+        assert_eq!(expected(op, a, b), (0, result), "{} {} {}", op, a, b);
+    }
+    // Shares of more than the whole can overflow
+    check_single(OP_BPS_FLOOR, i64::MAX, 10_001);
+    assert_eq!(expected(OP_BPS_FLOOR, i64::MAX, 10_001), (2, 0));
+    check_single(OP_BPS_ROUND, i64::MIN, -10_000);
+    assert_eq!(expected(OP_BPS_ROUND, i64::MIN, -10_000), (2, 0));
+}
+
+#[test]
+fn division_by_zero_commits_a_failure() {
+    check_single(OP_DIVIDE, 42, 0);
+    check_single(OP_DIVIDE, i64::MIN, 0);
+    assert_eq!(expected(OP_DIVIDE, 42, 0), (1, 0));
+}
+
+#[test]
+fn unknown_operations_commit_a_failure() {
+    check_single(99, 1, 2);
+    check_single(-1, 1, 2);
+}
+
+#[test]
+fn versioned_frames_compute_like_legacy_ones() {
+    let legacy = frame(&[OP_MULTIPLY, 6, 7]);
+    for version in [FRAME_VERSION_1, FRAME_VERSION_2] {
+        let mut input = vec![version];
+        input.extend_from_slice(&legacy);
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        assert_eq!((journal.status, journal.result), (0, 42));
+        // The digest binds the header too
+        assert_eq!(journal.input_digest[..], Sha256::digest(&input)[..]);
+    }
+}
+
+#[test]
+fn version_1_frames_only_take_the_original_operations() {
+    let modulo = frame(&[OP_MODULO, 7, 3]);
+    let mut versioned = vec![FRAME_VERSION_1];
+    versioned.extend_from_slice(&modulo);
+    for input in [modulo.clone(), versioned] {
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        assert_eq!((journal.status, journal.result), (JOURNAL_STATUS_UNKNOWN_OP, 0));
+    }
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&modulo);
+    let journal = parse(&execute(&input).expect("the guest panicked"));
+    assert_eq!((journal.status, journal.result), (JOURNAL_STATUS_OK, 1));
+
+    // Expressions are held to the same set, token by token
+    let max = frame(&[OP_EXPRESSION, 3, 0, 4, 0, 9, 1, OP_MAX]);
+    let journal = parse(&execute(&max).expect("the guest panicked"));
+    assert_eq!(journal.status, JOURNAL_STATUS_UNKNOWN_OP);
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&max);
+    let journal = parse(&execute(&input).expect("the guest panicked"));
+    assert_eq!((journal.status, journal.result), (JOURNAL_STATUS_OK, 9));
+}
+
+#[test]
+fn chained_frames_start_from_the_previous_result() {
+    let previous = execute(&frame(&[OP_MULTIPLY, 6, 7])).expect("the guest panicked");
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&frame(&[OP_CHAINED, OP_ADD, 8]));
+    input.extend_from_slice(&previous);
+    // Zero-pad the journal to whole fields after the version byte
+    input.resize(input.len().div_ceil(8) * 8 + 1, 0);
+    let journal = execute(&input).expect("the guest panicked");
+    assert_eq!(journal.len(), JOURNAL_CHAINED_V1_LEN);
+    assert_eq!(journal[0], JOURNAL_CHAINED_VERSION_1);
+    assert_eq!((journal[1], i64::from_le_bytes(journal[2..10].try_into().unwrap())), (0, 50));
+    assert_eq!(i64::from_le_bytes(journal[10..18].try_into().unwrap()), 42);
+    assert_eq!(journal[18..50], Sha256::digest(&previous)[..]);
+    assert_eq!(journal[50..], Sha256::digest(&input)[..]);
+}
+
+#[test]
+fn batch_journals_commit_a_merkle_root_over_their_outcomes() {
+    let operations = [[OP_ADD, 1, 2], [OP_DIVIDE, 1, 0], [OP_MULTIPLY, 6, 7]];
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&frame(&[OP_BATCH, operations.len() as i64]));
+    for operation in &operations {
+        input.extend_from_slice(&frame(operation));
+    }
+    let journal = execute(&input).expect("the guest panicked");
+    assert_eq!((journal[0], journal[1]), (JOURNAL_BATCH_VERSION_2, 3));
+    assert_eq!(journal[2..34], Sha256::digest(&input)[..]);
+
+    let outcomes = &journal[66..];
+    assert_eq!(outcomes.len(), operations.len() * 9);
+    for (outcome, [op, a, b]) in outcomes.chunks_exact(9).zip(operations) {
+        assert_eq!((outcome[0], i64::from_le_bytes(outcome[1..].try_into().unwrap())), expected(op, a, b));
+    }
+    // Three leaves: the first two pair up and the third is carried to the root
+    let leaf = |outcome: &[u8]| Sha256::new().chain_update([0u8]).chain_update(outcome).finalize();
+    let node = |left: &[u8], right: &[u8]| {
+        Sha256::new().chain_update([1u8]).chain_update(left).chain_update(right).finalize()
+    };
+    let pair = node(&leaf(&outcomes[..9]), &leaf(&outcomes[9..18]));
+    assert_eq!(journal[34..66], node(&pair, &leaf(&outcomes[18..]))[..]);
+}
+
+/// Executes `a op b` in a wide frame and returns the journal's status and
+/// length-prefixed result, checking its layout and input digest
+fn execute_wide<T: WideInt>(op: i64, a: T, b: T) -> (u8, T) {
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&frame(&[OP_WIDE, T::BYTES as i64, op]));
+    let start = input.len();
+    input.resize(start + 2 * T::BYTES, 0);
+    a.write_le(&mut input[start..start + T::BYTES]);
+    b.write_le(&mut input[start + T::BYTES..]);
+
+    let journal = execute(&input).expect("the guest panicked");
+    assert_eq!(journal.len(), 1 + 1 + 1 + T::BYTES + 32, "journal {:02x?}", journal);
+    assert_eq!(journal[0], JOURNAL_WIDE_VERSION_1);
+    assert_eq!(journal[2] as usize, T::BYTES);
+    assert_eq!(journal[3 + T::BYTES..], Sha256::digest(&input)[..]);
+    (journal[1], T::read_le(&journal[3..3 + T::BYTES]))
+}
+
+#[test]
+fn wide_frames_commit_a_length_prefixed_result() {
+    // Past i64, within i128
+    let (a, b) = (i64::MAX as i128, 1_000_000i128);
+    assert_eq!(execute_wide(OP_MULTIPLY, a, b), (JOURNAL_STATUS_OK, a * b));
+    assert_eq!(execute_wide(OP_MULTIPLY, i128::MAX, 2), (JOURNAL_STATUS_OVERFLOW, 0));
+
+    // Past i128, within 256 bits
+    let (a, b) = (I256::from(i128::MAX), I256::from(i128::MIN));
+    let product = apply_wide(OP_MULTIPLY, a, b).expect("fits in 256 bits");
+    assert_eq!(execute_wide(OP_MULTIPLY, a, b), (JOURNAL_STATUS_OK, product));
+    assert_eq!(execute_wide(OP_DIVIDE, a, I256::ZERO).0, JOURNAL_STATUS_DIV_BY_ZERO);
+}
+
+#[test]
+fn decimal_frames_commit_the_rounded_result_and_its_scale() {
+    // 2.50 * 1.075 is 2.6875, rounded half to even
+    for (scale, result) in [(2, 269), (3, 2688), (4, 26875)] {
+        let mut input = vec![FRAME_VERSION_2];
+        input.extend_from_slice(&frame(&[OP_DECIMAL, OP_MULTIPLY, scale, 250, 2, 1075, 3]));
+        let journal = execute(&input).expect("the guest panicked");
+        assert_eq!(journal.len(), JOURNAL_DECIMAL_V1_LEN);
+        assert_eq!((journal[0], journal[1]), (JOURNAL_DECIMAL_VERSION_1, JOURNAL_STATUS_OK));
+        assert_eq!(i64::from_le_bytes(journal[2..10].try_into().unwrap()), result);
+        assert_eq!(journal[10] as i64, scale);
+        assert_eq!(journal[11..], Sha256::digest(&input)[..]);
+    }
+    // Scales past 18 prove an invalid operand
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&frame(&[OP_DECIMAL, OP_ADD, 19, 1, 0, 1, 0]));
+    let journal = execute(&input).expect("the guest panicked");
+    assert_eq!(journal[1], JOURNAL_STATUS_INVALID_OPERAND);
+}
+
+/// A version 2 statistics frame computing `stat` over `values`, preceded by their count
+fn statistics_frame(stat: i64, values: &[i64]) -> Vec<u8> {
+    let mut input = vec![FRAME_VERSION_2];
+    input.extend_from_slice(&frame(&[OP_STATISTICS, stat, values.len() as i64]));
+    input.extend_from_slice(&frame(values));
+    input
+}
+
+#[test]
+fn statistics_frames_aggregate_a_length_prefixed_vector() {
+    let values = [7, -3, 12, 5, 0, 9];
+    for stat in STATISTICS {
+        let input = statistics_frame(stat, &values);
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        let native = calculator_ops::statistic(stat, &mut values.clone()).expect("computes natively");
+        assert_eq!((journal.status, journal.result), (JOURNAL_STATUS_OK, native), "statistic {}", stat);
+        assert_eq!(journal.input_digest[..], Sha256::digest(&input)[..]);
+    }
+    let journal = parse(&execute(&statistics_frame(STAT_VARIANCE, &[i64::MIN, i64::MAX])).unwrap());
+    assert_eq!(journal.status, JOURNAL_STATUS_OVERFLOW);
+
+    // A count that disagrees with the values, or exceeds the limit
+    let mut short = statistics_frame(STAT_SUM, &[1, 2, 3]);
+    short.truncate(short.len() - 8);
+    let too_many = statistics_frame(STAT_SUM, &[1; MAX_STATISTICS_VALUES + 1]);
+    for input in [short, too_many, statistics_frame(STAT_SUM, &[])] {
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        assert_eq!(journal.status, JOURNAL_STATUS_MALFORMED_INPUT);
+    }
+}
+
+/// Executes a range frame proving whether `a op b` is within `[min, max]`,
+/// with `a` as the private input, and returns the journal's status and flag
+fn execute_range(op: i64, a: i64, b: i64, min: i64, max: i64) -> (u8, bool) {
+    let mut public = vec![FRAME_VERSION_2];
+    public.extend_from_slice(&frame(&[OP_PRIVATE_RANGE, op, b, min, max]));
+    let env = ExecutorEnv::builder()
+        .write_slice(&public)
+        .write_slice(&a.to_le_bytes())
+        .build()
+        .expect("executor env");
+    let journal = default_executor().execute(env, elf()).expect("the guest panicked").journal.bytes;
+    assert_eq!(journal.len(), JOURNAL_RANGE_V1_LEN);
+    assert_eq!(journal[0], JOURNAL_RANGE_VERSION_1);
+    // Only the public part is bound, so the digest reveals nothing of `a`
+    assert_eq!(journal[3..], Sha256::digest(&public)[..]);
+    (journal[1], journal[2] == 1)
+}
+
+#[test]
+fn range_frames_prove_bounds_without_the_private_operand() {
+    // A balance of 1_500 plus a deposit of 250, checked against [1_000, 2_000]
+    assert_eq!(execute_range(OP_ADD, 1_500, 250, 1_000, 2_000), (JOURNAL_STATUS_OK, true));
+    assert_eq!(execute_range(OP_ADD, 750, 250, 1_000, 2_000), (JOURNAL_STATUS_OK, true));
+    // Out of range on either side
+    assert_eq!(execute_range(OP_ADD, 749, 250, 1_000, 2_000), (JOURNAL_STATUS_OK, false));
+    assert_eq!(execute_range(OP_MULTIPLY, 1_000, 3, 1_000, 2_000), (JOURNAL_STATUS_OK, false));
+    // A failed operation is never within bounds
+    assert_eq!(execute_range(OP_DIVIDE, 1_500, 0, i64::MIN, i64::MAX), (JOURNAL_STATUS_DIV_BY_ZERO, false));
+    assert_eq!(execute_range(OP_ADD, i64::MAX, 1, i64::MIN, i64::MAX), (JOURNAL_STATUS_OVERFLOW, false));
+}
+
+#[test]
+fn malformed_frames_commit_a_failure_journal() {
+    let mut unsupported_version = vec![9u8];
+    unsupported_version.extend_from_slice(&frame(&[OP_ADD, 1, 2]));
+    let inputs = [
+        // Not a whole number of fields
+        vec![0u8; 23],
+        unsupported_version,
+        // Too many fields for a single operation
+        frame(&[OP_ADD, 1, 2, 3]),
+        // An expression popping more operands than it pushed
+        frame(&[OP_EXPRESSION, 1, 1, OP_ADD]),
+    ];
+    for input in inputs {
+        let journal = parse(&execute(&input).expect("the guest panicked"));
+        assert_eq!((journal.status, journal.result), (JOURNAL_STATUS_MALFORMED_INPUT, 0), "{:02x?}", input);
+        assert_eq!(journal.input_digest[..], Sha256::digest(&input)[..]);
+    }
+}